@@ -38,3 +38,58 @@ float point_distance_to_triangle(float3 pt, Triangle tri) {
 
   return sqrt(dist_squared);
 }
+
+// Get the barycentric weights of the vertices a, b and c for the closest point on the triangle,
+// by the Voronoi regions of the vertices, the edges and the face.
+float3 closest_barycentrics_on_triangle(float3 pt, Triangle tri) {
+  const float3 ab = tri.b - tri.a;
+  const float3 ac = tri.c - tri.a;
+
+  // The vertex region of a.
+  const float3 ap = pt - tri.a;
+  const float d1 = dot(ab, ap);
+  const float d2 = dot(ac, ap);
+  if (d1 <= 0.0f && d2 <= 0.0f)
+    return float3(1.0f, 0.0f, 0.0f);
+
+  // The vertex region of b.
+  const float3 bp = pt - tri.b;
+  const float d3 = dot(ab, bp);
+  const float d4 = dot(ac, bp);
+  if (d3 >= 0.0f && d4 <= d3)
+    return float3(0.0f, 1.0f, 0.0f);
+
+  // The edge region of ab.
+  const float vc = d1 * d4 - d3 * d2;
+  if (vc <= 0.0f && d1 >= 0.0f && d3 <= 0.0f) {
+    const float v = d1 / (d1 - d3);
+    return float3(1.0f - v, v, 0.0f);
+  }
+
+  // The vertex region of c.
+  const float3 cp = pt - tri.c;
+  const float d5 = dot(ab, cp);
+  const float d6 = dot(ac, cp);
+  if (d6 >= 0.0f && d5 <= d6)
+    return float3(0.0f, 0.0f, 1.0f);
+
+  // The edge region of ac.
+  const float vb = d5 * d2 - d1 * d6;
+  if (vb <= 0.0f && d2 >= 0.0f && d6 <= 0.0f) {
+    const float w = d2 / (d2 - d6);
+    return float3(1.0f - w, 0.0f, w);
+  }
+
+  // The edge region of bc.
+  const float va = d3 * d6 - d5 * d4;
+  if (va <= 0.0f && (d4 - d3) >= 0.0f && (d5 - d6) >= 0.0f) {
+    const float w = (d4 - d3) / ((d4 - d3) + (d5 - d6));
+    return float3(0.0f, 1.0f - w, w);
+  }
+
+  // The face region, the degenerate triangles are guarded against the zero area.
+  const float denom = 1.0f / max(va + vb + vc, 1e-20f);
+  const float v = vb * denom;
+  const float w = vc * denom;
+  return float3(1.0f - v - w, v, w);
+}
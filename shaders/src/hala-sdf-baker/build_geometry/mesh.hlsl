@@ -3,6 +3,8 @@ cbuffer MeshUniformBuffer {
   int _vertex_position_offset;
   int _vertex_stride;
   int _index_stride;
+  int _padding0;
+  float4x4 _local_to_bake;
 };
 
 [[vk::binding(1, 1)]]
@@ -27,5 +29,5 @@ float3 get_vertex_pos(uint triangle_id, uint vertex_id) {
   const uint index = _index_stride == 2 ? load_index16(index_id) : load_index32(index_id);
   const uint vert_index = _vertex_position_offset + index * _vertex_stride;
   const uint3 vert_raw = _vertices_buffer.Load3(vert_index);
-  return asfloat(vert_raw);
+  return mul(_local_to_bake, float4(asfloat(vert_raw), 1.0)).xyz;
 }
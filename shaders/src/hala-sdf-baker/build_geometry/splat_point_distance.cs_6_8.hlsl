@@ -0,0 +1,47 @@
+#include "../baker/udf_baker.hlsl"
+
+#define GRID_MARGIN 1
+
+struct PushConstants {
+  float point_radius;
+  uint num_of_points;
+};
+
+[[vk::push_constant]]
+PushConstants g_push_constants;
+
+[[vk::binding(0, 1)]]
+ByteAddressBuffer _points_buffer;
+
+[[vk::binding(1, 1)]]
+RWTexture3D<uint> _distance_texture_rw;
+
+[numthreads(64, 1, 1)]
+void main(uint3 id: SV_DispatchThreadID) {
+  if (id.x >= g_push_constants.num_of_points) {
+    return;
+  }
+
+  // The points are tightly packed float3 in the bake space, the radius is normalized by the max size.
+  const float3 point_pos = asfloat(_points_buffer.Load3(id.x * 12));
+  const float3 point_uvw = (point_pos - _center + _extents) / _max_size;
+  const float radius = g_push_constants.point_radius;
+
+  // Splat the voxels around the sphere, so the seeds of the jump flooding are near the surface.
+  const int margin = int(ceil(radius * _max_dimension)) + GRID_MARGIN;
+  int3 voxel_min = int3(floor(point_uvw * _max_dimension)) - margin;
+  int3 voxel_max = int3(floor(point_uvw * _max_dimension)) + margin;
+  voxel_min = max(0, min(voxel_min, int3(_dimensions) - 1));
+  voxel_max = max(0, min(voxel_max, int3(_dimensions) - 1));
+
+  for (int z = voxel_min.z; z <= voxel_max.z; ++z) {
+    for (int y = voxel_min.y; y <= voxel_max.y; ++y) {
+      for (int x = voxel_min.x; x <= voxel_max.x; ++x) {
+        const float3 voxel_coord = (float3(x, y, z) + float3(0.5, 0.5, 0.5)) / _max_dimension;
+        float distance = max(length(voxel_coord - point_uvw) - radius, 0.0);
+        uint distance_as_uint = float_flip(distance);
+        InterlockedMin(_distance_texture_rw[int3(x, y, z)], distance_as_uint);
+      }
+    }
+  }
+}
@@ -3,6 +3,8 @@
 struct PushConstants {
   float threshold;
   float offset;
+  float band;
+  uint keep_closest_triangles;
 };
 
 [[vk::push_constant]]
@@ -29,6 +31,9 @@ RWStructuredBuffer<float4> _voxels_buffer_rw;
 [[vk::binding(6, 1)]]
 RWTexture3D<float> _distance_texture_rw;
 
+[[vk::binding(7, 1)]]
+RWStructuredBuffer<uint4> _closest_triangles_buffer_rw;
+
 [numthreads(8, 8, 8)]
 void main(uint3 id : SV_DispatchThreadID) {
   if (id.x >= _dimensions.x || id.y >= _dimensions.y || id.z >= _dimensions.z)
@@ -52,17 +57,56 @@ void main(uint3 id : SV_DispatchThreadID) {
   }
   uint end_triangle_id = _accum_counters_buffer[id3(id_seed)];
 
+  // The triangles in the seed voxel are at most one voxel diagonal closer than its center,
+  // so the voxels farther than the narrow band skip them.
+  const float far_distance = g_push_constants.band + abs(g_push_constants.offset);
+  const float seed_distance = length(seed_coord - voxel_coord);
+
   float distance = 1e6f;
-  for (uint i = start_triangle_id; (i < end_triangle_id) && (i < _upper_bound_count - 1); i++) {
-    const uint triangle_index = _triangles_in_voxels[i];
-    Triangle tri = _triangles_uvw[triangle_index];
-    distance = min(distance, point_distance_to_triangle(voxel_coord, tri));
-  }
-  if (1e6f - distance < COMMON_EPS) {
-    distance = length(seed_coord - voxel_coord);
+  uint closest_triangle_index = 0xFFFFFFFF;
+  [branch]
+  if (g_push_constants.band > 0 && seed_distance - 1.7320508f / _max_dimension > far_distance) {
+    distance = far_distance;
+  } else {
+    for (uint i = start_triangle_id; (i < end_triangle_id) && (i < _upper_bound_count - 1); i++) {
+      const uint triangle_index = _triangles_in_voxels[i];
+      Triangle tri = _triangles_uvw[triangle_index];
+      const float triangle_distance = point_distance_to_triangle(voxel_coord, tri);
+      if (triangle_distance < distance) {
+        distance = triangle_distance;
+        closest_triangle_index = triangle_index;
+      }
+    }
+    if (1e6f - distance < COMMON_EPS) {
+      distance = seed_distance;
+    }
   }
   distance = sign_d * distance - g_push_constants.offset;
 
-  _voxels_buffer_rw[id3(id)] = float4(distance, distance, distance, distance);
+  // The gradient points from the closest point to the voxel outside and the other way inside,
+  // the voxels without the closest triangle take the direction from the seed.
+  // The closest triangle and the barycentrics of the closest point are kept for the attribute transfer.
+  float3 direction = voxel_coord - seed_coord;
+  uint4 closest_triangle = uint4(0xFFFFFFFF, 0, 0, 0);
+  [branch]
+  if (closest_triangle_index != 0xFFFFFFFF) {
+    Triangle tri = _triangles_uvw[closest_triangle_index];
+    const float3 bary = closest_barycentrics_on_triangle(voxel_coord, tri);
+    closest_triangle = uint4(closest_triangle_index, asuint(bary));
+    direction = voxel_coord - (tri.a * bary.x + tri.b * bary.y + tri.c * bary.z);
+    // The voxel center on the triangle takes the face normal.
+    if (dot2(direction) < COMMON_EPS * COMMON_EPS) {
+      direction = cross(tri.b - tri.a, tri.c - tri.a);
+    }
+  }
+  const float direction_length = length(direction);
+  const float3 gradient = direction_length > 0.0f ? sign_d * direction / direction_length : float3(0.0f, 0.0f, 0.0f);
+
+  _voxels_buffer_rw[id3(id)] = float4(gradient, distance);
   _distance_texture_rw[id] = distance;
+  // The closest triangles buffer is a single element if they are not kept.
+  [branch]
+  if (g_push_constants.keep_closest_triangles != 0) {
+    _closest_triangles_buffer_rw[id3(id)] = closest_triangle;
+  }
 }
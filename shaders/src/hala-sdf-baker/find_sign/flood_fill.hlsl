@@ -0,0 +1,8 @@
+// The states of the voxels in the fill map.
+#define FILL_OPEN 0.0f // Not reached yet.
+#define FILL_EXTERIOR 1.0f // Reached from the border.
+#define FILL_WALL 2.0f // Crossed by the triangles or in the dilation radius of them.
+
+inline bool is_in_grid(int3 coord) {
+  return all(coord >= 0) && all(coord < int3(_dimensions));
+}
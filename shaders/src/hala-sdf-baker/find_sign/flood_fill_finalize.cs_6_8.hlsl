@@ -0,0 +1,43 @@
+#include "../baker/sdf_baker.hlsl"
+#include "flood_fill.hlsl"
+
+[[vk::binding(0, 1)]]
+Texture3D<float> _fill_map;
+
+[[vk::binding(1, 1)]]
+RWTexture3D<float> _sign_map_rw;
+
+[numthreads(4, 4, 4)]
+void main(uint3 id: SV_DispatchThreadID) {
+  if (id.x >= _dimensions.x || id.y >= _dimensions.y || id.z >= _dimensions.z)
+    return;
+
+  // The open voxels are inside. The wall takes the side of its nearest face-adjacent voxels out of the walls
+  // along the 6 axes, the exterior on a tie. The grid border is always met, so the search ends.
+  const float state = _fill_map[id];
+  bool is_inside = state != FILL_EXTERIOR;
+  if (state == FILL_WALL) {
+    int num_of_exterior = 0;
+    int num_of_open = 0;
+    for (int n = 1; n <= int(_max_dimension) && num_of_exterior + num_of_open == 0; ++n) {
+      for (int i = 0; i < 6; ++i) {
+        int3 coord = int3(id);
+        coord[i / 2] += i % 2 == 0 ? -n : n;
+        // The voxels out of the grid are the exterior.
+        float neighbor = FILL_EXTERIOR;
+        if (is_in_grid(coord)) {
+          neighbor = _fill_map[coord];
+        }
+        if (neighbor == FILL_EXTERIOR) {
+          ++num_of_exterior;
+        } else if (neighbor == FILL_OPEN) {
+          ++num_of_open;
+        }
+      }
+    }
+    is_inside = num_of_open > num_of_exterior;
+  }
+
+  // 1 inside and 0 outside, the same as the normalized sign passes.
+  _sign_map_rw[id] = is_inside ? 1.0f : 0.0f;
+}
@@ -0,0 +1,45 @@
+#include "../baker/sdf_baker.hlsl"
+#include "flood_fill.hlsl"
+
+struct PushConstants {
+  int dilation;
+};
+
+[[vk::push_constant]]
+PushConstants g_push_constants;
+
+[[vk::binding(0, 1)]]
+StructuredBuffer<uint> _accum_counter_buffer;
+
+[[vk::binding(1, 1)]]
+RWTexture3D<float> _fill_map_rw;
+
+inline bool has_triangles(int3 coord) {
+  const uint index = id3(coord);
+  const uint start_triangle_id = index > 0 ? _accum_counter_buffer[index - 1] : 0;
+  return _accum_counter_buffer[index] > start_triangle_id;
+}
+
+[numthreads(4, 4, 4)]
+void main(uint3 id: SV_DispatchThreadID) {
+  if (id.x >= _dimensions.x || id.y >= _dimensions.y || id.z >= _dimensions.z)
+    return;
+
+  // The voxel is the wall if any voxel with triangles is in the dilation sphere.
+  const int r = g_push_constants.dilation;
+  bool is_wall = false;
+  for (int z = -r; z <= r && !is_wall; ++z) {
+    for (int y = -r; y <= r && !is_wall; ++y) {
+      for (int x = -r; x <= r && !is_wall; ++x) {
+        const int3 coord = int3(id) + int3(x, y, z);
+        if (x * x + y * y + z * z <= r * r && is_in_grid(coord)) {
+          is_wall = has_triangles(coord);
+        }
+      }
+    }
+  }
+
+  // The fill starts from the open voxels on the border of the grid.
+  const bool is_border = any(id == 0) || any(id == _dimensions - 1);
+  _fill_map_rw[id] = is_wall ? FILL_WALL : (is_border ? FILL_EXTERIOR : FILL_OPEN);
+}
@@ -0,0 +1,61 @@
+#include "../baker/sdf_baker.hlsl"
+#include "flood_fill.hlsl"
+
+struct PushConstants {
+  uint axis;
+  uint connectivity;
+};
+
+[[vk::push_constant]]
+PushConstants g_push_constants;
+
+[[vk::binding(0, 1)]]
+RWTexture3D<float> _fill_map_rw;
+
+// Set to 1 when any voxel is reached, the baker sweeps again until it stays 0.
+[[vk::binding(1, 1)]]
+RWStructuredBuffer<uint> _changed_buffer;
+
+[numthreads(8, 8, 1)]
+void main(uint3 id: SV_DispatchThreadID) {
+  // Each thread walks the line along the axis, at (id.x, id.y) of the other 2 axes.
+  const uint axis = g_push_constants.axis;
+  const uint u_axis = (axis + 1) % 3;
+  const uint v_axis = (axis + 2) % 3;
+  if (id.x >= _dimensions[u_axis] || id.y >= _dimensions[v_axis])
+    return;
+
+  int3 coord = 0;
+  coord[u_axis] = id.x;
+  coord[v_axis] = id.y;
+  const int line_length = _dimensions[axis];
+
+  // Walk forward then backward, the open voxel is reached if the previous slice is reached.
+  // The states only change from open to exterior, so the lines read by the other threads in any order.
+  for (int direction = 1; direction >= -1; direction -= 2) {
+    for (int n = 1; n < line_length; ++n) {
+      coord[axis] = direction > 0 ? n : line_length - 1 - n;
+      if (_fill_map_rw[coord] != FILL_OPEN)
+        continue;
+
+      int3 previous = coord;
+      previous[axis] -= direction;
+      bool is_reached = _fill_map_rw[previous] == FILL_EXTERIOR;
+      if (g_push_constants.connectivity == 26) {
+        // The edge and corner neighbors in the previous slice.
+        for (int v = -1; v <= 1 && !is_reached; ++v) {
+          for (int u = -1; u <= 1 && !is_reached; ++u) {
+            int3 neighbor = previous;
+            neighbor[u_axis] += u;
+            neighbor[v_axis] += v;
+            is_reached = is_in_grid(neighbor) && _fill_map_rw[neighbor] == FILL_EXTERIOR;
+          }
+        }
+      }
+      if (is_reached) {
+        _fill_map_rw[coord] = FILL_EXTERIOR;
+        _changed_buffer[0] = 1;
+      }
+    }
+  }
+}
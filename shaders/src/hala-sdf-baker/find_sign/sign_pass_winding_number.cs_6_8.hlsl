@@ -0,0 +1,86 @@
+#include "../baker/sdf_baker.hlsl"
+
+// The same as WINDING_NUMBER_STACK_SIZE, the baker checks the BVH fits before the upload.
+#define STACK_SIZE 64
+
+struct PushConstants {
+  float accuracy;
+};
+
+[[vk::push_constant]]
+PushConstants g_push_constants;
+
+// The BVH nodes, 12 32 bits values per node.
+// The area weighted normal sum, the radius, the centroid, the right index, the start and the count of the triangles,
+// the number of all triangles under the node and 1 padding.
+// The left child of the inner node is the next node, the count of the inner node is 0.
+[[vk::binding(0, 1)]]
+ByteAddressBuffer _nodes_buffer;
+
+// The triangles in the order of the nodes, 9 floats per triangle in the bake space.
+[[vk::binding(1, 1)]]
+ByteAddressBuffer _triangles_buffer;
+
+[[vk::binding(2, 1)]]
+RWTexture3D<float> _sign_map_rw;
+
+// The signed solid angle of the triangle seen from the point, by Van Oosterom and Strackee.
+float triangle_solid_angle(float3 p, float3 v0, float3 v1, float3 v2) {
+  const float3 a = v0 - p;
+  const float3 b = v1 - p;
+  const float3 c = v2 - p;
+  const float length_a = length(a);
+  const float length_b = length(b);
+  const float length_c = length(c);
+
+  const float determinant = dot(a, cross(b, c));
+  const float divisor = length_a * length_b * length_c
+    + dot(a, b) * length_c
+    + dot(b, c) * length_a
+    + dot(c, a) * length_b;
+
+  return 2.0f * atan2(determinant, divisor);
+}
+
+[numthreads(4, 4, 4)]
+void main(uint3 id: SV_DispatchThreadID) {
+  if (id.x >= _dimensions.x || id.y >= _dimensions.y || id.z >= _dimensions.z)
+    return;
+
+  const float3 voxel_uvw = (float3(id) + float3(0.5f, 0.5f, 0.5f)) / _max_dimension;
+  const float3 p = voxel_uvw * _max_size + _center - _extents;
+
+  // The far away nodes use the dipole approximation, the near ones sum the exact solid angles.
+  float solid_angle = 0.0f;
+  uint stack[STACK_SIZE];
+  uint stack_size = 1;
+  stack[0] = 0;
+  while (stack_size > 0) {
+    const uint node_address = stack[--stack_size] * 48;
+    const float3 area_normal = asfloat(_nodes_buffer.Load3(node_address));
+    const float radius = asfloat(_nodes_buffer.Load(node_address + 12));
+    const float3 centroid = asfloat(_nodes_buffer.Load3(node_address + 16));
+    const uint4 right_start_count_total = _nodes_buffer.Load4(node_address + 28);
+
+    const float3 to_centroid = centroid - p;
+    const float distance = length(to_centroid);
+    if (distance > g_push_constants.accuracy * radius) {
+      solid_angle += dot(area_normal, to_centroid) / (distance * distance * distance);
+    } else if (right_start_count_total.z > 0 || stack_size + 2 > STACK_SIZE) {
+      // The leaf sums its triangles, so does the inner node without the stack room for its children.
+      const uint count = right_start_count_total.z > 0 ? right_start_count_total.z : right_start_count_total.w;
+      for (uint i = right_start_count_total.y; i < right_start_count_total.y + count; ++i) {
+        const float3 v0 = asfloat(_triangles_buffer.Load3(i * 36));
+        const float3 v1 = asfloat(_triangles_buffer.Load3(i * 36 + 12));
+        const float3 v2 = asfloat(_triangles_buffer.Load3(i * 36 + 24));
+        solid_angle += triangle_solid_angle(p, v0, v1, v2);
+      }
+    } else {
+      stack[stack_size++] = node_address / 48 + 1;
+      stack[stack_size++] = right_start_count_total.x;
+    }
+  }
+
+  // About 1 inside and 0 outside, so the same in/out threshold works as the ray map.
+  _sign_map_rw[id] = solid_angle / (4.0f * PI);
+}
@@ -20,6 +20,7 @@ cbuffer GlobalUniformBuffer {
   float _offset;
   uint3 _dimensions;
   float _inv_resolution;
+  uint _num_of_mips;   // The number of the mip levels from 1, 0 without the mip chain.
 };
 
 [[vk::combinedImageSampler]]
@@ -30,6 +31,10 @@ Texture3D<float4> g_texture;
 [[vk::binding(1, 0)]]
 SamplerState g_sampler;
 
+// The voxels of the mip levels from 1 one after another, X changing fastest in each level.
+[[vk::binding(2, 0)]]
+StructuredBuffer<float> g_mips;
+
 struct ToFragment {
   float4 position: SV_Position;
   [[vk::location(0)]] float3 position_ws: TEXCOORD0;
@@ -4,6 +4,24 @@ inline float sample_surface(float3 position) {
   return g_texture.SampleLevel(g_sampler, position, 0).r + _offset;
 }
 
+// Get the dimensions of the mip level, halved and rounded down at each level.
+inline uint3 get_mip_dimensions(uint level) {
+  return max(_dimensions >> level, uint3(1, 1, 1));
+}
+
+// Read the voxel of the mip level from 1 with the nearest filter.
+// The mip voxel is a lower bound of the distance anywhere in its cell, so it never oversteps the surface.
+inline float sample_mip(float3 position, uint level) {
+  uint offset = 0;
+  for (uint i = 1; i < level; i++) {
+    const uint3 mip_dimensions = get_mip_dimensions(i);
+    offset += mip_dimensions.x * mip_dimensions.y * mip_dimensions.z;
+  }
+  const uint3 mip_dimensions = get_mip_dimensions(level);
+  const uint3 id = min(uint3(saturate(position) * float3(mip_dimensions)), mip_dimensions - 1);
+  return g_mips[offset + id.x + mip_dimensions.x * (id.y + mip_dimensions.y * id.z)] + _offset;
+}
+
 void ray_marching(float3 ray_origin, float3 ray_direction, float t_min, float t_max, float min_surface_distance, out float4 color, out float depth) {
   color = float4(0, 0, 0, 0);
   depth = 0;
@@ -11,11 +29,28 @@ void ray_marching(float3 ray_origin, float3 ray_direction, float t_min, float t_
   const float max_size = 2.0 * max(max(g_push_constants.extents[0], g_push_constants.extents[1]), g_push_constants.extents[2]);
   const float3 inv_extents = float3(1.0 / g_push_constants.extents[0], 1.0 / g_push_constants.extents[1], 1.0 / g_push_constants.extents[2]);
   const float3 voxel_size = 1.0 / float3(_dimensions);
+  uint level = _num_of_mips;
   float t = t_min;
   for (int i = 0; i < 2048; i++) {
     const float3 position = ray_origin + ray_direction * t;
     float3 uvw = position * inv_extents;
     uvw = uvw * 0.5 + 0.5; // Normalize to [0, 1] range.
+
+    // March coarse to fine, step by the coarse level until its distance is within a voxel of the level.
+    [branch]
+    if (level > 0) {
+      const float mip_distance = sample_mip(uvw, level);
+      if (mip_distance > float(1u << level) * _inv_resolution) {
+        t += mip_distance * max_size;
+        if (t > t_max) {
+          break;
+        }
+      } else {
+        level--;
+      }
+      continue;
+    }
+
     const float sampled_distance = sample_surface(uvw); // Distance is in UVW space.
 
     if (sampled_distance < min_surface_distance) {
@@ -20,5 +20,6 @@ void main(uint3 id: SV_DispatchThreadID) {
   // Even though more voxels are initialized, we want to treat as seeds only the ones
   // within one voxel of the surface. Otherwise the distance estimate is not very smooth
   // as it sees the chunky bounding boxes of bigger triangles.
-  _jump_buffer_rw[voxel_index] = abs(distance) > SQRT_3 ? 0xFFFFFFFF : voxel_index;
+  // The distance is normalized by the max size, so one voxel diagonal is SQRT_3 / _max_dimension.
+  _jump_buffer_rw[voxel_index] = abs(distance) > SQRT_3 / _max_dimension ? 0xFFFFFFFF : voxel_index;
 }
\ No newline at end of file
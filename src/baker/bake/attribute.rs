@@ -0,0 +1,141 @@
+use std::collections::HashMap;
+
+use hala_renderer::{
+  scene,
+  error::HalaRendererError,
+};
+
+use crate::baker::SDFBaker;
+use crate::volume::{
+  AttributeVolume,
+  SceneAttributes,
+  SurfaceAttributes,
+  SurfaceMaterial,
+  SurfaceTexture,
+  NO_MATERIAL,
+};
+use crate::mesh::read_gltf_vertex_colors;
+
+/// Collect the vertex attributes, the base colors of the materials and their textures from the scene.
+/// The scene loader keeps TEXCOORD_0 in the vertices but not COLOR_0, which is read from the file by SDFBaker::load_vertex_colors.
+/// The byte textures are sRGB like the glTF base color textures, the float ones are linear.
+/// param scene_in_cpu: The scene in the CPU, before it is uploaded to the GPU.
+/// return: The scene attributes.
+pub(crate) fn get_scene_attributes(scene_in_cpu: &scene::cpu::HalaScene) -> SceneAttributes {
+  let primitives = scene_in_cpu.meshes.iter()
+    .flat_map(|mesh| mesh.primitives.iter())
+    .map(|primitive| SurfaceAttributes {
+      indices: primitive.indices.clone(),
+      tex_coords: primitive.vertices.iter().map(|v| glam::Vec2::from(v.tex_coord).to_array()).collect(),
+      colors: Vec::new(),
+      material_index: if (primitive.material_index as usize) < scene_in_cpu.materials.len() { primitive.material_index } else { NO_MATERIAL },
+    })
+    .collect();
+
+  // Only the textures used as the base colors are converted, each one once.
+  let mut textures = Vec::new();
+  let mut texture_slots = HashMap::new();
+  let materials = scene_in_cpu.materials.iter()
+    .map(|material| {
+      let base_color = glam::Vec3::from(material.base_color);
+      let base_color_texture = scene_in_cpu.textures.get(material.base_color_map_index as usize)
+        .and_then(|image_index| {
+          if let Some(slot) = texture_slots.get(image_index) {
+            return Some(*slot);
+          }
+          let image = scene_in_cpu.image_data.get(*image_index as usize)?;
+          let texture = match &image.data_type {
+            scene::HalaImageDataType::ByteData(data) => SurfaceTexture::from_bytes(image.width, image.height, data, true),
+            scene::HalaImageDataType::FloatData(data) => SurfaceTexture::from_values(image.width, image.height, data, false),
+          };
+          match texture {
+            Ok(texture) => {
+              texture_slots.insert(*image_index, textures.len());
+              textures.push(texture);
+              Some(textures.len() - 1)
+            },
+            Err(e) => {
+              log::warn!("The base color image {} is skipped: {:?}", image_index, e);
+              None
+            },
+          }
+        });
+      SurfaceMaterial {
+        base_color: [base_color.x, base_color.y, base_color.z, material.opacity],
+        base_color_texture,
+      }
+    })
+    .collect();
+
+  SceneAttributes {
+    primitives,
+    materials,
+    textures,
+  }
+}
+
+impl SDFBaker {
+
+  /// Load COLOR_0 of the primitives of the glTF scene from its file, after the scene is set and before the attributes are baked.
+  /// The vertex colors are optional, so they are skipped with a warning if the file can not be read,
+  /// and the primitives whose count or vertex count differs from the loaded scene keep no vertex colors.
+  /// param path: The glTF or GLB file path of the scene.
+  pub fn load_vertex_colors<P: AsRef<std::path::Path>>(&mut self, path: P) {
+    if self.scene_attributes.primitives.is_empty() {
+      return;
+    }
+    let colors = match read_gltf_vertex_colors(path) {
+      Ok(colors) => colors,
+      Err(e) => {
+        log::warn!("Failed to read the vertex colors, they are skipped: {:?}", e);
+        return;
+      },
+    };
+    // The vertex colors can not be matched to the primitives if the loader skipped some of them.
+    if colors.len() != self.scene_attributes.primitives.len() {
+      log::warn!(
+        "The glTF file has {} primitives but the scene has {}, the vertex colors are skipped.",
+        colors.len(),
+        self.scene_attributes.primitives.len(),
+      );
+      return;
+    }
+
+    // The texture coordinates are kept for every vertex of the loaded primitive.
+    for (index, (primitive, colors)) in self.scene_attributes.primitives.iter_mut().zip(colors).enumerate() {
+      if colors.is_empty() || colors.len() == primitive.tex_coords.len() {
+        primitive.colors = colors;
+      } else {
+        log::warn!(
+          "The primitive {} has {} vertex colors for {} vertices, the vertex colors are skipped.",
+          index,
+          colors.len(),
+          primitive.tex_coords.len(),
+        );
+        primitive.colors = Vec::new();
+      }
+    }
+  }
+
+  /// Sample the vertex attributes of the scene at the closest surface point of each voxel of the last SDF bake.
+  /// return: The attribute volume.
+  pub fn get_attribute_volume(&self) -> Result<AttributeVolume, HalaRendererError> {
+    if self.scene_attributes.primitives.is_empty() {
+      return Err(HalaRendererError::new("Only the glTF scene has the vertex attributes to bake.", None));
+    }
+    let closest = self.get_closest_triangles()?;
+    self.scene_attributes.sample_volume(&closest)
+      .map_err(|e| HalaRendererError::new(&format!("Failed to sample the vertex attributes. {:?}", e), None))
+  }
+
+  /// Save the color, the texture coordinates and the material index of the voxels as the KTX2 3D textures.
+  /// param path: The file path, the channel name is appended to the file stem of each texture.
+  /// return: The result.
+  pub fn save_attributes<P: AsRef<std::path::Path>>(&self, path: P) -> Result<(), HalaRendererError> {
+    self.get_attribute_volume()?.save(path)
+      .map_err(|e| HalaRendererError::new(&format!("Failed to save the attribute files. {:?}", e), None))?;
+
+    Ok(())
+  }
+
+}
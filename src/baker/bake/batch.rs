@@ -0,0 +1,135 @@
+use std::path::Path;
+
+use hala_renderer::error::HalaRendererError;
+
+use crate::baker::SDFBaker;
+use crate::baker::settings::MergeMode;
+use crate::volume::{
+  BakeManifest,
+  BakeManifestItem,
+  sanitize_file_name,
+};
+
+/// The file name of the manifest written by bake all.
+pub const BAKE_MANIFEST_FILE_NAME: &str = "manifest.yaml";
+
+impl SDFBaker {
+
+  /// Get the items baked by bake all with the merge mode.
+  /// Each primitive is an item in the primitive mode, named after its mesh with the primitive index appended
+  /// if the mesh has more than one primitive. Each mesh is an item in the mesh mode, and the whole scene is
+  /// the only item in the scene mode.
+  /// return: The (name, selected mesh index, mesh index, primitive index) list.
+  pub fn get_bake_items(&self) -> Result<Vec<(String, i32, usize, Option<usize>)>, HalaRendererError> {
+    if self.settings.merge_mode == MergeMode::Scene {
+      return Ok(vec![("scene".to_string(), 0, 0, None)]);
+    }
+
+    let mut items = Vec::with_capacity(self.num_of_meshes);
+    let mut first = 0;
+    for (mesh_index, num_of_primitives) in self.mesh_primitive_counts.iter().copied().enumerate() {
+      let mesh_name = self.mesh_names.get(mesh_index).cloned().unwrap_or_else(|| format!("mesh_{}", mesh_index));
+      if self.settings.merge_mode == MergeMode::Mesh {
+        if num_of_primitives > 0 {
+          items.push((mesh_name, first as i32, mesh_index, None));
+        }
+      } else {
+        for primitive_index in 0..num_of_primitives {
+          let name = if num_of_primitives > 1 {
+            format!("{}_{}", mesh_name, primitive_index)
+          } else {
+            mesh_name.clone()
+          };
+          items.push((name, (first + primitive_index) as i32, mesh_index, Some(primitive_index)));
+        }
+      }
+      first += num_of_primitives;
+    }
+
+    Ok(items)
+  }
+
+  /// Bake all items one by one, save one file per item and the manifest to the directory.
+  /// The box of each item is fitted to its bounds with the current padding and resolution.
+  /// The selected mesh and its box are restored after baking.
+  /// param output_dir: The output directory.
+  /// return: The manifest.
+  pub fn bake_all<P: AsRef<Path>>(&mut self, output_dir: P) -> Result<BakeManifest, HalaRendererError> {
+    let output_dir = output_dir.as_ref();
+    std::fs::create_dir_all(output_dir)
+      .map_err(|e| HalaRendererError::new(&format!("Failed to create the output directory {:?}. {:?}", output_dir, e), None))?;
+
+    let mut manifest = BakeManifest {
+      is_sdf: self.settings.is_sdf,
+      output_format: self.settings.output_format,
+      value_encoding: self.settings.value_encoding,
+      distance_unit: self.settings.distance_unit,
+      items: Vec::new(),
+    };
+
+    let selected_mesh_index = self.settings.selected_mesh_index;
+    let result = self.bake_all_items(output_dir, &mut manifest);
+
+    self.settings.selected_mesh_index = selected_mesh_index;
+    self.fit_box_to_bounds();
+    self.snap_box_to_bounds();
+    result?;
+
+    let manifest_path = output_dir.join(BAKE_MANIFEST_FILE_NAME);
+    manifest.save(&manifest_path)
+      .map_err(|e| HalaRendererError::new(&format!("Failed to save the manifest. {:?}", e), None))?;
+
+    Ok(manifest)
+  }
+
+  /// Bake all items and add them to the manifest.
+  /// param output_dir: The output directory.
+  /// param manifest: The manifest.
+  /// return: The result.
+  fn bake_all_items(&mut self, output_dir: &Path, manifest: &mut BakeManifest) -> Result<(), HalaRendererError> {
+    let mut used_file_names = std::collections::HashSet::new();
+    for (index, (name, selected_mesh_index, mesh_index, primitive_index)) in self.get_bake_items()?.into_iter().enumerate() {
+      // Make the file name unique in case of the duplicated node names.
+      // The suffixed name may also be taken, e.g. by a node literally named "name_3", so try the next suffixes.
+      let base_name = sanitize_file_name(&name);
+      let extension = self.settings.output_format.extension();
+      let mut file_name = format!("{}.{}", base_name, extension);
+      let mut suffix = index;
+      while !used_file_names.insert(file_name.clone()) {
+        file_name = format!("{}_{}.{}", base_name, suffix, extension);
+        suffix += 1;
+      }
+
+      self.settings.selected_mesh_index = selected_mesh_index;
+      self.fit_box_to_bounds();
+      self.snap_box_to_bounds();
+
+      let now = std::time::Instant::now();
+      let volume = if self.settings.is_sdf {
+        self.bake_sdf()?;
+        self.get_sdf_volume()?
+      } else {
+        self.bake_udf()?;
+        self.get_udf_volume()?
+      };
+      let path = output_dir.join(&file_name);
+      volume.save(&path, self.settings.output_format, self.settings.value_encoding, self.settings.narrow_band)
+        .map_err(|e| HalaRendererError::new(&format!("Failed to save the file {:?}. {:?}", path, e), None))?;
+      log::info!("Bake \"{}\" to \"{}\" used {}ms.", name, path.display(), now.elapsed().as_millis());
+
+      manifest.items.push(BakeManifestItem {
+        name,
+        file: file_name,
+        mesh_index,
+        primitive_index,
+        center: volume.center,
+        extents: volume.extents,
+        dimensions: volume.dimensions,
+        voxel_size: volume.voxel_size,
+      });
+    }
+
+    Ok(())
+  }
+
+}
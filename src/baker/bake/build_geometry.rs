@@ -17,7 +17,6 @@ impl SDFBaker {
     &mut self,
     num_of_triangles: u32,
     dimensions: &[u32; 3],
-    upper_bound_count: u32,
   ) -> Result<(), HalaRendererError> {
     let triangle_uvw_buffer_size = (num_of_triangles * 3 * std::mem::size_of::<[f32; 4]>() as u32) as u64;
     if let Some(triangle_uvw_buffer) = &self.sdf_baker_resources.triangle_uvw_buffer {
@@ -92,24 +91,6 @@ impl SDFBaker {
       self.sdf_baker_resources.num_of_triangles = num_of_triangles;
     };
 
-    let triangles_in_voxels_buffer_size = (upper_bound_count * std::mem::size_of::<u32>() as u32) as u64;
-    if let Some(triangles_in_voxels_buffer) = &self.sdf_baker_resources.triangles_in_voxels_buffer {
-      if triangles_in_voxels_buffer.size != triangles_in_voxels_buffer_size {
-        self.sdf_baker_resources.triangles_in_voxels_buffer = None;
-      }
-    }
-    if self.sdf_baker_resources.triangles_in_voxels_buffer.is_none() {
-      self.sdf_baker_resources.triangles_in_voxels_buffer = Some(
-        hala_gfx::HalaBuffer::new(
-          Rc::clone(&self.resources.context.borrow().logical_device),
-          triangles_in_voxels_buffer_size,
-          hala_gfx::HalaBufferUsageFlags::STORAGE_BUFFER,
-          hala_gfx::HalaMemoryLocation::GpuOnly,
-          "triangles_in_voxels.buffer",
-        )?
-      );
-    };
-
     let (width, height) = (
       [dimensions[0], dimensions[2], dimensions[1]],
       [dimensions[1], dimensions[0], dimensions[2]],
@@ -195,6 +176,46 @@ impl SDFBaker {
     Ok(())
   }
 
+  /// Create the triangle IDs buffer with the exact count of the triangles in all voxels.
+  /// param upper_bound_count: The number of the triangle IDs.
+  /// return: The result.
+  pub(super) fn build_geometry_create_triangle_ids_buffer(
+    &mut self,
+    upper_bound_count: u32,
+  ) -> Result<(), HalaRendererError> {
+    let triangles_in_voxels_buffer_size = upper_bound_count as u64 * std::mem::size_of::<u32>() as u64;
+    if triangles_in_voxels_buffer_size > self.max_buffer_size {
+      return Err(HalaRendererError::new(
+        &format!(
+          "The triangle IDs buffer needs {} bytes for {} triangles in the voxels, more than the device limit {} bytes. Please lower the resolution or simplify the mesh.",
+          triangles_in_voxels_buffer_size,
+          upper_bound_count,
+          self.max_buffer_size,
+        ),
+        None,
+      ));
+    }
+
+    if let Some(triangles_in_voxels_buffer) = &self.sdf_baker_resources.triangles_in_voxels_buffer {
+      if triangles_in_voxels_buffer.size != triangles_in_voxels_buffer_size {
+        self.sdf_baker_resources.triangles_in_voxels_buffer = None;
+      }
+    }
+    if self.sdf_baker_resources.triangles_in_voxels_buffer.is_none() {
+      self.sdf_baker_resources.triangles_in_voxels_buffer = Some(
+        hala_gfx::HalaBuffer::new(
+          Rc::clone(&self.resources.context.borrow().logical_device),
+          triangles_in_voxels_buffer_size,
+          hala_gfx::HalaBufferUsageFlags::STORAGE_BUFFER,
+          hala_gfx::HalaMemoryLocation::GpuOnly,
+          "triangles_in_voxels.buffer",
+        )?
+      );
+    };
+
+    Ok(())
+  }
+
   #[allow(clippy::too_many_arguments)]
   pub(super) fn build_geometry_update(
     &self,
@@ -207,14 +228,11 @@ impl SDFBaker {
     vertices_buffer: &hala_gfx::HalaBuffer,
     voxels_buffer: &hala_gfx::HalaBuffer,
     counters_buffer: &hala_gfx::HalaBuffer,
-    accum_counters_buffer: &hala_gfx::HalaBuffer,
-    triangles_in_voxels_buffer: &hala_gfx::HalaBuffer,
   ) -> Result<
     (
       &hala_gfx::HalaDescriptorSet,
       &hala_gfx::HalaDescriptorSet,
       &hala_gfx::HalaDescriptorSet,
-      &hala_gfx::HalaDescriptorSet,
     ),
     HalaRendererError
   > {
@@ -222,6 +240,8 @@ impl SDFBaker {
       vertex_position_offset: 0,
       vertex_stride: std::mem::size_of::<hala_renderer::scene::HalaVertex>() as u32,
       index_stride: std::mem::size_of::<u32>() as u32,
+      padding0: 0,
+      local_to_bake: self.get_local_to_bake_matrix(),
     };
     log::debug!("Mesh uniform: {:?}", mesh_uniform);
     self.sdf_baker_resources.mesh_uniform_buffer.update_memory(0, std::slice::from_ref(&mesh_uniform))?;
@@ -330,6 +350,22 @@ impl SDFBaker {
       4,
       &[counters_buffer],
     );
+
+    Ok((
+      generate_triangles_uvw_and_dir_descriptor_set,
+      conservative_rasterization_descriptor_set,
+      write_uvw_and_coverage_descriptor_set,
+    ))
+  }
+
+  pub(super) fn build_geometry_triangle_ids_update(
+    &self,
+    coord_flip_buffer: &hala_gfx::HalaBuffer,
+    aabb_buffer: &hala_gfx::HalaBuffer,
+    vertices_buffer: &hala_gfx::HalaBuffer,
+    accum_counters_buffer: &hala_gfx::HalaBuffer,
+    triangles_in_voxels_buffer: &hala_gfx::HalaBuffer,
+  ) -> Result<&hala_gfx::HalaDescriptorSet, HalaRendererError> {
     let write_triangle_ids_to_voxels_descriptor_set = self.sdf_baker_resources.descriptor_sets.get("write_triangle_ids_to_voxels")
       .ok_or(HalaRendererError::new("Failed to get the write_triangle_ids_to_voxels descriptor set.", None))?;
     write_triangle_ids_to_voxels_descriptor_set.update_storage_buffers(
@@ -358,12 +394,7 @@ impl SDFBaker {
       &[triangles_in_voxels_buffer],
     );
 
-    Ok((
-      generate_triangles_uvw_and_dir_descriptor_set,
-      conservative_rasterization_descriptor_set,
-      write_uvw_and_coverage_descriptor_set,
-      write_triangle_ids_to_voxels_descriptor_set,
-    ))
+    Ok(write_triangle_ids_to_voxels_descriptor_set)
   }
 
   #[allow(clippy::too_many_arguments)]
@@ -30,6 +30,31 @@ impl SDFBaker {
       );
     }
 
+    // The closest triangle index and the barycentrics of the closest point of each voxel.
+    // The shader skips the write if they are not kept, so a single element is bound instead.
+    let num_of_closest_triangles = if self.settings.keep_closest_triangles {
+      dimensions.iter().map(|d| *d as u64).product::<u64>()
+    } else {
+      1
+    };
+    let closest_triangles_buffer_size = num_of_closest_triangles * 4 * std::mem::size_of::<u32>() as u64;
+    if let Some(closest_triangles_buffer) = &self.sdf_baker_resources.closest_triangles_buffer {
+      if closest_triangles_buffer.size != closest_triangles_buffer_size {
+        self.sdf_baker_resources.closest_triangles_buffer = None;
+      }
+    }
+    if self.sdf_baker_resources.closest_triangles_buffer.is_none() {
+      self.sdf_baker_resources.closest_triangles_buffer = Some(
+        hala_gfx::HalaBuffer::new(
+          Rc::clone(&self.resources.context.borrow().logical_device),
+          closest_triangles_buffer_size,
+          hala_gfx::HalaBufferUsageFlags::STORAGE_BUFFER | hala_gfx::HalaBufferUsageFlags::TRANSFER_SRC,
+          hala_gfx::HalaMemoryLocation::GpuOnly,
+          "closest_triangles.buffer",
+        )?
+      );
+    }
+
     Ok(())
   }
 
@@ -43,6 +68,7 @@ impl SDFBaker {
     voxels_texture: &hala_gfx::HalaImage,
     voxels_buffer: &hala_gfx::HalaBuffer,
     distance_texture: &hala_gfx::HalaImage,
+    closest_triangles_buffer: &hala_gfx::HalaBuffer,
   ) -> Result<&hala_gfx::HalaDescriptorSet, HalaRendererError> {
     let dtw_descriptor_set = self.sdf_baker_resources.descriptor_sets.get("distance_transform")
       .ok_or(HalaRendererError::new("Failed to get the distance_transform descriptor set.", None))?;
@@ -81,6 +107,11 @@ impl SDFBaker {
       6,
       &[distance_texture],
     );
+    dtw_descriptor_set.update_storage_buffers(
+      0,
+      7,
+      &[closest_triangles_buffer],
+    );
     Ok(dtw_descriptor_set)
   }
 
@@ -91,12 +122,14 @@ impl SDFBaker {
     voxels_texture: &hala_gfx::HalaImage,
     voxels_buffer: &hala_gfx::HalaBuffer,
     distance_texture: &hala_gfx::HalaImage,
+    closest_triangles_buffer: &hala_gfx::HalaBuffer,
     descriptor_set: &hala_gfx::HalaDescriptorSet,
     dimensions: &[u32; 3],
   ) -> Result<(), HalaRendererError> {
     // voxels_texture be going to be read by compute shaders.
     // voxels_buffer be going to be written by compute shaders.
     // distance_texture be going to be written by compute shaders.
+    // closest_triangles_buffer be going to be written by compute shaders.
     {
       command_buffers.set_image_barriers(
         0,
@@ -134,6 +167,13 @@ impl SDFBaker {
             buffer: voxels_buffer.raw,
             ..Default::default()
           },
+          hala_gfx::HalaBufferBarrierInfo {
+            src_stage_mask: hala_gfx::HalaPipelineStageFlags2::COMPUTE_SHADER,
+            dst_stage_mask: hala_gfx::HalaPipelineStageFlags2::COMPUTE_SHADER,
+            size: closest_triangles_buffer.size,
+            buffer: closest_triangles_buffer.raw,
+            ..Default::default()
+          },
         ],
       );
     }
@@ -151,9 +191,17 @@ impl SDFBaker {
         ],
       );
 
+      // The narrow band is normalized by the max dimension like the distances, 0 computes all voxels.
+      let band = if self.settings.is_narrow_band {
+        self.settings.narrow_band / self.settings.max_resolution as f32
+      } else {
+        0.0f32
+      };
       let mut push_constants = Vec::new();
       push_constants.extend_from_slice(&self.settings.in_out_threshold.to_le_bytes());
       push_constants.extend_from_slice(&self.settings.surface_offset.to_le_bytes());
+      push_constants.extend_from_slice(&band.to_le_bytes());
+      push_constants.extend_from_slice(&(self.settings.keep_closest_triangles as u32).to_le_bytes());
       program.push_constants(
         0,
         command_buffers,
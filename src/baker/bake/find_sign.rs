@@ -3,6 +3,14 @@ use std::rc::Rc;
 use hala_renderer::error::HalaRendererError;
 
 use crate::baker::SDFBaker;
+use crate::baker::settings::SignMode;
+use crate::baker::cpu::{
+  SDFCPUBaker,
+  bvh::WINDING_NUMBER_ACCURACY,
+  bvh::WINDING_NUMBER_STACK_SIZE,
+  flood_fill::FLOOD_FILL_SWEEP_ROUNDS,
+  flood_fill::FLOOD_FILL_MAX_SWEEP_ROUNDS,
+};
 
 impl SDFBaker {
 
@@ -50,9 +58,84 @@ impl SDFBaker {
       );
     }
 
+    if self.sdf_baker_resources.flood_fill_changed_buffer.is_none() {
+      self.sdf_baker_resources.flood_fill_changed_buffer = Some(
+        hala_gfx::HalaBuffer::new(
+          Rc::clone(&self.resources.context.borrow().logical_device),
+          std::mem::size_of::<u32>() as u64,
+          hala_gfx::HalaBufferUsageFlags::STORAGE_BUFFER | hala_gfx::HalaBufferUsageFlags::TRANSFER_SRC,
+          hala_gfx::HalaMemoryLocation::CpuToGpu,
+          "flood_fill_changed.buffer",
+        )?
+      );
+    }
+
+    Ok(())
+  }
+
+  /// Build the BVH of the merged triangles on the CPU and upload it for the winding number.
+  /// It is rebuilt in every bake, so the changed merge mode or bake space is always picked up.
+  /// return: The result.
+  pub(super) fn find_sign_create_winding_number_buffers(&mut self) -> Result<(), HalaRendererError> {
+    self.sdf_baker_resources.winding_number_nodes_buffer = None;
+    self.sdf_baker_resources.winding_number_triangles_buffer = None;
+
+    let mesh = self.get_merged_mesh()?;
+    if mesh.num_of_triangles() == 0 {
+      return Err(HalaRendererError::new("The merged geometry has no triangle.", None));
+    }
+    let cpu_baker = SDFCPUBaker::new(&mesh);
+    // The shader walks the BVH with a fixed stack, so a deeper BVH would skip the nodes.
+    let stack_size = cpu_baker.get_bvh().get_winding_stack_size();
+    if stack_size > WINDING_NUMBER_STACK_SIZE {
+      return Err(HalaRendererError::new(
+        &format!("The BVH of the winding number needs a stack of {} nodes, more than {}.", stack_size, WINDING_NUMBER_STACK_SIZE),
+        None,
+      ));
+    }
+    let node_bytes = cpu_baker.get_bvh().get_winding_node_bytes();
+    let triangle_bytes = cpu_baker.get_bvh().get_triangle_bytes();
+
+    let context = self.resources.context.borrow();
+    let nodes_buffer = hala_gfx::HalaBuffer::new(
+      Rc::clone(&context.logical_device),
+      node_bytes.len() as u64,
+      hala_gfx::HalaBufferUsageFlags::STORAGE_BUFFER,
+      hala_gfx::HalaMemoryLocation::CpuToGpu,
+      "winding_number_nodes.buffer",
+    )?;
+    nodes_buffer.update_memory(0, node_bytes.as_slice())?;
+    let triangles_buffer = hala_gfx::HalaBuffer::new(
+      Rc::clone(&context.logical_device),
+      triangle_bytes.len() as u64,
+      hala_gfx::HalaBufferUsageFlags::STORAGE_BUFFER,
+      hala_gfx::HalaMemoryLocation::CpuToGpu,
+      "winding_number_triangles.buffer",
+    )?;
+    triangles_buffer.update_memory(0, triangle_bytes.as_slice())?;
+
+    self.sdf_baker_resources.winding_number_nodes_buffer = Some(nodes_buffer);
+    self.sdf_baker_resources.winding_number_triangles_buffer = Some(triangles_buffer);
+
     Ok(())
   }
 
+  /// Get the sign map which holds the result of the find sign stage.
+  /// param sign_map: The sign map.
+  /// param sign_map_bis: The other sign map.
+  /// return: The final sign map.
+  pub(super) fn get_final_sign_map<'a>(
+    &self,
+    sign_map: &'a hala_gfx::HalaImage,
+    sign_map_bis: &'a hala_gfx::HalaImage,
+  ) -> &'a hala_gfx::HalaImage {
+    if self.settings.sign_mode != SignMode::RayMap || self.settings.sign_passes_count % 2 == 0 {
+      sign_map
+    } else {
+      sign_map_bis
+    }
+  }
+
   pub(super) fn find_sign_update(
     &self,
     ray_map: &hala_gfx::HalaImage,
@@ -281,4 +364,331 @@ impl SDFBaker {
     Ok(get_write_sign_map(self.settings.sign_passes_count))
   }
 
+  pub(super) fn find_sign_winding_number_update(
+    &self,
+    sign_map: &hala_gfx::HalaImage,
+  ) -> Result<&hala_gfx::HalaDescriptorSet, HalaRendererError> {
+    let nodes_buffer = self.sdf_baker_resources.winding_number_nodes_buffer.as_ref()
+      .ok_or(HalaRendererError::new("Failed to get the winding_number_nodes buffer.", None))?;
+    let triangles_buffer = self.sdf_baker_resources.winding_number_triangles_buffer.as_ref()
+      .ok_or(HalaRendererError::new("Failed to get the winding_number_triangles buffer.", None))?;
+
+    let sign_pass_winding_number_descriptor_set = self.sdf_baker_resources.descriptor_sets.get("sign_pass_winding_number")
+      .ok_or(HalaRendererError::new("Failed to get the sign_pass_winding_number descriptor set.", None))?;
+    sign_pass_winding_number_descriptor_set.update_storage_buffers(
+      0,
+      0,
+      &[nodes_buffer],
+    );
+    sign_pass_winding_number_descriptor_set.update_storage_buffers(
+      0,
+      1,
+      &[triangles_buffer],
+    );
+    sign_pass_winding_number_descriptor_set.update_storage_images(
+      0,
+      2,
+      &[sign_map],
+    );
+
+    Ok(sign_pass_winding_number_descriptor_set)
+  }
+
+  pub(super) fn find_sign_winding_number_compute<'a>(
+    &self,
+    command_buffers: &hala_gfx::HalaCommandBufferSet,
+    sign_map: &'a hala_gfx::HalaImage,
+    sign_pass_winding_number_descriptor_set: &hala_gfx::HalaDescriptorSet,
+    dimensions: &[u32; 3],
+  ) -> Result<&'a hala_gfx::HalaImage, HalaRendererError> {
+    // sign_map be going to be written by compute shaders.
+    {
+      command_buffers.set_image_barriers(
+        0,
+        &[
+          hala_gfx::HalaImageBarrierInfo {
+            old_layout: hala_gfx::HalaImageLayout::GENERAL,
+            new_layout: hala_gfx::HalaImageLayout::GENERAL,
+            src_stage_mask: hala_gfx::HalaPipelineStageFlags2::COMPUTE_SHADER,
+            dst_stage_mask: hala_gfx::HalaPipelineStageFlags2::COMPUTE_SHADER,
+            aspect_mask: hala_gfx::HalaImageAspectFlags::COLOR,
+            image: sign_map.raw,
+            ..Default::default()
+          },
+        ],
+      );
+    }
+
+    // The only pass.
+    {
+      let sign_pass_winding_number_program = self.sdf_baker_resources.compute_programs.get("sign_pass_winding_number")
+        .ok_or(HalaRendererError::new("Failed to get the sign_pass_winding_number program.", None))?;
+      sign_pass_winding_number_program.bind(
+        0,
+        command_buffers,
+        &[
+          &self.sdf_baker_resources.static_descriptor_set,
+          sign_pass_winding_number_descriptor_set,
+        ]
+      );
+      sign_pass_winding_number_program.push_constants(
+        0,
+        command_buffers,
+        0,
+        &WINDING_NUMBER_ACCURACY.to_le_bytes(),
+      );
+      sign_pass_winding_number_program.dispatch(
+        0,
+        command_buffers,
+        (dimensions[0] + 4 - 1) / 4,
+        (dimensions[1] + 4 - 1) / 4,
+        (dimensions[2] + 4 - 1) / 4,
+      );
+    }
+
+    Ok(sign_map)
+  }
+
+  pub(super) fn find_sign_flood_fill_update(
+    &self,
+    accum_counters_buffer: &hala_gfx::HalaBuffer,
+    sign_map: &hala_gfx::HalaImage,
+    sign_map_bis: &hala_gfx::HalaImage,
+  ) -> Result<
+  (
+    &hala_gfx::HalaDescriptorSet,
+    &hala_gfx::HalaDescriptorSet,
+    &hala_gfx::HalaDescriptorSet,
+  ),
+    HalaRendererError
+  > {
+    // sign_map_bis is used as the fill map.
+    let flood_fill_initialize_descriptor_set = self.sdf_baker_resources.descriptor_sets.get("flood_fill_initialize")
+      .ok_or(HalaRendererError::new("Failed to get the flood_fill_initialize descriptor set.", None))?;
+    flood_fill_initialize_descriptor_set.update_storage_buffers(
+      0,
+      0,
+      &[accum_counters_buffer],
+    );
+    flood_fill_initialize_descriptor_set.update_storage_images(
+      0,
+      1,
+      &[sign_map_bis],
+    );
+
+    let flood_fill_sweep_descriptor_set = self.sdf_baker_resources.descriptor_sets.get("flood_fill_sweep")
+      .ok_or(HalaRendererError::new("Failed to get the flood_fill_sweep descriptor set.", None))?;
+    flood_fill_sweep_descriptor_set.update_storage_images(
+      0,
+      0,
+      &[sign_map_bis],
+    );
+    let flood_fill_changed_buffer = self.sdf_baker_resources.flood_fill_changed_buffer.as_ref()
+      .ok_or(HalaRendererError::new("Failed to get the flood_fill_changed buffer.", None))?;
+    flood_fill_sweep_descriptor_set.update_storage_buffers(
+      0,
+      1,
+      &[flood_fill_changed_buffer],
+    );
+
+    let flood_fill_finalize_descriptor_set = self.sdf_baker_resources.descriptor_sets.get("flood_fill_finalize")
+      .ok_or(HalaRendererError::new("Failed to get the flood_fill_finalize descriptor set.", None))?;
+    flood_fill_finalize_descriptor_set.update_sampled_images(
+      0,
+      0,
+      &[sign_map_bis],
+    );
+    flood_fill_finalize_descriptor_set.update_storage_images(
+      0,
+      1,
+      &[sign_map],
+    );
+
+    Ok((
+      flood_fill_initialize_descriptor_set,
+      flood_fill_sweep_descriptor_set,
+      flood_fill_finalize_descriptor_set,
+    ))
+  }
+
+  #[allow(clippy::too_many_arguments)]
+  pub(super) fn find_sign_flood_fill_compute<'a>(
+    &self,
+    command_buffers: &hala_gfx::HalaCommandBufferSet,
+    sign_map: &'a hala_gfx::HalaImage,
+    sign_map_bis: &hala_gfx::HalaImage,
+    flood_fill_initialize_descriptor_set: &hala_gfx::HalaDescriptorSet,
+    flood_fill_sweep_descriptor_set: &hala_gfx::HalaDescriptorSet,
+    flood_fill_finalize_descriptor_set: &hala_gfx::HalaDescriptorSet,
+    dimensions: &[u32; 3],
+  ) -> Result<&'a hala_gfx::HalaImage, HalaRendererError> {
+    // sign_map_bis be going to be written by compute shaders.
+    {
+      command_buffers.set_image_barriers(
+        0,
+        &[
+          hala_gfx::HalaImageBarrierInfo {
+            old_layout: hala_gfx::HalaImageLayout::GENERAL,
+            new_layout: hala_gfx::HalaImageLayout::GENERAL,
+            src_stage_mask: hala_gfx::HalaPipelineStageFlags2::COMPUTE_SHADER,
+            dst_stage_mask: hala_gfx::HalaPipelineStageFlags2::COMPUTE_SHADER,
+            aspect_mask: hala_gfx::HalaImageAspectFlags::COLOR,
+            image: sign_map_bis.raw,
+            ..Default::default()
+          },
+        ],
+      );
+    }
+
+    // Mark the walls and the open border voxels.
+    {
+      let flood_fill_initialize_program = self.sdf_baker_resources.compute_programs.get("flood_fill_initialize")
+        .ok_or(HalaRendererError::new("Failed to get the flood_fill_initialize program.", None))?;
+      flood_fill_initialize_program.bind(
+        0,
+        command_buffers,
+        &[
+          &self.sdf_baker_resources.static_descriptor_set,
+          flood_fill_initialize_descriptor_set,
+        ]
+      );
+      flood_fill_initialize_program.push_constants(
+        0,
+        command_buffers,
+        0,
+        &self.settings.fill_dilation.to_le_bytes(),
+      );
+      flood_fill_initialize_program.dispatch(
+        0,
+        command_buffers,
+        (dimensions[0] + 4 - 1) / 4,
+        (dimensions[1] + 4 - 1) / 4,
+        (dimensions[2] + 4 - 1) / 4,
+      );
+    }
+
+    let fill_map_barrier = || hala_gfx::HalaImageBarrierInfo {
+      old_layout: hala_gfx::HalaImageLayout::GENERAL,
+      new_layout: hala_gfx::HalaImageLayout::GENERAL,
+      src_stage_mask: hala_gfx::HalaPipelineStageFlags2::COMPUTE_SHADER,
+      src_access_mask: hala_gfx::HalaAccessFlags2::SHADER_WRITE,
+      dst_stage_mask: hala_gfx::HalaPipelineStageFlags2::COMPUTE_SHADER,
+      dst_access_mask: hala_gfx::HalaAccessFlags2::SHADER_READ | hala_gfx::HalaAccessFlags2::SHADER_WRITE,
+      aspect_mask: hala_gfx::HalaImageAspectFlags::COLOR,
+      image: sign_map_bis.raw,
+      ..Default::default()
+    };
+
+    // Sweep the exterior along all axes both ways, in batches until a batch reaches no voxel.
+    // Each batch is submitted and its changed flag read back, then the recording goes on for the rest of the bake.
+    {
+      let flood_fill_sweep_program = self.sdf_baker_resources.compute_programs.get("flood_fill_sweep")
+        .ok_or(HalaRendererError::new("Failed to get the flood_fill_sweep program.", None))?;
+      let flood_fill_changed_buffer = self.sdf_baker_resources.flood_fill_changed_buffer.as_ref()
+        .ok_or(HalaRendererError::new("Failed to get the flood_fill_changed buffer.", None))?;
+
+      let mut num_of_rounds = 0;
+      loop {
+        flood_fill_changed_buffer.update_memory(0, &[0u32])?;
+        for _ in 0..FLOOD_FILL_SWEEP_ROUNDS {
+          for axis in 0..3u32 {
+            command_buffers.set_image_barriers(0, &[fill_map_barrier()]);
+
+            flood_fill_sweep_program.bind(
+              0,
+              command_buffers,
+              &[
+                &self.sdf_baker_resources.static_descriptor_set,
+                flood_fill_sweep_descriptor_set,
+              ]
+            );
+
+            let mut push_constants = Vec::new();
+            push_constants.extend_from_slice(&axis.to_le_bytes());
+            push_constants.extend_from_slice(&(self.settings.fill_connectivity as u32).to_le_bytes());
+            flood_fill_sweep_program.push_constants(
+              0,
+              command_buffers,
+              0,
+              &push_constants,
+            );
+
+            let u_axis = ((axis + 1) % 3) as usize;
+            let v_axis = ((axis + 2) % 3) as usize;
+            flood_fill_sweep_program.dispatch(
+              0,
+              command_buffers,
+              (dimensions[u_axis] + 8 - 1) / 8,
+              (dimensions[v_axis] + 8 - 1) / 8,
+              1,
+            );
+          }
+        }
+        num_of_rounds += FLOOD_FILL_SWEEP_ROUNDS;
+
+        command_buffers.end(0)?;
+        {
+          let context = self.resources.context.borrow();
+          let logical_device = context.logical_device.borrow();
+
+          logical_device.graphics_submit(command_buffers, 0, 0)?;
+          logical_device.graphics_wait(0)?;
+        }
+        let is_changed = self.debug_get_buffer_data::<u32>(flood_fill_changed_buffer)?
+          .first()
+          .is_some_and(|changed| *changed != 0);
+        command_buffers.reset(0, false)?;
+        command_buffers.begin(0, hala_gfx::HalaCommandBufferUsageFlags::ONE_TIME_SUBMIT)?;
+
+        if !is_changed {
+          break;
+        }
+        if num_of_rounds >= FLOOD_FILL_MAX_SWEEP_ROUNDS {
+          log::warn!("The flood fill still changes after {} sweep rounds, the voxels not reached are inside.", num_of_rounds);
+          break;
+        }
+      }
+      log::debug!("The flood fill converges in {} sweep rounds.", num_of_rounds);
+    }
+
+    // Write the voxels not reached to the sign map as inside.
+    {
+      command_buffers.set_image_barriers(
+        0,
+        &[
+          fill_map_barrier(),
+          hala_gfx::HalaImageBarrierInfo {
+            old_layout: hala_gfx::HalaImageLayout::GENERAL,
+            new_layout: hala_gfx::HalaImageLayout::GENERAL,
+            src_stage_mask: hala_gfx::HalaPipelineStageFlags2::COMPUTE_SHADER,
+            dst_stage_mask: hala_gfx::HalaPipelineStageFlags2::COMPUTE_SHADER,
+            aspect_mask: hala_gfx::HalaImageAspectFlags::COLOR,
+            image: sign_map.raw,
+            ..Default::default()
+          },
+        ],
+      );
+
+      let flood_fill_finalize_program = self.sdf_baker_resources.compute_programs.get("flood_fill_finalize")
+        .ok_or(HalaRendererError::new("Failed to get the flood_fill_finalize program.", None))?;
+      flood_fill_finalize_program.bind(
+        0,
+        command_buffers,
+        &[
+          &self.sdf_baker_resources.static_descriptor_set,
+          flood_fill_finalize_descriptor_set,
+        ]
+      );
+      flood_fill_finalize_program.dispatch(
+        0,
+        command_buffers,
+        (dimensions[0] + 4 - 1) / 4,
+        (dimensions[1] + 4 - 1) / 4,
+        (dimensions[2] + 4 - 1) / 4,
+      );
+    }
+
+    Ok(sign_map)
+  }
+
 }
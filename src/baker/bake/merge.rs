@@ -0,0 +1,175 @@
+use std::rc::Rc;
+
+use hala_renderer::{
+  scene,
+  error::HalaRendererError,
+};
+
+use crate::baker::SDFBaker;
+use crate::baker::settings::{
+  MergeMode,
+  BakeSpace,
+};
+use crate::mesh::TriangleMesh;
+
+/// The merged geometry of several primitives.
+pub struct SDFBakerMergedGeometry {
+  pub merge_mode: MergeMode,
+  pub bake_space: BakeSpace,
+  pub selected_mesh_index: i32,
+  pub num_of_triangles: u32,
+  pub index_buffer: hala_gfx::HalaBuffer,
+  pub vertex_buffer: hala_gfx::HalaBuffer,
+}
+
+impl SDFBaker {
+
+  /// Get the (mesh index, primitive index) of the primitive.
+  /// param index: The primitive index in the scene.
+  /// return: The mesh index and the primitive index in the mesh.
+  pub fn get_primitive_location(&self, index: i32) -> Result<(usize, usize), HalaRendererError> {
+    let mut first = 0;
+    for (mesh_index, num_of_primitives) in self.mesh_primitive_counts.iter().enumerate() {
+      if (index as usize) < first + num_of_primitives {
+        return Ok((mesh_index, index as usize - first));
+      }
+      first += num_of_primitives;
+    }
+
+    Err(HalaRendererError::new(&format!("The primitive index {} is out of range.", index), None))
+  }
+
+  /// Get the primitives merged with the selected one by the merge mode.
+  /// return: The primitive indices in the scene.
+  pub fn get_merged_primitive_indices(&self) -> Result<Vec<usize>, HalaRendererError> {
+    match self.settings.merge_mode {
+      MergeMode::Primitive => Ok(vec![self.settings.selected_mesh_index as usize]),
+      MergeMode::Mesh => {
+        let (mesh_index, primitive_index) = self.get_primitive_location(self.settings.selected_mesh_index)?;
+        let first = self.settings.selected_mesh_index as usize - primitive_index;
+        Ok((first..first + self.mesh_primitive_counts[mesh_index]).collect())
+      },
+      MergeMode::Scene => Ok((0..self.num_of_meshes).collect()),
+    }
+  }
+
+  /// Whether the baked geometry is in world space.
+  /// The merged scene is always in world space.
+  /// return: True if in world space.
+  pub fn is_bake_in_world_space(&self) -> bool {
+    self.settings.merge_mode == MergeMode::Scene || self.settings.bake_space == BakeSpace::World
+  }
+
+  /// Get the model matrix of the baked geometry.
+  /// The geometry in world space has no model matrix, others are in the space of the selected mesh.
+  /// return: The model matrix.
+  pub fn get_bake_model_matrix(&self) -> glam::Mat4 {
+    if self.is_bake_in_world_space() {
+      glam::Mat4::IDENTITY
+    } else {
+      *self.get_model_matrix_in_scene(self.settings.selected_mesh_index)
+    }
+  }
+
+  /// Get the matrix applied to the vertices by the bake stages.
+  /// The merged geometry is already in the bake space, the selected primitive is transformed to world space if needed.
+  /// return: The local to bake space matrix.
+  pub(super) fn get_local_to_bake_matrix(&self) -> glam::Mat4 {
+    if self.merged_geometry.is_none() && self.is_bake_in_world_space() {
+      *self.get_model_matrix_in_scene(self.settings.selected_mesh_index)
+    } else {
+      glam::Mat4::IDENTITY
+    }
+  }
+
+  /// Get the merged triangles on the CPU.
+  /// Each primitive is transformed by its own node transform in world space.
+  /// return: The triangle mesh in the space of the baked geometry.
+  pub fn get_merged_mesh(&self) -> Result<TriangleMesh, HalaRendererError> {
+    let mut merged = TriangleMesh::default();
+    for index in self.get_merged_primitive_indices()? {
+      let mesh = self.primitive_meshes.get(index)
+        .ok_or(HalaRendererError::new(&format!("Failed to get the triangles of the primitive {}.", index), None))?;
+      if self.is_bake_in_world_space() {
+        merged.append(&mesh.transformed(self.get_model_matrix_in_scene(index as i32)));
+      } else {
+        merged.append(mesh);
+      }
+    }
+    Ok(merged)
+  }
+
+  /// Get the bounds of the merged geometry.
+  /// return: The bounds.
+  pub(super) fn get_merged_bounds(&self) -> Result<scene::HalaBounds, HalaRendererError> {
+    let (min, max) = self.get_merged_mesh()?.bounds()
+      .ok_or(HalaRendererError::new("The merged geometry has no triangle.", None))?;
+    Ok(scene::HalaBounds {
+      center: [(min[0] + max[0]) / 2.0, (min[1] + max[1]) / 2.0, (min[2] + max[2]) / 2.0],
+      extents: [(max[0] - min[0]) / 2.0, (max[1] - min[1]) / 2.0, (max[2] - min[2]) / 2.0],
+    })
+  }
+
+  /// Merge the primitives into one triangle soup on the GPU if the merge mode is not primitive.
+  /// The merged buffers have the same layout as the scene buffers, so the bake stages read them as one primitive.
+  /// The mesh loaded from the mesh file has no scene in the GPU, so it is always uploaded as the merged geometry.
+  /// The point cloud has no triangle to merge.
+  /// return: The result.
+  pub(super) fn update_merged_geometry(&mut self) -> Result<(), HalaRendererError> {
+    if (self.settings.merge_mode == MergeMode::Primitive && self.scene_in_gpu.is_some()) || self.point_cloud.is_some() {
+      self.merged_geometry = None;
+      return Ok(());
+    }
+    if let Some(merged_geometry) = &self.merged_geometry {
+      if merged_geometry.merge_mode == self.settings.merge_mode
+        && merged_geometry.bake_space == self.settings.bake_space
+        && merged_geometry.selected_mesh_index == self.settings.selected_mesh_index {
+        return Ok(());
+      }
+    }
+    self.merged_geometry = None;
+
+    let mesh = self.get_merged_mesh()?;
+    if mesh.num_of_triangles() == 0 {
+      return Err(HalaRendererError::new("The merged geometry has no triangle.", None));
+    }
+    let vertex_bytes = mesh.get_vertex_bytes(std::mem::size_of::<hala_renderer::scene::HalaVertex>());
+
+    let context = self.resources.context.borrow();
+    let index_buffer = hala_gfx::HalaBuffer::new(
+      Rc::clone(&context.logical_device),
+      std::mem::size_of_val(mesh.indices.as_slice()) as u64,
+      hala_gfx::HalaBufferUsageFlags::STORAGE_BUFFER,
+      hala_gfx::HalaMemoryLocation::CpuToGpu,
+      "merged_index.buffer",
+    )?;
+    index_buffer.update_memory(0, mesh.indices.as_slice())?;
+    let vertex_buffer = hala_gfx::HalaBuffer::new(
+      Rc::clone(&context.logical_device),
+      vertex_bytes.len() as u64,
+      hala_gfx::HalaBufferUsageFlags::STORAGE_BUFFER,
+      hala_gfx::HalaMemoryLocation::CpuToGpu,
+      "merged_vertex.buffer",
+    )?;
+    vertex_buffer.update_memory(0, vertex_bytes.as_slice())?;
+    drop(context);
+
+    log::debug!(
+      "Merge {} triangles of the {} mode with the selected mesh {}.",
+      mesh.num_of_triangles(),
+      self.settings.merge_mode.name(),
+      self.settings.selected_mesh_index,
+    );
+    self.merged_geometry = Some(SDFBakerMergedGeometry {
+      merge_mode: self.settings.merge_mode,
+      bake_space: self.settings.bake_space,
+      selected_mesh_index: self.settings.selected_mesh_index,
+      num_of_triangles: mesh.num_of_triangles() as u32,
+      index_buffer,
+      vertex_buffer,
+    });
+
+    Ok(())
+  }
+
+}
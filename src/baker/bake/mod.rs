@@ -1,3 +1,5 @@
+use std::rc::Rc;
+
 use glam::Vec4Swizzles;
 
 use hala_renderer::{
@@ -7,13 +9,38 @@ use hala_renderer::{
 };
 
 use crate::baker::{
-  Axis,
   SDFBaker,
   SDFBakerResources,
+  UDFBakerResources,
 };
 
+use crate::baker::settings::{
+  MergeMode,
+  SignMode,
+  GradientMode,
+  BakeBackend,
+};
+use crate::baker::cpu::SDFCPUBaker;
 use crate::baker::sdf_resources::SDFBakerCSGlobalUniform;
 use crate::baker::udf_resources::UDFBakerCSGlobalUniform;
+use crate::volume::{
+  SDFVolume,
+  DistanceUnit,
+  VolumeBrick,
+  split_into_bricks,
+  bound_far_distances,
+  build_mip_chain,
+  central_difference_gradients,
+  ClosestTriangleVolume,
+  TriangleRange,
+  VolumeDiff,
+  diff_volumes,
+};
+use crate::mesh::{
+  TriangleMesh,
+  MeshFormat,
+  extract_isosurface,
+};
 
 pub mod sdf_initialize;
 pub mod build_geometry;
@@ -24,7 +51,11 @@ pub mod surface_closing;
 pub mod distance_transform;
 pub mod udf_initialize;
 pub mod splat_triangle_distance;
+pub mod splat_point_distance;
 pub mod udf_jump_flooding;
+pub mod batch;
+pub mod merge;
+pub mod attribute;
 
 impl SDFBaker {
 
@@ -68,97 +99,18 @@ impl SDFBaker {
   /// Fit the desired box to the bounds.
   pub fn fit_box_to_bounds(&mut self) {
     let bounds = self.get_selected_mesh_bounds().unwrap();
-
-    let max_size = bounds.get_size().iter().fold(0.0, |a: f32, b| a.max(*b));
-    let voxel_size = max_size / self.settings.max_resolution as f32;
-    let padding = [
-      self.settings.padding[0] * voxel_size,
-      self.settings.padding[1] * voxel_size,
-      self.settings.padding[2] * voxel_size,
-    ];
-
-    let center = [
-      bounds.center[0],
-      bounds.center[1],
-      bounds.center[2]
-    ];
-    let size = [
-      (bounds.extents[0] + padding[0]) * 2.0,
-      (bounds.extents[1] + padding[1]) * 2.0,
-      (bounds.extents[2] + padding[2]) * 2.0
-    ];
-    self.settings.center = center;
-    self.settings.desired_size = size;
+    self.settings.fit_box_to_bounds(&bounds);
   }
 
   /// Snap the actual box to the bounds.
   pub fn snap_box_to_bounds(&mut self) {
-    let max_size = self.settings.desired_size.iter().fold(0.0, |a: f32, b| a.max(*b));
-    let ref_axis = if max_size == self.settings.desired_size[0] {
-      Axis::X
-    } else if max_size == self.settings.desired_size[1] {
-      Axis::Y
-    } else {
-      Axis::Z
-    };
-
-    self.settings.actual_size = match ref_axis {
-      Axis::X => {
-        let dim_x = (self.settings.max_resolution as f32 * self.settings.desired_size[0] / max_size).round().max(1.0);
-        let dim_y = (self.settings.max_resolution as f32 * self.settings.desired_size[1] / max_size).ceil().max(1.0);
-        let dim_z = (self.settings.max_resolution as f32 * self.settings.desired_size[2] / max_size).ceil().max(1.0);
-        let voxel_size = max_size / dim_x;
-        [dim_x * voxel_size, dim_y * voxel_size, dim_z * voxel_size]
-      },
-      Axis::Y => {
-        let dim_x = (self.settings.max_resolution as f32 * self.settings.desired_size[0] / max_size).ceil().max(1.0);
-        let dim_y = (self.settings.max_resolution as f32 * self.settings.desired_size[1] / max_size).round().max(1.0);
-        let dim_z = (self.settings.max_resolution as f32 * self.settings.desired_size[2] / max_size).ceil().max(1.0);
-        let voxel_size = max_size / dim_y;
-        [dim_x * voxel_size, dim_y * voxel_size, dim_z * voxel_size]
-      },
-      Axis::Z => {
-        let dim_x = (self.settings.max_resolution as f32 * self.settings.desired_size[0] / max_size).ceil().max(1.0);
-        let dim_y = (self.settings.max_resolution as f32 * self.settings.desired_size[1] / max_size).ceil().max(1.0);
-        let dim_z = (self.settings.max_resolution as f32 * self.settings.desired_size[2] / max_size).round().max(1.0);
-        let voxel_size = max_size / dim_z;
-        [dim_x * voxel_size, dim_y * voxel_size, dim_z * voxel_size]
-      },
-    }
+    self.settings.snap_box_to_bounds();
   }
 
   /// Estimate the grid size.
   /// return: The grid size.
   pub fn estimate_grid_size(&self) -> [u32; 3] {
-    let max_extent = self.settings.desired_size.iter().fold(0.0, |a: f32, b| a.max(*b));
-    let ref_axis = if max_extent == self.settings.desired_size[0] {
-      Axis::X
-    } else if max_extent == self.settings.desired_size[1] {
-      Axis::Y
-    } else {
-      Axis::Z
-    };
-
-    match ref_axis {
-      Axis::X => {
-        let dim_x = (self.settings.max_resolution as f32 * self.settings.desired_size[0] / max_extent).round().max(1.0);
-        let dim_y = (self.settings.max_resolution as f32 * self.settings.desired_size[1] / max_extent).ceil().max(1.0);
-        let dim_z = (self.settings.max_resolution as f32 * self.settings.desired_size[2] / max_extent).ceil().max(1.0);
-        [dim_x as u32, dim_y as u32, dim_z as u32]
-      },
-      Axis::Y => {
-        let dim_x = (self.settings.max_resolution as f32 * self.settings.desired_size[0] / max_extent).ceil().max(1.0);
-        let dim_y = (self.settings.max_resolution as f32 * self.settings.desired_size[1] / max_extent).round().max(1.0);
-        let dim_z = (self.settings.max_resolution as f32 * self.settings.desired_size[2] / max_extent).ceil().max(1.0);
-        [dim_x as u32, dim_y as u32, dim_z as u32]
-      },
-      Axis::Z => {
-        let dim_x = (self.settings.max_resolution as f32 * self.settings.desired_size[0] / max_extent).ceil().max(1.0);
-        let dim_y = (self.settings.max_resolution as f32 * self.settings.desired_size[1] / max_extent).ceil().max(1.0);
-        let dim_z = (self.settings.max_resolution as f32 * self.settings.desired_size[2] / max_extent).round().max(1.0);
-        [dim_x as u32, dim_y as u32, dim_z as u32]
-      },
-    }
+    self.settings.estimate_grid_size()
   }
 
   /// Get the selected mesh primitive.
@@ -180,9 +132,23 @@ impl SDFBaker {
     Err(HalaRendererError::new("Failed to get the mesh buffers.", None))
   }
 
-  /// Get selected mesh's index and vertex buffer.
+  /// Get the number of triangles to bake, which is the merged one if the primitives are merged.
+  /// return: The number of triangles.
+  fn get_selected_num_of_triangles(&self) -> Result<u32, HalaRendererError> {
+    if let Some(merged_geometry) = &self.merged_geometry {
+      return Ok(merged_geometry.num_of_triangles);
+    }
+
+    Ok(self.get_selected_mesh_primitive()?.index_count / 3)
+  }
+
+  /// Get selected mesh's index and vertex buffer, which are the merged ones if the primitives are merged.
   /// return: The selected mesh's index and vertex buffer.
   fn get_selected_mesh_buffers(&self) -> Result<(&hala_gfx::HalaBuffer, &hala_gfx::HalaBuffer), HalaRendererError> {
+    if let Some(merged_geometry) = &self.merged_geometry {
+      return Ok((&merged_geometry.index_buffer, &merged_geometry.vertex_buffer));
+    }
+
     let mut index = 0;
     let scene_in_gpu = self.scene_in_gpu.as_ref()
       .ok_or(HalaRendererError::new("Failed to get the scene in the GPU.", None))?;
@@ -199,9 +165,25 @@ impl SDFBaker {
     Err(HalaRendererError::new("Failed to get the mesh buffers.", None))
   }
 
-  /// Get the bounds of the selected mesh.
+  /// Get the bounds of the selected mesh, which are the union bounds if the primitives are merged.
+  /// The bounds are recomputed from the transformed vertices in world space.
+  /// The mesh loaded from the mesh file has no scene in the GPU, its bounds are computed from the triangles.
+  /// The bounds of the point cloud are expanded by the point radius.
   /// return: The bounds of the selected mesh.
-  fn get_selected_mesh_bounds(&self) -> Result<&scene::HalaBounds, HalaRendererError> {
+  fn get_selected_mesh_bounds(&self) -> Result<scene::HalaBounds, HalaRendererError> {
+    if let Some(points) = &self.point_cloud {
+      let (min, max) = points.bounds()
+        .ok_or(HalaRendererError::new("The point cloud has no point.", None))?;
+      let radius = self.settings.point_radius;
+      return Ok(scene::HalaBounds {
+        center: [(min[0] + max[0]) / 2.0, (min[1] + max[1]) / 2.0, (min[2] + max[2]) / 2.0],
+        extents: [(max[0] - min[0]) / 2.0 + radius, (max[1] - min[1]) / 2.0 + radius, (max[2] - min[2]) / 2.0 + radius],
+      });
+    }
+    if self.settings.merge_mode != MergeMode::Primitive || self.is_bake_in_world_space() || self.scene_in_gpu.is_none() {
+      return self.get_merged_bounds();
+    }
+
     let mut index = 0;
     let scene_in_gpu = self.scene_in_gpu.as_ref()
       .ok_or(HalaRendererError::new("Failed to get the scene in the GPU.", None))?;
@@ -209,7 +191,10 @@ impl SDFBaker {
     for mesh in scene_in_gpu.meshes.iter() {
       for prim in mesh.primitives.iter() {
         if index == self.settings.selected_mesh_index {
-          return Ok(&prim.bounds);
+          return Ok(scene::HalaBounds {
+            center: prim.bounds.center,
+            extents: prim.bounds.extents,
+          });
         }
         index += 1;
       }
@@ -259,22 +244,24 @@ impl SDFBaker {
   }
 
   /// Create all buffers and images for the baker.
+  /// The triangle IDs buffer is created after the triangles in the voxels are counted.
   /// param num_of_triangles: The number of triangles.
   /// param dimensions: The dimensions of the voxels.
-  /// param upper_bound_count: The upper bound count.
   /// return: The result.
   fn create_sdf_buffers_images(
     &mut self,
     num_of_triangles: u32,
     dimensions: &[u32; 3],
-    upper_bound_count: u32,
   ) -> Result<(), HalaRendererError> {
     let num_of_voxels = dimensions[0] * dimensions[1] * dimensions[2];
 
-    self.build_geometry_create_buffers_images(num_of_triangles, dimensions, upper_bound_count)?;
+    self.build_geometry_create_buffers_images(num_of_triangles, dimensions)?;
     self.prefix_sum_create_buffers_images(num_of_voxels)?;
     self.ray_map_create_buffers_images(dimensions)?;
     self.find_sign_create_buffers_images(dimensions)?;
+    if self.settings.sign_mode == SignMode::WindingNumber {
+      self.find_sign_create_winding_number_buffers()?;
+    }
     self.surface_closing_create_buffers_images(dimensions)?;
     self.distance_transform_create_buffers_images(dimensions)?;
 
@@ -289,27 +276,52 @@ impl SDFBaker {
     dispatch_size
   }
 
-  /// Bake the SDF.
-  pub fn bake_sdf(&mut self) -> Result<(), HalaRendererError> {
-    let primitive = self.get_selected_mesh_primitive()?;
-
-    // Setup.
-    let num_of_triangles = primitive.index_count / 3;
-    let dimensions = self.estimate_grid_size();
-    let num_of_voxels = dimensions[0] * dimensions[1] * dimensions[2];
+  /// Update the global uniform buffer of the SDF baker.
+  /// The max size and the max dimension are always of the whole grid, so a brick has the same voxel size.
+  /// param dimensions: The dimensions of the voxels.
+  /// param bounds: The bounds of the voxels.
+  /// param upper_bound_count: The size of the triangle IDs buffer.
+  /// param num_of_triangles: The number of triangles.
+  /// return: The result.
+  fn update_sdf_global_uniform(
+    &self,
+    dimensions: &[u32; 3],
+    bounds: &scene::HalaBounds,
+    upper_bound_count: u32,
+    num_of_triangles: u32,
+  ) -> Result<(), HalaRendererError> {
     let max_size = self.settings.actual_size.iter().fold(0.0, |a: f32, b| a.max(*b));
-    // Triangle ID buffer max size.
-    // Assume only half of the voxels have triangles.
-    let num_of_voxels_has_triangles = dimensions[0] as f64 * dimensions[1] as f64 * dimensions[2] as f64 / 2.0f64;
-    // Assume one triangle is shared by 8 voxels. Assume the number of triangles in a voxel is sqrt(_numOfTriangles).
-    let avg_triangles_per_voxel = (num_of_triangles as f64 / num_of_voxels_has_triangles * 8.0f64).max((num_of_triangles as f64).sqrt());
-    let upper_bound_count64 = (num_of_voxels_has_triangles * avg_triangles_per_voxel) as u64;
-    let upper_bound_count = (1536 * (1 << 18)).min(upper_bound_count64) as u32; // Limit the buffer size to 1536 * 2^18.
-    let upper_bound_count = upper_bound_count.max(1024); // At least 1024 triangle.
-    let num_of_jfa_passes = self.settings.max_resolution.ilog2();
+    let global_uniform = SDFBakerCSGlobalUniform {
+      dimensions: *dimensions,
+      max_dimension: self.settings.max_resolution as u32,
+      upper_bound_count,
+      num_of_triangles,
+      max_size,
+      padding0: 0.0,
+      center: bounds.center,
+      padding1: 0.0,
+      extents: bounds.extents,
+    };
+    log::debug!("Global uniform: {:?}", global_uniform);
+    self.sdf_baker_resources.global_uniform_buffer.update_memory(0, std::slice::from_ref(&global_uniform))?;
 
-    // Create buffers and images.
-    self.create_sdf_buffers_images(num_of_triangles, &dimensions, upper_bound_count)?;
+    Ok(())
+  }
+
+  /// Count the triangles in all voxels.
+  /// It runs the initialize, build geometry, first draw and prefix sum passes,
+  /// and reads back the total from the exclusive prefix sum of the counters.
+  /// param num_of_triangles: The number of triangles.
+  /// param dimensions: The dimensions of the voxels.
+  /// param bounds: The bounds of the voxels.
+  /// return: The number of triangles in all voxels.
+  fn count_triangles_in_voxels(
+    &self,
+    num_of_triangles: u32,
+    dimensions: &[u32; 3],
+    bounds: &scene::HalaBounds,
+  ) -> Result<u32, HalaRendererError> {
+    let num_of_voxels = dimensions[0] * dimensions[1] * dimensions[2];
     let triangle_uvw_buffer = self.sdf_baker_resources.triangle_uvw_buffer.as_ref()
       .ok_or(HalaRendererError::new("Failed to get the triangle_uvw buffer.", None))?;
     let coord_flip_buffer = self.sdf_baker_resources.coord_flip_buffer.as_ref()
@@ -334,8 +346,6 @@ impl SDFBaker {
       .ok_or(HalaRendererError::new("Failed to get the tmp buffer.", None))?;
     let additional_sum_blocks_buffer = self.sdf_baker_resources.additional_sum_blocks_buffer.as_ref()
       .ok_or(HalaRendererError::new("Failed to get the additional_sum_blocks buffer.", None))?;
-    let triangles_in_voxels_buffer = self.sdf_baker_resources.triangles_in_voxels_buffer.as_ref()
-      .ok_or(HalaRendererError::new("Failed to get the triangles_in_voxels buffer.", None))?;
     let ray_map = self.sdf_baker_resources.ray_map.as_ref()
       .ok_or(HalaRendererError::new("Failed to get the ray_map.", None))?;
     let sign_map = self.sdf_baker_resources.sign_map.as_ref()
@@ -346,8 +356,6 @@ impl SDFBaker {
       .ok_or(HalaRendererError::new("Failed to get the voxels_texture.", None))?;
     let voxels_texture_bis = self.sdf_baker_resources.voxels_texture_bis.as_ref()
       .ok_or(HalaRendererError::new("Failed to get the voxels_texture_bis.", None))?;
-    let distance_texture = self.sdf_baker_resources.distance_texture.as_ref()
-      .ok_or(HalaRendererError::new("Failed to get the distance_texture.", None))?;
     let render_targets = [
       self.sdf_baker_resources.render_targets[0].as_ref()
         .ok_or(HalaRendererError::new("Failed to get the render_target 0.", None))?,
@@ -356,52 +364,12 @@ impl SDFBaker {
       self.sdf_baker_resources.render_targets[2].as_ref()
         .ok_or(HalaRendererError::new("Failed to get the render_target 2.", None))?,
     ];
-    let bounds = self.settings.get_bounds();
     let (index_buffer, vertex_buffer) = self.get_selected_mesh_buffers()?;
 
-    // Update uniform buffers.
-    let global_uniform = SDFBakerCSGlobalUniform {
-      dimensions,
-      max_dimension: self.settings.max_resolution as u32,
-      upper_bound_count,
-      num_of_triangles,
-      max_size,
-      padding0: 0.0,
-      center: bounds.center,
-      padding1: 0.0,
-      extents: bounds.extents,
-    };
-    log::debug!("Global uniform: {:?}", global_uniform);
-    self.sdf_baker_resources.global_uniform_buffer.update_memory(0, std::slice::from_ref(&global_uniform))?;
+    // The triangle IDs buffer is not used yet.
+    self.update_sdf_global_uniform(dimensions, bounds, 0, num_of_triangles)?;
 
     // Update the descriptor sets.
-    for (index, descriptor_set) in self.sdf_baker_resources.image_2_screen_descriptor_sets.iter().enumerate() {
-      descriptor_set.update_combined_image_samplers(
-        0,
-        0,
-        &[(render_targets[index], self.sdf_baker_resources.image_2_screen_sampler.as_ref())],
-      );
-    }
-    self.cross_xyz_descriptor_set.update_combined_image_samplers(
-      0,
-      0,
-      &[
-        (ray_map, self.image3d_sampler.as_ref())
-      ],
-    );
-    self.sdf_visualization_descriptor_set.update_uniform_buffers(
-      0,
-      0,
-      &[self.sdf_visualization_uniform_buffer.as_ref()],
-    );
-    self.sdf_visualization_descriptor_set.update_combined_image_samplers(
-      0,
-      1,
-      &[
-        (distance_texture, self.image3d_sampler.as_ref())
-      ],
-    );
-
     let initialize_descriptor_set = self.sdf_initialize_update(
       voxels_buffer,
       counters_buffer,
@@ -416,9 +384,8 @@ impl SDFBaker {
       generate_triangles_uvw_and_dir_descriptor_set,
       conservative_rasterization_descriptor_set,
       write_uvw_and_coverage_descriptor_set,
-      write_triangle_ids_to_voxels_descriptor_set,
     ) = self.build_geometry_update(
-      &bounds,
+      bounds,
       index_buffer,
       vertex_buffer,
       triangle_uvw_buffer,
@@ -427,8 +394,6 @@ impl SDFBaker {
       vertices_buffer,
       voxels_buffer,
       counters_buffer,
-      accum_counters_buffer,
-      triangles_in_voxels_buffer,
     )?;
     let (
       in_bucket_sum_descriptor_set,
@@ -447,46 +412,6 @@ impl SDFBaker {
       accum_sum_blocks_buffer,
       accum_counters_buffer,
     )?;
-    let (
-      generate_ray_map_local2x2_descriptor_set,
-      ray_map_sum_x_descriptor_set,
-      ray_map_sum_y_descriptor_set,
-      ray_map_sum_z_descriptor_set,
-    ) = self.ray_map_update(
-      accum_counters_buffer,
-      triangles_in_voxels_buffer,
-      triangle_uvw_buffer,
-      ray_map,
-    )?;
-    let (
-      sign_pass_6rays_descriptor_set,
-      sign_pass_neighbors_descriptor_set,
-      sign_pass_neighbors_2_descriptor_set,
-    ) = self.find_sign_update(
-      ray_map,
-      sign_map,
-      sign_map_bis,
-    )?;
-    let (
-      in_out_edge_descriptor_set,
-      buffer_2_image_descriptor_set,
-      jfa_descriptor_set,
-      jfa_2_descriptor_set,
-    ) = self.surface_closing_update(
-      voxels_buffer,
-      if self.settings.sign_passes_count % 2 == 0 { sign_map } else { sign_map_bis },
-      voxels_texture,
-      voxels_texture_bis,
-    )?;
-    let dtw_descriptor_set = self.distance_transform_update(
-      triangle_uvw_buffer,
-      triangles_in_voxels_buffer,
-      accum_counters_buffer,
-      if self.settings.sign_passes_count % 2 == 0 { sign_map } else { sign_map_bis },
-      if num_of_jfa_passes % 2 == 0 { voxels_texture_bis } else { voxels_texture },
-      voxels_buffer,
-      distance_texture,
-    )?;
 
     // Send commands to the compute queue.
     let command_buffers = &self.bake_command_buffers;
@@ -500,7 +425,7 @@ impl SDFBaker {
       counters_buffer,
       accum_counters_buffer,
       initialize_descriptor_set,
-      &dimensions,
+      dimensions,
     )?;
 
     // Build geometry.
@@ -548,6 +473,240 @@ impl SDFBaker {
       num_of_voxels,
     )?;
 
+    command_buffers.end(0)?;
+
+    // Submit & wait.
+    {
+      let context = self.resources.context.borrow();
+      let logical_device = context.logical_device.borrow();
+
+      logical_device.graphics_submit(command_buffers, 0, 0)?;
+      logical_device.graphics_wait(0)?;
+    }
+
+    // The prefix sum is exclusive, so the last counter is added to the total. Only the last u32 of each buffer is read back.
+    let last_index = num_of_voxels as u64 - 1;
+    let last_counter = self.get_buffer_elements::<u32>(counters_buffer, last_index, 1)?[0];
+    let last_accum_counter = self.get_buffer_elements::<u32>(accum_counters_buffer, last_index, 1)?[0];
+    let num_of_triangles_in_voxels = last_accum_counter.checked_add(last_counter)
+      .ok_or(HalaRendererError::new("The number of triangles in the voxels overflows.", None))?;
+    log::debug!("The number of triangles in the voxels is {}.", num_of_triangles_in_voxels);
+
+    Ok(num_of_triangles_in_voxels)
+  }
+
+  /// Bake the SDF.
+  /// The grid is baked brick by brick if it does not fit in the memory budget or a single allocation.
+  pub fn bake_sdf(&mut self) -> Result<(), HalaRendererError> {
+    if self.point_cloud.is_some() {
+      return Err(HalaRendererError::new("The point cloud has no inside, it can only be baked as the UDF.", None));
+    }
+    self.baked_volume_data = None;
+    self.closest_triangle_ranges = None;
+    if self.settings.backend == BakeBackend::Cpu {
+      return self.bake_on_cpu(true);
+    }
+    self.update_merged_geometry()?;
+
+    let dimensions = self.estimate_grid_size();
+    let bytes_per_voxel = SDFBakerResources::get_bytes_per_voxel(self.settings.keep_closest_triangles);
+    let bricks = self.get_bake_bricks(&dimensions, bytes_per_voxel, SDFBakerResources::MAX_BYTES_PER_VOXEL_IN_BUFFER)?;
+    if bricks.len() <= 1 {
+      let bounds = self.settings.get_bounds();
+      self.bake_sdf_grid(&dimensions, &bounds)?;
+      // Record the triangle ranges of the geometry baked now, the settings may change before the closest triangles are read.
+      if self.settings.keep_closest_triangles {
+        self.closest_triangle_ranges = Some(self.get_closest_triangle_ranges()?);
+      }
+    } else {
+      // The ray map and the flood fill take the grid borders as the outside, which is not true for the inner bricks.
+      if self.settings.sign_mode != SignMode::WindingNumber {
+        return Err(HalaRendererError::new(
+          &format!(
+            "The grid {:?} is baked in {} bricks, which only find the sign by the winding number, not the {}. Please set the sign mode to winding_number or raise the memory budget.",
+            dimensions,
+            bricks.len(),
+            self.settings.sign_mode.name(),
+          ),
+          None,
+        ));
+      }
+      if self.settings.gradient_mode == GradientMode::ClosestPoint {
+        return Err(HalaRendererError::new(
+          &format!(
+            "The grid {:?} is baked in {} bricks, which keep no closest points for the gradients. Please set the gradient mode to central_difference or raise the memory budget.",
+            dimensions,
+            bricks.len(),
+          ),
+          None,
+        ));
+      }
+      let data = self.bake_bricks(&dimensions, &bricks, true, |baker, brick_dimensions, brick_bounds| {
+        let num_of_triangles_in_voxels = baker.bake_sdf_grid(brick_dimensions, brick_bounds)?;
+        let (mut data, _) = baker.get_sdf_grid_data(brick_dimensions)?;
+        // The brick crossed by no triangle has no seed for the distances, only the signs are right.
+        if num_of_triangles_in_voxels == 0 {
+          data.iter_mut().for_each(|v| *v = f32::INFINITY.copysign(*v));
+        }
+        Ok(data)
+      })?;
+      self.baked_volume_data = Some(data);
+    }
+
+    self.update_sdf_visualization_mips(true)
+  }
+
+  /// Bake the SDF of the voxels in the bounds, the whole grid or a brick of it.
+  /// param dimensions: The dimensions of the voxels.
+  /// param bounds: The bounds of the voxels.
+  /// return: The number of the triangles in the voxels, 0 if no triangle crosses them.
+  fn bake_sdf_grid(&mut self, dimensions: &[u32; 3], bounds: &scene::HalaBounds) -> Result<u32, HalaRendererError> {
+    // Setup.
+    let num_of_triangles = self.get_selected_num_of_triangles()?;
+    let num_of_jfa_passes = Self::get_num_of_jfa_passes(dimensions);
+
+    // Create buffers and images.
+    self.create_sdf_buffers_images(num_of_triangles, dimensions)?;
+
+    // Count the triangles in the voxels, and create the triangle IDs buffer of the exact size.
+    // The shaders stop before _upper_bound_count - 1, so one more ID is reserved.
+    let num_of_triangles_in_voxels = self.count_triangles_in_voxels(num_of_triangles, dimensions, bounds)?;
+    let upper_bound_count = num_of_triangles_in_voxels.checked_add(1)
+      .ok_or(HalaRendererError::new(&format!("The {} triangles in the voxels are too many.", num_of_triangles_in_voxels), None))?;
+    self.build_geometry_create_triangle_ids_buffer(upper_bound_count)?;
+
+    let triangle_uvw_buffer = self.sdf_baker_resources.triangle_uvw_buffer.as_ref()
+      .ok_or(HalaRendererError::new("Failed to get the triangle_uvw buffer.", None))?;
+    let coord_flip_buffer = self.sdf_baker_resources.coord_flip_buffer.as_ref()
+      .ok_or(HalaRendererError::new("Failed to get the coord_flip buffer.", None))?;
+    let aabb_buffer = self.sdf_baker_resources.aabb_buffer.as_ref()
+      .ok_or(HalaRendererError::new("Failed to get the aabb buffer.", None))?;
+    let vertices_buffer = self.sdf_baker_resources.vertices_buffer.as_ref()
+      .ok_or(HalaRendererError::new("Failed to get the vertices buffer.", None))?;
+    let voxels_buffer = self.sdf_baker_resources.voxels_buffer.as_ref()
+      .ok_or(HalaRendererError::new("Failed to get the voxels buffer.", None))?;
+    let accum_counters_buffer = self.sdf_baker_resources.accum_counters_buffer.as_ref()
+      .ok_or(HalaRendererError::new("Failed to get the accum_counters buffer.", None))?;
+    let triangles_in_voxels_buffer = self.sdf_baker_resources.triangles_in_voxels_buffer.as_ref()
+      .ok_or(HalaRendererError::new("Failed to get the triangles_in_voxels buffer.", None))?;
+    let ray_map = self.sdf_baker_resources.ray_map.as_ref()
+      .ok_or(HalaRendererError::new("Failed to get the ray_map.", None))?;
+    let sign_map = self.sdf_baker_resources.sign_map.as_ref()
+      .ok_or(HalaRendererError::new("Failed to get the sign_map.", None))?;
+    let sign_map_bis = self.sdf_baker_resources.sign_map_bis.as_ref()
+      .ok_or(HalaRendererError::new("Failed to get the sign_map_bis.", None))?;
+    let voxels_texture = self.sdf_baker_resources.voxels_texture.as_ref()
+      .ok_or(HalaRendererError::new("Failed to get the voxels_texture.", None))?;
+    let voxels_texture_bis = self.sdf_baker_resources.voxels_texture_bis.as_ref()
+      .ok_or(HalaRendererError::new("Failed to get the voxels_texture_bis.", None))?;
+    let distance_texture = self.sdf_baker_resources.distance_texture.as_ref()
+      .ok_or(HalaRendererError::new("Failed to get the distance_texture.", None))?;
+    let closest_triangles_buffer = self.sdf_baker_resources.closest_triangles_buffer.as_ref()
+      .ok_or(HalaRendererError::new("Failed to get the closest_triangles buffer.", None))?;
+    let render_targets = [
+      self.sdf_baker_resources.render_targets[0].as_ref()
+        .ok_or(HalaRendererError::new("Failed to get the render_target 0.", None))?,
+      self.sdf_baker_resources.render_targets[1].as_ref()
+        .ok_or(HalaRendererError::new("Failed to get the render_target 1.", None))?,
+      self.sdf_baker_resources.render_targets[2].as_ref()
+        .ok_or(HalaRendererError::new("Failed to get the render_target 2.", None))?,
+    ];
+
+    // Update uniform buffers.
+    self.update_sdf_global_uniform(dimensions, bounds, upper_bound_count, num_of_triangles)?;
+
+    // Update the descriptor sets.
+    for (index, descriptor_set) in self.sdf_baker_resources.image_2_screen_descriptor_sets.iter().enumerate() {
+      descriptor_set.update_combined_image_samplers(
+        0,
+        0,
+        &[(render_targets[index], self.sdf_baker_resources.image_2_screen_sampler.as_ref())],
+      );
+    }
+    self.cross_xyz_descriptor_set.update_combined_image_samplers(
+      0,
+      0,
+      &[
+        (ray_map, self.image3d_sampler.as_ref())
+      ],
+    );
+    self.sdf_visualization_descriptor_set.update_uniform_buffers(
+      0,
+      0,
+      &[self.sdf_visualization_uniform_buffer.as_ref()],
+    );
+    self.sdf_visualization_descriptor_set.update_combined_image_samplers(
+      0,
+      1,
+      &[
+        (distance_texture, self.image3d_sampler.as_ref())
+      ],
+    );
+
+    let write_triangle_ids_to_voxels_descriptor_set = self.build_geometry_triangle_ids_update(
+      coord_flip_buffer,
+      aabb_buffer,
+      vertices_buffer,
+      accum_counters_buffer,
+      triangles_in_voxels_buffer,
+    )?;
+    let (
+      generate_ray_map_local2x2_descriptor_set,
+      ray_map_sum_x_descriptor_set,
+      ray_map_sum_y_descriptor_set,
+      ray_map_sum_z_descriptor_set,
+    ) = self.ray_map_update(
+      accum_counters_buffer,
+      triangles_in_voxels_buffer,
+      triangle_uvw_buffer,
+      ray_map,
+    )?;
+    let (
+      sign_pass_6rays_descriptor_set,
+      sign_pass_neighbors_descriptor_set,
+      sign_pass_neighbors_2_descriptor_set,
+    ) = self.find_sign_update(
+      ray_map,
+      sign_map,
+      sign_map_bis,
+    )?;
+    let sign_pass_winding_number_descriptor_set = if self.settings.sign_mode == SignMode::WindingNumber {
+      Some(self.find_sign_winding_number_update(sign_map)?)
+    } else {
+      None
+    };
+    let flood_fill_descriptor_sets = if self.settings.sign_mode == SignMode::FloodFill {
+      Some(self.find_sign_flood_fill_update(accum_counters_buffer, sign_map, sign_map_bis)?)
+    } else {
+      None
+    };
+    let (
+      in_out_edge_descriptor_set,
+      buffer_2_image_descriptor_set,
+      jfa_descriptor_set,
+      jfa_2_descriptor_set,
+    ) = self.surface_closing_update(
+      voxels_buffer,
+      self.get_final_sign_map(sign_map, sign_map_bis),
+      voxels_texture,
+      voxels_texture_bis,
+    )?;
+    let dtw_descriptor_set = self.distance_transform_update(
+      triangle_uvw_buffer,
+      triangles_in_voxels_buffer,
+      accum_counters_buffer,
+      self.get_final_sign_map(sign_map, sign_map_bis),
+      if num_of_jfa_passes % 2 == 0 { voxels_texture_bis } else { voxels_texture },
+      voxels_buffer,
+      distance_texture,
+      closest_triangles_buffer,
+    )?;
+
+    // Send commands to the compute queue.
+    let command_buffers = &self.bake_command_buffers;
+    command_buffers.reset(0, false)?;
+    command_buffers.begin(0, hala_gfx::HalaCommandBufferUsageFlags::ONE_TIME_SUBMIT)?;
+
     // Second draw pass.
     self.build_geometry_draw_pass_2(
       command_buffers,
@@ -569,20 +728,43 @@ impl SDFBaker {
       ray_map_sum_x_descriptor_set,
       ray_map_sum_y_descriptor_set,
       ray_map_sum_z_descriptor_set,
-      &dimensions,
+      dimensions,
     )?;
 
     // Find sign.
-    let sign_map = self.find_sign_compute(
-      command_buffers,
-      ray_map,
-      sign_map,
-      sign_map_bis,
-      sign_pass_6rays_descriptor_set,
-      sign_pass_neighbors_descriptor_set,
-      sign_pass_neighbors_2_descriptor_set,
-      &dimensions,
-    )?;
+    let sign_map = if let Some(sign_pass_winding_number_descriptor_set) = sign_pass_winding_number_descriptor_set {
+      self.find_sign_winding_number_compute(
+        command_buffers,
+        sign_map,
+        sign_pass_winding_number_descriptor_set,
+        dimensions,
+      )?
+    } else if let Some((
+      flood_fill_initialize_descriptor_set,
+      flood_fill_sweep_descriptor_set,
+      flood_fill_finalize_descriptor_set,
+    )) = flood_fill_descriptor_sets {
+      self.find_sign_flood_fill_compute(
+        command_buffers,
+        sign_map,
+        sign_map_bis,
+        flood_fill_initialize_descriptor_set,
+        flood_fill_sweep_descriptor_set,
+        flood_fill_finalize_descriptor_set,
+        dimensions,
+      )?
+    } else {
+      self.find_sign_compute(
+        command_buffers,
+        ray_map,
+        sign_map,
+        sign_map_bis,
+        sign_pass_6rays_descriptor_set,
+        sign_pass_neighbors_descriptor_set,
+        sign_pass_neighbors_2_descriptor_set,
+        dimensions,
+      )?
+    };
 
     // Surface closing.
     let voxels_texture = self.surface_closing_compute(
@@ -595,7 +777,7 @@ impl SDFBaker {
       buffer_2_image_descriptor_set,
       jfa_descriptor_set,
       jfa_2_descriptor_set,
-      &dimensions,
+      dimensions,
     )?;
 
     // Distance transform winding.
@@ -604,8 +786,9 @@ impl SDFBaker {
       voxels_texture,
       voxels_buffer,
       distance_texture,
+      closest_triangles_buffer,
       dtw_descriptor_set,
-      &dimensions,
+      dimensions,
     )?;
 
     command_buffers.end(0)?;
@@ -619,17 +802,6 @@ impl SDFBaker {
       logical_device.graphics_wait(0)?;
     }
 
-    // Validate the triangle IDs buffer size.
-    {
-      let data = self.debug_get_buffer_data::<u32>(accum_counters_buffer)?;
-      let last_counter = data[num_of_voxels as usize - 1];
-      if last_counter > upper_bound_count {
-        log::error!("The triangle IDs buffer size is too small. The last counter is {}. The upper bound count is {}.", last_counter, upper_bound_count);
-      } else {
-        log::debug!("The triangle IDs buffer size is OK. The last counter is {}. The upper bound count is {}.", last_counter, upper_bound_count);
-      }
-    }
-
     // Debug.
     {
       // let data = self.debug_get_buffer_data::<u32>(accum_counters_buffer)?;
@@ -642,30 +814,331 @@ impl SDFBaker {
       // }
     }
 
-    Ok(())
+    Ok(num_of_triangles_in_voxels)
   }
 
+  /// Get the baked SDF volume from the GPU, or the stitched voxels of the tiled bake.
+  /// return: The SDF volume.
+  pub fn get_sdf_volume(&self) -> Result<SDFVolume, HalaRendererError> {
+    let dimensions = self.estimate_grid_size();
+    if let Some(data) = self.baked_volume_data.as_ref() {
+      return self.new_volume(dimensions, true, data, &[]);
+    }
+    let (data, gradients) = self.get_sdf_grid_data(&dimensions)?;
+
+    self.new_volume(
+      dimensions,
+      true,
+      &data,
+      &gradients,
+    )
+  }
+
+  /// Read back the baked SDF of the voxels from the GPU.
+  /// param dimensions: The dimensions of the voxels.
+  /// return: The normalized voxel data and the gradients from the closest points.
+  fn get_sdf_grid_data(&self, dimensions: &[u32; 3]) -> Result<(Vec<f32>, Vec<[f32; 3]>), HalaRendererError> {
+    let voxels_buffer = self.sdf_baker_resources.voxels_buffer.as_ref()
+      .ok_or(HalaRendererError::new("Failed to get the voxels_buffer.", None))?;
+    let num_of_voxels = dimensions.iter().map(|d| *d as usize).product::<usize>();
+    // The voxels buffer stores the gradient in xyz and the distance in w.
+    let data = self.debug_get_buffer_data::<[f32; 4]>(voxels_buffer)?;
+    if data.len() < num_of_voxels {
+      return Err(HalaRendererError::new("The voxels_buffer is smaller than the grid, please bake first.", None));
+    }
+
+    Ok((
+      data.iter().take(num_of_voxels).map(|v| v[3]).collect(),
+      data.iter().take(num_of_voxels).map(|v| [v[0], v[1], v[2]]).collect(),
+    ))
+  }
+
+  /// Save the SDF to the file in the format of the settings.
+  /// param path: The file path.
+  /// return: The result.
   pub fn save_sdf<P: AsRef<std::path::Path>>(&self, path: P) -> Result<(), HalaRendererError> {
-    let distance_buffer = self.sdf_baker_resources.voxels_buffer.as_ref()
-      .ok_or(HalaRendererError::new("Failed to get the distance_buffer.", None))?;
+    let volume = self.get_sdf_volume()?;
+    volume.save(path, self.settings.output_format, self.settings.value_encoding, self.settings.narrow_band)
+      .map_err(|e| HalaRendererError::new(&format!("Failed to save the SDF file. {:?}", e), None))?;
+
+    Ok(())
+  }
+
+  /// Get the triangle range of each merged primitive in the closest triangles buffer.
+  /// The triangles of the merged primitives are concatenated in the order of the merged indices.
+  /// return: The triangle ranges.
+  fn get_closest_triangle_ranges(&self) -> Result<Vec<TriangleRange>, HalaRendererError> {
+    let mut ranges = Vec::new();
+    let mut first_triangle = 0u32;
+    for index in self.get_merged_primitive_indices()? {
+      let mesh = self.primitive_meshes.get(index)
+        .ok_or(HalaRendererError::new(&format!("Failed to get the triangles of the primitive {}.", index), None))?;
+      ranges.push(TriangleRange {
+        primitive_index: index as u32,
+        first_triangle,
+        num_of_triangles: mesh.num_of_triangles() as u32,
+      });
+      first_triangle += mesh.num_of_triangles() as u32;
+    }
+
+    Ok(ranges)
+  }
+
+  /// Get the closest triangle of each voxel and the barycentrics of the closest point from the GPU.
+  /// The closest triangles are only kept by the GPU SDF bake of the whole grid with the keep_closest_triangles setting,
+  /// not by the tiled, CPU or UDF bake.
+  /// return: The closest triangle volume.
+  pub fn get_closest_triangles(&self) -> Result<ClosestTriangleVolume, HalaRendererError> {
+    let ranges = self.closest_triangle_ranges.as_ref()
+      .ok_or(HalaRendererError::new("The closest triangles are only kept by the GPU SDF bake of the whole grid with the keep_closest_triangles setting.", None))?;
+    let closest_triangles_buffer = self.sdf_baker_resources.closest_triangles_buffer.as_ref()
+      .ok_or(HalaRendererError::new("Failed to get the closest_triangles buffer, please bake first.", None))?;
     let dimensions = self.estimate_grid_size();
-    let num_of_voxels = dimensions[0] * dimensions[1] * dimensions[2];
-    let data = self.debug_get_buffer_data::<f32>(distance_buffer)?;
-    let mut file = std::fs::File::create(path)
-      .map_err(|e| HalaRendererError::new(&format!("Failed to create the UDF file. {}", e), Some(Box::new(e))) )?;
-    let mut writer = std::io::BufWriter::new(&mut file);
-    // Write dimensions.
-    std::io::Write::write_all(&mut writer, format!("{} {} {}\n", dimensions[0], dimensions[1], dimensions[2]).as_bytes())
-      .map_err(|e| HalaRendererError::new(&format!("Failed to write the dimensions. {}", e), Some(Box::new(e))) )?;
-    // Write UDF data.
-    for i in 0..num_of_voxels {
-      std::io::Write::write_all(&mut writer, format!("{}\n", data[i as usize]).as_bytes())
-        .map_err(|e| HalaRendererError::new(&format!("Failed to write the UDF data. {}", e), Some(Box::new(e))) )?;
+    let num_of_voxels = dimensions.iter().map(|d| *d as usize).product::<usize>();
+    // The closest triangles buffer stores the triangle index in x and the barycentrics as floats in yzw.
+    let data = self.debug_get_buffer_data::<[u32; 4]>(closest_triangles_buffer)?;
+    if data.len() != num_of_voxels {
+      return Err(HalaRendererError::new("The closest_triangles buffer does not match the grid, please bake first.", None));
+    }
+
+    let bounds = self.settings.get_bounds();
+    let volume = ClosestTriangleVolume {
+      dimensions,
+      center: bounds.center,
+      extents: bounds.extents,
+      voxel_size: self.settings.actual_size[0] / dimensions[0] as f32,
+      ranges: ranges.clone(),
+      triangles: data.iter().map(|v| v[0]).collect(),
+      barycentrics: data.iter().map(|v| [f32::from_bits(v[1]), f32::from_bits(v[2]), f32::from_bits(v[3])]).collect(),
+    };
+    volume.validate()
+      .map_err(|e| HalaRendererError::new(&format!("The closest triangles do not match the baked geometry. {:?}", e), None))?;
+
+    Ok(volume)
+  }
+
+  /// Save the closest triangle of each voxel and the barycentrics of the closest point to the file.
+  /// param path: The file path.
+  /// return: The result.
+  pub fn save_closest_triangles<P: AsRef<std::path::Path>>(&self, path: P) -> Result<(), HalaRendererError> {
+    self.get_closest_triangles()?.save(path)
+      .map_err(|e| HalaRendererError::new(&format!("Failed to save the closest triangles file. {:?}", e), None))?;
+
+    Ok(())
+  }
+
+  /// Create a volume with the bounds of the settings.
+  /// The baked data is normalized by the longest side of the bounds in both SDF and UDF,
+  /// it is converted to the distance unit of the settings.
+  /// param dimensions: The dimensions of the voxels.
+  /// param is_sdf: Whether the data is signed.
+  /// param data: The normalized voxel data, copied once into the volume.
+  /// param closest_point_gradients: The gradients from the closest points, empty if the bake has none.
+  /// return: The volume.
+  fn new_volume(
+    &self,
+    dimensions: [u32; 3],
+    is_sdf: bool,
+    data: &[f32],
+    closest_point_gradients: &[[f32; 3]],
+  ) -> Result<SDFVolume, HalaRendererError> {
+    // The gradients fall back to the central differences without the closest points,
+    // they are computed from the borrowed data before the narrow band clamp flattens the far voxels.
+    let gradients = match self.settings.gradient_mode {
+      GradientMode::None => Vec::new(),
+      GradientMode::ClosestPoint if !closest_point_gradients.is_empty() => closest_point_gradients.to_vec(),
+      _ => central_difference_gradients(dimensions, data),
+    };
+    // The narrow band mode clamps the voxels out of the band, whose distances are not exact.
+    let band = if self.settings.is_narrow_band {
+      self.settings.narrow_band / self.settings.max_resolution as f32
+    } else {
+      f32::INFINITY
+    };
+    let bounds = self.settings.get_bounds();
+    let mut volume = SDFVolume {
+      dimensions,
+      center: bounds.center,
+      extents: bounds.extents,
+      voxel_size: self.settings.actual_size[0] / dimensions[0] as f32,
+      is_sdf,
+      surface_offset: self.settings.surface_offset,
+      unit: DistanceUnit::Normalized,
+      data: data.iter().map(|v| v.clamp(-band, band)).collect(),
+      mips: Vec::new(),
+      gradients,
+    };
+    if self.settings.generate_mips {
+      volume.build_mips()
+        .map_err(|e| HalaRendererError::new(&format!("Failed to build the mip chain. {:?}", e), None))?;
     }
+    volume.convert_unit(self.settings.distance_unit)
+      .map_err(|e| HalaRendererError::new(&format!("Failed to convert the distance unit. {:?}", e), None))?;
+
+    Ok(volume)
+  }
+
+  /// Upload the mip chain of the last bake to the SDF visualization, in the normalized unit of the distance texture.
+  /// The mip chain is only built when the window shows the visualization.
+  /// param is_sdf: Whether the last bake is SDF or UDF.
+  /// return: The result.
+  fn update_sdf_visualization_mips(&mut self, is_sdf: bool) -> Result<(), HalaRendererError> {
+    self.sdf_visualization_mips_buffer = None;
+    self.sdf_visualization_num_of_mips = 0;
+    if !self.settings.generate_mips || self.sdf_visualization_program.is_none() {
+      return Ok(());
+    }
+
+    // The mips are built from the normalized distances already read back, or read back once from the GPU,
+    // without the gradients and the unit conversion of a saved volume.
+    let dimensions = self.estimate_grid_size();
+    let grid_data;
+    let data = match self.baked_volume_data.as_ref() {
+      Some(data) => data.as_slice(),
+      None => {
+        grid_data = if is_sdf { self.get_sdf_grid_data(&dimensions)?.0 } else { self.get_udf_grid_data(&dimensions)? };
+        grid_data.as_slice()
+      },
+    };
+    let max_dimension = dimensions.iter().fold(1, |a, b| a.max(*b));
+    let mips = build_mip_chain(dimensions, data, 0.5 * 3.0f32.sqrt() / max_dimension as f32)
+      .map_err(|e| HalaRendererError::new(&format!("Failed to build the mip chain. {:?}", e), None))?;
+    let data = mips.concat();
+    if data.is_empty() {
+      return Ok(());
+    }
+
+    let mips_buffer = hala_gfx::HalaBuffer::new(
+      Rc::clone(&self.resources.context.borrow().logical_device),
+      (data.len() * std::mem::size_of::<f32>()) as u64,
+      hala_gfx::HalaBufferUsageFlags::STORAGE_BUFFER,
+      hala_gfx::HalaMemoryLocation::CpuToGpu,
+      "sdf_visualization.mips_buffer",
+    )?;
+    mips_buffer.update_memory(0, data.as_slice())?;
+    self.sdf_visualization_descriptor_set.update_storage_buffers(
+      0,
+      2,
+      &[&mips_buffer],
+    );
+    self.sdf_visualization_mips_buffer = Some(mips_buffer);
+    self.sdf_visualization_num_of_mips = mips.len() as u32;
 
     Ok(())
   }
 
+  /// Split the grid into the bricks by the memory budget and the tile halo of the settings.
+  /// param dimensions: The dimensions of the grid.
+  /// param bytes_per_voxel: The bytes per voxel of the buffers and images of the bake.
+  /// param max_bytes_per_voxel_in_buffer: The bytes per voxel of the largest buffer, which must fit the max buffer size of the device.
+  /// return: The bricks, only one if the whole grid fits.
+  fn get_bake_bricks(
+    &self,
+    dimensions: &[u32; 3],
+    bytes_per_voxel: u64,
+    max_bytes_per_voxel_in_buffer: u64,
+  ) -> Result<Vec<VolumeBrick>, HalaRendererError> {
+    let max_voxels_per_brick = self.max_buffer_size / max_bytes_per_voxel_in_buffer;
+    let max_voxels = if self.settings.memory_budget > 0 {
+      (self.settings.memory_budget as u64 * 1024 * 1024 / bytes_per_voxel).min(max_voxels_per_brick)
+    } else {
+      max_voxels_per_brick
+    };
+    let bricks = split_into_bricks(*dimensions, self.get_tile_halo()?, max_voxels)
+      .map_err(|e| HalaRendererError::new(&format!("Failed to split the grid into the bricks. {:?}", e), None))?;
+    if bricks.len() > 1 {
+      log::info!("The grid {:?} is baked in {} bricks of at most {} voxels.", dimensions, bricks.len(), max_voxels);
+    }
+
+    Ok(bricks)
+  }
+
+  /// Get the tile halo of the settings, the jump radius if it is 0.
+  /// return: The tile halo in voxels.
+  fn get_tile_halo(&self) -> Result<u32, HalaRendererError> {
+    let jump_radius = self.settings.get_jump_radius();
+    match self.settings.tile_halo {
+      0 => Ok(jump_radius as u32),
+      tile_halo if tile_halo < jump_radius => Err(HalaRendererError::new(
+        &format!("The tile halo {} is less than the jump radius {} of the narrow band.", tile_halo, jump_radius),
+        None,
+      )),
+      tile_halo => Ok(tile_halo as u32),
+    }
+  }
+
+  /// Bake the grid brick by brick, and stitch the core voxels of the bricks on the CPU.
+  /// Only the buffers and images of one brick are on the GPU at a time.
+  /// The voxels farther than the halo from the surfaces of their bricks are bounded by the halo and the coarse distances on the CPU.
+  /// param dimensions: The dimensions of the grid.
+  /// param bricks: The bricks of the grid.
+  /// param is_sdf: Whether the distances are signed.
+  /// param bake_brick: Bake the voxels of the dimensions in the bounds and read them back.
+  /// return: The normalized voxel data of the grid.
+  fn bake_bricks<F>(
+    &mut self,
+    dimensions: &[u32; 3],
+    bricks: &[VolumeBrick],
+    is_sdf: bool,
+    bake_brick: F,
+  ) -> Result<Vec<f32>, HalaRendererError>
+  where
+    F: Fn(&mut Self, &[u32; 3], &scene::HalaBounds) -> Result<Vec<f32>, HalaRendererError>,
+  {
+    let bounds = self.settings.get_bounds();
+    let voxel_size = self.settings.actual_size[0] / dimensions[0] as f32;
+    let num_of_voxels = dimensions.iter().map(|d| *d as usize).product::<usize>();
+    let mut data = vec![0.0; num_of_voxels];
+    for (index, brick) in bricks.iter().enumerate() {
+      let extents = brick.size.map(|s| s as f32 * voxel_size * 0.5);
+      let brick_bounds = scene::HalaBounds {
+        center: [0, 1, 2].map(|i| bounds.center[i] - bounds.extents[i] + brick.min[i] as f32 * voxel_size + extents[i]),
+        extents,
+      };
+
+      let now = std::time::Instant::now();
+      let brick_data = bake_brick(self, &brick.size, &brick_bounds)?;
+      brick.copy_core_to(&brick_data, *dimensions, &mut data)
+        .map_err(|e| HalaRendererError::new(&format!("Failed to stitch the brick {}. {:?}", index, e), None))?;
+      log::info!("Bake the brick {}/{} of {:?} voxels at {:?} used {}ms.", index + 1, bricks.len(), brick.size, brick.min, now.elapsed().as_millis());
+    }
+
+    // The coarse distances are to the same triangles, or to the spheres of the points.
+    let now = std::time::Instant::now();
+    let (mesh, radius) = match self.point_cloud.as_ref() {
+      Some(point_cloud) => (
+        TriangleMesh::new(
+          point_cloud.positions.clone(),
+          (0..point_cloud.positions.len() as u32).flat_map(|i| [i, i, i]).collect(),
+        ),
+        self.settings.point_radius,
+      ),
+      None => (self.get_merged_mesh()?, 0.0),
+    };
+    let cpu_baker = SDFCPUBaker::new(&mesh);
+    let max_size = self.settings.actual_size.iter().fold(0.0, |a: f32, b| a.max(*b));
+    let max_dimension = dimensions.iter().fold(1, |a, b| a.max(*b));
+    let min_corner = glam::Vec3::from_array(bounds.center) - glam::Vec3::from_array(bounds.extents);
+    // The coarse samples are at least the halo apart and at most 128 along an axis.
+    let halo = self.get_tile_halo()?;
+    bound_far_distances(
+      *dimensions,
+      &mut data,
+      is_sdf,
+      self.settings.surface_offset,
+      halo,
+      1.0 / max_dimension as f32,
+      halo.max(max_dimension.div_ceil(128)),
+      |coord| {
+        let pt = min_corner + (glam::Vec3::from_array(coord.map(|c| c as f32)) + glam::Vec3::splat(0.5)) * voxel_size;
+        (cpu_baker.get_distance(pt).unwrap_or(f32::MAX) - radius).max(0.0) / max_size
+      },
+    ).map_err(|e| HalaRendererError::new(&format!("Failed to bound the far distances of the bricks. {:?}", e), None))?;
+    log::info!("Bound the far distances of the bricks used {}ms.", now.elapsed().as_millis());
+
+    Ok(data)
+  }
+
   /// Create all buffers and images for the baker.
   /// param num_of_voxels: The number of triangles.
   /// param dimensions: The dimensions of the voxels.
@@ -682,32 +1155,64 @@ impl SDFBaker {
   }
 
   /// Bake the UDF.
+  /// The grid is baked brick by brick if it does not fit in the memory budget or a single allocation.
   pub fn bake_udf(&mut self) -> Result<(), HalaRendererError> {
-    let primitive = self.get_selected_mesh_primitive()?;
+    self.baked_volume_data = None;
+    self.closest_triangle_ranges = None;
+    if self.settings.backend == BakeBackend::Cpu {
+      return self.bake_on_cpu(false);
+    }
+    self.update_merged_geometry()?;
+    if self.settings.gradient_mode == GradientMode::ClosestPoint {
+      log::warn!("The UDF bake computes the gradients by the central differences instead of the closest points.");
+    }
+
+    let dimensions = self.estimate_grid_size();
+    let bricks = self.get_bake_bricks(&dimensions, UDFBakerResources::BYTES_PER_VOXEL, UDFBakerResources::MAX_BYTES_PER_VOXEL_IN_BUFFER)?;
+    if bricks.len() <= 1 {
+      let bounds = self.settings.get_bounds();
+      self.bake_udf_grid(&dimensions, &bounds)?;
+    } else {
+      let data = self.bake_bricks(&dimensions, &bricks, false, |baker, brick_dimensions, brick_bounds| {
+        baker.bake_udf_grid(brick_dimensions, brick_bounds)?;
+        baker.get_udf_grid_data(brick_dimensions)
+      })?;
+      self.baked_volume_data = Some(data);
+    }
 
+    self.update_sdf_visualization_mips(false)
+  }
+
+  /// Bake the UDF of the voxels in the bounds, the whole grid or a brick of it.
+  /// The max size and the max dimension are always of the whole grid, so a brick has the same voxel size.
+  /// param dimensions: The dimensions of the voxels.
+  /// param bounds: The bounds of the voxels.
+  /// return: The result.
+  fn bake_udf_grid(&mut self, dimensions: &[u32; 3], bounds: &scene::HalaBounds) -> Result<(), HalaRendererError> {
     // Setup.
-    let num_of_triangles = primitive.index_count / 3;
+    let num_of_triangles = if self.point_cloud.is_some() { 0 } else { self.get_selected_num_of_triangles()? };
     let max_size = self.settings.actual_size.iter().fold(0.0, |a: f32, b| a.max(*b));
     let normalized_size = self.settings.actual_size.iter().map(|a| a / max_size).collect::<Vec<f32>>();
     let max_distance = (normalized_size[0] * normalized_size[0] + normalized_size[1] * normalized_size[1] + normalized_size[2] * normalized_size[2]).sqrt();
-    let dimensions = self.estimate_grid_size();
     let num_of_voxels = dimensions[0] * dimensions[1] * dimensions[2];
-    let bounds = self.settings.get_bounds();
-    let max_dimension = dimensions.iter().fold(0, |a, b| a.max(*b));
+    let max_dimension = self.settings.max_resolution as u32;
+
+    let jump_offsets = self.get_jump_flooding_offsets(dimensions);
 
     // Create buffers and images.
-    self.create_udf_buffers_images(num_of_voxels, &dimensions)?;
+    self.create_udf_buffers_images(num_of_voxels, dimensions)?;
+    // The finalize pass writes the distances to the buffer read by the last pass.
+    self.udf_baker_resources.is_distance_in_jump_buffer_bis = jump_offsets.len() % 2 == 0;
     let distance_texture = self.udf_baker_resources.distance_texture.as_ref()
       .ok_or(HalaRendererError::new("Failed to get the distance_texture.", None))?;
     let jump_buffer = self.udf_baker_resources.jump_buffer.as_ref()
       .ok_or(HalaRendererError::new("Failed to get the jump_buffer.", None))?;
     let jump_buffer_bis = self.udf_baker_resources.jump_buffer_bis.as_ref()
       .ok_or(HalaRendererError::new("Failed to get the jump_buffer_bis.", None))?;
-    let (index_buffer, vertex_buffer) = self.get_selected_mesh_buffers()?;
 
     // Update uniform buffers.
     let global_uniform = UDFBakerCSGlobalUniform {
-      dimensions,
+      dimensions: *dimensions,
       num_of_voxels,
       num_of_triangles,
       initial_distance: max_distance * 1.01,
@@ -754,11 +1259,20 @@ impl SDFBaker {
     ) = self.udf_initialize_update(
       distance_texture,
     )?;
-    let splat_triangle_distance_descriptor_set = self.splat_triangle_distance_update(
-      index_buffer,
-      vertex_buffer,
-      distance_texture,
-    )?;
+    // The point cloud seeds the jump flooding with the distances to the points instead of the triangles.
+    let splat_distance_descriptor_set = if let Some(point_buffer) = self.point_buffer.as_ref() {
+      self.splat_point_distance_update(
+        point_buffer,
+        distance_texture,
+      )?
+    } else {
+      let (index_buffer, vertex_buffer) = self.get_selected_mesh_buffers()?;
+      self.splat_triangle_distance_update(
+        index_buffer,
+        vertex_buffer,
+        distance_texture,
+      )?
+    };
     let (
       jump_flooding_initialize_descriptor_set,
       jump_flooding_odd_descriptor_set,
@@ -768,6 +1282,7 @@ impl SDFBaker {
       distance_texture,
       jump_buffer,
       jump_buffer_bis,
+      jump_offsets.len() as u32,
     )?;
 
     // Send commands to the compute queue.
@@ -780,23 +1295,32 @@ impl SDFBaker {
       command_buffers,
       distance_texture,
       initialize_descriptor_set,
-      &dimensions,
+      dimensions,
     )?;
 
-    // Splat triangle distance.
-    self.splat_triangle_distance_compute(
-      command_buffers,
-      distance_texture,
-      splat_triangle_distance_descriptor_set,
-      num_of_triangles,
-    )?;
+    // Splat triangle or point distance.
+    if let Some(points) = self.point_cloud.as_ref() {
+      self.splat_point_distance_compute(
+        command_buffers,
+        distance_texture,
+        splat_distance_descriptor_set,
+        points.num_of_points() as u32,
+      )?;
+    } else {
+      self.splat_triangle_distance_compute(
+        command_buffers,
+        distance_texture,
+        splat_distance_descriptor_set,
+        num_of_triangles,
+      )?;
+    }
 
     // Finialize
     self.udf_initialize_compute_pass_2(
       command_buffers,
       distance_texture,
       finalize_descriptor_set,
-      &dimensions,
+      dimensions,
     )?;
 
     // Jump flooding.
@@ -810,7 +1334,8 @@ impl SDFBaker {
       jump_flooding_odd_descriptor_set,
       jump_flooding_even_descriptor_set,
       jump_flooding_finalize_descriptor_set,
-      &dimensions,
+      &jump_offsets,
+      dimensions,
     )?;
 
     command_buffers.end(0)?;
@@ -839,33 +1364,103 @@ impl SDFBaker {
     Ok(())
   }
 
-  pub fn save_udf<P: AsRef<std::path::Path>>(&self, path: P) -> Result<(), HalaRendererError> {
-    let num_of_steps = self.settings.max_resolution.ilog2();
-    let get_read_jump_buffer = |i| {
-      if i % 2 == 0 {
-        self.udf_baker_resources.jump_buffer_bis.as_ref()
-      } else {
-        self.udf_baker_resources.jump_buffer.as_ref()
-      }
-    };
+  /// Get the baked UDF volume from the GPU, or the stitched voxels of the tiled bake.
+  /// return: The UDF volume.
+  pub fn get_udf_volume(&self) -> Result<SDFVolume, HalaRendererError> {
+    let dimensions = self.estimate_grid_size();
+    if let Some(data) = self.baked_volume_data.as_ref() {
+      return self.new_volume(dimensions, false, data, &[]);
+    }
+    let data = self.get_udf_grid_data(&dimensions)?;
 
-    let distance_buffer = get_read_jump_buffer(num_of_steps)
+    self.new_volume(
+      dimensions,
+      false,
+      &data,
+      &[],
+    )
+  }
+
+  /// Read back the baked UDF of the voxels from the GPU.
+  /// param dimensions: The dimensions of the voxels.
+  /// return: The normalized voxel data.
+  fn get_udf_grid_data(&self, dimensions: &[u32; 3]) -> Result<Vec<f32>, HalaRendererError> {
+    let distance_buffer = self.udf_baker_resources.get_distance_buffer()
       .ok_or(HalaRendererError::new("Failed to get the distance_buffer.", None))?;
-    let dimensions = self.estimate_grid_size();
-    let num_of_voxels = dimensions[0] * dimensions[1] * dimensions[2];
+    let num_of_voxels = dimensions.iter().map(|d| *d as usize).product::<usize>();
     let data = self.debug_get_buffer_data::<f32>(distance_buffer)?;
-    let mut file = std::fs::File::create(path)
-      .map_err(|e| HalaRendererError::new(&format!("Failed to create the UDF file. {}", e), Some(Box::new(e))) )?;
-    let mut writer = std::io::BufWriter::new(&mut file);
-    // Write dimensions.
-    std::io::Write::write_all(&mut writer, format!("{} {} {}\n", dimensions[0], dimensions[1], dimensions[2]).as_bytes())
-      .map_err(|e| HalaRendererError::new(&format!("Failed to write the dimensions. {}", e), Some(Box::new(e))) )?;
-    // Write UDF data.
-    for i in 0..num_of_voxels {
-      std::io::Write::write_all(&mut writer, format!("{}\n", data[i as usize]).as_bytes())
-        .map_err(|e| HalaRendererError::new(&format!("Failed to write the UDF data. {}", e), Some(Box::new(e))) )?;
+    if data.len() < num_of_voxels {
+      return Err(HalaRendererError::new("The distance_buffer is smaller than the grid, please bake first.", None));
     }
 
+    Ok(data[..num_of_voxels].to_vec())
+  }
+
+  /// Save the UDF to the file in the format of the settings.
+  /// param path: The file path.
+  /// return: The result.
+  pub fn save_udf<P: AsRef<std::path::Path>>(&self, path: P) -> Result<(), HalaRendererError> {
+    let volume = self.get_udf_volume()?;
+    volume.save(path, self.settings.output_format, self.settings.value_encoding, self.settings.narrow_band)
+      .map_err(|e| HalaRendererError::new(&format!("Failed to save the UDF file. {:?}", e), None))?;
+
+    Ok(())
+  }
+
+  /// Bake the SDF or UDF of the merged triangles by the CPU baker, the same grid as the GPU bake.
+  /// The GPU resources are untouched, so the preview still shows the last GPU bake.
+  /// param is_sdf: Whether to bake the SDF or UDF.
+  /// return: The result.
+  fn bake_on_cpu(&mut self, is_sdf: bool) -> Result<(), HalaRendererError> {
+    if self.point_cloud.is_some() {
+      return Err(HalaRendererError::new("The CPU backend only bakes the triangles, not the point cloud.", None));
+    }
+    self.sdf_visualization_mips_buffer = None;
+    self.sdf_visualization_num_of_mips = 0;
+
+    let now = std::time::Instant::now();
+    let data = SDFCPUBaker::new(&self.get_merged_mesh()?).bake_data(&self.settings, is_sdf)
+      .map_err(|e| HalaRendererError::new(&format!("Failed to bake on the CPU. {:?}", e), None))?;
+    log::info!("Bake the grid {:?} on the CPU used {}ms.", self.estimate_grid_size(), now.elapsed().as_millis());
+    self.baked_volume_data = Some(data);
+
+    Ok(())
+  }
+
+  /// Compare the last bake with the CPU bake of the same triangles and settings.
+  /// Both volumes get the same narrow band clamp and distance unit, the CPU bake is the reference.
+  /// return: The difference of the last bake minus the CPU bake.
+  pub fn compare_with_cpu(&self) -> Result<VolumeDiff, HalaRendererError> {
+    if self.point_cloud.is_some() {
+      return Err(HalaRendererError::new("The CPU backend only bakes the triangles, not the point cloud.", None));
+    }
+    let is_sdf = self.settings.is_sdf;
+    let volume = if is_sdf { self.get_sdf_volume()? } else { self.get_udf_volume()? };
+
+    let now = std::time::Instant::now();
+    let data = SDFCPUBaker::new(&self.get_merged_mesh()?).bake_data(&self.settings, is_sdf)
+      .map_err(|e| HalaRendererError::new(&format!("Failed to bake on the CPU. {:?}", e), None))?;
+    log::info!("Bake the reference on the CPU used {}ms.", now.elapsed().as_millis());
+    let reference = self.new_volume(volume.dimensions, is_sdf, &data, &[])?;
+
+    diff_volumes(&volume, &reference)
+      .map_err(|e| HalaRendererError::new(&format!("Failed to compare with the CPU bake. {:?}", e), None))
+  }
+
+  /// Extract the isosurface of the baked SDF or UDF and save it to the file.
+  /// param path: The file path, the format is detected by the extension, obj, ply, stl or glb.
+  /// param iso_value: The iso value in the distance unit of the settings.
+  /// return: The result.
+  pub fn save_isosurface<P: AsRef<std::path::Path>>(&self, path: P, iso_value: f32) -> Result<(), HalaRendererError> {
+    let volume = if self.settings.is_sdf {
+      self.get_sdf_volume()?
+    } else {
+      self.get_udf_volume()?
+    };
+    MeshFormat::from_path(path.as_ref())
+      .and_then(|format| extract_isosurface(&volume, iso_value)?.save(path.as_ref(), format))
+      .map_err(|e| HalaRendererError::new(&format!("Failed to save the isosurface. {:?}", e), None))?;
+
     Ok(())
   }
 
@@ -0,0 +1,89 @@
+use hala_renderer::error::HalaRendererError;
+
+use crate::baker::SDFBaker;
+
+impl SDFBaker {
+
+  pub(super) fn splat_point_distance_update(
+    &self,
+    point_buffer: &hala_gfx::HalaBuffer,
+    distance_texture: &hala_gfx::HalaImage,
+  ) -> Result<
+    &hala_gfx::HalaDescriptorSet,
+    HalaRendererError
+  > {
+    let descriptor_set = self.udf_baker_resources.descriptor_sets.get("splat_point_distance")
+      .ok_or(HalaRendererError::new("Failed to get the splat_point_distance descriptor set.", None))?;
+    descriptor_set.update_storage_buffers(
+      0,
+      0,
+      &[point_buffer],
+    );
+    descriptor_set.update_storage_images(
+      0,
+      1,
+      &[distance_texture],
+    );
+
+    Ok(descriptor_set)
+  }
+
+  pub(super) fn splat_point_distance_compute(
+    &self,
+    command_buffers: &hala_gfx::HalaCommandBufferSet,
+    distance_texture: &hala_gfx::HalaImage,
+    descriptor_set: &hala_gfx::HalaDescriptorSet,
+    num_of_points: u32,
+  ) -> Result<(), HalaRendererError> {
+    command_buffers.set_image_barriers(
+      0,
+      &[
+        hala_gfx::HalaImageBarrierInfo {
+          old_layout: hala_gfx::HalaImageLayout::UNDEFINED,
+          new_layout: hala_gfx::HalaImageLayout::GENERAL,
+          src_stage_mask: hala_gfx::HalaPipelineStageFlags2::COMPUTE_SHADER,
+          src_access_mask: hala_gfx::HalaAccessFlags2::SHADER_WRITE,
+          dst_stage_mask: hala_gfx::HalaPipelineStageFlags2::COMPUTE_SHADER,
+          dst_access_mask: hala_gfx::HalaAccessFlags2::SHADER_READ | hala_gfx::HalaAccessFlags2::SHADER_WRITE,
+          aspect_mask: hala_gfx::HalaImageAspectFlags::COLOR,
+          image: distance_texture.raw,
+          ..Default::default()
+        },
+      ],
+    );
+
+    let program = self.udf_baker_resources.compute_programs.get("splat_point_distance")
+      .ok_or(HalaRendererError::new("Failed to get the splat_point_distance program.", None))?;
+    program.bind(
+      0,
+      command_buffers,
+      &[
+        &self.udf_baker_resources.static_descriptor_set,
+        descriptor_set,
+      ]
+    );
+
+    // The radius is normalized by the longest side of the box as the distances.
+    let max_size = self.settings.actual_size.iter().fold(0.0, |a: f32, b| a.max(*b));
+    let mut push_constants = Vec::new();
+    push_constants.extend_from_slice(&(self.settings.point_radius / max_size).to_le_bytes());
+    push_constants.extend_from_slice(&num_of_points.to_le_bytes());
+    program.push_constants(
+      0,
+      command_buffers,
+      0,
+      &push_constants,
+    );
+
+    program.dispatch(
+      0,
+      command_buffers,
+      (num_of_points + 64 - 1) / 64,
+      1,
+      1,
+    );
+
+    Ok(())
+  }
+
+}
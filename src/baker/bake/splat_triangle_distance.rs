@@ -18,6 +18,8 @@ impl SDFBaker {
       vertex_position_offset: 0,
       vertex_stride: std::mem::size_of::<hala_renderer::scene::HalaVertex>() as u32,
       index_stride: std::mem::size_of::<u32>() as u32,
+      padding0: 0,
+      local_to_bake: self.get_local_to_bake_matrix(),
     };
     log::debug!("Mesh uniform: {:?}", mesh_uniform);
     self.udf_baker_resources.mesh_uniform_buffer.update_memory(0, std::slice::from_ref(&mesh_uniform))?;
@@ -3,6 +3,7 @@ use std::rc::Rc;
 use hala_renderer::error::HalaRendererError;
 
 use crate::baker::SDFBaker;
+use crate::baker::settings::SignMode;
 
 impl SDFBaker {
 
@@ -128,6 +129,15 @@ impl SDFBaker {
     ))
   }
 
+  /// Get the number of the JFA passes of the surface closing for the grid.
+  /// The steps start from the half of the power of two not less than the max dimension, so the seeds reach the far voxels of any grid.
+  /// param dimensions: The dimensions of the grid.
+  /// return: The number of the passes.
+  pub(super) fn get_num_of_jfa_passes(dimensions: &[u32; 3]) -> u32 {
+    let max_dimension = dimensions.iter().fold(1, |a, b| a.max(*b));
+    max_dimension.next_power_of_two().ilog2()
+  }
+
   #[allow(clippy::too_many_arguments)]
   pub(super) fn surface_closing_compute<'a: 'b, 'b>(
     &self,
@@ -177,7 +187,8 @@ impl SDFBaker {
       let program = self.sdf_baker_resources.compute_programs.get("in_out_edge")
         .ok_or(HalaRendererError::new("Failed to get the in_out_edge compute program.", None))?;
 
-      let threshold = if self.settings.sign_passes_count == 0 {
+      // The unrefined ray map sums 6 rays, the winding number is already normalized.
+      let threshold = if self.settings.sign_mode == SignMode::RayMap && self.settings.sign_passes_count == 0 {
         self.settings.in_out_threshold * 6.0
       } else {
         self.settings.in_out_threshold
@@ -343,7 +354,7 @@ impl SDFBaker {
     };
 
     // JFA 1 to N passes.
-    let num_of_steps = self.settings.max_resolution.ilog2();
+    let num_of_steps = Self::get_num_of_jfa_passes(dimensions);
     {
       let program = self.sdf_baker_resources.compute_programs.get("jfa")
         .ok_or(HalaRendererError::new("Failed to get the jfa compute program.", None))?;
@@ -49,11 +49,27 @@ impl SDFBaker {
     Ok(())
   }
 
+  /// Get the offsets of the jump flooding passes for the grid.
+  /// The steps start from the half of the power of two not less than the max dimension, so the seeds reach the far voxels of any grid.
+  /// The JFA+1 and JFA+2 refinement passes append the offsets 1, or 2 and 1.
+  /// param dimensions: The dimensions of the grid.
+  /// return: The offsets in voxels.
+  pub(super) fn get_jump_flooding_offsets(&self, dimensions: &[u32; 3]) -> Vec<u32> {
+    let max_dimension = dimensions.iter().fold(1, |a, b| a.max(*b));
+    let num_of_steps = max_dimension.next_power_of_two().ilog2();
+    let mut offsets = (1..=num_of_steps)
+      .map(|i| 1 << (num_of_steps - i))
+      .collect::<Vec<u32>>();
+    offsets.extend((1..=self.settings.jfa_refinement_passes.max(0) as u32).rev().map(|i| 1 << (i - 1)));
+    offsets
+  }
+
   pub(super) fn jump_flooding_update(
     &self,
     distance_texture: &hala_gfx::HalaImage,
     jump_buffer: &hala_gfx::HalaBuffer,
     jump_buffer_bis: &hala_gfx::HalaBuffer,
+    num_of_passes: u32,
   ) -> Result<
     (
       &hala_gfx::HalaDescriptorSet,
@@ -63,7 +79,6 @@ impl SDFBaker {
     ),
     HalaRendererError,
   > {
-    let num_of_steps = self.settings.max_resolution.ilog2();
     let get_read_jump_buffer = |i: u32| {
       if i % 2 == 0 { // even
         jump_buffer_bis
@@ -130,7 +145,7 @@ impl SDFBaker {
     jump_flooding_finalize_descriptor_set.update_storage_buffers(
       0,
       0,
-      &[get_write_jump_buffer(num_of_steps)],
+      &[get_write_jump_buffer(num_of_passes)],
     );
     jump_flooding_finalize_descriptor_set.update_storage_images(
       0,
@@ -140,7 +155,7 @@ impl SDFBaker {
     jump_flooding_finalize_descriptor_set.update_storage_buffers(
       0,
       2,
-      &[get_read_jump_buffer(num_of_steps)],
+      &[get_read_jump_buffer(num_of_passes)],
     );
 
     Ok((
@@ -162,6 +177,7 @@ impl SDFBaker {
     jump_flooding_odd_descriptor_set: &hala_gfx::HalaDescriptorSet,
     jump_flooding_even_descriptor_set: &hala_gfx::HalaDescriptorSet,
     jump_flooding_finalize_descriptor_set: &hala_gfx::HalaDescriptorSet,
+    jump_offsets: &[u32],
     dimensions: &[u32; 3]
   ) -> Result<&'b hala_gfx::HalaBuffer, HalaRendererError> {
     // distance_texture be going to be read by compute shaders.
@@ -221,7 +237,7 @@ impl SDFBaker {
       );
     }
 
-    let num_of_steps = self.settings.max_resolution.ilog2();
+    let num_of_passes = jump_offsets.len() as u32;
     let get_read_jump_buffer = |i| {
       if i % 2 == 0 {
         jump_buffer_bis
@@ -263,8 +279,7 @@ impl SDFBaker {
         ],
       );
 
-      for i in 1..=num_of_steps {
-        let offset = (1 << (num_of_steps - i)) as u32;
+      for (i, offset) in (1..=num_of_passes).zip(jump_offsets.iter()) {
         let read_buffer = get_read_jump_buffer(i);
         let write_buffer = get_write_jump_buffer(i);
         command_buffers.set_buffer_barriers(
@@ -321,8 +336,8 @@ impl SDFBaker {
 
     // Finalize.
     {
-      let read_buffer = get_write_jump_buffer(num_of_steps);
-      let write_buffer = get_read_jump_buffer(num_of_steps);
+      let read_buffer = get_write_jump_buffer(num_of_passes);
+      let write_buffer = get_read_jump_buffer(num_of_passes);
       command_buffers.set_buffer_barriers(
         0,
         &[
@@ -390,7 +405,7 @@ impl SDFBaker {
       );
     }
 
-    Ok(get_read_jump_buffer(num_of_steps))
+    Ok(get_read_jump_buffer(num_of_passes))
   }
 
 }
\ No newline at end of file
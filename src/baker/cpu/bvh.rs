@@ -0,0 +1,263 @@
+use glam::Vec3;
+
+use crate::baker::cpu::triangle::{
+  point_distance_to_triangle,
+  triangle_solid_angle,
+};
+
+/// The max number of triangles in a leaf node.
+const MAX_TRIANGLES_IN_LEAF: usize = 4;
+
+/// The node far away than this times its radius uses the dipole approximation of the winding number.
+pub const WINDING_NUMBER_ACCURACY: f32 = 2.0;
+
+/// The stack size of the winding number traversal, the same as STACK_SIZE of sign_pass_winding_number.
+pub const WINDING_NUMBER_STACK_SIZE: usize = 64;
+
+/// The BVH node.
+/// The left child of the inner node is the next node, the right child is at right_index.
+#[derive(Debug, Clone, Copy)]
+struct BVHNode {
+  min: Vec3,
+  max: Vec3,
+  right_index: u32,
+  start: u32,
+  count: u32, // 0 for the inner node.
+  num_of_triangles: u32, // All triangles under the node, from start.
+  // The area weighted normal sum, centroid and radius of the triangles for the winding number.
+  area_normal: Vec3,
+  centroid: Vec3,
+  radius: f32,
+}
+
+/// The bounding volume hierarchy of the triangles.
+pub struct BVH {
+  nodes: Vec<BVHNode>,
+  triangles: Vec<[Vec3; 3]>,
+  triangle_indices: Vec<u32>,
+}
+
+/// The implementation of the BVH.
+impl BVH {
+
+  /// Build the BVH.
+  /// param triangles: The triangles.
+  /// return: The BVH.
+  pub fn new(triangles: Vec<[Vec3; 3]>) -> Self {
+    let mut bvh = Self {
+      nodes: Vec::with_capacity(triangles.len().max(1) * 2 / MAX_TRIANGLES_IN_LEAF + 1),
+      triangle_indices: (0..triangles.len() as u32).collect(),
+      triangles,
+    };
+    if !bvh.triangles.is_empty() {
+      bvh.build_node(0, bvh.triangles.len());
+      bvh.triangles = bvh.triangle_indices.iter().map(|i| bvh.triangles[*i as usize]).collect();
+    }
+    bvh
+  }
+
+  /// Get the number of triangles.
+  /// return: The number of triangles.
+  pub fn num_of_triangles(&self) -> usize {
+    self.triangles.len()
+  }
+
+  /// Get the triangles in the order of the nodes.
+  /// return: The triangles.
+  pub fn get_triangles(&self) -> &[[Vec3; 3]] {
+    &self.triangles
+  }
+
+  /// Build the node of the triangles in the range recursively.
+  /// param start: The start of the range in the triangle indices.
+  /// param end: The end of the range in the triangle indices.
+  fn build_node(&mut self, start: usize, end: usize) {
+    let mut min = Vec3::splat(f32::MAX);
+    let mut max = Vec3::splat(f32::MIN);
+    let mut centroid_min = Vec3::splat(f32::MAX);
+    let mut centroid_max = Vec3::splat(f32::MIN);
+    let mut area_normal = Vec3::ZERO;
+    let mut weighted_centroid = Vec3::ZERO;
+    let mut area = 0.0;
+    for index in self.triangle_indices[start..end].iter() {
+      let [a, b, c] = self.triangles[*index as usize];
+      min = min.min(a).min(b).min(c);
+      max = max.max(a).max(b).max(c);
+      let centroid = (a + b + c) / 3.0;
+      centroid_min = centroid_min.min(centroid);
+      centroid_max = centroid_max.max(centroid);
+      let normal = (b - a).cross(c - a) * 0.5;
+      area_normal += normal;
+      weighted_centroid += centroid * normal.length();
+      area += normal.length();
+    }
+    let centroid = if area > 0.0 {
+      weighted_centroid / area
+    } else {
+      (min + max) * 0.5
+    };
+    let radius = self.triangle_indices[start..end].iter()
+      .flat_map(|index| self.triangles[*index as usize])
+      .fold(0.0f32, |r, v| r.max(v.distance(centroid)));
+
+    let node_index = self.nodes.len();
+    self.nodes.push(BVHNode {
+      min,
+      max,
+      right_index: 0,
+      start: start as u32,
+      count: (end - start) as u32,
+      num_of_triangles: (end - start) as u32,
+      area_normal,
+      centroid,
+      radius,
+    });
+    if end - start <= MAX_TRIANGLES_IN_LEAF {
+      return;
+    }
+
+    // Split at the median of the centroids along the longest axis.
+    let axis = (centroid_max - centroid_min).max_position();
+    let middle = (start + end) / 2;
+    let triangles = &self.triangles;
+    self.triangle_indices[start..end].select_nth_unstable_by(middle - start, |a, b| {
+      let [a0, a1, a2] = triangles[*a as usize];
+      let [b0, b1, b2] = triangles[*b as usize];
+      (a0[axis] + a1[axis] + a2[axis]).total_cmp(&(b0[axis] + b1[axis] + b2[axis]))
+    });
+
+    self.nodes[node_index].count = 0;
+    self.build_node(start, middle);
+    self.nodes[node_index].right_index = self.nodes.len() as u32;
+    self.build_node(middle, end);
+  }
+
+  /// Get the squared distance from the point to the box of the node.
+  /// param node: The node.
+  /// param pt: The point.
+  /// return: The squared distance, 0 if the point is inside.
+  #[inline]
+  fn node_distance_squared(node: &BVHNode, pt: Vec3) -> f32 {
+    let d = (node.min - pt).max(pt - node.max).max(Vec3::ZERO);
+    d.dot(d)
+  }
+
+  /// Get the nodes for the winding number on the GPU, 12 little-endian 32 bits values per node.
+  /// They are the area weighted normal sum, the radius, the centroid, the right index, the start and the count of the triangles,
+  /// the number of all triangles under the node and 1 padding.
+  /// return: The bytes.
+  pub fn get_winding_node_bytes(&self) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(self.nodes.len() * 48);
+    for node in self.nodes.iter() {
+      for v in node.area_normal.to_array().iter().chain(std::iter::once(&node.radius)).chain(node.centroid.to_array().iter()) {
+        bytes.extend_from_slice(&v.to_le_bytes());
+      }
+      for v in [node.right_index, node.start, node.count, node.num_of_triangles, 0] {
+        bytes.extend_from_slice(&v.to_le_bytes());
+      }
+    }
+    bytes
+  }
+
+  /// Get the max stack size of the winding number traversal, which pushes both children of an inner node.
+  /// return: The stack size, 0 if there is no node.
+  pub fn get_winding_stack_size(&self) -> usize {
+    if self.nodes.is_empty() {
+      return 0;
+    }
+
+    // Each node with the stack size after it is popped.
+    let mut stack_size = 1;
+    let mut stack = vec![(0usize, 0usize)];
+    while let Some((node_index, base)) = stack.pop() {
+      let node = &self.nodes[node_index];
+      if node.count == 0 {
+        stack_size = stack_size.max(base + 2);
+        stack.push((node_index + 1, base));
+        stack.push((node.right_index as usize, base + 1));
+      }
+    }
+    stack_size
+  }
+
+  /// Get the triangles in the order of the nodes, 9 little-endian f32 per triangle.
+  /// return: The bytes.
+  pub fn get_triangle_bytes(&self) -> Vec<u8> {
+    self.triangles.iter()
+      .flat_map(|t| t.iter().flat_map(|v| v.to_array()))
+      .flat_map(|v| v.to_le_bytes())
+      .collect()
+  }
+
+  /// Find the closest triangle to the point.
+  /// param pt: The point.
+  /// return: The original triangle index and the distance, None if there is no triangle.
+  pub fn closest_triangle(&self, pt: Vec3) -> Option<(usize, f32)> {
+    if self.nodes.is_empty() {
+      return None;
+    }
+
+    let mut closest_index = 0;
+    let mut closest_distance = f32::MAX;
+    let mut stack = vec![(0usize, Self::node_distance_squared(&self.nodes[0], pt))];
+    while let Some((node_index, distance_squared)) = stack.pop() {
+      if distance_squared >= closest_distance * closest_distance {
+        continue;
+      }
+      let node = &self.nodes[node_index];
+      if node.count > 0 {
+        for i in node.start as usize..(node.start + node.count) as usize {
+          let distance = point_distance_to_triangle(pt, &self.triangles[i]);
+          if distance < closest_distance {
+            closest_distance = distance;
+            closest_index = self.triangle_indices[i] as usize;
+          }
+        }
+      } else {
+        // Visit the nearer child first.
+        let left = (node_index + 1, Self::node_distance_squared(&self.nodes[node_index + 1], pt));
+        let right = (node.right_index as usize, Self::node_distance_squared(&self.nodes[node.right_index as usize], pt));
+        if left.1 < right.1 {
+          stack.push(right);
+          stack.push(left);
+        } else {
+          stack.push(left);
+          stack.push(right);
+        }
+      }
+    }
+
+    Some((closest_index, closest_distance))
+  }
+
+  /// Get the generalized winding number of the triangles at the point.
+  /// The far away nodes use the dipole approximation, the near ones sum the exact solid angles.
+  /// param pt: The point.
+  /// return: The winding number, about 1 inside and 0 outside of a closed mesh.
+  pub fn winding_number(&self, pt: Vec3) -> f32 {
+    if self.nodes.is_empty() {
+      return 0.0;
+    }
+
+    let mut solid_angle = 0.0;
+    let mut stack = vec![0usize];
+    while let Some(node_index) = stack.pop() {
+      let node = &self.nodes[node_index];
+      let to_centroid = node.centroid - pt;
+      let distance = to_centroid.length();
+      if distance > WINDING_NUMBER_ACCURACY * node.radius {
+        solid_angle += node.area_normal.dot(to_centroid) / (distance * distance * distance);
+      } else if node.count > 0 {
+        for i in node.start as usize..(node.start + node.count) as usize {
+          solid_angle += triangle_solid_angle(pt, &self.triangles[i]);
+        }
+      } else {
+        stack.push(node_index + 1);
+        stack.push(node.right_index as usize);
+      }
+    }
+
+    solid_angle / (4.0 * std::f32::consts::PI)
+  }
+
+}
@@ -0,0 +1,183 @@
+/// The sweep rounds between the checks of the flood fill, each round sweeps along all axes both ways.
+pub const FLOOD_FILL_SWEEP_ROUNDS: u32 = 8;
+
+/// The max sweep rounds of the flood fill, a safety cap for the corridors which turn more than this times 3.
+pub const FLOOD_FILL_MAX_SWEEP_ROUNDS: u32 = 4096;
+
+/// The states of the voxels in the fill map, the same as flood_fill.hlsl.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FillState {
+  Open, // Not reached yet.
+  Exterior, // Reached from the border.
+  Wall, // Crossed by the triangles or in the dilation radius of them.
+}
+
+/// The flood fill of the exterior from the border of the grid, the reference of the flood fill sign mode of the GPU baker.
+/// It sweeps the lines along the axes like the GPU until a batch of rounds changes no voxel.
+pub struct FloodFill {
+  pub dimensions: [u32; 3],
+  pub states: Vec<FillState>, // In X, Y, Z order.
+  pub num_of_rounds: u32, // The sweep rounds run until no voxel changed.
+}
+
+/// The implementation of the flood fill.
+impl FloodFill {
+
+  /// Mark the walls and fill the exterior from the border.
+  /// param dimensions: The dimensions of the voxels.
+  /// param walls: Whether each voxel is crossed by the triangles, in X, Y, Z order.
+  /// param connectivity: The neighbors of the fill, 6 or 26.
+  /// param dilation: The radius in voxels to dilate the walls by.
+  /// return: The flood fill.
+  pub fn new(dimensions: [u32; 3], walls: &[bool], connectivity: i32, dilation: i32) -> Self {
+    let mut fill = Self {
+      dimensions,
+      states: vec![FillState::Open; walls.len()],
+      num_of_rounds: 0,
+    };
+
+    // The voxel is the wall if any voxel with triangles is in the dilation sphere.
+    let r = dilation;
+    for z in 0..dimensions[2] as i32 {
+      for y in 0..dimensions[1] as i32 {
+        for x in 0..dimensions[0] as i32 {
+          let mut is_wall = false;
+          for dz in -r..=r {
+            for dy in -r..=r {
+              for dx in -r..=r {
+                if !is_wall && dx * dx + dy * dy + dz * dz <= r * r {
+                  is_wall = fill.index([x + dx, y + dy, z + dz]).is_some_and(|i| walls[i]);
+                }
+              }
+            }
+          }
+          let is_border = x == 0 || y == 0 || z == 0
+            || x == dimensions[0] as i32 - 1 || y == dimensions[1] as i32 - 1 || z == dimensions[2] as i32 - 1;
+          let index = fill.index([x, y, z]).unwrap();
+          fill.states[index] = if is_wall {
+            FillState::Wall
+          } else if is_border {
+            FillState::Exterior
+          } else {
+            FillState::Open
+          };
+        }
+      }
+    }
+
+    while fill.num_of_rounds < FLOOD_FILL_MAX_SWEEP_ROUNDS {
+      let mut is_changed = false;
+      for _ in 0..FLOOD_FILL_SWEEP_ROUNDS {
+        for axis in 0..3 {
+          is_changed |= fill.sweep(axis, connectivity);
+        }
+      }
+      fill.num_of_rounds += FLOOD_FILL_SWEEP_ROUNDS;
+      if !is_changed {
+        break;
+      }
+    }
+
+    fill
+  }
+
+  /// Get the index of the voxel.
+  /// param coord: The voxel coordinate.
+  /// return: The index, None if it is out of the grid.
+  fn index(&self, coord: [i32; 3]) -> Option<usize> {
+    if (0..3).all(|i| coord[i] >= 0 && coord[i] < self.dimensions[i] as i32) {
+      Some(((coord[2] as u32 * self.dimensions[1] + coord[1] as u32) * self.dimensions[0] + coord[0] as u32) as usize)
+    } else {
+      None
+    }
+  }
+
+  /// Get the state of the voxel.
+  /// param coord: The voxel coordinate.
+  /// return: The state, None if it is out of the grid.
+  pub fn state(&self, coord: [i32; 3]) -> Option<FillState> {
+    self.index(coord).map(|i| self.states[i])
+  }
+
+  /// Walk each line along the axis forward then backward, the open voxel is reached if the previous slice is reached.
+  /// param axis: The axis.
+  /// param connectivity: The neighbors of the fill, 6 or 26.
+  /// return: True if any voxel is reached.
+  fn sweep(&mut self, axis: usize, connectivity: i32) -> bool {
+    let u_axis = (axis + 1) % 3;
+    let v_axis = (axis + 2) % 3;
+    let line_length = self.dimensions[axis] as i32;
+    let mut is_changed = false;
+    for v in 0..self.dimensions[v_axis] as i32 {
+      for u in 0..self.dimensions[u_axis] as i32 {
+        let mut coord = [0; 3];
+        coord[u_axis] = u;
+        coord[v_axis] = v;
+        for direction in [1, -1] {
+          for n in 1..line_length {
+            coord[axis] = if direction > 0 { n } else { line_length - 1 - n };
+            if self.state(coord) != Some(FillState::Open) {
+              continue;
+            }
+
+            let mut previous = coord;
+            previous[axis] -= direction;
+            let mut is_reached = self.state(previous) == Some(FillState::Exterior);
+            if connectivity == 26 {
+              // The edge and corner neighbors in the previous slice.
+              for dv in -1..=1 {
+                for du in -1..=1 {
+                  let mut neighbor = previous;
+                  neighbor[u_axis] += du;
+                  neighbor[v_axis] += dv;
+                  is_reached |= self.state(neighbor) == Some(FillState::Exterior);
+                }
+              }
+            }
+            if is_reached {
+              let index = self.index(coord).unwrap();
+              self.states[index] = FillState::Exterior;
+              is_changed = true;
+            }
+          }
+        }
+      }
+    }
+    is_changed
+  }
+
+  /// Whether the voxel is inside, the same as flood_fill_finalize.
+  /// The open voxels are inside. The wall takes the side of its nearest face-adjacent voxels out of the walls
+  /// along the 6 axes, the exterior on a tie.
+  /// param coord: The voxel coordinate.
+  /// return: True if inside.
+  pub fn is_inside(&self, coord: [i32; 3]) -> bool {
+    match self.state(coord) {
+      Some(FillState::Open) => true,
+      Some(FillState::Wall) => {
+        // The grid border is always met, so the search ends.
+        let max_dimension = self.dimensions.iter().fold(0, |a, b| a.max(*b)) as i32;
+        for n in 1..=max_dimension {
+          let mut num_of_exterior = 0;
+          let mut num_of_open = 0;
+          for i in 0..6 {
+            let mut neighbor = coord;
+            neighbor[i / 2] += if i % 2 == 0 { -n } else { n };
+            // The voxels out of the grid are the exterior.
+            match self.state(neighbor) {
+              None | Some(FillState::Exterior) => num_of_exterior += 1,
+              Some(FillState::Open) => num_of_open += 1,
+              Some(FillState::Wall) => {},
+            }
+          }
+          if num_of_exterior + num_of_open > 0 {
+            return num_of_open > num_of_exterior;
+          }
+        }
+        false
+      },
+      _ => false,
+    }
+  }
+
+}
@@ -0,0 +1,231 @@
+use anyhow::Result;
+
+use glam::Vec3;
+
+use crate::baker::settings::{
+  SDFBakerSettings,
+  SignMode,
+};
+use crate::mesh::TriangleMesh;
+use crate::volume::{
+  SDFVolume,
+  DistanceUnit,
+};
+
+pub mod triangle;
+pub mod bvh;
+pub mod flood_fill;
+
+use bvh::BVH;
+use flood_fill::FloodFill;
+use triangle::triangle_overlaps_box;
+
+/// The winding number above this is inside of the mesh.
+pub const WINDING_NUMBER_THRESHOLD: f32 = 0.5;
+
+/// The SDF baker on the CPU.
+/// It is the fallback on the machines without GPU and the ground truth of the GPU baker.
+/// The triangles are in the same space as the box of the settings.
+pub struct SDFCPUBaker {
+  bvh: BVH,
+}
+
+/// The implementation of the CPU baker.
+impl SDFCPUBaker {
+
+  /// Create the CPU baker and build the BVH of the triangles.
+  /// param mesh: The triangle mesh.
+  /// return: The CPU baker.
+  pub fn new(mesh: &TriangleMesh) -> Self {
+    let triangles = (0..mesh.num_of_triangles())
+      .map(|i| mesh.triangle(i).map(Vec3::from_array))
+      .collect();
+    Self {
+      bvh: BVH::new(triangles),
+    }
+  }
+
+  /// Get the BVH of the triangles.
+  /// return: The BVH.
+  pub fn get_bvh(&self) -> &BVH {
+    &self.bvh
+  }
+
+  /// Get the unsigned distance from the point to the triangles.
+  /// param pt: The point.
+  /// return: The distance, None if there is no triangle.
+  pub fn get_distance(&self, pt: Vec3) -> Option<f32> {
+    self.bvh.closest_triangle(pt).map(|(_, distance)| distance)
+  }
+
+  /// Whether the point is inside of the triangles by the generalized winding number.
+  /// param pt: The point.
+  /// return: True if inside.
+  pub fn is_inside(&self, pt: Vec3) -> bool {
+    self.bvh.winding_number(pt) > WINDING_NUMBER_THRESHOLD
+  }
+
+  /// Bake the SDF or UDF by the settings.
+  /// param settings: The baker settings with the box fitted and snapped.
+  /// return: The volume.
+  pub fn bake(&self, settings: &SDFBakerSettings) -> Result<SDFVolume> {
+    if settings.is_sdf {
+      self.bake_sdf(settings)
+    } else {
+      self.bake_udf(settings)
+    }
+  }
+
+  /// Bake the SDF, the same grid layout as the GPU baker.
+  /// param settings: The baker settings with the box fitted and snapped.
+  /// return: The SDF volume.
+  pub fn bake_sdf(&self, settings: &SDFBakerSettings) -> Result<SDFVolume> {
+    self.bake_volume(settings, true)
+  }
+
+  /// Bake the UDF, the same grid layout as the GPU baker.
+  /// param settings: The baker settings with the box fitted and snapped.
+  /// return: The UDF volume.
+  pub fn bake_udf(&self, settings: &SDFBakerSettings) -> Result<SDFVolume> {
+    self.bake_volume(settings, false)
+  }
+
+  /// Bake the volume at the voxel centers, converted to the distance unit of the settings.
+  /// param settings: The baker settings.
+  /// param is_sdf: Whether the distance is signed.
+  /// return: The volume.
+  fn bake_volume(&self, settings: &SDFBakerSettings, is_sdf: bool) -> Result<SDFVolume> {
+    let dimensions = settings.estimate_grid_size();
+    let bounds = settings.get_bounds();
+    let data = self.bake_data(settings, is_sdf)?;
+
+    let mut volume = SDFVolume {
+      dimensions,
+      center: bounds.center,
+      extents: bounds.extents,
+      voxel_size: settings.actual_size[0] / dimensions[0] as f32,
+      is_sdf,
+      surface_offset: settings.surface_offset,
+      unit: DistanceUnit::Normalized,
+      data,
+      mips: Vec::new(),
+      gradients: Vec::new(),
+    };
+    volume.convert_unit(settings.distance_unit)?;
+
+    Ok(volume)
+  }
+
+  /// Bake the distances at the voxel centers, the same data as the GPU baker reads back.
+  /// The distance is normalized by the longest side of the box and offset by the surface offset as the GPU baker.
+  /// param settings: The baker settings.
+  /// param is_sdf: Whether the distance is signed.
+  /// return: The normalized distances in X, Y, Z order.
+  pub fn bake_data(&self, settings: &SDFBakerSettings, is_sdf: bool) -> Result<Vec<f32>> {
+    if self.bvh.num_of_triangles() == 0 {
+      return Err(anyhow::anyhow!("There is no triangle to bake."));
+    }
+
+    let dimensions = settings.estimate_grid_size();
+    let bounds = settings.get_bounds();
+    let max_size = settings.actual_size.iter().fold(0.0, |a: f32, b| a.max(*b));
+    let max_dimension = dimensions.iter().fold(0, |a, b| a.max(*b));
+    let voxel_size = max_size / max_dimension as f32;
+    let min_corner = Vec3::from_array(bounds.center) - Vec3::from_array(bounds.extents);
+    let surface_offset = settings.surface_offset;
+    let flood_fill = if is_sdf && settings.sign_mode == SignMode::FloodFill {
+      Some(self.flood_fill(settings))
+    } else {
+      None
+    };
+
+    Ok(self.compute_voxels(dimensions, |x, y, z| {
+      let pt = min_corner + (Vec3::new(x as f32, y as f32, z as f32) + Vec3::splat(0.5)) * voxel_size;
+      let distance = self.get_distance(pt).unwrap_or(f32::MAX) / max_size;
+      if is_sdf {
+        let is_inside = match flood_fill.as_ref() {
+          Some(flood_fill) => flood_fill.is_inside([x as i32, y as i32, z as i32]),
+          None => self.is_inside(pt),
+        };
+        let sign = if is_inside { -1.0 } else { 1.0 };
+        sign * distance - surface_offset
+      } else {
+        distance + surface_offset
+      }
+    }))
+  }
+
+  /// Get the voxels crossed by the triangles, as the conservative rasterization of the GPU baker.
+  /// param settings: The baker settings.
+  /// return: Whether each voxel is crossed, in X, Y, Z order.
+  pub fn get_wall_voxels(&self, settings: &SDFBakerSettings) -> Vec<bool> {
+    let dimensions = settings.estimate_grid_size();
+    let bounds = settings.get_bounds();
+    let max_size = settings.actual_size.iter().fold(0.0, |a: f32, b| a.max(*b));
+    let max_dimension = dimensions.iter().fold(0, |a, b| a.max(*b));
+    let voxel_size = max_size / max_dimension as f32;
+    let min_corner = Vec3::from_array(bounds.center) - Vec3::from_array(bounds.extents);
+    let max_coord = Vec3::new(dimensions[0] as f32, dimensions[1] as f32, dimensions[2] as f32) - Vec3::ONE;
+
+    let mut walls = vec![false; (dimensions[0] * dimensions[1] * dimensions[2]) as usize];
+    for tri in self.bvh.get_triangles().iter() {
+      let [a, b, c] = tri.map(|p| (p - min_corner) / voxel_size);
+      let start = a.min(b).min(c).floor().clamp(Vec3::ZERO, max_coord).to_array().map(|v| v as u32);
+      let end = a.max(b).max(c).floor().clamp(Vec3::ZERO, max_coord).to_array().map(|v| v as u32);
+      for z in start[2]..=end[2] {
+        for y in start[1]..=end[1] {
+          for x in start[0]..=end[0] {
+            let center = min_corner + (Vec3::new(x as f32, y as f32, z as f32) + Vec3::splat(0.5)) * voxel_size;
+            if triangle_overlaps_box(tri, center, Vec3::splat(voxel_size * 0.5)) {
+              walls[((z * dimensions[1] + y) * dimensions[0] + x) as usize] = true;
+            }
+          }
+        }
+      }
+    }
+    walls
+  }
+
+  /// Fill the exterior from the border of the grid by the fill settings.
+  /// param settings: The baker settings.
+  /// return: The flood fill.
+  pub fn flood_fill(&self, settings: &SDFBakerSettings) -> FloodFill {
+    FloodFill::new(
+      settings.estimate_grid_size(),
+      &self.get_wall_voxels(settings),
+      settings.fill_connectivity,
+      settings.fill_dilation,
+    )
+  }
+
+  /// Compute the value of each voxel in parallel, the Z slices are split into the threads.
+  /// param dimensions: The dimensions of the voxels.
+  /// param f: The function from the voxel coordinate to the value.
+  /// return: The values in X, Y, Z order.
+  fn compute_voxels<F>(&self, dimensions: [u32; 3], f: F) -> Vec<f32>
+  where
+    F: Fn(u32, u32, u32) -> f32 + Sync,
+  {
+    let slice_size = (dimensions[0] * dimensions[1]) as usize;
+    let mut data = vec![0.0f32; slice_size * dimensions[2] as usize];
+    let num_of_threads = std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
+    let slices_per_thread = (dimensions[2] as usize).div_ceil(num_of_threads).max(1);
+
+    std::thread::scope(|scope| {
+      for (chunk_index, chunk) in data.chunks_mut(slice_size * slices_per_thread).enumerate() {
+        let f = &f;
+        scope.spawn(move || {
+          for (i, value) in chunk.iter_mut().enumerate() {
+            let z = (chunk_index * slices_per_thread + i / slice_size) as u32;
+            let y = ((i % slice_size) / dimensions[0] as usize) as u32;
+            let x = (i % dimensions[0] as usize) as u32;
+            *value = f(x, y, z);
+          }
+        });
+      }
+    });
+
+    data
+  }
+
+}
@@ -0,0 +1,133 @@
+use glam::Vec3;
+
+/// Get the squared length of the vector.
+/// param v: The vector.
+/// return: The squared length.
+#[inline]
+fn dot2(v: Vec3) -> f32 {
+  v.dot(v)
+}
+
+/// Get the sign of the value as HLSL does, 0 for 0.
+/// param v: The value.
+/// return: -1, 0 or 1.
+#[inline]
+fn sign(v: f32) -> f32 {
+  if v > 0.0 {
+    1.0
+  } else if v < 0.0 {
+    -1.0
+  } else {
+    0.0
+  }
+}
+
+/// Get the squared distance from the point to the edge.
+/// param edge: The edge vector.
+/// param vector_p_to_v: The vector from the start vertex of the edge to the point.
+/// return: The squared distance.
+#[inline]
+fn edge_distance_squared(edge: Vec3, vector_p_to_v: Vec3) -> f32 {
+  let length_squared = dot2(edge);
+  let t = if length_squared > 0.0 {
+    (edge.dot(vector_p_to_v) / length_squared).clamp(0.0, 1.0)
+  } else {
+    0.0
+  };
+  dot2(edge * t - vector_p_to_v)
+}
+
+/// Get the distance from the point to the triangle.
+/// The same math as point_distance_to_triangle in baker.hlsl, with the degenerated edges guarded.
+/// param pt: The point.
+/// param tri: The 3 vertices of the triangle.
+/// return: The distance.
+pub fn point_distance_to_triangle(pt: Vec3, tri: &[Vec3; 3]) -> f32 {
+  let [a, b, c] = *tri;
+
+  // Calculate edge vectors.
+  let edge_a_to_b = b - a;
+  let edge_b_to_c = c - b;
+  let edge_c_to_a = a - c;
+
+  // Calculate vectors from point to triangle vertices.
+  let vector_p_to_a = pt - a;
+  let vector_p_to_b = pt - b;
+  let vector_p_to_c = pt - c;
+
+  // Calculate normal of the triangle
+  let normal = edge_a_to_b.cross(edge_c_to_a);
+
+  // Inside/outside test using signs of dot products
+  let is_outside = sign(edge_a_to_b.cross(normal).dot(vector_p_to_a))
+    + sign(edge_b_to_c.cross(normal).dot(vector_p_to_b))
+    + sign(edge_c_to_a.cross(normal).dot(vector_p_to_c)) < 2.0;
+
+  let dist_squared = if is_outside {
+    // If outside, calculate distance to the nearest edge
+    edge_distance_squared(edge_a_to_b, vector_p_to_a)
+      .min(edge_distance_squared(edge_b_to_c, vector_p_to_b))
+      .min(edge_distance_squared(edge_c_to_a, vector_p_to_c))
+  } else {
+    // If inside, calculate distance to the face
+    normal.dot(vector_p_to_a) * normal.dot(vector_p_to_a) / dot2(normal)
+  };
+
+  dist_squared.sqrt()
+}
+
+/// Get the signed solid angle of the triangle seen from the point.
+/// The angle is positive when the point is behind the counter-clockwise face.
+/// param pt: The point.
+/// param tri: The 3 vertices of the triangle.
+/// return: The solid angle in steradians.
+pub fn triangle_solid_angle(pt: Vec3, tri: &[Vec3; 3]) -> f32 {
+  let a = tri[0] - pt;
+  let b = tri[1] - pt;
+  let c = tri[2] - pt;
+  let length_a = a.length();
+  let length_b = b.length();
+  let length_c = c.length();
+
+  let determinant = a.dot(b.cross(c));
+  let divisor = length_a * length_b * length_c
+    + a.dot(b) * length_c
+    + b.dot(c) * length_a
+    + c.dot(a) * length_b;
+
+  2.0 * determinant.atan2(divisor)
+}
+
+/// Whether the triangle overlaps the axis aligned box, by the separating axis test of Akenine-Möller.
+/// The touching triangle overlaps, as the conservative rasterization of the GPU baker.
+/// param tri: The 3 vertices of the triangle.
+/// param center: The center of the box.
+/// param half_size: The half size of the box.
+/// return: True if they overlap.
+pub fn triangle_overlaps_box(tri: &[Vec3; 3], center: Vec3, half_size: Vec3) -> bool {
+  let v = tri.map(|p| p - center);
+  let edges = [v[1] - v[0], v[2] - v[1], v[0] - v[2]];
+
+  // Project the triangle and the box on the axis, they are separated if the intervals do not overlap.
+  let is_separated = |axis: Vec3| {
+    let p = v.map(|p| p.dot(axis));
+    let radius = half_size.dot(axis.abs());
+    p[0].min(p[1]).min(p[2]) > radius || p[0].max(p[1]).max(p[2]) < -radius
+  };
+
+  // The 9 cross products of the edges and the box axes, the 3 box axes and the triangle normal.
+  for edge in edges.iter() {
+    for axis in [Vec3::X, Vec3::Y, Vec3::Z] {
+      let axis = axis.cross(*edge);
+      if axis != Vec3::ZERO && is_separated(axis) {
+        return false;
+      }
+    }
+  }
+  for axis in [Vec3::X, Vec3::Y, Vec3::Z] {
+    if is_separated(axis) {
+      return false;
+    }
+  }
+  !is_separated(edges[0].cross(edges[1]))
+}
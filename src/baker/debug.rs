@@ -102,20 +102,22 @@ impl SDFBaker {
     descriptor_set: &hala_gfx::HalaDescriptorSet,
     rect: &[f32; 4],
   ) -> Result<(), HalaRendererError> {
-    self.sdf_baker_resources.image_2_screen_program.bind(
+    let program = self.sdf_baker_resources.image_2_screen_program.as_ref()
+      .ok_or(HalaRendererError::new("Failed to get the image to screen program.", None))?;
+    program.bind(
       index,
       command_buffers,
       &[descriptor_set]
     );
 
-    self.sdf_baker_resources.image_2_screen_program.push_constants_f32(
+    program.push_constants_f32(
       index,
       command_buffers,
       0,
       rect,
     );
 
-    self.sdf_baker_resources.image_2_screen_program.draw(
+    program.draw(
       index,
       command_buffers,
       4,
@@ -140,14 +142,16 @@ impl SDFBaker {
     bounds: &HalaBounds,
     mvp_mtx: &[f32; 16],
   ) -> Result<(), HalaRendererError> {
-    self.cross_xyz_program.bind(
+    let program = self.cross_xyz_program.as_ref()
+      .ok_or(HalaRendererError::new("Failed to get the cross xyz program.", None))?;
+    program.bind(
       index,
       command_buffers,
       &[self.cross_xyz_descriptor_set.as_ref()]
     );
 
     // Push constants.
-    self.cross_xyz_program.push_constants_f32(
+    program.push_constants_f32(
       index,
       command_buffers,
       0,
@@ -162,7 +166,7 @@ impl SDFBaker {
     );
 
     // Draw planes.
-    self.cross_xyz_program.draw(
+    program.draw(
       index,
       command_buffers,
       18,
@@ -190,7 +194,9 @@ impl SDFBaker {
     color: &[f32; 4],
     offset: f32,
   ) -> Result<(), HalaRendererError> {
-    self.sdf_visualization_program.bind(
+    let program = self.sdf_visualization_program.as_ref()
+      .ok_or(HalaRendererError::new("Failed to get the sdf visualization program.", None))?;
+    program.bind(
       index,
       command_buffers,
       &[
@@ -199,7 +205,7 @@ impl SDFBaker {
     );
 
     // Push constants.
-    self.sdf_visualization_program.push_constants_f32(
+    program.push_constants_f32(
       index,
       command_buffers,
       0,
@@ -212,7 +218,7 @@ impl SDFBaker {
     );
 
     // Draw planes.
-    self.sdf_visualization_program.draw(
+    program.draw(
       index,
       command_buffers,
       36,
@@ -263,4 +269,54 @@ impl SDFBaker {
     Ok(data)
   }
 
-}
\ No newline at end of file
+  /// Get the elements in a range of the buffer, only the range is copied to the staging buffer.
+  /// param buffer: The buffer.
+  /// param first_element: The index of the first element.
+  /// param num_of_elements: The number of the elements.
+  /// return: The elements.
+  pub(super) fn get_buffer_elements<T: Copy + Default>(
+    &self,
+    buffer: &hala_gfx::HalaBuffer,
+    first_element: u64,
+    num_of_elements: u64,
+  ) -> Result<Vec<T>, HalaRendererError> {
+    let element_size = std::mem::size_of::<T>() as u64;
+    let offset = first_element * element_size;
+    let size = num_of_elements * element_size;
+    if offset + size > buffer.size {
+      return Err(HalaRendererError::new(
+        &format!("The {} bytes at the offset {} are out of the buffer of {} bytes.", size, offset, buffer.size),
+        None,
+      ));
+    }
+    let context = self.resources.context.borrow();
+
+    let staging_buffer = hala_gfx::HalaBuffer::new(
+      Rc::clone(&context.logical_device),
+      size,
+      hala_gfx::HalaBufferUsageFlags::TRANSFER_DST,
+      hala_gfx::HalaMemoryLocation::GpuToCpu,
+      "elements_staging_buffer",
+    )?;
+
+    context.logical_device.borrow().transfer_execute_and_submit(
+      &self.resources.transfer_command_buffers,
+      0,
+      |_logical_device, command_buffers, index| {
+        command_buffers.copy_buffer_2_buffer(
+          index,
+          buffer,
+          offset,
+          &staging_buffer,
+          0,
+        );
+      },
+      0
+    )?;
+    let mut data = vec![T::default(); num_of_elements as usize];
+    staging_buffer.download_memory::<T>(0, data.as_mut_slice())?;
+
+    Ok(data)
+  }
+
+}
@@ -115,7 +115,7 @@ impl SDFBaker {
     }
 
     // Draw debug image3d.
-    let mvp_mtx = self.get_mvp_matrix_in_scene(self.settings.selected_mesh_index).to_cols_array();
+    let mvp_mtx = (self.get_vp_matrix_in_scene() * self.get_bake_model_matrix()).to_cols_array();
     if self.settings.is_sdf && self.settings.show_ray_map && self.sdf_baker_resources.ray_map.is_some() {
       self.debug_draw_image3d(
         index,
@@ -316,14 +316,16 @@ impl SDFBaker {
     mvp_mtx: &[f32; 16],
     color: [f32; 4],
   ) -> Result<(), HalaRendererError> {
-    self.bounds_program.bind(
+    let program = self.bounds_program.as_ref()
+      .ok_or(HalaRendererError::new("Failed to get the bounds program.", None))?;
+    program.bind(
       index,
       command_buffers,
       &[] as &[&hala_gfx::HalaDescriptorSet]
     );
 
     // Push constants.
-    self.bounds_program.push_constants_f32(
+    program.push_constants_f32(
       index,
       command_buffers,
       0,
@@ -339,7 +341,7 @@ impl SDFBaker {
     );
 
     // Draw lines.
-    self.bounds_program.draw(
+    program.draw(
       index,
       command_buffers,
       2,
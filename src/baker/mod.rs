@@ -4,7 +4,7 @@ use hala_gfx::HalaGPURequirements;
 
 use hala_renderer::error::HalaRendererError;
 use hala_renderer::scene::{
-  cpu, gpu, loader
+  gpu, loader
 };
 use hala_renderer::shader_cache::HalaShaderCache;
 use hala_renderer::graphics_program::HalaGraphicsProgram;
@@ -22,6 +22,7 @@ pub mod udf_resources;
 pub mod draw;
 pub mod debug;
 pub mod bake;
+pub mod cpu;
 
 use crate::config;
 use crate::baker::settings::SDFBakerSettings;
@@ -30,6 +31,16 @@ use crate::baker::sdf_resources::{
   SDFBakerSDFVisualizationUniform,
 };
 use crate::baker::udf_resources::UDFBakerResources;
+use crate::baker::bake::merge::SDFBakerMergedGeometry;
+use crate::baker::bake::attribute::get_scene_attributes;
+use crate::mesh::{
+  TriangleMesh,
+  PointCloud,
+};
+use crate::volume::{
+  SceneAttributes,
+  TriangleRange,
+};
 
 /// The axis enum.
 pub(crate) enum Axis {
@@ -78,19 +89,29 @@ pub struct SDFBaker {
   pub(crate) global_uniform_buffer: std::mem::ManuallyDrop<hala_gfx::HalaBuffer>,
 
   pub(crate) baker_config: config::BakerConfig,
+  pub(crate) max_buffer_size: u64, // The max size in bytes of a storage buffer, the smaller of maxStorageBufferRange and maxMemoryAllocationSize of the device.
   pub(crate) sdf_baker_resources: std::mem::ManuallyDrop<SDFBakerResources>,
   pub(crate) udf_baker_resources: std::mem::ManuallyDrop<UDFBakerResources>,
 
   pub(crate) wireframe_program: Option<HalaGraphicsProgram>,
   pub(crate) wireframe_debug_program: Option<HalaGraphicsProgram>,
-  pub(crate) bounds_program: std::mem::ManuallyDrop<HalaGraphicsProgram>,
+  pub(crate) bounds_program: Option<HalaGraphicsProgram>,
 
   pub(crate) dynamic_descriptor_set: Option<hala_gfx::HalaDescriptorSet>,
   pub(crate) object_uniform_buffers: Vec<Vec<hala_gfx::HalaBuffer>>,
 
   pub(crate) scene_in_gpu: Option<gpu::HalaScene>,
   pub(crate) num_of_meshes: usize,
+  pub(crate) mesh_names: Vec<String>,
+  pub(crate) mesh_primitive_counts: Vec<usize>,
+  pub(crate) primitive_meshes: Vec<TriangleMesh>,
+  pub(crate) scene_attributes: SceneAttributes, // The vertex attributes of the glTF scene, empty for the mesh and point cloud files.
+  pub(crate) merged_geometry: Option<SDFBakerMergedGeometry>,
+  pub(crate) point_cloud: Option<PointCloud>,
+  pub(crate) point_buffer: Option<hala_gfx::HalaBuffer>,
   pub(crate) matrices_in_scene: Vec<glam::Mat4>,
+  pub(crate) baked_volume_data: Option<Vec<f32>>, // The normalized voxels of the last tiled or CPU bake, the GPU holds none or only the last brick.
+  pub(crate) closest_triangle_ranges: Option<Vec<TriangleRange>>, // The triangle ranges of the last GPU SDF bake of the whole grid which kept the closest triangles.
 
   pub(crate) textures_descriptor_set: Option<hala_gfx::HalaDescriptorSet>,
 
@@ -100,11 +121,13 @@ pub struct SDFBaker {
   pub(crate) image3d_sampler: std::mem::ManuallyDrop<hala_gfx::HalaSampler>,
 
   pub(crate) cross_xyz_descriptor_set: std::mem::ManuallyDrop<hala_gfx::HalaDescriptorSet>,
-  pub(crate) cross_xyz_program: std::mem::ManuallyDrop<HalaGraphicsProgram>,
+  pub(crate) cross_xyz_program: Option<HalaGraphicsProgram>,
 
   pub(crate) sdf_visualization_uniform_buffer: std::mem::ManuallyDrop<hala_gfx::HalaBuffer>,
   pub(crate) sdf_visualization_descriptor_set: std::mem::ManuallyDrop<hala_gfx::HalaDescriptorSet>,
-  pub(crate) sdf_visualization_program: std::mem::ManuallyDrop<HalaGraphicsProgram>,
+  pub(crate) sdf_visualization_program: Option<HalaGraphicsProgram>,
+  pub(crate) sdf_visualization_mips_buffer: Option<hala_gfx::HalaBuffer>, // The mip levels from 1 of the last bake in the normalized unit.
+  pub(crate) sdf_visualization_num_of_mips: u32,
 
   pub settings: SDFBakerSettings,
 
@@ -119,6 +142,8 @@ impl Drop for SDFBaker {
   fn drop(&mut self) {
     self.textures_descriptor_set = None;
 
+    self.merged_geometry = None;
+    self.point_buffer = None;
     self.scene_in_gpu = None;
 
     self.object_uniform_buffers.clear();
@@ -126,15 +151,16 @@ impl Drop for SDFBaker {
 
     self.wireframe_program = None;
     self.wireframe_debug_program = None;
+    self.sdf_visualization_program = None;
+    self.sdf_visualization_mips_buffer = None;
+    self.cross_xyz_program = None;
+    self.bounds_program = None;
     HalaShaderCache::get_instance().borrow_mut().clear();
     unsafe {
-      std::mem::ManuallyDrop::drop(&mut self.sdf_visualization_program);
       std::mem::ManuallyDrop::drop(&mut self.sdf_visualization_descriptor_set);
       std::mem::ManuallyDrop::drop(&mut self.sdf_visualization_uniform_buffer);
-      std::mem::ManuallyDrop::drop(&mut self.cross_xyz_program);
       std::mem::ManuallyDrop::drop(&mut self.cross_xyz_descriptor_set);
       std::mem::ManuallyDrop::drop(&mut self.image3d_sampler);
-      std::mem::ManuallyDrop::drop(&mut self.bounds_program);
       std::mem::ManuallyDrop::drop(&mut self.udf_baker_resources);
       std::mem::ManuallyDrop::drop(&mut self.sdf_baker_resources);
       std::mem::ManuallyDrop::drop(&mut self.global_uniform_buffer);
@@ -153,22 +179,32 @@ impl SDFBaker {
   /// Create a new renderer.
   /// param name: The name of the SDF baker.
   /// param gpu_req: The GPU requirements of the SDF baker.
-  /// param window: The window of the SDF baker.
+  /// param window: The window of the SDF baker, None for headless baking.
   /// return: the SDF baker.
   pub fn new(
     name: &str,
     gpu_req: &HalaGPURequirements,
-    window: &winit::window::Window,
+    window: Option<&winit::window::Window>,
   ) -> Result<Self, HalaRendererError> {
     let width = gpu_req.width;
     let height = gpu_req.height;
 
-    let resources = HalaRendererResources::new(
-      name,
-      gpu_req,
-      window,
-      &Self::get_descriptor_sizes(),
-    )?;
+    let resources = match window {
+      Some(window) => HalaRendererResources::new(
+        name,
+        gpu_req,
+        window,
+        &Self::get_descriptor_sizes(),
+      )?,
+      None => HalaRendererResources::new_headless(
+        name,
+        gpu_req,
+        &Self::get_descriptor_sizes(),
+      )?,
+    };
+    let is_headless = window.is_none();
+    let max_buffer_size = Self::get_max_buffer_size(&resources.context.borrow());
+    log::debug!("The max size of a storage buffer is {} bytes.", max_buffer_size);
 
     let bake_command_buffers = hala_gfx::HalaCommandBufferSet::new(
       Rc::clone(&resources.context.borrow().logical_device),
@@ -260,7 +296,7 @@ impl SDFBaker {
     let sdf_baker_resources = SDFBakerResources::new(
       Rc::clone(&resources.context.borrow().logical_device),
       Rc::clone(&resources.descriptor_pool),
-      &resources.context.borrow().swapchain,
+      if is_headless { None } else { Some(&resources.context.borrow().swapchain) },
       &sdf_baker_config,
       &pipeline_cache,
     )?;
@@ -279,19 +315,24 @@ impl SDFBaker {
       &pipeline_cache,
     )?;
 
+    // The graphics programs need a swapchain, so they are only created when we have a window.
     let bounds_desc = sdf_baker_config.graphics_programs.get("bounds").ok_or(HalaRendererError::new("Failed to get graphics program \"bounds\".", None))?;
-    let bounds_program = HalaGraphicsProgram::with_swapchain(
-      Rc::clone(&resources.context.borrow().logical_device),
-      &resources.context.borrow().swapchain,
-      &[] as &[&hala_gfx::HalaDescriptorSetLayout],
-      hala_gfx::HalaPipelineCreateFlags::default(),
-      &[] as &[hala_gfx::HalaVertexInputAttributeDescription],
-      &[] as &[hala_gfx::HalaVertexInputBindingDescription],
-      &[],
-      bounds_desc,
-      Some(&pipeline_cache),
-      "bounds",
-    )?;
+    let bounds_program = if is_headless {
+      None
+    } else {
+      Some(HalaGraphicsProgram::with_swapchain(
+        Rc::clone(&resources.context.borrow().logical_device),
+        &resources.context.borrow().swapchain,
+        &[] as &[&hala_gfx::HalaDescriptorSetLayout],
+        hala_gfx::HalaPipelineCreateFlags::default(),
+        &[] as &[hala_gfx::HalaVertexInputAttributeDescription],
+        &[] as &[hala_gfx::HalaVertexInputBindingDescription],
+        &[],
+        bounds_desc,
+        Some(&pipeline_cache),
+        "bounds",
+      )?)
+    };
 
     let image3d_sampler = hala_gfx::HalaSampler::new(
       Rc::clone(&resources.context.borrow().logical_device),
@@ -326,18 +367,22 @@ impl SDFBaker {
       0,
       "cross_xyz.descriptor_set",
     )?;
-    let cross_xyz_program = HalaGraphicsProgram::with_swapchain(
-      Rc::clone(&resources.context.borrow().logical_device),
-      &resources.context.borrow().swapchain,
-      &[&cross_xyz_descriptor_set.layout],
-      hala_gfx::HalaPipelineCreateFlags::default(),
-      &[] as &[hala_gfx::HalaVertexInputAttributeDescription],
-      &[] as &[hala_gfx::HalaVertexInputBindingDescription],
-      &[],
-      cross_xyz_desc,
-      Some(&pipeline_cache),
-      "cross_xyz",
-    )?;
+    let cross_xyz_program = if is_headless {
+      None
+    } else {
+      Some(HalaGraphicsProgram::with_swapchain(
+        Rc::clone(&resources.context.borrow().logical_device),
+        &resources.context.borrow().swapchain,
+        &[&cross_xyz_descriptor_set.layout],
+        hala_gfx::HalaPipelineCreateFlags::default(),
+        &[] as &[hala_gfx::HalaVertexInputAttributeDescription],
+        &[] as &[hala_gfx::HalaVertexInputBindingDescription],
+        &[],
+        cross_xyz_desc,
+        Some(&pipeline_cache),
+        "cross_xyz",
+      )?)
+    };
 
     let sdf_visualization_uniform_buffer = hala_gfx::HalaBuffer::new(
       Rc::clone(&resources.context.borrow().logical_device),
@@ -368,20 +413,24 @@ impl SDFBaker {
       0,
       "sdf_visualization.descriptor_set",
     )?;
-    let sdf_visualization_program = HalaGraphicsProgram::with_swapchain(
-      Rc::clone(&resources.context.borrow().logical_device),
-      &resources.context.borrow().swapchain,
-      &[
-        &sdf_visualization_descriptor_set.layout
-      ],
-      hala_gfx::HalaPipelineCreateFlags::default(),
-      &[] as &[hala_gfx::HalaVertexInputAttributeDescription],
-      &[] as &[hala_gfx::HalaVertexInputBindingDescription],
-      &[],
-      sdf_visualization_desc,
-      Some(&pipeline_cache),
-      "sdf_visualization",
-    )?;
+    let sdf_visualization_program = if is_headless {
+      None
+    } else {
+      Some(HalaGraphicsProgram::with_swapchain(
+        Rc::clone(&resources.context.borrow().logical_device),
+        &resources.context.borrow().swapchain,
+        &[
+          &sdf_visualization_descriptor_set.layout
+        ],
+        hala_gfx::HalaPipelineCreateFlags::default(),
+        &[] as &[hala_gfx::HalaVertexInputAttributeDescription],
+        &[] as &[hala_gfx::HalaVertexInputBindingDescription],
+        &[],
+        sdf_visualization_desc,
+        Some(&pipeline_cache),
+        "sdf_visualization",
+      )?)
+    };
 
     pipeline_cache.save("./out/pipeline_cache.bin")?;
 
@@ -398,19 +447,29 @@ impl SDFBaker {
       global_uniform_buffer: std::mem::ManuallyDrop::new(global_uniform_buffer),
 
       baker_config: sdf_baker_config,
+      max_buffer_size,
       sdf_baker_resources: std::mem::ManuallyDrop::new(sdf_baker_resources),
       udf_baker_resources: std::mem::ManuallyDrop::new(udf_baker_resources),
 
       wireframe_program: None,
       wireframe_debug_program: None,
-      bounds_program: std::mem::ManuallyDrop::new(bounds_program),
+      bounds_program,
 
       dynamic_descriptor_set: None,
       object_uniform_buffers: Vec::new(),
 
       scene_in_gpu: None,
       num_of_meshes: 0,
+      mesh_names: Vec::new(),
+      mesh_primitive_counts: Vec::new(),
+      primitive_meshes: Vec::new(),
+      scene_attributes: SceneAttributes::default(),
+      merged_geometry: None,
+      point_cloud: None,
+      point_buffer: None,
       matrices_in_scene: Vec::new(),
+      baked_volume_data: None,
+      closest_triangle_ranges: None,
 
       textures_descriptor_set: None,
 
@@ -422,11 +481,13 @@ impl SDFBaker {
       image3d_sampler: std::mem::ManuallyDrop::new(image3d_sampler),
 
       cross_xyz_descriptor_set: std::mem::ManuallyDrop::new(cross_xyz_descriptor_set),
-      cross_xyz_program: std::mem::ManuallyDrop::new(cross_xyz_program),
+      cross_xyz_program,
 
       sdf_visualization_uniform_buffer: std::mem::ManuallyDrop::new(sdf_visualization_uniform_buffer),
       sdf_visualization_descriptor_set: std::mem::ManuallyDrop::new(sdf_visualization_descriptor_set),
-      sdf_visualization_program: std::mem::ManuallyDrop::new(sdf_visualization_program),
+      sdf_visualization_program,
+      sdf_visualization_mips_buffer: None,
+      sdf_visualization_num_of_mips: 0,
 
       is_rotating_camera: false,
       begin_rotating_camera_x: f32::NAN,
@@ -434,13 +495,54 @@ impl SDFBaker {
     })
   }
 
+  /// Get the max size of a storage buffer of the device.
+  /// The whole buffers are bound to the shaders, so they fit both the range of the binding and a single allocation.
+  /// param context: The context.
+  /// return: The smaller of maxStorageBufferRange and maxMemoryAllocationSize in bytes.
+  fn get_max_buffer_size(context: &hala_gfx::HalaContext) -> u64 {
+    let mut maintenance3_properties = ash::vk::PhysicalDeviceMaintenance3Properties::default();
+    let max_storage_buffer_range = {
+      let mut properties2 = ash::vk::PhysicalDeviceProperties2::default()
+        .push_next(&mut maintenance3_properties);
+      unsafe {
+        context.instance.raw.get_physical_device_properties2(context.physical_device.raw, &mut properties2);
+      }
+      properties2.properties.limits.max_storage_buffer_range as u64
+    };
+    max_storage_buffer_range.min(maintenance3_properties.max_memory_allocation_size)
+  }
+
   /// Set the scene to be rendered.
   /// param scene_in_cpu: The scene in the CPU.
   /// return: The result.
-  pub fn set_scene(&mut self, scene_in_cpu: &mut cpu::HalaScene) -> Result<(), HalaRendererError> {
+  pub fn set_scene(&mut self, scene_in_cpu: &mut hala_renderer::scene::cpu::HalaScene) -> Result<(), HalaRendererError> {
+    // Name the meshes after the nodes referencing them, the unnamed ones are named by the index.
+    let mut mesh_names = (0..scene_in_cpu.meshes.len()).map(|i| format!("mesh_{}", i)).collect::<Vec<_>>();
+    for node in scene_in_cpu.nodes.iter() {
+      if let Some(name) = mesh_names.get_mut(node.mesh_index as usize) {
+        if !node.name.is_empty() {
+          *name = node.name.clone();
+        }
+      }
+    }
+    // Keep the triangles on the CPU to merge the primitives.
+    let primitive_meshes = scene_in_cpu.meshes.iter()
+      .flat_map(|mesh| mesh.primitives.iter())
+      .map(|primitive| TriangleMesh::new(
+        primitive.vertices.iter().map(|v| v.position).collect(),
+        primitive.indices.clone(),
+      ))
+      .collect::<Vec<_>>();
+    let mesh_primitive_counts = scene_in_cpu.meshes.iter().map(|mesh| mesh.primitives.len()).collect();
+    // Keep the vertex attributes and the base colors to bake the attribute volumes.
+    let scene_attributes = get_scene_attributes(scene_in_cpu);
+
     let scene_in_gpu = {
       let context = self.resources.context.borrow();
       // Release the old scene in the GPU.
+      self.merged_geometry = None;
+      self.point_cloud = None;
+      self.point_buffer = None;
       self.scene_in_gpu = None;
 
       // Upload the new scene to the GPU.
@@ -454,6 +556,13 @@ impl SDFBaker {
       false)
     }?;
 
+    self.num_of_meshes = 0;
+    self.matrices_in_scene.clear();
+    self.mesh_names = mesh_names;
+    self.mesh_primitive_counts = mesh_primitive_counts;
+    self.primitive_meshes = primitive_meshes;
+    self.closest_triangle_ranges = None;
+    self.scene_attributes = scene_attributes;
     for mesh in scene_in_gpu.meshes.iter() {
       for _ in mesh.primitives.iter() {
         self.num_of_meshes += 1;
@@ -469,6 +578,74 @@ impl SDFBaker {
     Ok(())
   }
 
+  /// Set the triangle mesh loaded from the mesh file as the only mesh to bake.
+  /// There is no scene in the GPU, so the mesh can only be baked and saved headlessly.
+  /// param name: The name of the mesh.
+  /// param mesh: The triangle mesh.
+  pub fn set_triangle_mesh(&mut self, name: &str, mesh: TriangleMesh) {
+    self.merged_geometry = None;
+    self.point_cloud = None;
+    self.point_buffer = None;
+    self.scene_in_gpu = None;
+
+    self.num_of_meshes = 1;
+    self.matrices_in_scene = vec![glam::Mat4::IDENTITY];
+    self.mesh_names = vec![name.to_string()];
+    self.mesh_primitive_counts = vec![1];
+    self.primitive_meshes = vec![mesh];
+    self.closest_triangle_ranges = None;
+    self.scene_attributes = SceneAttributes::default();
+    self.settings.selected_mesh_index = 0;
+
+    self.fit_box_to_bounds();
+    self.snap_box_to_bounds();
+  }
+
+  /// Set the point cloud loaded from the point cloud file as the only mesh to bake.
+  /// The point cloud can only be baked as the UDF headlessly, the points are uploaded to the GPU here.
+  /// param name: The name of the point cloud.
+  /// param points: The point cloud.
+  /// return: The result.
+  pub fn set_point_cloud(&mut self, name: &str, points: PointCloud) -> Result<(), HalaRendererError> {
+    if points.num_of_points() == 0 {
+      return Err(HalaRendererError::new("The point cloud has no point.", None));
+    }
+    self.merged_geometry = None;
+    self.point_cloud = None;
+    self.point_buffer = None;
+    self.scene_in_gpu = None;
+
+    let position_bytes = points.get_position_bytes();
+    let point_buffer = {
+      let context = self.resources.context.borrow();
+      let point_buffer = hala_gfx::HalaBuffer::new(
+        Rc::clone(&context.logical_device),
+        position_bytes.len() as u64,
+        hala_gfx::HalaBufferUsageFlags::STORAGE_BUFFER,
+        hala_gfx::HalaMemoryLocation::CpuToGpu,
+        "point.buffer",
+      )?;
+      point_buffer.update_memory(0, position_bytes.as_slice())?;
+      point_buffer
+    };
+
+    self.num_of_meshes = 1;
+    self.matrices_in_scene = vec![glam::Mat4::IDENTITY];
+    self.mesh_names = vec![name.to_string()];
+    self.mesh_primitive_counts = vec![1];
+    self.primitive_meshes = Vec::new();
+    self.closest_triangle_ranges = None;
+    self.scene_attributes = SceneAttributes::default();
+    self.point_cloud = Some(points);
+    self.point_buffer = Some(point_buffer);
+    self.settings.selected_mesh_index = 0;
+
+    self.fit_box_to_bounds();
+    self.snap_box_to_bounds();
+
+    Ok(())
+  }
+
   pub fn begin_rotate_camera(&mut self) -> Result<(), HalaRendererError> {
     self.is_rotating_camera = true;
     self.begin_rotating_camera_x = f32::NAN;
@@ -873,14 +1050,15 @@ impl HalaRendererTrait for SDFBaker {
 
     // Update SDF visualization uniform buffer.
     let sdf_visualization_uniform = SDFBakerSDFVisualizationUniform {
-      m_mtx: *self.get_model_matrix_in_scene(self.settings.selected_mesh_index),
-      i_m_mtx: self.get_model_matrix_in_scene(self.settings.selected_mesh_index).inverse(),
+      m_mtx: self.get_bake_model_matrix(),
+      i_m_mtx: self.get_bake_model_matrix().inverse(),
       vp_mtx: self.get_vp_matrix_in_scene(),
-      mvp_mtx: self.get_mvp_matrix_in_scene(self.settings.selected_mesh_index),
+      mvp_mtx: self.get_vp_matrix_in_scene() * self.get_bake_model_matrix(),
       camera_position: self.get_camera_position(0),
       offset: 0.0,
       dimensions: self.estimate_grid_size(),
       inv_resolution: 1.0 / self.settings.max_resolution as f32,
+      num_of_mips: self.sdf_visualization_num_of_mips,
     };
     self.sdf_visualization_uniform_buffer.update_memory(0, std::slice::from_ref(&sdf_visualization_uniform))?;
 
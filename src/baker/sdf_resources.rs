@@ -30,6 +30,8 @@ pub(super) struct SDFBakerCSMeshUniform {
   pub vertex_position_offset: u32,
   pub vertex_stride: u32,
   pub index_stride: u32,
+  pub padding0: u32,
+  pub local_to_bake: glam::Mat4,
 }
 
 #[repr(C, align(16))]
@@ -51,6 +53,7 @@ pub(super) struct SDFBakerSDFVisualizationUniform {
   pub offset: f32,
   pub dimensions: [u32; 3],
   pub inv_resolution: f32,
+  pub num_of_mips: u32,
 }
 
 /// The baker resources.
@@ -84,17 +87,21 @@ pub(crate) struct SDFBakerResources {
   pub(crate) ray_map: Option<hala_gfx::HalaImage>,
   pub(crate) sign_map: Option<hala_gfx::HalaImage>,
   pub(crate) sign_map_bis: Option<hala_gfx::HalaImage>,
+  pub(crate) winding_number_nodes_buffer: Option<hala_gfx::HalaBuffer>,
+  pub(crate) winding_number_triangles_buffer: Option<hala_gfx::HalaBuffer>,
+  pub(crate) flood_fill_changed_buffer: Option<hala_gfx::HalaBuffer>,
 
   pub(crate) voxels_texture: Option<hala_gfx::HalaImage>,
   pub(crate) voxels_texture_bis: Option<hala_gfx::HalaImage>,
 
   pub(crate) distance_texture: Option<hala_gfx::HalaImage>,
+  pub(crate) closest_triangles_buffer: Option<hala_gfx::HalaBuffer>,
 
   pub(crate) render_targets: [Option<hala_gfx::HalaImage>; 3],
 
   pub(crate) image_2_screen_sampler: hala_gfx::HalaSampler,
   pub(crate) image_2_screen_descriptor_sets: [hala_gfx::HalaDescriptorSet; 3],
-  pub(crate) image_2_screen_program: HalaGraphicsProgram,
+  pub(crate) image_2_screen_program: Option<HalaGraphicsProgram>,
 
   pub(crate) write_uvw_and_coverage_programs: [Option<HalaGraphicsProgram>; 3],
   pub(crate) write_triangle_ids_to_voxels_programs: [Option<HalaGraphicsProgram>; 3],
@@ -105,18 +112,37 @@ pub(crate) struct SDFBakerResources {
 impl SDFBakerResources {
 
   pub const PREFIX_SUM_THREAD_GROUP_SIZE: u32 = 512;
+  /// The bytes per voxel of the buffers and images, the voxels, counters, accumulated counters and temporary buffers,
+  /// the ray map, both sign maps, both voxels textures and the distance texture.
+  /// The triangle IDs and the closest triangles are not included.
+  pub const BYTES_PER_VOXEL: u64 = 16 + 4 + 4 + 4 + 16 + 4 + 4 + 16 + 16 + 4;
+  /// The bytes per voxel of the closest triangles buffer, the triangle index and the barycentrics.
+  pub const CLOSEST_TRIANGLES_BYTES_PER_VOXEL: u64 = 16;
+  /// The bytes per voxel of the largest buffer, the voxels buffer stores 4 floats per voxel in a single allocation.
+  pub const MAX_BYTES_PER_VOXEL_IN_BUFFER: u64 = 16;
+
+  /// Get the bytes per voxel of the buffers and images of a bake.
+  /// param keep_closest_triangles: Whether the bake keeps the closest triangles.
+  /// return: The bytes per voxel.
+  pub fn get_bytes_per_voxel(keep_closest_triangles: bool) -> u64 {
+    if keep_closest_triangles {
+      Self::BYTES_PER_VOXEL + Self::CLOSEST_TRIANGLES_BYTES_PER_VOXEL
+    } else {
+      Self::BYTES_PER_VOXEL
+    }
+  }
 
   /// Create a new SDF baker resources.
   /// param logical_device: The logical device.
   /// param descriptor_pool: The descriptor pool.
-  /// param swapchain: The swapchain.
+  /// param swapchain: The swapchain. None in headless mode.
   /// param baker_config: The baker config.
   /// param pipeline_cache: The pipeline cache.
   /// return: The result.
   pub(crate) fn new(
     logical_device: Rc<RefCell<hala_gfx::HalaLogicalDevice>>,
     descriptor_pool: Rc<RefCell<hala_gfx::HalaDescriptorPool>>,
-    swapchain: &hala_gfx::HalaSwapchain,
+    swapchain: Option<&hala_gfx::HalaSwapchain>,
     baker_config: &config::BakerConfig,
     pipeline_cache: &hala_gfx::HalaPipelineCache,
   ) -> Result<Self, HalaRendererError> {
@@ -272,20 +298,24 @@ impl SDFBakerResources {
         "image_2_screen_2.descriptor_set",
       )?,
     ];
-    let image_2_screen_program = HalaGraphicsProgram::with_swapchain(
-      logical_device.clone(),
-      swapchain,
-      &[&image_2_screen_descriptor_sets[0].layout],
-      hala_gfx::HalaPipelineCreateFlags::default(),
-      &[] as &[hala_gfx::HalaVertexInputAttributeDescription],
-      &[] as &[hala_gfx::HalaVertexInputBindingDescription],
-      &[
-        hala_gfx::HalaDynamicState::VIEWPORT,
-      ],
-      image_2_screen_desc,
-      Some(pipeline_cache),
-      "image_2_screen",
-    )?;
+    // The image to screen program is only used for debug drawing, so skip it without a swapchain.
+    let image_2_screen_program = match swapchain {
+      Some(swapchain) => Some(HalaGraphicsProgram::with_swapchain(
+        logical_device.clone(),
+        swapchain,
+        &[&image_2_screen_descriptor_sets[0].layout],
+        hala_gfx::HalaPipelineCreateFlags::default(),
+        &[] as &[hala_gfx::HalaVertexInputAttributeDescription],
+        &[] as &[hala_gfx::HalaVertexInputBindingDescription],
+        &[
+          hala_gfx::HalaDynamicState::VIEWPORT,
+        ],
+        image_2_screen_desc,
+        Some(pipeline_cache),
+        "image_2_screen",
+      )?),
+      None => None,
+    };
 
     let write_uvw_and_coverage_desc = baker_config.graphics_programs.get("write_uvw_and_coverage")
       .ok_or(HalaRendererError::new("Failed to get graphics program \"write_uvw_and_coverage\".", None))?;
@@ -367,11 +397,15 @@ impl SDFBakerResources {
       ray_map: None,
       sign_map: None,
       sign_map_bis: None,
+      winding_number_nodes_buffer: None,
+      winding_number_triangles_buffer: None,
+      flood_fill_changed_buffer: None,
 
       voxels_texture: None,
       voxels_texture_bis: None,
 
       distance_texture: None,
+      closest_triangles_buffer: None,
 
       render_targets: [IMAGE_REPEAT_NONE; 3],
 
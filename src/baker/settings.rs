@@ -1,37 +1,264 @@
+use serde::{Serialize, Deserialize};
+
 use hala_renderer::scene;
 
+use crate::baker::Axis;
+use crate::volume::{
+  OutputFormat,
+  ValueEncoding,
+  DistanceUnit,
+};
+
+/// The geometry merged into one field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MergeMode {
+  Primitive, // Only the selected primitive.
+  Mesh, // All primitives of the mesh which the selected primitive belongs to.
+  Scene, // All primitives in the scene, in world space.
+}
+
+/// The implementation of the merge mode.
+impl MergeMode {
+  pub const ALL: [MergeMode; 3] = [
+    MergeMode::Primitive,
+    MergeMode::Mesh,
+    MergeMode::Scene,
+  ];
+
+  /// Get the name of the mode.
+  /// return: The name.
+  pub fn name(&self) -> &'static str {
+    match self {
+      MergeMode::Primitive => "primitive",
+      MergeMode::Mesh => "mesh",
+      MergeMode::Scene => "scene",
+    }
+  }
+}
+
+impl std::str::FromStr for MergeMode {
+  type Err = anyhow::Error;
+
+  fn from_str(s: &str) -> anyhow::Result<Self> {
+    MergeMode::ALL.iter()
+      .find(|mode| mode.name().eq_ignore_ascii_case(s))
+      .copied()
+      .ok_or(anyhow::anyhow!("Unknown merge mode \"{}\".", s))
+  }
+}
+
+/// The space in which the geometry is baked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BakeSpace {
+  Object, // The local space of the selected mesh.
+  World, // The world space with the full node hierarchy transform.
+}
+
+/// The implementation of the bake space.
+impl BakeSpace {
+  pub const ALL: [BakeSpace; 2] = [
+    BakeSpace::Object,
+    BakeSpace::World,
+  ];
+
+  /// Get the name of the space.
+  /// return: The name.
+  pub fn name(&self) -> &'static str {
+    match self {
+      BakeSpace::Object => "object",
+      BakeSpace::World => "world",
+    }
+  }
+}
+
+impl std::str::FromStr for BakeSpace {
+  type Err = anyhow::Error;
+
+  fn from_str(s: &str) -> anyhow::Result<Self> {
+    BakeSpace::ALL.iter()
+      .find(|space| space.name().eq_ignore_ascii_case(s))
+      .copied()
+      .ok_or(anyhow::anyhow!("Unknown bake space \"{}\".", s))
+  }
+}
+
+/// The way to find the sign of the SDF.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SignMode {
+  RayMap, // The parity of the ray map, refined by the sign passes.
+  WindingNumber, // The generalized winding number, robust to the holes and the self-intersections.
+  FloodFill, // The voxels not reached by the flood fill from the border, for the open shells.
+}
+
+/// The implementation of the sign mode.
+impl SignMode {
+  pub const ALL: [SignMode; 3] = [
+    SignMode::RayMap,
+    SignMode::WindingNumber,
+    SignMode::FloodFill,
+  ];
+
+  /// Get the name of the mode.
+  /// return: The name.
+  pub fn name(&self) -> &'static str {
+    match self {
+      SignMode::RayMap => "ray_map",
+      SignMode::WindingNumber => "winding_number",
+      SignMode::FloodFill => "flood_fill",
+    }
+  }
+}
+
+impl std::str::FromStr for SignMode {
+  type Err = anyhow::Error;
+
+  fn from_str(s: &str) -> anyhow::Result<Self> {
+    SignMode::ALL.iter()
+      .find(|mode| mode.name().eq_ignore_ascii_case(s))
+      .copied()
+      .ok_or(anyhow::anyhow!("Unknown sign mode \"{}\".", s))
+  }
+}
+
+/// The way to compute the gradient channel of the volume.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GradientMode {
+  None, // No gradient channel.
+  ClosestPoint, // The direction from the closest point on the nearest triangle found by the distance transform.
+  CentralDifference, // The central differences of the baked distances.
+}
+
+/// The implementation of the gradient mode.
+impl GradientMode {
+  pub const ALL: [GradientMode; 3] = [
+    GradientMode::None,
+    GradientMode::ClosestPoint,
+    GradientMode::CentralDifference,
+  ];
+
+  /// Get the name of the mode.
+  /// return: The name.
+  pub fn name(&self) -> &'static str {
+    match self {
+      GradientMode::None => "none",
+      GradientMode::ClosestPoint => "closest_point",
+      GradientMode::CentralDifference => "central_difference",
+    }
+  }
+}
+
+impl std::str::FromStr for GradientMode {
+  type Err = anyhow::Error;
+
+  fn from_str(s: &str) -> anyhow::Result<Self> {
+    GradientMode::ALL.iter()
+      .find(|mode| mode.name().eq_ignore_ascii_case(s))
+      .copied()
+      .ok_or(anyhow::anyhow!("Unknown gradient mode \"{}\".", s))
+  }
+}
+
+/// The device to bake on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BakeBackend {
+  Gpu, // The compute and raster passes on the GPU.
+  Cpu, // The BVH of the triangles on the CPU threads, the exact reference of the GPU bake.
+}
+
+/// The implementation of the bake backend.
+impl BakeBackend {
+  pub const ALL: [BakeBackend; 2] = [
+    BakeBackend::Gpu,
+    BakeBackend::Cpu,
+  ];
+
+  /// Get the name of the backend.
+  /// return: The name.
+  pub fn name(&self) -> &'static str {
+    match self {
+      BakeBackend::Gpu => "gpu",
+      BakeBackend::Cpu => "cpu",
+    }
+  }
+}
+
+impl std::str::FromStr for BakeBackend {
+  type Err = anyhow::Error;
+
+  fn from_str(s: &str) -> anyhow::Result<Self> {
+    BakeBackend::ALL.iter()
+      .find(|backend| backend.name().eq_ignore_ascii_case(s))
+      .copied()
+      .ok_or(anyhow::anyhow!("Unknown bake backend \"{}\".", s))
+  }
+}
+
 /// The SDF baker settings.
-#[derive(Debug, Clone, Copy)]
+/// The debug flags and the derived box are not saved to the preset file.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
 pub struct SDFBakerSettings{
   pub is_sdf: bool, // Whether the baker is SDF or UDF.
 
   // Common settings.
+  #[serde(skip)]
   pub show_desired_box: bool,
+  #[serde(skip)]
   pub show_actual_box: bool,
+  #[serde(skip)]
   pub show_wireframe: bool,
+  #[serde(skip)]
   pub show_sdf: bool,
 
   // SDF settings.
+  #[serde(skip)]
   pub show_render_targets: bool,
+  #[serde(skip)]
   pub show_ray_map: bool,
 
   // UDF settings.
   // pub show_udf: bool, // Use the show_sdf instead.
 
   // Common settings.
+  pub backend: BakeBackend, // The CPU backend only bakes the triangles, without the preview and the closest triangles.
   pub selected_mesh_index: i32,
+  pub merge_mode: MergeMode,
+  pub bake_space: BakeSpace,
   pub max_resolution: i32,
   pub surface_offset: f32,
+  #[serde(skip)]
   pub center: [f32; 3],
+  #[serde(skip)]
   pub desired_size: [f32; 3],
+  #[serde(skip)]
   pub actual_size: [f32; 3],
   pub padding: [f32; 3],
+  pub output_format: OutputFormat,
+  pub value_encoding: ValueEncoding,
+  pub narrow_band: f32, // The half width of the narrow band in voxels, used by the narrow band mode and the NanoVDB and sparse formats.
+  pub is_narrow_band: bool, // Whether only the voxels within the narrow band get the exact distances, the others are clamped to the band.
+  pub distance_unit: DistanceUnit,
+  pub generate_mips: bool, // Whether to generate the full mip chain with the conservative min distance downsampling.
+  pub gradient_mode: GradientMode, // The gradient channel packed with the distance as RGBA by the KTX2 and DDS formats.
+  pub memory_budget: i32, // The memory budget in MB of the buffers and images of a bake, 0 bakes the grid at once if it fits in a single allocation.
+  pub tile_halo: i32, // The halo in voxels around each brick of the tiled bake, 0 for the jump radius, not less than it otherwise.
 
   // SDF settings.
+  pub sign_mode: SignMode,
   pub sign_passes_count: i32,
   pub in_out_threshold: f32,
+  pub fill_connectivity: i32, // The neighbors of the flood fill, 6 or 26.
+  pub fill_dilation: i32, // The radius in voxels to dilate the surface before the flood fill, to close the small gaps.
+  pub keep_closest_triangles: bool, // Whether the GPU bake of the whole grid keeps the closest triangle of each voxel for the closest triangles and attributes files.
 
   // UDF settings.
+  pub point_radius: f32, // The radius of the points of the point cloud in the world units.
+  pub jfa_refinement_passes: i32, // The extra passes after the jump flooding, 0, 1 for JFA+1 or 2 for JFA+2.
 }
 
 impl Default for SDFBakerSettings {
@@ -47,21 +274,173 @@ impl Default for SDFBakerSettings {
       show_render_targets: false,
       show_ray_map: false,
 
+      backend: BakeBackend::Gpu,
       selected_mesh_index: 0,
+      merge_mode: MergeMode::Primitive,
+      bake_space: BakeSpace::Object,
       max_resolution: 64,
       surface_offset: 0.0,
       center: [0.0, 0.0, 0.0],
       desired_size: [1.0, 1.0, 1.0],
       actual_size: [1.0, 1.0, 1.0],
       padding: [1.0, 1.0, 1.0],
+      output_format: OutputFormat::Text,
+      value_encoding: ValueEncoding::F32,
+      narrow_band: 3.0,
+      is_narrow_band: false,
+      distance_unit: DistanceUnit::Normalized,
+      generate_mips: false,
+      gradient_mode: GradientMode::None,
+      memory_budget: 0,
+      tile_halo: 0,
 
+      sign_mode: SignMode::RayMap,
       sign_passes_count: 1,
       in_out_threshold: 0.5,
+      fill_connectivity: 6,
+      fill_dilation: 0,
+      keep_closest_triangles: false,
+
+      point_radius: 0.0,
+      jfa_refinement_passes: 0,
     }
   }
 }
 
 impl SDFBakerSettings {
+  /// Apply the preset to the settings, the debug flags and the derived box are kept.
+  /// param preset: The preset settings.
+  pub fn apply_preset(&mut self, preset: &SDFBakerSettings) {
+    self.is_sdf = preset.is_sdf;
+    self.backend = preset.backend;
+    self.selected_mesh_index = preset.selected_mesh_index;
+    self.merge_mode = preset.merge_mode;
+    self.bake_space = preset.bake_space;
+    self.max_resolution = preset.max_resolution;
+    self.surface_offset = preset.surface_offset;
+    self.padding = preset.padding;
+    self.output_format = preset.output_format;
+    self.value_encoding = preset.value_encoding;
+    self.narrow_band = preset.narrow_band;
+    self.is_narrow_band = preset.is_narrow_band;
+    self.distance_unit = preset.distance_unit;
+    self.generate_mips = preset.generate_mips;
+    self.gradient_mode = preset.gradient_mode;
+    self.memory_budget = preset.memory_budget;
+    self.tile_halo = preset.tile_halo;
+    self.sign_mode = preset.sign_mode;
+    self.sign_passes_count = preset.sign_passes_count;
+    self.in_out_threshold = preset.in_out_threshold;
+    self.fill_connectivity = preset.fill_connectivity;
+    self.fill_dilation = preset.fill_dilation;
+    self.keep_closest_triangles = preset.keep_closest_triangles;
+    self.point_radius = preset.point_radius;
+    self.jfa_refinement_passes = preset.jfa_refinement_passes;
+  }
+
+  /// Fit the desired box to the bounds.
+  /// param bounds: The bounds of the geometry.
+  pub fn fit_box_to_bounds(&mut self, bounds: &scene::HalaBounds) {
+    let max_size = bounds.get_size().iter().fold(0.0, |a: f32, b| a.max(*b));
+    let voxel_size = max_size / self.max_resolution as f32;
+    let padding = [
+      self.padding[0] * voxel_size,
+      self.padding[1] * voxel_size,
+      self.padding[2] * voxel_size,
+    ];
+
+    let center = [
+      bounds.center[0],
+      bounds.center[1],
+      bounds.center[2]
+    ];
+    let size = [
+      (bounds.extents[0] + padding[0]) * 2.0,
+      (bounds.extents[1] + padding[1]) * 2.0,
+      (bounds.extents[2] + padding[2]) * 2.0
+    ];
+    self.center = center;
+    self.desired_size = size;
+  }
+
+  /// Snap the actual box to the bounds.
+  pub fn snap_box_to_bounds(&mut self) {
+    let max_size = self.desired_size.iter().fold(0.0, |a: f32, b| a.max(*b));
+    let ref_axis = if max_size == self.desired_size[0] {
+      Axis::X
+    } else if max_size == self.desired_size[1] {
+      Axis::Y
+    } else {
+      Axis::Z
+    };
+
+    self.actual_size = match ref_axis {
+      Axis::X => {
+        let dim_x = (self.max_resolution as f32 * self.desired_size[0] / max_size).round().max(1.0);
+        let dim_y = (self.max_resolution as f32 * self.desired_size[1] / max_size).ceil().max(1.0);
+        let dim_z = (self.max_resolution as f32 * self.desired_size[2] / max_size).ceil().max(1.0);
+        let voxel_size = max_size / dim_x;
+        [dim_x * voxel_size, dim_y * voxel_size, dim_z * voxel_size]
+      },
+      Axis::Y => {
+        let dim_x = (self.max_resolution as f32 * self.desired_size[0] / max_size).ceil().max(1.0);
+        let dim_y = (self.max_resolution as f32 * self.desired_size[1] / max_size).round().max(1.0);
+        let dim_z = (self.max_resolution as f32 * self.desired_size[2] / max_size).ceil().max(1.0);
+        let voxel_size = max_size / dim_y;
+        [dim_x * voxel_size, dim_y * voxel_size, dim_z * voxel_size]
+      },
+      Axis::Z => {
+        let dim_x = (self.max_resolution as f32 * self.desired_size[0] / max_size).ceil().max(1.0);
+        let dim_y = (self.max_resolution as f32 * self.desired_size[1] / max_size).ceil().max(1.0);
+        let dim_z = (self.max_resolution as f32 * self.desired_size[2] / max_size).round().max(1.0);
+        let voxel_size = max_size / dim_z;
+        [dim_x * voxel_size, dim_y * voxel_size, dim_z * voxel_size]
+      },
+    }
+  }
+
+  /// Estimate the grid size.
+  /// return: The grid size.
+  pub fn estimate_grid_size(&self) -> [u32; 3] {
+    let max_extent = self.desired_size.iter().fold(0.0, |a: f32, b| a.max(*b));
+    let ref_axis = if max_extent == self.desired_size[0] {
+      Axis::X
+    } else if max_extent == self.desired_size[1] {
+      Axis::Y
+    } else {
+      Axis::Z
+    };
+
+    match ref_axis {
+      Axis::X => {
+        let dim_x = (self.max_resolution as f32 * self.desired_size[0] / max_extent).round().max(1.0);
+        let dim_y = (self.max_resolution as f32 * self.desired_size[1] / max_extent).ceil().max(1.0);
+        let dim_z = (self.max_resolution as f32 * self.desired_size[2] / max_extent).ceil().max(1.0);
+        [dim_x as u32, dim_y as u32, dim_z as u32]
+      },
+      Axis::Y => {
+        let dim_x = (self.max_resolution as f32 * self.desired_size[0] / max_extent).ceil().max(1.0);
+        let dim_y = (self.max_resolution as f32 * self.desired_size[1] / max_extent).round().max(1.0);
+        let dim_z = (self.max_resolution as f32 * self.desired_size[2] / max_extent).ceil().max(1.0);
+        [dim_x as u32, dim_y as u32, dim_z as u32]
+      },
+      Axis::Z => {
+        let dim_x = (self.max_resolution as f32 * self.desired_size[0] / max_extent).ceil().max(1.0);
+        let dim_y = (self.max_resolution as f32 * self.desired_size[1] / max_extent).ceil().max(1.0);
+        let dim_z = (self.max_resolution as f32 * self.desired_size[2] / max_extent).round().max(1.0);
+        [dim_x as u32, dim_y as u32, dim_z as u32]
+      },
+    }
+  }
+
+  /// Get the jump radius, the min halo of the tiled bake.
+  /// The seeds jump across the brick borders to the voxels in the narrow band, which get the exact distances,
+  /// plus the seed voxel of the distance transform and the voxels of the JFA refinement passes.
+  /// return: The jump radius in voxels.
+  pub fn get_jump_radius(&self) -> i32 {
+    self.narrow_band.max(0.0).ceil() as i32 + 1 + self.jfa_refinement_passes.clamp(0, 2)
+  }
+
   pub fn get_bounds(&self) -> scene::HalaBounds {
     scene::HalaBounds {
       center: self.center,
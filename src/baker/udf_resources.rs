@@ -28,6 +28,8 @@ pub(super) struct UDFBakerCSMeshUniform {
   pub vertex_position_offset: u32,
   pub vertex_stride: u32,
   pub index_stride: u32,
+  pub padding0: u32,
+  pub local_to_bake: glam::Mat4,
 }
 
 /// The UDF baker resources.
@@ -41,6 +43,7 @@ pub(crate) struct UDFBakerResources {
 
   pub(crate) jump_buffer: Option<hala_gfx::HalaBuffer>,
   pub(crate) jump_buffer_bis: Option<hala_gfx::HalaBuffer>,
+  pub(crate) is_distance_in_jump_buffer_bis: bool, // Which jump buffer the last bake finalized the distances to.
 
   pub(crate) descriptor_sets: HashMap<String, hala_gfx::HalaDescriptorSet>,
 
@@ -50,6 +53,11 @@ pub(crate) struct UDFBakerResources {
 /// The implementation of UDF baker resources.
 impl UDFBakerResources {
 
+  /// The bytes per voxel of the buffers and images, the distance texture and both jump buffers.
+  pub const BYTES_PER_VOXEL: u64 = 4 + 4 + 4;
+  /// The bytes per voxel of the largest buffer, the jump buffers store a voxel index per voxel in a single allocation.
+  pub const MAX_BYTES_PER_VOXEL_IN_BUFFER: u64 = 4;
+
   /// Create a new UDF baker resources.
   /// param logical_device: The logical device.
   /// param descriptor_pool: The descriptor pool.
@@ -159,10 +167,21 @@ impl UDFBakerResources {
 
       jump_buffer: None,
       jump_buffer_bis: None,
+      is_distance_in_jump_buffer_bis: false,
 
       descriptor_sets,
       compute_programs,
     })
   }
 
+  /// Get the jump buffer which holds the distances of the last bake.
+  /// return: The distance buffer.
+  pub(crate) fn get_distance_buffer(&self) -> Option<&hala_gfx::HalaBuffer> {
+    if self.is_distance_in_jump_buffer_bis {
+      self.jump_buffer_bis.as_ref()
+    } else {
+      self.jump_buffer.as_ref()
+    }
+  }
+
 }
@@ -1,3 +1,5 @@
+use std::io::Write;
+
 use anyhow::{
   Result,
   Context,
@@ -19,12 +21,225 @@ use hala_renderer::{
 use hala_sdf_baker::{
   config,
   baker::SDFBaker,
+  baker::settings::{
+    SDFBakerSettings,
+    MergeMode,
+    BakeSpace,
+    SignMode,
+    GradientMode,
+    BakeBackend,
+  },
+  volume::{
+    OutputFormat,
+    ValueEncoding,
+    DistanceUnit,
+    VolumeDiff,
+  },
+  mesh::{
+    TriangleMesh,
+    PointCloud,
+    MeshFormat,
+    write_scene_glb,
+  },
 };
 
+/// The baker settings overridden by the command line.
+#[derive(Debug, Default)]
+struct SettingsOverrides {
+  is_sdf: Option<bool>,
+  backend: Option<BakeBackend>,
+  max_resolution: Option<i32>,
+  padding: Option<[f32; 3]>,
+  selected_mesh_index: Option<i32>,
+  merge_mode: Option<MergeMode>,
+  bake_space: Option<BakeSpace>,
+  surface_offset: Option<f32>,
+  sign_mode: Option<SignMode>,
+  sign_passes_count: Option<i32>,
+  in_out_threshold: Option<f32>,
+  fill_connectivity: Option<i32>,
+  fill_dilation: Option<i32>,
+  keep_closest_triangles: Option<bool>,
+  point_radius: Option<f32>,
+  jfa_refinement_passes: Option<i32>,
+  output_format: Option<OutputFormat>,
+  value_encoding: Option<ValueEncoding>,
+  narrow_band: Option<f32>,
+  is_narrow_band: Option<bool>,
+  distance_unit: Option<DistanceUnit>,
+  generate_mips: Option<bool>,
+  gradient_mode: Option<GradientMode>,
+  memory_budget: Option<i32>,
+  tile_halo: Option<i32>,
+}
+
+/// The implementation of the settings overrides.
+impl SettingsOverrides {
+
+  /// Parse the overrides from the command line arguments.
+  /// param matches: The command line arguments.
+  /// return: The overrides.
+  pub fn from_matches(matches: &clap::ArgMatches) -> Self {
+    let is_sdf = if matches.get_flag("sdf") {
+      Some(true)
+    } else if matches.get_flag("udf") {
+      Some(false)
+    } else {
+      None
+    };
+
+    Self {
+      is_sdf,
+      backend: matches.get_one::<BakeBackend>("backend").copied(),
+      max_resolution: matches.get_one::<i32>("resolution").copied(),
+      padding: matches.get_one::<[f32; 3]>("padding").copied(),
+      selected_mesh_index: matches.get_one::<i32>("mesh").copied(),
+      merge_mode: matches.get_one::<MergeMode>("merge").copied(),
+      bake_space: matches.get_one::<BakeSpace>("space").copied(),
+      surface_offset: matches.get_one::<f32>("surface-offset").copied(),
+      sign_mode: matches.get_one::<SignMode>("sign-mode").copied(),
+      sign_passes_count: matches.get_one::<i32>("sign-passes").copied(),
+      in_out_threshold: matches.get_one::<f32>("threshold").copied(),
+      fill_connectivity: matches.get_one::<i32>("fill-connectivity").copied(),
+      fill_dilation: matches.get_one::<i32>("fill-dilation").copied(),
+      // Saving the closest triangles or the attributes needs them to be kept by the bake.
+      keep_closest_triangles: if matches.get_flag("keep-closest-triangles")
+        || matches.get_one::<String>("closest-triangles").is_some()
+        || matches.get_one::<String>("attributes").is_some() {
+        Some(true)
+      } else {
+        None
+      },
+      point_radius: matches.get_one::<f32>("point-radius").copied(),
+      jfa_refinement_passes: matches.get_one::<i32>("jfa-refinement").copied(),
+      output_format: matches.get_one::<OutputFormat>("format").copied(),
+      value_encoding: matches.get_one::<ValueEncoding>("encoding").copied(),
+      narrow_band: matches.get_one::<f32>("narrow-band").copied(),
+      is_narrow_band: if matches.get_flag("narrow-band-mode") { Some(true) } else { None },
+      distance_unit: matches.get_one::<DistanceUnit>("unit").copied(),
+      generate_mips: if matches.get_flag("mips") { Some(true) } else { None },
+      gradient_mode: matches.get_one::<GradientMode>("gradient").copied(),
+      memory_budget: matches.get_one::<i32>("memory-budget").copied(),
+      tile_halo: matches.get_one::<i32>("tile-halo").copied(),
+    }
+  }
+
+  /// Apply the overrides to the baker settings, validate them and refit the bounding box.
+  /// param baker: The SDF baker.
+  /// return: The result.
+  pub fn apply(&self, baker: &mut SDFBaker) -> Result<()> {
+    if let Some(is_sdf) = self.is_sdf {
+      baker.settings.is_sdf = is_sdf;
+    }
+    if let Some(backend) = self.backend {
+      baker.settings.backend = backend;
+    }
+    if let Some(selected_mesh_index) = self.selected_mesh_index {
+      baker.settings.selected_mesh_index = selected_mesh_index;
+    }
+    if let Some(merge_mode) = self.merge_mode {
+      baker.settings.merge_mode = merge_mode;
+    }
+    if let Some(bake_space) = self.bake_space {
+      baker.settings.bake_space = bake_space;
+    }
+    if let Some(max_resolution) = self.max_resolution {
+      baker.settings.max_resolution = max_resolution;
+    }
+    if let Some(padding) = self.padding {
+      baker.settings.padding = [padding[0].max(0.0), padding[1].max(0.0), padding[2].max(0.0)];
+    }
+    if let Some(surface_offset) = self.surface_offset {
+      baker.settings.surface_offset = surface_offset;
+    }
+    if let Some(sign_mode) = self.sign_mode {
+      baker.settings.sign_mode = sign_mode;
+    }
+    if let Some(sign_passes_count) = self.sign_passes_count {
+      baker.settings.sign_passes_count = sign_passes_count;
+    }
+    if let Some(in_out_threshold) = self.in_out_threshold {
+      baker.settings.in_out_threshold = in_out_threshold;
+    }
+    if let Some(fill_connectivity) = self.fill_connectivity {
+      baker.settings.fill_connectivity = fill_connectivity;
+    }
+    if let Some(fill_dilation) = self.fill_dilation {
+      baker.settings.fill_dilation = fill_dilation;
+    }
+    if let Some(keep_closest_triangles) = self.keep_closest_triangles {
+      baker.settings.keep_closest_triangles = keep_closest_triangles;
+    }
+    if let Some(point_radius) = self.point_radius {
+      baker.settings.point_radius = point_radius;
+    }
+    if let Some(jfa_refinement_passes) = self.jfa_refinement_passes {
+      baker.settings.jfa_refinement_passes = jfa_refinement_passes;
+    }
+    if let Some(output_format) = self.output_format {
+      baker.settings.output_format = output_format;
+    }
+    if let Some(value_encoding) = self.value_encoding {
+      baker.settings.value_encoding = value_encoding;
+    }
+    if let Some(narrow_band) = self.narrow_band {
+      baker.settings.narrow_band = narrow_band;
+    }
+    if let Some(is_narrow_band) = self.is_narrow_band {
+      baker.settings.is_narrow_band = is_narrow_band;
+    }
+    if let Some(distance_unit) = self.distance_unit {
+      baker.settings.distance_unit = distance_unit;
+    }
+    if let Some(generate_mips) = self.generate_mips {
+      baker.settings.generate_mips = generate_mips;
+    }
+    if let Some(gradient_mode) = self.gradient_mode {
+      baker.settings.gradient_mode = gradient_mode;
+    }
+    if let Some(memory_budget) = self.memory_budget {
+      baker.settings.memory_budget = memory_budget;
+    }
+    if let Some(tile_halo) = self.tile_halo {
+      baker.settings.tile_halo = tile_halo;
+    }
+
+    config::validate_bake_settings(&baker.settings)?;
+    if baker.settings.selected_mesh_index as usize >= baker.get_num_of_meshes() {
+      return Err(anyhow::anyhow!(
+        "The mesh index {} is out of range, the scene has {} meshes.",
+        baker.settings.selected_mesh_index,
+        baker.get_num_of_meshes(),
+      ));
+    }
+
+    baker.fit_box_to_bounds();
+    baker.snap_box_to_bounds();
+
+    Ok(())
+  }
+
+}
+
 /// The SDF baker application context.
 struct SDFBakerApplicationContext {
   log_file: String,
   output_file: String,
+  settings_file: String,
+  settings: SDFBakerSettings,
+  is_headless: bool,
+  is_bake_all: bool,
+  is_point_cloud: bool,
+  isosurface_file: String,
+  is_extract: bool,
+  iso_value: f32,
+  closest_triangles_file: String,
+  is_save_closest_triangles: bool,
+  attributes_file: String,
+  is_save_attributes: bool,
+  vertex_colors_file: Option<String>,
+  is_compare_cpu: bool,
+  overrides: SettingsOverrides,
   config: config::AppConfig,
   baker: Option<SDFBaker>,
   imgui: Option<HalaImGui>,
@@ -49,11 +264,38 @@ impl SDFBakerApplicationContext {
     };
     let config_file = matches.get_one::<String>("config").with_context(|| "Failed to get the config file path.")?;
     let output_file = matches.get_one::<String>("output").with_context(|| "Failed to get the output file path.")?;
+    let settings_file = match matches.get_one::<String>("settings") {
+      Some(settings_file) => settings_file.to_string(),
+      None => std::path::Path::new(config_file).with_file_name("bake_settings.yaml").to_string_lossy().to_string(),
+    };
+    let is_headless = matches.get_flag("headless");
+    let is_bake_all = matches.get_flag("bake-all");
+    let is_point_cloud = matches.get_flag("points");
+    let extract_file = matches.get_one::<String>("extract").cloned();
+    let iso_value = matches.get_one::<f32>("iso").copied().unwrap_or(0.0);
+    let closest_triangles_file = matches.get_one::<String>("closest-triangles").cloned();
+    let attributes_file = matches.get_one::<String>("attributes").cloned();
+    let is_compare_cpu = matches.get_flag("compare-cpu");
+    let overrides = SettingsOverrides::from_matches(&matches);
 
     // Load the configure.
     let config = config::load_app_config(config_file)?;
     log::debug!("Config: {:?}", config);
     config::validate_app_config(&config)?;
+    // The point cloud has no inside, so it can not be baked as the SDF.
+    if overrides.is_sdf == Some(true) && (is_point_cloud || is_point_cloud_file(&config.scene_file)) {
+      return Err(anyhow::anyhow!("The point cloud \"{}\" has no inside, it can only be baked as the UDF, not with --sdf.", config.scene_file));
+    }
+
+    // Load the bake settings preset if it exists.
+    let settings = if std::path::Path::new(&settings_file).exists() {
+      let settings = config::load_bake_settings(&settings_file)?;
+      log::debug!("Bake settings: {:?}", settings);
+      config::validate_bake_settings(&settings)?;
+      settings
+    } else {
+      SDFBakerSettings::default()
+    };
 
     // Create out directory.
     std::fs::create_dir_all("./out")
@@ -62,12 +304,127 @@ impl SDFBakerApplicationContext {
     Ok(Self {
       log_file: log_file.to_string(),
       output_file: output_file.to_string(),
+      settings_file,
+      settings,
+      is_headless,
+      is_bake_all,
+      is_point_cloud,
+      isosurface_file: extract_file.clone().unwrap_or(get_isosurface_file(output_file)),
+      is_extract: extract_file.is_some(),
+      iso_value,
+      closest_triangles_file: closest_triangles_file.clone().unwrap_or(get_closest_triangles_file(output_file)),
+      is_save_closest_triangles: closest_triangles_file.is_some(),
+      attributes_file: attributes_file.clone().unwrap_or(get_attributes_file(output_file)),
+      is_save_attributes: attributes_file.is_some(),
+      vertex_colors_file: None,
+      is_compare_cpu,
+      overrides,
       config,
       baker: None,
       imgui: None,
     })
   }
 
+  /// Bake the scene without a window and save the result to the output file.
+  /// return: The result.
+  pub fn run_headless(&mut self) -> Result<()> {
+    // The XYZ files or any file with --points are loaded as the point cloud,
+    // the OBJ, PLY and STL files as one triangle mesh, others as the glTF scene.
+    let now = std::time::Instant::now();
+    let (mut scene, mesh, points) = if self.is_point_cloud || is_point_cloud_file(&self.config.scene_file) {
+      (None, None, Some(PointCloud::load(&self.config.scene_file)?))
+    } else if is_mesh_file(&self.config.scene_file) {
+      (None, Some(TriangleMesh::load(&self.config.scene_file)?), None)
+    } else {
+      (Some(scene::cpu::HalaScene::new(&self.config.scene_file)?), None, None)
+    };
+    log::info!("Load scene used {}ms.", now.elapsed().as_millis());
+
+    // Setup the baker without the surface.
+    let gpu_req = hala_gfx::HalaGPURequirements {
+      width: self.config.window.width as u32,
+      height: self.config.window.height as u32,
+      version: (1, 3, 0),
+      require_srgb_surface: false,
+      require_mesh_shader: false,
+      require_ray_tracing: false,
+      require_10bits_output: false,
+      is_low_latency: false,
+      require_depth: true,
+      require_printf_in_shader: cfg!(debug_assertions),
+      ..Default::default()
+    };
+
+    let mut baker = SDFBaker::new(
+      "SDF Baker",
+      &gpu_req,
+      None,
+    )?;
+
+    baker.settings.apply_preset(&self.settings);
+    if let Some(scene) = scene.as_mut() {
+      baker.set_scene(scene)?;
+    }
+    let name = std::path::Path::new(&self.config.scene_file).file_stem()
+      .map(|s| s.to_string_lossy().to_string())
+      .unwrap_or_else(|| "mesh".to_string());
+    if let Some(mesh) = mesh {
+      baker.set_triangle_mesh(&name, mesh);
+    }
+    let is_point_cloud = points.is_some();
+    if let Some(points) = points {
+      baker.set_point_cloud(&name, points)?;
+    }
+    self.overrides.apply(&mut baker)?;
+    // The point cloud has no inside, so it is always baked as the UDF, --sdf is rejected with it by the arguments.
+    if is_point_cloud && baker.settings.is_sdf {
+      log::warn!("The point cloud can only be baked as the UDF, switch to the UDF.");
+      baker.settings.is_sdf = false;
+    }
+    if self.is_extract && !baker.settings.is_sdf && self.iso_value <= 0.0 {
+      return Err(anyhow::anyhow!("The UDF isosurface needs a positive --iso, not {}.", self.iso_value));
+    }
+
+    let now = std::time::Instant::now();
+    if self.is_bake_all {
+      let output_dir = get_bake_all_dir(&self.output_file);
+      let manifest = baker.bake_all(&output_dir)?;
+      log::info!("Bake {} items to \"{}\" used {}ms.", manifest.items.len(), output_dir.display(), now.elapsed().as_millis());
+    } else {
+      if baker.settings.is_sdf {
+        baker.bake_sdf()?;
+        baker.save_sdf(std::path::Path::new(&self.output_file))?;
+      } else {
+        baker.bake_udf()?;
+        baker.save_udf(std::path::Path::new(&self.output_file))?;
+      }
+      log::info!("Bake and save \"{}\" used {}ms.", self.output_file, now.elapsed().as_millis());
+      if self.is_extract {
+        baker.save_isosurface(&self.isosurface_file, self.iso_value)?;
+        log::info!("Extract the isosurface to \"{}\".", self.isosurface_file);
+      }
+      if self.is_save_closest_triangles {
+        baker.save_closest_triangles(&self.closest_triangles_file)?;
+        log::info!("Save the closest triangles to \"{}\".", self.closest_triangles_file);
+      }
+      if self.is_save_attributes {
+        // The scene loader does not keep COLOR_0, so it is read from the file only for the attributes.
+        baker.load_vertex_colors(&self.config.scene_file);
+        baker.save_attributes(&self.attributes_file)?;
+        log::info!("Save the vertex attributes to \"{}\".", self.attributes_file);
+      }
+      if self.is_compare_cpu {
+        let diff = baker.compare_with_cpu()?;
+        log_cpu_diff(&diff);
+      }
+    }
+
+    baker.wait_idle()?;
+    self.baker = Some(baker);
+
+    Ok(())
+  }
+
 }
 
 /// The implementation of the application context trait for the SDF baker application context.
@@ -109,8 +466,31 @@ impl HalaApplicationContextTrait for SDFBakerApplicationContext {
   /// param window: The window.
   /// return: The result.
   fn before_run(&mut self, _width: u32, _height: u32, window: &winit::window::Window) -> Result<()> {
+    if self.is_point_cloud || is_point_cloud_file(&self.config.scene_file) {
+      return Err(anyhow::anyhow!(
+        "The point cloud file \"{}\" can only be baked with --headless, open the mesh or the glTF scene in the window.",
+        self.config.scene_file,
+      ));
+    }
+
+    // The OBJ, PLY and STL meshes are written as the temporary glTF scene with a camera and a light for the window.
     let now = std::time::Instant::now();
-    let mut scene = scene::cpu::HalaScene::new(&self.config.scene_file)?;
+    let scene_file = if is_mesh_file(&self.config.scene_file) {
+      let mesh = TriangleMesh::load(&self.config.scene_file)?;
+      let name = std::path::Path::new(&self.config.scene_file).file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| "mesh".to_string());
+      let scene_file = std::env::temp_dir().join(format!("{}.glb", name));
+      let mut writer = std::io::BufWriter::new(std::fs::File::create(&scene_file)
+        .with_context(|| format!("Failed to create the scene file \"{}\".", scene_file.display()))?);
+      write_scene_glb(&mut writer, &mesh, &name)?;
+      writer.flush()
+        .with_context(|| format!("Failed to write the scene file \"{}\".", scene_file.display()))?;
+      scene_file.to_string_lossy().to_string()
+    } else {
+      self.config.scene_file.clone()
+    };
+    let mut scene = scene::cpu::HalaScene::new(&scene_file)?;
     log::info!("Load scene used {}ms.", now.elapsed().as_millis());
 
     // Setup the renderer.
@@ -131,10 +511,16 @@ impl HalaApplicationContextTrait for SDFBakerApplicationContext {
     let mut baker = SDFBaker::new(
       "SDF Baker",
       &gpu_req,
-      window,
+      Some(window),
     )?;
 
+    baker.settings.apply_preset(&self.settings);
     baker.set_scene(&mut scene)?;
+    // The vertex colors are read from the glTF file at the first attribute bake, the temporary mesh scene has none.
+    if !is_mesh_file(&self.config.scene_file) {
+      self.vertex_colors_file = Some(scene_file);
+    }
+    self.overrides.apply(&mut baker)?;
 
     baker.commit()?;
 
@@ -177,8 +563,27 @@ impl HalaApplicationContextTrait for SDFBakerApplicationContext {
 
               if let Some(_t) = ui.tree_node("Advanced Settings") {
                 if baker.settings.is_sdf {
+                  let mut sign_index = SignMode::ALL.iter().position(|m| *m == baker.settings.sign_mode).unwrap_or(0);
+                  if ui.combo_simple_string("Sign Mode", &mut sign_index, &SignMode::ALL.map(|m| m.name())) {
+                    baker.settings.sign_mode = SignMode::ALL[sign_index];
+                  }
                   let _ = ui.input_int("Sign Passes Count", &mut baker.settings.sign_passes_count).build();
                   let _ = ui.input_float("In/Out Threshold", &mut baker.settings.in_out_threshold).build();
+                  if baker.settings.sign_mode == SignMode::FloodFill {
+                    let mut connectivity_index = if baker.settings.fill_connectivity == 26 { 1 } else { 0 };
+                    if ui.combo_simple_string("Fill Connectivity", &mut connectivity_index, &["6", "26"]) {
+                      baker.settings.fill_connectivity = if connectivity_index == 1 { 26 } else { 6 };
+                    }
+                    if ui.input_int("Fill Dilation", &mut baker.settings.fill_dilation).build() {
+                      baker.settings.fill_dilation = baker.settings.fill_dilation.max(0);
+                    }
+                  }
+                  let _ = ui.checkbox("Keep Closest Triangles", &mut baker.settings.keep_closest_triangles);
+                } else {
+                  let mut refinement_index = baker.settings.jfa_refinement_passes.clamp(0, 2) as usize;
+                  if ui.combo_simple_string("JFA Refinement", &mut refinement_index, &["JFA", "JFA+1", "JFA+2"]) {
+                    baker.settings.jfa_refinement_passes = refinement_index as i32;
+                  }
                 }
                 let _ = ui.input_float("Surface Offset", &mut baker.settings.surface_offset).build();
 
@@ -193,13 +598,35 @@ impl HalaApplicationContextTrait for SDFBakerApplicationContext {
                   need_to_fit = true;
                   need_to_snap = true;
                 }
+                let mut backend_index = BakeBackend::ALL.iter().position(|b| *b == baker.settings.backend).unwrap_or(0);
+                if ui.combo_simple_string("Backend", &mut backend_index, &BakeBackend::ALL.map(|b| b.name())) {
+                  baker.settings.backend = BakeBackend::ALL[backend_index];
+                }
+                let mut merge_index = MergeMode::ALL.iter().position(|m| *m == baker.settings.merge_mode).unwrap_or(0);
+                if ui.combo_simple_string("Merge Mode", &mut merge_index, &MergeMode::ALL.map(|m| m.name())) {
+                  baker.settings.merge_mode = MergeMode::ALL[merge_index];
+                  need_to_fit = true;
+                  need_to_snap = true;
+                }
+                let mut space_index = BakeSpace::ALL.iter().position(|s| *s == baker.settings.bake_space).unwrap_or(0);
+                if ui.combo_simple_string("Bake Space", &mut space_index, &BakeSpace::ALL.map(|s| s.name())) {
+                  baker.settings.bake_space = BakeSpace::ALL[space_index];
+                  need_to_fit = true;
+                  need_to_snap = true;
+                }
                 if imgui::Drag::new("Max Resolution")
-                  .range(2, 1024)
+                  .range(2, 2048)
                   .build(ui, &mut baker.settings.max_resolution)
                 {
                   need_to_fit = true;
                   need_to_snap = true;
                 }
+                if ui.input_int("Memory Budget (MB)", &mut baker.settings.memory_budget).build() {
+                  baker.settings.memory_budget = baker.settings.memory_budget.max(0);
+                }
+                if ui.input_int("Tile Halo", &mut baker.settings.tile_halo).build() {
+                  baker.settings.tile_halo = baker.settings.tile_halo.max(0);
+                }
                 let _ = ui.input_float3("Center", &mut baker.settings.center).build();
                 ui.disabled(true, || {
                   let _ = ui.input_float3("Desired Size", &mut baker.settings.desired_size).build();
@@ -213,6 +640,32 @@ impl HalaApplicationContextTrait for SDFBakerApplicationContext {
                   need_to_snap = true;
                 }
 
+                let mut unit_index = DistanceUnit::ALL.iter().position(|u| *u == baker.settings.distance_unit).unwrap_or(0);
+                if ui.combo_simple_string("Distance Unit", &mut unit_index, &DistanceUnit::ALL.map(|u| u.name())) {
+                  baker.settings.distance_unit = DistanceUnit::ALL[unit_index];
+                }
+                let _ = ui.checkbox("Generate Mips", &mut baker.settings.generate_mips);
+                let mut gradient_index = GradientMode::ALL.iter().position(|m| *m == baker.settings.gradient_mode).unwrap_or(0);
+                if ui.combo_simple_string("Gradient Mode", &mut gradient_index, &GradientMode::ALL.map(|m| m.name())) {
+                  baker.settings.gradient_mode = GradientMode::ALL[gradient_index];
+                }
+                let mut format_index = OutputFormat::ALL.iter().position(|f| *f == baker.settings.output_format).unwrap_or(0);
+                if ui.combo_simple_string("Output Format", &mut format_index, &OutputFormat::ALL.map(|f| f.name())) {
+                  baker.settings.output_format = OutputFormat::ALL[format_index];
+                }
+                let _ = ui.checkbox("Narrow Band Mode", &mut baker.settings.is_narrow_band);
+                if baker.settings.is_narrow_band || matches!(baker.settings.output_format, OutputFormat::NanoVdb | OutputFormat::Sparse) {
+                  if ui.input_float("Narrow Band", &mut baker.settings.narrow_band).build() {
+                    baker.settings.narrow_band = baker.settings.narrow_band.max(0.5);
+                  }
+                }
+                if !matches!(baker.settings.output_format, OutputFormat::Text | OutputFormat::NanoVdb) {
+                  let mut encoding_index = ValueEncoding::ALL.iter().position(|e| *e == baker.settings.value_encoding).unwrap_or(0);
+                  if ui.combo_simple_string("Value Encoding", &mut encoding_index, &ValueEncoding::ALL.map(|e| e.name())) {
+                    baker.settings.value_encoding = ValueEncoding::ALL[encoding_index];
+                  }
+                }
+
                 if need_to_fit {
                   baker.fit_box_to_bounds();
                 }
@@ -236,6 +689,8 @@ impl HalaApplicationContextTrait for SDFBakerApplicationContext {
 
               ui.separator();
 
+              let _ = ui.input_float("Iso Value", &mut self.iso_value).build();
+
               if ui.button_with_size("Bake", [100.0, 30.0]) {
                 match if baker.settings.is_sdf {
                   baker.bake_sdf()
@@ -265,6 +720,96 @@ impl HalaApplicationContextTrait for SDFBakerApplicationContext {
                   }
                 }
               }
+
+              ui.same_line();
+              if ui.button_with_size("Extract", [100.0, 30.0]) {
+                match baker.save_isosurface(&self.isosurface_file, self.iso_value) {
+                  Ok(_) => {
+                    log::info!("Extract the isosurface to \"{}\" success.", self.isosurface_file);
+                  },
+                  Err(e) => {
+                    log::error!("Extract failed: {:?}", e);
+                  }
+                }
+              }
+              ui.same_line();
+              if ui.button_with_size("Closest", [100.0, 30.0]) {
+                match baker.save_closest_triangles(&self.closest_triangles_file) {
+                  Ok(_) => {
+                    log::info!("Save the closest triangles to \"{}\" success.", self.closest_triangles_file);
+                  },
+                  Err(e) => {
+                    log::error!("Save the closest triangles failed: {:?}", e);
+                  }
+                }
+              }
+              ui.same_line();
+              if ui.button_with_size("Attributes", [100.0, 30.0]) {
+                if let Some(vertex_colors_file) = self.vertex_colors_file.take() {
+                  baker.load_vertex_colors(&vertex_colors_file);
+                }
+                match baker.save_attributes(&self.attributes_file) {
+                  Ok(_) => {
+                    log::info!("Save the vertex attributes to \"{}\" success.", self.attributes_file);
+                  },
+                  Err(e) => {
+                    log::error!("Save the vertex attributes failed: {:?}", e);
+                  }
+                }
+              }
+              ui.same_line();
+              if ui.button_with_size("Compare CPU", [100.0, 30.0]) {
+                match baker.compare_with_cpu() {
+                  Ok(diff) => {
+                    log_cpu_diff(&diff);
+                  },
+                  Err(e) => {
+                    log::error!("Compare with the CPU bake failed: {:?}", e);
+                  }
+                }
+              }
+              ui.same_line();
+              if ui.button_with_size("Bake All", [100.0, 30.0]) {
+                let output_dir = get_bake_all_dir(&self.output_file);
+                match baker.bake_all(&output_dir) {
+                  Ok(manifest) => {
+                    log::info!("Bake all {} items to \"{}\" success.", manifest.items.len(), output_dir.display());
+                  },
+                  Err(e) => {
+                    log::error!("Bake all failed: {:?}", e);
+                  }
+                }
+              }
+
+              if ui.button_with_size("Load Preset", [100.0, 30.0]) {
+                match config::load_bake_settings(&self.settings_file)
+                  .and_then(|settings| config::validate_bake_settings(&settings).map(|_| settings))
+                {
+                  Ok(settings) => {
+                    baker.settings.apply_preset(&settings);
+                    baker.settings.selected_mesh_index = baker.settings.selected_mesh_index.clamp(0, baker.get_num_of_meshes() as i32 - 1);
+                    baker.fit_box_to_bounds();
+                    baker.snap_box_to_bounds();
+                    log::info!("Load preset from \"{}\" success.", self.settings_file);
+                  },
+                  Err(e) => {
+                    log::error!("Load preset failed: {:?}", e);
+                  }
+                }
+              }
+              ui.same_line();
+              if ui.button_with_size("Save Preset", [100.0, 30.0]) {
+                match config::validate_bake_settings(&baker.settings)
+                  .and_then(|_| config::save_bake_settings(&self.settings_file, &baker.settings))
+                {
+                  Ok(_) => {
+                    log::info!("Save preset to \"{}\" success.", self.settings_file);
+                  },
+                  Err(e) => {
+                    log::error!("Save preset failed: {:?}", e);
+                  }
+                }
+              }
             }
           );
 
@@ -339,14 +884,132 @@ fn cli() -> Command {
     .arg(arg!(-l --log <LOG_FILE> "The file path of the log file. Default is ./logs/sdf_baker.log."))
     .arg(arg!(-c --config [CONFIG_FILE] "The file path of the config file."))
     .arg(arg!(-o --output [OUTPUT_FILE] "The file path of the output file."))
+    .arg(arg!(-s --settings [SETTINGS_FILE] "The file path of the bake settings preset. Default is bake_settings.yaml next to the config file."))
+    .arg(arg!(--headless "Bake to the output file without a window and exit."))
+    .arg(arg!(--"bake-all" "Bake all meshes to the directory named after the output file without the extension, with a manifest."))
+    .arg(arg!(--sdf "Bake the SDF.").conflicts_with("udf"))
+    .arg(arg!(--udf "Bake the UDF."))
+    .arg(arg!(--backend [BACKEND] "The device to bake on, gpu or cpu. The CPU bake only supports the triangles.").value_parser(|s: &str| s.parse::<BakeBackend>().map_err(|e| e.to_string())))
+    .arg(arg!(--"compare-cpu" "Bake the same grid on the CPU after the bake and log the difference, the CPU bake is the reference."))
+    .arg(arg!(--resolution [RESOLUTION] "The max resolution of the grid.").value_parser(clap::value_parser!(i32)))
+    .arg(arg!(--padding [PADDING] "The padding in voxels, \"x,y,z\" or a single value.").value_parser(parse_padding))
+    .arg(arg!(--mesh [MESH_INDEX] "The index of the mesh to bake.").value_parser(clap::value_parser!(i32)))
+    .arg(arg!(--merge [MERGE_MODE] "Merge the primitives into one field, primitive, mesh or scene.").value_parser(|s: &str| s.parse::<MergeMode>().map_err(|e| e.to_string())))
+    .arg(arg!(--space [BAKE_SPACE] "The space to bake in, object or world.").value_parser(|s: &str| s.parse::<BakeSpace>().map_err(|e| e.to_string())))
+    .arg(arg!(--"surface-offset" [SURFACE_OFFSET] "The surface offset.").value_parser(clap::value_parser!(f32)))
+    .arg(arg!(--"sign-mode" [SIGN_MODE] "The way to find the sign of the SDF, ray_map, winding_number or flood_fill.").value_parser(|s: &str| s.parse::<SignMode>().map_err(|e| e.to_string())))
+    .arg(arg!(--"sign-passes" [SIGN_PASSES] "The sign passes count of the SDF.").value_parser(clap::value_parser!(i32)))
+    .arg(arg!(--threshold [THRESHOLD] "The in/out threshold of the SDF.").value_parser(clap::value_parser!(f32)))
+    .arg(arg!(--"fill-connectivity" [CONNECTIVITY] "The neighbors of the flood fill sign mode, 6 or 26.").value_parser(clap::value_parser!(i32)))
+    .arg(arg!(--"fill-dilation" [DILATION] "The radius in voxels to dilate the surface before the flood fill, to close the small gaps.").value_parser(clap::value_parser!(i32)))
+    .arg(arg!(--points "Load the scene file as the point cloud and bake the UDF to the points, implied by the xyz extension."))
+    .arg(arg!(--"point-radius" [POINT_RADIUS] "The radius of the points in the world units, the points are baked as spheres.").value_parser(clap::value_parser!(f32)))
+    .arg(arg!(--"jfa-refinement" [PASSES] "The extra passes after the UDF jump flooding, 0, 1 for JFA+1 or 2 for JFA+2.").value_parser(clap::value_parser!(i32)))
+    .arg(arg!(--format [FORMAT] "The output file format, text, binary, ktx2, dds, nanovdb or sparse.").value_parser(|s: &str| s.parse::<OutputFormat>().map_err(|e| e.to_string())))
+    .arg(arg!(--encoding [ENCODING] "The value encoding, f32, f16, unorm8 or unorm16. The binary and sparse formats only support f32 and f16.").value_parser(|s: &str| s.parse::<ValueEncoding>().map_err(|e| e.to_string())))
+    .arg(arg!(--"narrow-band" [NARROW_BAND] "The half width of the narrow band in voxels, used by the narrow band mode and the NanoVDB and sparse formats.").value_parser(clap::value_parser!(f32)))
+    .arg(arg!(--"narrow-band-mode" "Only compute the exact distances within the narrow band and clamp the others to the band."))
+    .arg(arg!(--extract [MESH_FILE] "Extract the isosurface of the bake to the OBJ, PLY, STL or GLB file by the extension."))
+    .arg(arg!(--"keep-closest-triangles" "Keep the closest triangle of each voxel of the GPU SDF bake of the whole grid, implied by --closest-triangles and --attributes."))
+    .arg(arg!(--"closest-triangles" [CLOSEST_FILE] "Save the closest triangle and the barycentrics of the closest point of each voxel of the SDF bake to the file."))
+    .arg(arg!(--attributes [ATTRIBUTES_FILE] "Save the color, UV and material index at the closest point of each voxel of the glTF SDF bake as the KTX2 3D textures, suffixed by _color, _uv and _material."))
+    .arg(arg!(--iso [ISO_VALUE] "The iso value of the extracted isosurface in the distance unit, positive for the UDF. Default is 0.").value_parser(clap::value_parser!(f32)))
+    .arg(arg!(--"memory-budget" [MEGABYTES] "The memory budget of a bake in MB, the grid is baked in bricks if it needs more. Default is 0 for no budget.").value_parser(clap::value_parser!(i32)))
+    .arg(arg!(--"tile-halo" [HALO] "The halo in voxels around each brick of the tiled bake, not less than the jump radius of the narrow band. Default is 0 for the jump radius.").value_parser(clap::value_parser!(i32)))
+    .arg(arg!(--mips "Generate the full mip chain of the volume with the conservative min distance downsampling, stored by the binary, KTX2 and DDS formats."))
+    .arg(arg!(--gradient [GRADIENT_MODE] "The gradient channel packed with the distance as RGBA by the KTX2 and DDS formats, none, closest_point or central_difference.").value_parser(|s: &str| s.parse::<GradientMode>().map_err(|e| e.to_string())))
+    .arg(arg!(--unit [UNIT] "The distance unit, normalized, voxel or world.").value_parser(|s: &str| s.parse::<DistanceUnit>().map_err(|e| e.to_string())))
+}
+
+/// Log the difference between the bake and the CPU reference.
+/// param diff: The difference of the bake minus the CPU bake.
+fn log_cpu_diff(diff: &VolumeDiff) {
+  log::info!(
+    "Compare with the CPU bake: RMSE {}, max absolute error {} at {:?}, {} sign flips.",
+    diff.rmse,
+    diff.max_error,
+    diff.max_error_location,
+    diff.sign_flips.len(),
+  );
+  for location in diff.sign_flips.iter().take(32) {
+    log::info!("  The sign flips at {:?}.", location);
+  }
+}
+
+/// Whether the scene file is the OBJ, PLY or STL mesh file by the extension.
+/// param scene_file: The scene file path.
+/// return: True if it is the mesh file.
+fn is_mesh_file(scene_file: &str) -> bool {
+  matches!(MeshFormat::from_path(scene_file), Ok(MeshFormat::Obj | MeshFormat::Ply | MeshFormat::Stl))
+}
+
+/// Whether the scene file is the XYZ point cloud file by the extension.
+/// param scene_file: The scene file path.
+/// return: True if it is the point cloud file.
+fn is_point_cloud_file(scene_file: &str) -> bool {
+  std::path::Path::new(scene_file).extension()
+    .and_then(|e| e.to_str())
+    .is_some_and(|e| e.eq_ignore_ascii_case("xyz"))
+}
+
+/// Get the default isosurface file, which is the output file path with the obj extension.
+/// param output_file: The output file path.
+/// return: The isosurface file path.
+fn get_isosurface_file(output_file: &str) -> String {
+  std::path::Path::new(output_file).with_extension("obj").to_string_lossy().to_string()
+}
+
+/// Get the default closest triangles file, which is the output file path with the hct extension.
+/// param output_file: The output file path.
+/// return: The closest triangles file path.
+fn get_closest_triangles_file(output_file: &str) -> String {
+  std::path::Path::new(output_file).with_extension("hct").to_string_lossy().to_string()
+}
+
+/// Get the default attributes file, which is the output file path with the ktx2 extension.
+/// The channel names are appended to the file stem, so it does not overwrite the output file.
+/// param output_file: The output file path.
+/// return: The attributes file path.
+fn get_attributes_file(output_file: &str) -> String {
+  std::path::Path::new(output_file).with_extension("ktx2").to_string_lossy().to_string()
+}
+
+/// Get the output directory of bake all, which is the output file path without the extension.
+/// param output_file: The output file path.
+/// return: The output directory.
+fn get_bake_all_dir(output_file: &str) -> std::path::PathBuf {
+  std::path::Path::new(output_file).with_extension("")
+}
+
+/// Parse the padding argument.
+/// param value: The argument value.
+/// return: The padding.
+fn parse_padding(value: &str) -> std::result::Result<[f32; 3], String> {
+  let values = value.split(',')
+    .map(|v| v.trim().parse::<f32>().map_err(|e| format!("Invalid padding \"{}\": {}", value, e)))
+    .collect::<std::result::Result<Vec<_>, _>>()?;
+  match values.as_slice() {
+    [v] => Ok([*v, *v, *v]),
+    [x, y, z] => Ok([*x, *y, *z]),
+    _ => Err(format!("Invalid padding \"{}\", expect \"x,y,z\" or a single value.", value)),
+  }
 }
 
 /// The normal main function.
 fn main() -> Result<()> {
   // Initialize the application.
-  let context = SDFBakerApplicationContext::new()?;
+  let mut context = SDFBakerApplicationContext::new()?;
   context.init()?;
 
+  // Bake without the window, any error makes the process exit with a non-zero code.
+  if context.is_headless {
+    if let Err(e) = context.run_headless() {
+      log::error!("Headless bake failed: {:?}", e);
+      return Err(e);
+    }
+    return Ok(());
+  }
+
   // Run the application.
   let mut app = HalaApplication::new(Box::new(context));
   app.run()?;
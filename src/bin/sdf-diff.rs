@@ -0,0 +1,83 @@
+use anyhow::{
+  Result,
+  Context,
+};
+
+use clap::{arg, Command};
+
+use hala_sdf_baker::volume::{
+  SDFVolume,
+  OutputFormat,
+  ValueEncoding,
+  VolumeDiff,
+  NUM_OF_HISTOGRAM_BINS,
+  diff_volumes,
+};
+
+/// The command line interface.
+fn cli() -> Command {
+  Command::new("sdf-diff")
+    .about("Compare two baked SDF or UDF volumes.")
+    .arg_required_else_help(true)
+    .arg(arg!(<FILE_A> "The file path of the first volume."))
+    .arg(arg!(<FILE_B> "The file path of the second volume."))
+    .arg(arg!(-o --output [OUTPUT_FILE] "The file path to save the difference volume, the first minus the second."))
+    .arg(arg!(--format [FORMAT] "The format of the difference volume, text, binary, ktx2, dds, nanovdb or sparse. Default is text.").value_parser(|s: &str| s.parse::<OutputFormat>().map_err(|e| e.to_string())))
+    .arg(arg!(--encoding [ENCODING] "The value encoding of the difference volume. Default is f32.").value_parser(|s: &str| s.parse::<ValueEncoding>().map_err(|e| e.to_string())))
+    .arg(arg!(--"narrow-band" [NARROW_BAND] "The half width of the NanoVDB and sparse narrow band in voxels. Default is 3.").value_parser(clap::value_parser!(f32)))
+    .arg(arg!(--locations [LOCATIONS] "The max number of sign flip locations to print. Default is 32.").value_parser(clap::value_parser!(usize)))
+}
+
+/// Print the report of the difference.
+/// param diff: The difference.
+/// param num_of_locations: The max number of sign flip locations to print.
+fn print_report(diff: &VolumeDiff, num_of_locations: usize) {
+  let volume = &diff.difference;
+  println!("Dimensions: {:?}", volume.dimensions);
+  println!("Unit: {}", volume.unit.name());
+  println!("RMSE: {}", diff.rmse);
+  println!("Max absolute error: {} at {:?}", diff.max_error, diff.max_error_location);
+  println!("Sign flips: {}", diff.sign_flips.len());
+  for location in diff.sign_flips.iter().take(num_of_locations) {
+    println!("  {:?}", location);
+  }
+  if diff.sign_flips.len() > num_of_locations {
+    println!("  ... and {} more", diff.sign_flips.len() - num_of_locations);
+  }
+
+  println!(
+    "Error histogram per Z slice, {} bins of width {}:",
+    NUM_OF_HISTOGRAM_BINS,
+    diff.max_error / NUM_OF_HISTOGRAM_BINS as f32,
+  );
+  println!("{:>6} {:>12} {:>12} {:>6}  histogram", "slice", "rmse", "max", "flips");
+  for (z, slice) in diff.slices.iter().enumerate() {
+    println!(
+      "{:>6} {:>12.6e} {:>12.6e} {:>6}  {:?}",
+      z, slice.rmse, slice.max_error, slice.num_of_sign_flips, slice.histogram,
+    );
+  }
+}
+
+fn main() -> Result<()> {
+  let matches = cli().get_matches();
+  let file_a = matches.get_one::<String>("FILE_A").unwrap();
+  let file_b = matches.get_one::<String>("FILE_B").unwrap();
+
+  let volume_a = SDFVolume::load(file_a)?;
+  let volume_b = SDFVolume::load(file_b)?;
+  let diff = diff_volumes(&volume_a, &volume_b)
+    .with_context(|| format!("Failed to compare {} and {}.", file_a, file_b))?;
+
+  print_report(&diff, matches.get_one::<usize>("locations").copied().unwrap_or(32));
+
+  if let Some(output) = matches.get_one::<String>("output") {
+    let format = matches.get_one::<OutputFormat>("format").copied().unwrap_or(OutputFormat::Text);
+    let encoding = matches.get_one::<ValueEncoding>("encoding").copied().unwrap_or(ValueEncoding::F32);
+    let narrow_band = matches.get_one::<f32>("narrow-band").copied().unwrap_or(3.0);
+    diff.difference.save(output, format, encoding, narrow_band)?;
+    println!("Save the difference volume to {}.", output);
+  }
+
+  Ok(())
+}
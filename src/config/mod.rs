@@ -7,6 +7,12 @@ mod baker;
 pub use window::*;
 pub use baker::*;
 
+use crate::baker::settings::{
+  SDFBakerSettings,
+  GradientMode,
+};
+use crate::volume::OutputFormat;
+
 /// The application configure.
 #[derive(Debug, Deserialize, Default, Clone)]
 pub struct AppConfig {
@@ -34,4 +40,85 @@ pub fn load_app_config(config_file: &str) -> Result<AppConfig> {
   let config: AppConfig = serde_yaml::from_str(&config_str)
     .with_context(|| format!("Failed to parse the config file: {}", config_file))?;
   Ok(config)
-}
\ No newline at end of file
+}
+
+/// Validate the bake settings.
+/// param: settings: the bake settings.
+/// return: the result of the validation.
+pub fn validate_bake_settings(settings: &SDFBakerSettings) -> Result<()> {
+  if settings.max_resolution <= 0 {
+    return Err(anyhow::anyhow!("The max resolution {} is not positive.", settings.max_resolution));
+  }
+  if !(2..=2048).contains(&settings.max_resolution) {
+    return Err(anyhow::anyhow!("The max resolution {} is out of range [2, 2048].", settings.max_resolution));
+  }
+  if settings.selected_mesh_index < 0 {
+    return Err(anyhow::anyhow!("The selected mesh index {} is negative.", settings.selected_mesh_index));
+  }
+  if settings.padding.iter().any(|p| !p.is_finite() || *p < 0.0) {
+    return Err(anyhow::anyhow!("The padding {:?} is negative.", settings.padding));
+  }
+  if !settings.surface_offset.is_finite() {
+    return Err(anyhow::anyhow!("The surface offset {} is not finite.", settings.surface_offset));
+  }
+  if settings.sign_passes_count < 0 {
+    return Err(anyhow::anyhow!("The sign passes count {} is negative.", settings.sign_passes_count));
+  }
+  if !(0.0..=1.0).contains(&settings.in_out_threshold) {
+    return Err(anyhow::anyhow!("The in/out threshold {} is out of range [0, 1].", settings.in_out_threshold));
+  }
+  if settings.fill_connectivity != 6 && settings.fill_connectivity != 26 {
+    return Err(anyhow::anyhow!("The fill connectivity {} is neither 6 nor 26.", settings.fill_connectivity));
+  }
+  if settings.fill_dilation < 0 {
+    return Err(anyhow::anyhow!("The fill dilation {} is negative.", settings.fill_dilation));
+  }
+  if !settings.point_radius.is_finite() || settings.point_radius < 0.0 {
+    return Err(anyhow::anyhow!("The point radius {} is negative.", settings.point_radius));
+  }
+  if !(0..=2).contains(&settings.jfa_refinement_passes) {
+    return Err(anyhow::anyhow!("The JFA refinement passes {} is out of range [0, 2].", settings.jfa_refinement_passes));
+  }
+  if !settings.narrow_band.is_finite() || settings.narrow_band <= 0.0 {
+    return Err(anyhow::anyhow!("The narrow band {} is not positive.", settings.narrow_band));
+  }
+  if settings.memory_budget < 0 {
+    return Err(anyhow::anyhow!("The memory budget {} is negative.", settings.memory_budget));
+  }
+  if settings.tile_halo < 0 {
+    return Err(anyhow::anyhow!("The tile halo {} is negative.", settings.tile_halo));
+  }
+  if settings.tile_halo > 0 && settings.tile_halo < settings.get_jump_radius() {
+    return Err(anyhow::anyhow!("The tile halo {} is less than the jump radius {} of the narrow band.", settings.tile_halo, settings.get_jump_radius()));
+  }
+  if matches!(settings.output_format, OutputFormat::Binary | OutputFormat::Sparse) && settings.value_encoding.is_normalized() {
+    return Err(anyhow::anyhow!("The {} format does not support the {} encoding.", settings.output_format.name(), settings.value_encoding.name()));
+  }
+  if settings.gradient_mode != GradientMode::None && !matches!(settings.output_format, OutputFormat::Ktx2 | OutputFormat::Dds) {
+    return Err(anyhow::anyhow!("The {} format does not store the gradient channel, use ktx2 or dds.", settings.output_format.name()));
+  }
+  Ok(())
+}
+
+/// Load the bake settings.
+/// param: settings_file: the bake settings file path.
+/// return: the bake settings.
+pub fn load_bake_settings(settings_file: &str) -> Result<SDFBakerSettings> {
+  let settings_str = std::fs::read_to_string(settings_file)
+    .with_context(|| format!("Failed to read the bake settings file: {}", settings_file))?;
+  let settings: SDFBakerSettings = serde_yaml::from_str(&settings_str)
+    .with_context(|| format!("Failed to parse the bake settings file: {}", settings_file))?;
+  Ok(settings)
+}
+
+/// Save the bake settings.
+/// param: settings_file: the bake settings file path.
+/// param: settings: the bake settings.
+/// return: the result.
+pub fn save_bake_settings(settings_file: &str, settings: &SDFBakerSettings) -> Result<()> {
+  let settings_str = serde_yaml::to_string(settings)
+    .with_context(|| "Failed to serialize the bake settings.")?;
+  std::fs::write(settings_file, settings_str)
+    .with_context(|| format!("Failed to write the bake settings file: {}", settings_file))?;
+  Ok(())
+}
@@ -1,2 +1,4 @@
 pub mod config;
 pub mod baker;
+pub mod volume;
+pub mod mesh;
@@ -0,0 +1,178 @@
+use std::io::Write;
+
+use anyhow::{Result, Context};
+
+use super::TriangleMesh;
+
+/// The magic number of the binary glTF.
+pub const GLB_MAGIC: u32 = 0x4654_6C67; // "glTF"
+/// The JSON chunk type.
+const GLB_CHUNK_JSON: u32 = 0x4E4F_534A; // "JSON"
+/// The binary chunk type.
+const GLB_CHUNK_BIN: u32 = 0x004E_4942; // "BIN\0"
+
+/// Write the mesh in the binary glTF 2.0 format.
+/// The mesh is one node with one primitive of the positions and the u32 indices.
+/// param writer: The writer.
+/// param mesh: The triangle mesh.
+/// return: The result.
+pub fn write_glb<W: Write>(writer: &mut W, mesh: &TriangleMesh) -> Result<()> {
+  let (min, max) = mesh.bounds()
+    .ok_or(anyhow::anyhow!("The glTF file can not be written without a triangle."))?;
+
+  // The binary chunk has the positions followed by the indices.
+  let positions_length = mesh.positions.len() * 12;
+  let indices_length = mesh.indices.len() * 4;
+  let mut bin = Vec::with_capacity(positions_length + indices_length);
+  for p in mesh.positions.iter() {
+    for v in p.iter() {
+      bin.extend_from_slice(&v.to_le_bytes());
+    }
+  }
+  for i in mesh.indices.iter() {
+    bin.extend_from_slice(&i.to_le_bytes());
+  }
+
+  let json = format!(
+    concat!(
+      "{{\"asset\":{{\"version\":\"2.0\",\"generator\":\"hala-sdf-baker\"}},",
+      "\"scene\":0,\"scenes\":[{{\"nodes\":[0]}}],\"nodes\":[{{\"mesh\":0}}],",
+      "\"meshes\":[{{\"primitives\":[{{\"attributes\":{{\"POSITION\":0}},\"indices\":1}}]}}],",
+      "\"accessors\":[",
+      "{{\"bufferView\":0,\"componentType\":5126,\"count\":{},\"type\":\"VEC3\",\"min\":[{},{},{}],\"max\":[{},{},{}]}},",
+      "{{\"bufferView\":1,\"componentType\":5125,\"count\":{},\"type\":\"SCALAR\"}}],",
+      "\"bufferViews\":[",
+      "{{\"buffer\":0,\"byteOffset\":0,\"byteLength\":{},\"target\":34962}},",
+      "{{\"buffer\":0,\"byteOffset\":{},\"byteLength\":{},\"target\":34963}}],",
+      "\"buffers\":[{{\"byteLength\":{}}}]}}",
+    ),
+    mesh.positions.len(), min[0], min[1], min[2], max[0], max[1], max[2],
+    mesh.indices.len(),
+    positions_length,
+    positions_length, indices_length,
+    bin.len(),
+  ).into_bytes();
+
+  write_chunks(writer, json, bin)
+}
+
+/// Write the mesh as a binary glTF 2.0 scene which the scene loader of the window can open.
+/// Besides the mesh with the smooth normals and a grey material, the scene has a camera looking at the mesh
+/// from the front right top and a point light at the camera.
+/// param writer: The writer.
+/// param mesh: The triangle mesh.
+/// param name: The name of the mesh node.
+/// return: The result.
+pub fn write_scene_glb<W: Write>(writer: &mut W, mesh: &TriangleMesh, name: &str) -> Result<()> {
+  let (min, max) = mesh.bounds()
+    .ok_or(anyhow::anyhow!("The glTF file can not be written without a triangle."))?;
+  let min = glam::Vec3::from_array(min);
+  let max = glam::Vec3::from_array(max);
+
+  // The normals are the sums of the area weighted face normals.
+  let mut normals = vec![glam::Vec3::ZERO; mesh.positions.len()];
+  for i in 0..mesh.num_of_triangles() {
+    let [a, b, c] = mesh.triangle(i).map(glam::Vec3::from_array);
+    let normal = (b - a).cross(c - a);
+    for index in mesh.indices[i * 3..i * 3 + 3].iter() {
+      normals[*index as usize] += normal;
+    }
+  }
+
+  // The binary chunk has the positions, the normals and the indices.
+  let positions_length = mesh.positions.len() * 12;
+  let indices_length = mesh.indices.len() * 4;
+  let mut bin = Vec::with_capacity(positions_length * 2 + indices_length);
+  for p in mesh.positions.iter() {
+    for v in p.iter() {
+      bin.extend_from_slice(&v.to_le_bytes());
+    }
+  }
+  for n in normals.iter() {
+    for v in n.try_normalize().unwrap_or(glam::Vec3::Y).to_array().iter() {
+      bin.extend_from_slice(&v.to_le_bytes());
+    }
+  }
+  for i in mesh.indices.iter() {
+    bin.extend_from_slice(&i.to_le_bytes());
+  }
+
+  // The camera sees the bounding sphere with the vertical field of view.
+  let y_fov = 0.8f32;
+  let center = (min + max) * 0.5;
+  let radius = ((max - min).length() * 0.5).max(1e-3);
+  let distance = radius / (y_fov * 0.5).sin() * 1.1;
+  let eye = center + glam::Vec3::new(1.0, 0.75, 1.0).normalize() * distance;
+  // The glTF camera looks down its -Z axis with its +Y axis up.
+  let back = (eye - center).normalize();
+  let right = glam::Vec3::Y.cross(back).normalize();
+  let rotation = glam::Quat::from_mat3(&glam::Mat3::from_cols(right, back.cross(right), back));
+
+  let json = format!(
+    concat!(
+      "{{\"asset\":{{\"version\":\"2.0\",\"generator\":\"hala-sdf-baker\"}},",
+      "\"extensionsUsed\":[\"KHR_lights_punctual\"],",
+      "\"extensions\":{{\"KHR_lights_punctual\":{{\"lights\":[{{\"type\":\"point\",\"color\":[1,1,1],\"intensity\":{}}}]}}}},",
+      "\"scene\":0,\"scenes\":[{{\"nodes\":[0,1,2]}}],",
+      "\"nodes\":[",
+      "{{\"name\":{:?},\"mesh\":0}},",
+      "{{\"name\":\"Camera\",\"camera\":0,\"rotation\":[{},{},{},{}],\"translation\":[{},{},{}]}},",
+      "{{\"name\":\"Light\",\"extensions\":{{\"KHR_lights_punctual\":{{\"light\":0}}}},\"translation\":[{},{},{}]}}],",
+      "\"cameras\":[{{\"type\":\"perspective\",\"perspective\":{{\"aspectRatio\":1.3333334,\"yfov\":{},\"znear\":{},\"zfar\":{}}}}}],",
+      "\"materials\":[{{\"doubleSided\":true,\"pbrMetallicRoughness\":{{\"baseColorFactor\":[0.8,0.8,0.8,1],\"metallicFactor\":0,\"roughnessFactor\":0.5}}}}],",
+      "\"meshes\":[{{\"primitives\":[{{\"attributes\":{{\"POSITION\":0,\"NORMAL\":1}},\"indices\":2,\"material\":0}}]}}],",
+      "\"accessors\":[",
+      "{{\"bufferView\":0,\"componentType\":5126,\"count\":{},\"type\":\"VEC3\",\"min\":[{},{},{}],\"max\":[{},{},{}]}},",
+      "{{\"bufferView\":0,\"byteOffset\":{},\"componentType\":5126,\"count\":{},\"type\":\"VEC3\"}},",
+      "{{\"bufferView\":1,\"componentType\":5125,\"count\":{},\"type\":\"SCALAR\"}}],",
+      "\"bufferViews\":[",
+      "{{\"buffer\":0,\"byteOffset\":0,\"byteLength\":{},\"target\":34962}},",
+      "{{\"buffer\":0,\"byteOffset\":{},\"byteLength\":{},\"target\":34963}}],",
+      "\"buffers\":[{{\"byteLength\":{}}}]}}",
+    ),
+    // The point light in candela lights the mesh at the distance like the sun.
+    1000.0 * distance * distance,
+    name,
+    rotation.x, rotation.y, rotation.z, rotation.w, eye.x, eye.y, eye.z,
+    eye.x, eye.y, eye.z,
+    y_fov, distance * 1e-3, distance + radius * 4.0,
+    mesh.positions.len(), min.x, min.y, min.z, max.x, max.y, max.z,
+    positions_length, mesh.positions.len(),
+    mesh.indices.len(),
+    positions_length * 2,
+    positions_length * 2, indices_length,
+    bin.len(),
+  ).into_bytes();
+
+  write_chunks(writer, json, bin)
+}
+
+/// Write the GLB header and the JSON and binary chunks.
+/// param writer: The writer.
+/// param json: The JSON chunk.
+/// param bin: The binary chunk.
+/// return: The result.
+fn write_chunks<W: Write>(writer: &mut W, mut json: Vec<u8>, mut bin: Vec<u8>) -> Result<()> {
+  // Both chunks are aligned to 4 bytes, the JSON chunk is padded with spaces.
+  while !json.len().is_multiple_of(4) {
+    json.push(b' ');
+  }
+  while !bin.len().is_multiple_of(4) {
+    bin.push(0);
+  }
+
+  let total_length = 12 + 8 + json.len() + 8 + bin.len();
+  let mut bytes = Vec::with_capacity(total_length);
+  bytes.extend_from_slice(&GLB_MAGIC.to_le_bytes());
+  bytes.extend_from_slice(&2u32.to_le_bytes());
+  bytes.extend_from_slice(&(total_length as u32).to_le_bytes());
+  bytes.extend_from_slice(&(json.len() as u32).to_le_bytes());
+  bytes.extend_from_slice(&GLB_CHUNK_JSON.to_le_bytes());
+  bytes.extend_from_slice(&json);
+  bytes.extend_from_slice(&(bin.len() as u32).to_le_bytes());
+  bytes.extend_from_slice(&GLB_CHUNK_BIN.to_le_bytes());
+  bytes.extend_from_slice(&bin);
+  writer.write_all(&bytes)
+    .with_context(|| "Failed to write the glTF data.")?;
+  Ok(())
+}
@@ -0,0 +1,126 @@
+use anyhow::Result;
+
+use crate::volume::SDFVolume;
+
+use super::TriangleMesh;
+
+/// The marker of the cell without a vertex.
+const NO_VERTEX: u32 = u32::MAX;
+
+/// The 12 edges of the cell, the corner index is x + 2y + 4z.
+const CELL_EDGES: [(usize, usize); 12] = [
+  (0, 1), (2, 3), (4, 5), (6, 7),
+  (0, 2), (1, 3), (4, 6), (5, 7),
+  (0, 4), (1, 5), (2, 6), (3, 7),
+];
+
+/// Extract the isosurface from the volume by dual contouring with the surface nets vertex placement.
+/// Each cell between 8 voxel centers crossed by the surface gets one vertex at the average of the edge crossings,
+/// and each crossed edge connects the 4 cells around it with a quad.
+/// The values below the iso value are inside, so the triangles face the outside of the SDF or of the UDF shell.
+/// param volume: The volume with the bounds.
+/// param iso_value: The iso value in the distance unit of the volume, 0 for the SDF surface, positive for the UDF shell.
+/// return: The triangle mesh in the space of the volume bounds.
+pub fn extract_isosurface(volume: &SDFVolume, iso_value: f32) -> Result<TriangleMesh> {
+  volume.validate()?;
+  if !volume.voxel_size.is_finite() || volume.voxel_size <= 0.0 {
+    return Err(anyhow::anyhow!("The volume without the bounds can not be placed."));
+  }
+  // The UDF never goes below 0, so only a positive iso value crosses it.
+  if !volume.is_sdf && iso_value <= 0.0 {
+    return Err(anyhow::anyhow!("The UDF has no isosurface at the iso value {}, use a positive iso value for its shell.", iso_value));
+  }
+
+  let dims = volume.dimensions.map(|d| d as usize);
+  let min_corner = [
+    volume.center[0] - volume.extents[0],
+    volume.center[1] - volume.extents[1],
+    volume.center[2] - volume.extents[2],
+  ];
+  let value = |p: [usize; 3]| volume.data[p[0] + dims[0] * (p[1] + dims[1] * p[2])] - iso_value;
+  let position = |p: [f32; 3]| [
+    min_corner[0] + (p[0] + 0.5) * volume.voxel_size,
+    min_corner[1] + (p[1] + 0.5) * volume.voxel_size,
+    min_corner[2] + (p[2] + 0.5) * volume.voxel_size,
+  ];
+
+  let mut mesh = TriangleMesh::default();
+  if dims.iter().any(|d| *d < 2) {
+    return Ok(mesh);
+  }
+
+  // Place one vertex in each crossed cell.
+  let cell_dims = [dims[0] - 1, dims[1] - 1, dims[2] - 1];
+  let cell_index = |c: [usize; 3]| c[0] + cell_dims[0] * (c[1] + cell_dims[1] * c[2]);
+  let mut cell_vertices = vec![NO_VERTEX; cell_dims[0] * cell_dims[1] * cell_dims[2]];
+  for z in 0..cell_dims[2] {
+    for y in 0..cell_dims[1] {
+      for x in 0..cell_dims[0] {
+        let corners = (0..8)
+          .map(|i| [x + (i & 1), y + ((i >> 1) & 1), z + ((i >> 2) & 1)])
+          .collect::<Vec<_>>();
+        let values = corners.iter().map(|c| value(*c)).collect::<Vec<_>>();
+
+        let mut sum = [0.0f32; 3];
+        let mut num_of_crossings = 0;
+        for (i, j) in CELL_EDGES.iter().copied() {
+          if (values[i] < 0.0) == (values[j] < 0.0) {
+            continue;
+          }
+          let t = values[i] / (values[i] - values[j]);
+          for (axis, s) in sum.iter_mut().enumerate() {
+            *s += corners[i][axis] as f32 + t * (corners[j][axis] as f32 - corners[i][axis] as f32);
+          }
+          num_of_crossings += 1;
+        }
+        if num_of_crossings > 0 {
+          cell_vertices[cell_index([x, y, z])] = mesh.positions.len() as u32;
+          mesh.positions.push(position(sum.map(|s| s / num_of_crossings as f32)));
+        }
+      }
+    }
+  }
+
+  // Connect the 4 cells around each crossed edge, the axes b and c follow a in the cyclic order.
+  for axis in 0..3 {
+    let b = (axis + 1) % 3;
+    let c = (axis + 2) % 3;
+    for z in 0..dims[2] {
+      for y in 0..dims[1] {
+        for x in 0..dims[0] {
+          let p = [x, y, z];
+          // The edge on the boundary has less than 4 cells around it.
+          if p[axis] + 1 >= dims[axis] || p[b] == 0 || p[c] == 0 || p[b] >= cell_dims[b] || p[c] >= cell_dims[c] {
+            continue;
+          }
+          let mut q = p;
+          q[axis] += 1;
+          let is_inside = value(p) < 0.0;
+          if is_inside == (value(q) < 0.0) {
+            continue;
+          }
+
+          let cell = |db: usize, dc: usize| {
+            let mut cell = p;
+            cell[b] = p[b] - 1 + db;
+            cell[c] = p[c] - 1 + dc;
+            cell
+          };
+          let quad = [cell(0, 0), cell(1, 0), cell(1, 1), cell(0, 1)].map(|cell| cell_vertices[cell_index(cell)]);
+          if quad.contains(&NO_VERTEX) {
+            continue;
+          }
+
+          // The quad faces +axis in this order, flip it if the inside is at the end of the edge.
+          if is_inside {
+            mesh.indices.extend_from_slice(&[quad[0], quad[1], quad[2], quad[0], quad[2], quad[3]]);
+          } else {
+            mesh.indices.extend_from_slice(&[quad[0], quad[2], quad[1], quad[0], quad[3], quad[2]]);
+          }
+        }
+      }
+    }
+  }
+
+  Ok(mesh)
+}
@@ -0,0 +1,196 @@
+use std::path::Path;
+
+use anyhow::{Result, Context};
+
+mod isosurface;
+mod obj;
+mod ply;
+mod stl;
+mod glb;
+mod points;
+mod vertex_color;
+
+pub use isosurface::*;
+pub use obj::*;
+pub use ply::*;
+pub use stl::*;
+pub use glb::*;
+pub use points::*;
+pub use vertex_color::*;
+
+/// The mesh file format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MeshFormat {
+  Obj,
+  Ply,
+  Stl,
+  Glb,
+}
+
+/// The implementation of the mesh file format.
+impl MeshFormat {
+  pub const ALL: [MeshFormat; 4] = [
+    MeshFormat::Obj,
+    MeshFormat::Ply,
+    MeshFormat::Stl,
+    MeshFormat::Glb,
+  ];
+
+  /// Get the name of the format.
+  /// return: The name.
+  pub fn name(&self) -> &'static str {
+    match self {
+      MeshFormat::Obj => "obj",
+      MeshFormat::Ply => "ply",
+      MeshFormat::Stl => "stl",
+      MeshFormat::Glb => "glb",
+    }
+  }
+
+  /// Get the format from the extension of the file path.
+  /// param path: The file path.
+  /// return: The format.
+  pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self> {
+    let path = path.as_ref();
+    let extension = path.extension()
+      .and_then(|e| e.to_str())
+      .ok_or(anyhow::anyhow!("The mesh file {:?} has no extension.", path))?;
+    extension.parse()
+  }
+}
+
+impl std::str::FromStr for MeshFormat {
+  type Err = anyhow::Error;
+
+  fn from_str(s: &str) -> Result<Self> {
+    MeshFormat::ALL.iter()
+      .find(|format| format.name().eq_ignore_ascii_case(s))
+      .copied()
+      .ok_or(anyhow::anyhow!("Unknown mesh format \"{}\".", s))
+  }
+}
+
+/// The triangle mesh with only the positions, used to merge and bake the geometry on the CPU.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TriangleMesh {
+  pub positions: Vec<[f32; 3]>,
+  pub indices: Vec<u32>,
+}
+
+/// The implementation of the triangle mesh.
+impl TriangleMesh {
+
+  /// Create a triangle mesh.
+  /// param positions: The vertex positions.
+  /// param indices: The triangle indices.
+  /// return: The triangle mesh.
+  pub fn new(positions: Vec<[f32; 3]>, indices: Vec<u32>) -> Self {
+    Self {
+      positions,
+      indices,
+    }
+  }
+
+  /// Get the number of triangles.
+  /// return: The number of triangles.
+  pub fn num_of_triangles(&self) -> usize {
+    self.indices.len() / 3
+  }
+
+  /// Get the positions of the triangle.
+  /// param index: The triangle index.
+  /// return: The 3 positions.
+  pub fn triangle(&self, index: usize) -> [[f32; 3]; 3] {
+    [
+      self.positions[self.indices[index * 3] as usize],
+      self.positions[self.indices[index * 3 + 1] as usize],
+      self.positions[self.indices[index * 3 + 2] as usize],
+    ]
+  }
+
+  /// Get the axis aligned bounding box.
+  /// return: The (min, max) corners, None if the mesh is empty.
+  pub fn bounds(&self) -> Option<([f32; 3], [f32; 3])> {
+    let mut positions = self.indices.iter().map(|i| self.positions[*i as usize]);
+    let first = positions.next()?;
+    Some(positions.fold((first, first), |(min, max), p| (
+      [min[0].min(p[0]), min[1].min(p[1]), min[2].min(p[2])],
+      [max[0].max(p[0]), max[1].max(p[1]), max[2].max(p[2])],
+    )))
+  }
+
+  /// Get the mesh with the positions transformed by the matrix.
+  /// param matrix: The transform matrix.
+  /// return: The transformed mesh.
+  pub fn transformed(&self, matrix: &glam::Mat4) -> Self {
+    Self {
+      positions: self.positions.iter()
+        .map(|p| matrix.transform_point3(glam::Vec3::from_array(*p)).to_array())
+        .collect(),
+      indices: self.indices.clone(),
+    }
+  }
+
+  /// Append the triangles of the other mesh.
+  /// param other: The other mesh.
+  pub fn append(&mut self, other: &TriangleMesh) {
+    let base = self.positions.len() as u32;
+    self.positions.extend_from_slice(&other.positions);
+    self.indices.extend(other.indices.iter().map(|i| base + i));
+  }
+
+  /// Get the vertex data with the position at the beginning of each vertex.
+  /// param stride: The vertex stride in bytes, at least 12.
+  /// return: The bytes.
+  pub fn get_vertex_bytes(&self, stride: usize) -> Vec<u8> {
+    let mut bytes = vec![0u8; self.positions.len() * stride];
+    for (i, p) in self.positions.iter().enumerate() {
+      for (j, v) in p.iter().enumerate() {
+        bytes[i * stride + j * 4..i * stride + j * 4 + 4].copy_from_slice(&v.to_le_bytes());
+      }
+    }
+    bytes
+  }
+
+  /// Save the mesh to the file.
+  /// param path: The file path.
+  /// param format: The file format.
+  /// return: The result.
+  pub fn save<P: AsRef<Path>>(&self, path: P, format: MeshFormat) -> Result<()> {
+    let path = path.as_ref();
+    let file = std::fs::File::create(path)
+      .with_context(|| format!("Failed to create the mesh file: {:?}", path))?;
+    let mut writer = std::io::BufWriter::new(file);
+    match format {
+      MeshFormat::Obj => write_obj(&mut writer, self),
+      MeshFormat::Ply => write_ply(&mut writer, self),
+      MeshFormat::Stl => write_stl(&mut writer, self),
+      MeshFormat::Glb => write_glb(&mut writer, self),
+    }.with_context(|| format!("Failed to write the mesh file: {:?}", path))?;
+    std::io::Write::flush(&mut writer)
+      .with_context(|| format!("Failed to flush the mesh file: {:?}", path))?;
+    Ok(())
+  }
+
+  /// Load the mesh from the file, the format is chosen by the extension.
+  /// param path: The file path of the OBJ, PLY or STL file.
+  /// return: The triangle mesh.
+  pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+    let path = path.as_ref();
+    let format = MeshFormat::from_path(path)?;
+    let file = std::fs::File::open(path)
+      .with_context(|| format!("Failed to open the mesh file: {:?}", path))?;
+    let mut reader = std::io::BufReader::new(file);
+    let mesh = match format {
+      MeshFormat::Obj => read_obj(&mut reader),
+      MeshFormat::Ply => read_ply(&mut reader),
+      MeshFormat::Stl => read_stl(&mut reader),
+      MeshFormat::Glb => Err(anyhow::anyhow!("Loading the GLB mesh is not supported, open it as the scene.")),
+    }.with_context(|| format!("Failed to read the mesh file: {:?}", path))?;
+    if mesh.num_of_triangles() == 0 {
+      return Err(anyhow::anyhow!("The mesh file {:?} has no triangle.", path));
+    }
+    Ok(mesh)
+  }
+
+}
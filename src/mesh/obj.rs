@@ -0,0 +1,74 @@
+use std::io::{Read, Write};
+
+use anyhow::{Result, Context};
+
+use super::TriangleMesh;
+
+/// Write the mesh in the Wavefront OBJ format.
+/// param writer: The writer.
+/// param mesh: The triangle mesh.
+/// return: The result.
+pub fn write_obj<W: Write>(writer: &mut W, mesh: &TriangleMesh) -> Result<()> {
+  writer.write_all(b"# Exported by hala-sdf-baker.\n")
+    .with_context(|| "Failed to write the OBJ header.")?;
+  for p in mesh.positions.iter() {
+    writer.write_all(format!("v {} {} {}\n", p[0], p[1], p[2]).as_bytes())
+      .with_context(|| "Failed to write the vertices.")?;
+  }
+  // The OBJ indices start from 1.
+  for triangle in mesh.indices.chunks_exact(3) {
+    writer.write_all(format!("f {} {} {}\n", triangle[0] + 1, triangle[1] + 1, triangle[2] + 1).as_bytes())
+      .with_context(|| "Failed to write the faces.")?;
+  }
+  Ok(())
+}
+
+/// Read the mesh in the Wavefront OBJ format.
+/// Only the vertex positions and the faces are read, the polygons are triangulated as fans.
+/// All objects and groups are read into one mesh.
+/// param reader: The reader.
+/// return: The triangle mesh.
+pub fn read_obj<R: Read>(reader: &mut R) -> Result<TriangleMesh> {
+  let mut content = String::new();
+  reader.read_to_string(&mut content)
+    .with_context(|| "Failed to read the OBJ data.")?;
+
+  let mut mesh = TriangleMesh::default();
+  for (line_index, line) in content.lines().enumerate() {
+    let mut fields = line.split_whitespace();
+    match fields.next() {
+      Some("v") => {
+        let values = fields.take(3)
+          .map(|v| v.parse::<f32>().with_context(|| format!("Invalid vertex at line {}.", line_index + 1)))
+          .collect::<Result<Vec<_>>>()?;
+        if values.len() != 3 {
+          return Err(anyhow::anyhow!("The vertex at line {} has less than 3 coordinates.", line_index + 1));
+        }
+        mesh.positions.push([values[0], values[1], values[2]]);
+      },
+      Some("f") => {
+        // The index is the first part of "v/vt/vn", 1-based or negative from the end.
+        let indices = fields
+          .map(|f| {
+            let index = f.split('/').next().unwrap_or("").parse::<i64>()
+              .with_context(|| format!("Invalid face at line {}.", line_index + 1))?;
+            let index = if index < 0 { mesh.positions.len() as i64 + index } else { index - 1 };
+            if index < 0 || index >= mesh.positions.len() as i64 {
+              return Err(anyhow::anyhow!("The face at line {} refers to the missing vertex {}.", line_index + 1, f));
+            }
+            Ok(index as u32)
+          })
+          .collect::<Result<Vec<_>>>()?;
+        if indices.len() < 3 {
+          return Err(anyhow::anyhow!("The face at line {} has less than 3 vertices.", line_index + 1));
+        }
+        for i in 1..indices.len() - 1 {
+          mesh.indices.extend_from_slice(&[indices[0], indices[i], indices[i + 1]]);
+        }
+      },
+      _ => {},
+    }
+  }
+
+  Ok(mesh)
+}
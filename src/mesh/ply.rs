@@ -0,0 +1,249 @@
+use std::io::{Read, Write};
+
+use anyhow::{Result, Context};
+
+use super::TriangleMesh;
+
+/// Write the mesh in the little-endian binary PLY format.
+/// param writer: The writer.
+/// param mesh: The triangle mesh.
+/// return: The result.
+pub fn write_ply<W: Write>(writer: &mut W, mesh: &TriangleMesh) -> Result<()> {
+  let header = format!(
+    "ply\nformat binary_little_endian 1.0\ncomment Exported by hala-sdf-baker.\nelement vertex {}\nproperty float x\nproperty float y\nproperty float z\nelement face {}\nproperty list uchar uint vertex_indices\nend_header\n",
+    mesh.positions.len(),
+    mesh.num_of_triangles(),
+  );
+  writer.write_all(header.as_bytes())
+    .with_context(|| "Failed to write the PLY header.")?;
+
+  let mut bytes = Vec::with_capacity(mesh.positions.len() * 12 + mesh.num_of_triangles() * 13);
+  for p in mesh.positions.iter() {
+    for v in p.iter() {
+      bytes.extend_from_slice(&v.to_le_bytes());
+    }
+  }
+  for triangle in mesh.indices.chunks_exact(3) {
+    bytes.push(3);
+    for i in triangle.iter() {
+      bytes.extend_from_slice(&i.to_le_bytes());
+    }
+  }
+  writer.write_all(&bytes)
+    .with_context(|| "Failed to write the PLY data.")?;
+  Ok(())
+}
+
+/// The storage format of the PLY data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PlyEncoding {
+  Ascii,
+  BinaryLittleEndian,
+  BinaryBigEndian,
+}
+
+/// The property of the PLY element.
+#[derive(Debug, Clone)]
+enum PlyProperty {
+  Scalar(String, String),
+  List(String, String, String), // The name, the type of the count and the type of the items.
+}
+
+/// The element of the PLY header.
+#[derive(Debug, Clone)]
+struct PlyElement {
+  name: String,
+  count: usize,
+  properties: Vec<PlyProperty>,
+}
+
+/// The reader of the PLY values in the body.
+struct PlyValueReader<'a> {
+  encoding: PlyEncoding,
+  bytes: &'a [u8],
+  offset: usize,
+  tokens: std::str::SplitAsciiWhitespace<'a>,
+}
+
+/// The implementation of the PLY value reader.
+impl<'a> PlyValueReader<'a> {
+
+  /// Get the size in bytes of the scalar type.
+  /// param ty: The type name.
+  /// return: The size.
+  fn type_size(ty: &str) -> Result<usize> {
+    match ty {
+      "char" | "int8" | "uchar" | "uint8" => Ok(1),
+      "short" | "int16" | "ushort" | "uint16" => Ok(2),
+      "int" | "int32" | "uint" | "uint32" | "float" | "float32" => Ok(4),
+      "double" | "float64" => Ok(8),
+      _ => Err(anyhow::anyhow!("Unknown PLY type \"{}\".", ty)),
+    }
+  }
+
+  /// Read the next value as f64.
+  /// param ty: The type name.
+  /// return: The value.
+  fn read(&mut self, ty: &str) -> Result<f64> {
+    if self.encoding == PlyEncoding::Ascii {
+      let token = self.tokens.next().ok_or(anyhow::anyhow!("The PLY data ends unexpectedly."))?;
+      return token.parse::<f64>().with_context(|| format!("Invalid PLY value \"{}\".", token));
+    }
+
+    let size = Self::type_size(ty)?;
+    let raw = self.bytes.get(self.offset..self.offset + size)
+      .ok_or(anyhow::anyhow!("The PLY data ends unexpectedly."))?;
+    self.offset += size;
+    let mut buffer = [0u8; 8];
+    buffer[..size].copy_from_slice(raw);
+    if self.encoding == PlyEncoding::BinaryBigEndian {
+      buffer[..size].reverse();
+    }
+    let value = match ty {
+      "char" | "int8" => buffer[0] as i8 as f64,
+      "uchar" | "uint8" => buffer[0] as f64,
+      "short" | "int16" => i16::from_le_bytes([buffer[0], buffer[1]]) as f64,
+      "ushort" | "uint16" => u16::from_le_bytes([buffer[0], buffer[1]]) as f64,
+      "int" | "int32" => i32::from_le_bytes([buffer[0], buffer[1], buffer[2], buffer[3]]) as f64,
+      "uint" | "uint32" => u32::from_le_bytes([buffer[0], buffer[1], buffer[2], buffer[3]]) as f64,
+      "float" | "float32" => f32::from_le_bytes([buffer[0], buffer[1], buffer[2], buffer[3]]) as f64,
+      _ => f64::from_le_bytes(buffer),
+    };
+    Ok(value)
+  }
+
+}
+
+/// Convert the value read as f64 to the integer, the float or negative values are errors instead of being truncated.
+/// param value: The value.
+/// param what: The name of the value in the error.
+/// return: The integer.
+fn to_integer<T: TryFrom<i64>>(value: f64, what: &str) -> Result<T> {
+  if !value.is_finite() || value.fract() != 0.0 {
+    return Err(anyhow::anyhow!("The PLY {} {} is not an integer.", what, value));
+  }
+  // The cast saturates, so the values out of i64 are still out of T.
+  T::try_from(value as i64).map_err(|_| anyhow::anyhow!("The PLY {} {} is out of range.", what, value))
+}
+
+/// Read the mesh in the PLY format, ASCII or binary in either byte order.
+/// Only the vertex x, y, z and the face vertex indices are read, the other properties and elements are skipped.
+/// The polygons are triangulated as fans.
+/// param reader: The reader.
+/// return: The triangle mesh.
+pub fn read_ply<R: Read>(reader: &mut R) -> Result<TriangleMesh> {
+  let mut bytes = Vec::new();
+  reader.read_to_end(&mut bytes)
+    .with_context(|| "Failed to read the PLY data.")?;
+
+  // Parse the header line by line until "end_header".
+  let mut offset = 0;
+  let mut encoding = None;
+  let mut elements: Vec<PlyElement> = Vec::new();
+  let mut is_first_line = true;
+  loop {
+    let end = bytes[offset..].iter().position(|b| *b == b'\n')
+      .ok_or(anyhow::anyhow!("The PLY header has no \"end_header\"."))?;
+    let line = std::str::from_utf8(&bytes[offset..offset + end])
+      .with_context(|| "The PLY header is not text.")?
+      .trim();
+    offset += end + 1;
+
+    let fields = line.split_whitespace().collect::<Vec<_>>();
+    if is_first_line {
+      if line != "ply" {
+        return Err(anyhow::anyhow!("The file is not in the PLY format."));
+      }
+      is_first_line = false;
+      continue;
+    }
+    match fields.as_slice() {
+      ["format", format, ..] => {
+        encoding = Some(match *format {
+          "ascii" => PlyEncoding::Ascii,
+          "binary_little_endian" => PlyEncoding::BinaryLittleEndian,
+          "binary_big_endian" => PlyEncoding::BinaryBigEndian,
+          _ => return Err(anyhow::anyhow!("Unknown PLY format \"{}\".", format)),
+        });
+      },
+      ["element", name, count] => {
+        elements.push(PlyElement {
+          name: name.to_string(),
+          count: count.parse().with_context(|| format!("Invalid PLY element count \"{}\".", count))?,
+          properties: Vec::new(),
+        });
+      },
+      ["property", "list", count_type, item_type, name] => {
+        elements.last_mut()
+          .ok_or(anyhow::anyhow!("The PLY property \"{}\" is out of any element.", name))?
+          .properties.push(PlyProperty::List(name.to_string(), count_type.to_string(), item_type.to_string()));
+      },
+      ["property", ty, name] => {
+        elements.last_mut()
+          .ok_or(anyhow::anyhow!("The PLY property \"{}\" is out of any element.", name))?
+          .properties.push(PlyProperty::Scalar(name.to_string(), ty.to_string()));
+      },
+      ["end_header"] => break,
+      _ => {},
+    }
+  }
+  let encoding = encoding.ok_or(anyhow::anyhow!("The PLY header has no format."))?;
+
+  let body = &bytes[offset..];
+  let mut values = PlyValueReader {
+    encoding,
+    bytes: body,
+    offset: 0,
+    tokens: if encoding == PlyEncoding::Ascii {
+      std::str::from_utf8(body).with_context(|| "The ASCII PLY data is not text.")?.split_ascii_whitespace()
+    } else {
+      "".split_ascii_whitespace()
+    },
+  };
+
+  let mut mesh = TriangleMesh::default();
+  for element in elements.iter() {
+    for _ in 0..element.count {
+      let mut position = [0.0f32; 3];
+      let mut polygon = Vec::new();
+      for property in element.properties.iter() {
+        match property {
+          PlyProperty::Scalar(name, ty) => {
+            let value = values.read(ty)?;
+            if element.name == "vertex" {
+              match name.as_str() {
+                "x" => position[0] = value as f32,
+                "y" => position[1] = value as f32,
+                "z" => position[2] = value as f32,
+                _ => {},
+              }
+            }
+          },
+          PlyProperty::List(name, count_type, item_type) => {
+            let count = to_integer::<usize>(values.read(count_type)?, "list count")?;
+            let is_indices = element.name == "face" && (name == "vertex_indices" || name == "vertex_index");
+            for _ in 0..count {
+              let value = values.read(item_type)?;
+              if is_indices {
+                polygon.push(to_integer::<u32>(value, "vertex index")?);
+              }
+            }
+          },
+        }
+      }
+
+      if element.name == "vertex" {
+        mesh.positions.push(position);
+      } else if element.name == "face" && polygon.len() >= 3 {
+        for i in 1..polygon.len() - 1 {
+          mesh.indices.extend_from_slice(&[polygon[0], polygon[i], polygon[i + 1]]);
+        }
+      }
+    }
+  }
+
+  if let Some(index) = mesh.indices.iter().find(|i| **i as usize >= mesh.positions.len()) {
+    return Err(anyhow::anyhow!("The PLY face refers to the missing vertex {}.", index));
+  }
+  Ok(mesh)
+}
@@ -0,0 +1,104 @@
+use std::io::Read;
+use std::path::Path;
+
+use anyhow::{Result, Context};
+
+use super::read_ply;
+
+/// The point cloud with only the positions, baked as the UDF to the points.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PointCloud {
+  pub positions: Vec<[f32; 3]>,
+}
+
+/// The implementation of the point cloud.
+impl PointCloud {
+
+  /// Create a point cloud.
+  /// param positions: The point positions.
+  /// return: The point cloud.
+  pub fn new(positions: Vec<[f32; 3]>) -> Self {
+    Self {
+      positions,
+    }
+  }
+
+  /// Get the number of points.
+  /// return: The number of points.
+  pub fn num_of_points(&self) -> usize {
+    self.positions.len()
+  }
+
+  /// Get the axis aligned bounding box.
+  /// return: The (min, max) corners, None if the point cloud is empty.
+  pub fn bounds(&self) -> Option<([f32; 3], [f32; 3])> {
+    let first = *self.positions.first()?;
+    Some(self.positions.iter().fold((first, first), |(min, max), p| (
+      [min[0].min(p[0]), min[1].min(p[1]), min[2].min(p[2])],
+      [max[0].max(p[0]), max[1].max(p[1]), max[2].max(p[2])],
+    )))
+  }
+
+  /// Get the tightly packed positions, 3 little-endian f32 per point.
+  /// return: The bytes.
+  pub fn get_position_bytes(&self) -> Vec<u8> {
+    self.positions.iter()
+      .flat_map(|p| p.iter().flat_map(|v| v.to_le_bytes()))
+      .collect()
+  }
+
+  /// Load the point cloud from the XYZ or PLY file by the extension.
+  /// All vertices of the PLY file are read as points, the faces are ignored.
+  /// param path: The file path.
+  /// return: The point cloud.
+  pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+    let path = path.as_ref();
+    let extension = path.extension()
+      .and_then(|e| e.to_str())
+      .map(|e| e.to_ascii_lowercase())
+      .ok_or(anyhow::anyhow!("The point cloud file {:?} has no extension.", path))?;
+    let file = std::fs::File::open(path)
+      .with_context(|| format!("Failed to open the point cloud file: {:?}", path))?;
+    let mut reader = std::io::BufReader::new(file);
+    let points = match extension.as_str() {
+      "xyz" => read_xyz(&mut reader),
+      "ply" => read_ply(&mut reader).map(|mesh| PointCloud::new(mesh.positions)),
+      _ => Err(anyhow::anyhow!("Unknown point cloud format \"{}\".", extension)),
+    }.with_context(|| format!("Failed to read the point cloud file: {:?}", path))?;
+    if points.num_of_points() == 0 {
+      return Err(anyhow::anyhow!("The point cloud file {:?} has no point.", path));
+    }
+    Ok(points)
+  }
+
+}
+
+/// Read the point cloud in the XYZ text format.
+/// Each line begins with the x, y, z of a point, the other columns like the normal or the color are ignored.
+/// The empty lines and the lines beginning with "#" or "//" are skipped.
+/// param reader: The reader.
+/// return: The point cloud.
+pub fn read_xyz<R: Read>(reader: &mut R) -> Result<PointCloud> {
+  let mut content = String::new();
+  reader.read_to_string(&mut content)
+    .with_context(|| "Failed to read the XYZ data.")?;
+
+  let mut points = PointCloud::default();
+  for (line_index, line) in content.lines().enumerate() {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') || line.starts_with("//") {
+      continue;
+    }
+    let values = line.split(|c: char| c.is_whitespace() || c == ',' || c == ';')
+      .filter(|v| !v.is_empty())
+      .take(3)
+      .map(|v| v.parse::<f32>().with_context(|| format!("Invalid point at line {}.", line_index + 1)))
+      .collect::<Result<Vec<_>>>()?;
+    if values.len() != 3 {
+      return Err(anyhow::anyhow!("The point at line {} has less than 3 coordinates.", line_index + 1));
+    }
+    points.positions.push([values[0], values[1], values[2]]);
+  }
+
+  Ok(points)
+}
@@ -0,0 +1,109 @@
+use std::io::{Read, Write};
+
+use anyhow::{Result, Context};
+
+use super::TriangleMesh;
+
+/// The size of the binary STL header in bytes, 80 bytes of the comment and the triangle count.
+const STL_HEADER_SIZE: usize = 84;
+
+/// The size of each triangle in the binary STL, the normal, 3 vertices and the attribute byte count.
+const STL_TRIANGLE_SIZE: usize = 50;
+
+/// Write the mesh in the binary STL format.
+/// The normals are computed from the triangles.
+/// param writer: The writer.
+/// param mesh: The triangle mesh.
+/// return: The result.
+pub fn write_stl<W: Write>(writer: &mut W, mesh: &TriangleMesh) -> Result<()> {
+  let num_of_triangles = mesh.num_of_triangles();
+  let mut bytes = Vec::with_capacity(STL_HEADER_SIZE + num_of_triangles * STL_TRIANGLE_SIZE);
+  let mut header = [0u8; 80];
+  let comment = b"Exported by hala-sdf-baker.";
+  header[..comment.len()].copy_from_slice(comment);
+  bytes.extend_from_slice(&header);
+  bytes.extend_from_slice(&(num_of_triangles as u32).to_le_bytes());
+
+  for i in 0..num_of_triangles {
+    let triangle = mesh.triangle(i).map(glam::Vec3::from_array);
+    let normal = (triangle[1] - triangle[0]).cross(triangle[2] - triangle[0]).normalize_or_zero();
+    for v in std::iter::once(normal).chain(triangle) {
+      for c in v.to_array().iter() {
+        bytes.extend_from_slice(&c.to_le_bytes());
+      }
+    }
+    bytes.extend_from_slice(&0u16.to_le_bytes());
+  }
+
+  writer.write_all(&bytes)
+    .with_context(|| "Failed to write the STL data.")?;
+  Ok(())
+}
+
+/// Read the mesh in the STL format, ASCII or binary.
+/// The data is binary if its size matches the triangle count in the header, because some binary files also begin with "solid".
+/// The vertices are not shared between the triangles.
+/// param reader: The reader.
+/// return: The triangle mesh.
+pub fn read_stl<R: Read>(reader: &mut R) -> Result<TriangleMesh> {
+  let mut bytes = Vec::new();
+  reader.read_to_end(&mut bytes)
+    .with_context(|| "Failed to read the STL data.")?;
+
+  if bytes.len() >= STL_HEADER_SIZE {
+    let num_of_triangles = u32::from_le_bytes([bytes[80], bytes[81], bytes[82], bytes[83]]) as usize;
+    if bytes.len() == STL_HEADER_SIZE + num_of_triangles * STL_TRIANGLE_SIZE {
+      return Ok(read_binary_stl(&bytes[STL_HEADER_SIZE..], num_of_triangles));
+    }
+  }
+  if !bytes.trim_ascii_start().starts_with(b"solid") {
+    return Err(anyhow::anyhow!("The file is neither the binary nor the ASCII STL."));
+  }
+  read_ascii_stl(&bytes)
+}
+
+/// Read the triangles of the binary STL.
+/// param bytes: The bytes after the header.
+/// param num_of_triangles: The number of triangles.
+/// return: The triangle mesh.
+fn read_binary_stl(bytes: &[u8], num_of_triangles: usize) -> TriangleMesh {
+  let mut mesh = TriangleMesh::default();
+  for triangle in bytes.chunks_exact(STL_TRIANGLE_SIZE).take(num_of_triangles) {
+    // Skip the normal.
+    for vertex in triangle[12..48].chunks_exact(12) {
+      let value = |i: usize| f32::from_le_bytes([vertex[i], vertex[i + 1], vertex[i + 2], vertex[i + 3]]);
+      mesh.indices.push(mesh.positions.len() as u32);
+      mesh.positions.push([value(0), value(4), value(8)]);
+    }
+  }
+  mesh
+}
+
+/// Read the triangles of the ASCII STL.
+/// Every 3 "vertex" lines make one triangle, the facets of all solids are read into one mesh.
+/// param bytes: The text.
+/// return: The triangle mesh.
+fn read_ascii_stl(bytes: &[u8]) -> Result<TriangleMesh> {
+  let content = std::str::from_utf8(bytes)
+    .with_context(|| "The ASCII STL data is not text.")?;
+
+  let mut mesh = TriangleMesh::default();
+  for (line_index, line) in content.lines().enumerate() {
+    let mut fields = line.split_whitespace();
+    if fields.next() != Some("vertex") {
+      continue;
+    }
+    let values = fields.take(3)
+      .map(|v| v.parse::<f32>().with_context(|| format!("Invalid vertex at line {}.", line_index + 1)))
+      .collect::<Result<Vec<_>>>()?;
+    if values.len() != 3 {
+      return Err(anyhow::anyhow!("The vertex at line {} has less than 3 coordinates.", line_index + 1));
+    }
+    mesh.positions.push([values[0], values[1], values[2]]);
+  }
+  if mesh.positions.len() % 3 != 0 {
+    return Err(anyhow::anyhow!("The number of vertices {} is not a multiple of 3.", mesh.positions.len()));
+  }
+  mesh.indices = (0..mesh.positions.len() as u32).collect();
+  Ok(mesh)
+}
@@ -0,0 +1,28 @@
+use std::path::Path;
+
+use anyhow::{Result, Context};
+
+/// Read the COLOR_0 of all primitives of the glTF file, in the order of the meshes and their primitives.
+/// The colors are linear RGBA, the RGB colors get the alpha 1 and the normalized integers are converted to floats.
+/// Only the buffers are loaded, not the images.
+/// param path: The glTF or GLB file path.
+/// return: The vertex colors of each primitive, empty if the primitive has none.
+pub fn read_gltf_vertex_colors<P: AsRef<Path>>(path: P) -> Result<Vec<Vec<[f32; 4]>>> {
+  let path = path.as_ref();
+  let gltf = gltf::Gltf::open(path)
+    .with_context(|| format!("Failed to open the glTF file {:?}.", path))?;
+  let buffers = gltf::import_buffers(&gltf.document, path.parent(), gltf.blob.clone())
+    .with_context(|| format!("Failed to load the buffers of the glTF file {:?}.", path))?;
+
+  let colors = gltf.document.meshes()
+    .flat_map(|mesh| mesh.primitives())
+    .map(|primitive| {
+      let reader = primitive.reader(|buffer| buffers.get(buffer.index()).map(|data| &data.0[..]));
+      reader.read_colors(0)
+        .map(|colors| colors.into_rgba_f32().collect())
+        .unwrap_or_default()
+    })
+    .collect();
+
+  Ok(colors)
+}
@@ -0,0 +1,458 @@
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Result, Context};
+
+use super::{
+  ClosestTriangleVolume,
+  NO_CLOSEST_TRIANGLE,
+  KTX2_METADATA_PREFIX,
+  KHR_DF_TRANSFER_LINEAR,
+  KHR_DF_TRANSFER_SRGB,
+  KHR_DF_SAMPLE_DATATYPE_SIGNED,
+  KHR_DF_SAMPLE_DATATYPE_FLOAT,
+  build_channels_dfd,
+  write_ktx2_texture,
+  parse_ktx2_texture,
+  from_metadata,
+};
+
+/// The material index of the voxels without the closest triangle, or of the primitives without the material.
+pub const NO_MATERIAL: u32 = u32::MAX;
+
+const VK_FORMAT_R8G8B8A8_SRGB: u32 = 43;
+const VK_FORMAT_R32_UINT: u32 = 98;
+const VK_FORMAT_R32G32_SFLOAT: u32 = 103;
+
+/// The attribute channel of the volume, each one is saved as its own texture.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AttributeChannel {
+  Color,
+  TexCoord,
+  Material,
+}
+
+/// The implementation of the attribute channel.
+impl AttributeChannel {
+  pub const ALL: [AttributeChannel; 3] = [
+    AttributeChannel::Color,
+    AttributeChannel::TexCoord,
+    AttributeChannel::Material,
+  ];
+
+  /// Get the name of the channel.
+  /// return: The name.
+  pub fn name(&self) -> &'static str {
+    match self {
+      AttributeChannel::Color => "color",
+      AttributeChannel::TexCoord => "uv",
+      AttributeChannel::Material => "material",
+    }
+  }
+
+  /// Get the file path of the channel, which is the path with the channel name appended to the file stem.
+  /// param path: The file path of the attributes.
+  /// return: The file path of the channel.
+  pub fn get_path<P: AsRef<Path>>(&self, path: P) -> PathBuf {
+    let path = path.as_ref();
+    let stem = path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+    path.with_file_name(format!("{}_{}.ktx2", stem, self.name()))
+  }
+}
+
+/// The base color texture of a material, in linear RGBA.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SurfaceTexture {
+  pub width: u32,
+  pub height: u32,
+  pub texels: Vec<[f32; 4]>, // The rows from the top one, X changing fastest.
+}
+
+/// The implementation of the surface texture.
+impl SurfaceTexture {
+
+  /// Create the texture from the 8 bits texels of 1 to 4 channels.
+  /// The gray texels are expanded to RGB, the missing alpha is 1.
+  /// param width: The width.
+  /// param height: The height.
+  /// param data: The texels.
+  /// param is_srgb: Whether the color channels are sRGB encoded.
+  /// return: The texture.
+  pub fn from_bytes(width: u32, height: u32, data: &[u8], is_srgb: bool) -> Result<Self> {
+    let values = data.iter().map(|v| *v as f32 / 255.0).collect::<Vec<_>>();
+    Self::from_values(width, height, &values, is_srgb)
+  }
+
+  /// Create the texture from the float texels of 1 to 4 channels.
+  /// The gray texels are expanded to RGB, the missing alpha is 1.
+  /// param width: The width.
+  /// param height: The height.
+  /// param data: The texels.
+  /// param is_srgb: Whether the color channels are sRGB encoded.
+  /// return: The texture.
+  pub fn from_values(width: u32, height: u32, data: &[f32], is_srgb: bool) -> Result<Self> {
+    let num_of_texels = width as usize * height as usize;
+    let num_of_channels = data.len().checked_div(num_of_texels).unwrap_or(0);
+    if num_of_texels == 0 || !(1..=4).contains(&num_of_channels) || data.len() != num_of_texels * num_of_channels {
+      return Err(anyhow::anyhow!(
+        "The texture of {}x{} texels has {} values, which are not 1 to 4 channels.",
+        width,
+        height,
+        data.len(),
+      ));
+    }
+
+    let decode = |v: f32| if is_srgb { srgb_to_linear(v) } else { v };
+    let texels = data.chunks_exact(num_of_channels)
+      .map(|t| match t {
+        [l] => [decode(*l), decode(*l), decode(*l), 1.0],
+        [l, a] => [decode(*l), decode(*l), decode(*l), *a],
+        [r, g, b] => [decode(*r), decode(*g), decode(*b), 1.0],
+        _ => [decode(t[0]), decode(t[1]), decode(t[2]), t[3]],
+      })
+      .collect();
+    Ok(Self {
+      width,
+      height,
+      texels,
+    })
+  }
+
+  /// Sample the texture bilinearly with the repeat wrap mode.
+  /// param uv: The texture coordinates, (0, 0) is the top left corner.
+  /// return: The linear RGBA.
+  pub fn sample(&self, uv: [f32; 2]) -> [f32; 4] {
+    if self.texels.is_empty() {
+      return [1.0; 4];
+    }
+    let x = uv[0] * self.width as f32 - 0.5;
+    let y = uv[1] * self.height as f32 - 0.5;
+    let (x0, y0) = (x.floor(), y.floor());
+    let (fx, fy) = (x - x0, y - y0);
+    let texel = |i: i64, j: i64| {
+      let i = i.rem_euclid(self.width as i64) as usize;
+      let j = j.rem_euclid(self.height as i64) as usize;
+      self.texels[i + self.width as usize * j]
+    };
+    let (i, j) = (x0 as i64, y0 as i64);
+    let (t00, t10, t01, t11) = (texel(i, j), texel(i + 1, j), texel(i, j + 1), texel(i + 1, j + 1));
+    [0, 1, 2, 3].map(|c| {
+      let top = t00[c] + (t10[c] - t00[c]) * fx;
+      let bottom = t01[c] + (t11[c] - t01[c]) * fx;
+      top + (bottom - top) * fy
+    })
+  }
+
+}
+
+/// The base color of a material, the factor is multiplied by the texture.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SurfaceMaterial {
+  pub base_color: [f32; 4], // The linear RGBA factor.
+  pub base_color_texture: Option<usize>, // The index in the textures of the scene attributes.
+}
+
+/// The vertex attributes of a primitive.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SurfaceAttributes {
+  pub indices: Vec<u32>,
+  pub tex_coords: Vec<[f32; 2]>, // The TEXCOORD_0 of the vertices, empty if the primitive has none.
+  pub colors: Vec<[f32; 4]>, // The linear COLOR_0 of the vertices, empty if the primitive has none.
+  pub material_index: u32, // The material index in the scene, or NO_MATERIAL.
+}
+
+/// The vertex attributes, the materials and the base color textures of the primitives in the scene.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SceneAttributes {
+  pub primitives: Vec<SurfaceAttributes>, // The primitives of all meshes one after another.
+  pub materials: Vec<SurfaceMaterial>,
+  pub textures: Vec<SurfaceTexture>,
+}
+
+/// The implementation of the scene attributes.
+impl SceneAttributes {
+
+  /// Sample the attributes at the closest point of each voxel.
+  /// The color is the base color factor multiplied by the base color texture at TEXCOORD_0 and by COLOR_0,
+  /// the missing attributes are read as the glTF defaults.
+  /// The voxels without the closest triangle are transparent black, with zero texture coordinates and NO_MATERIAL.
+  /// param closest: The closest triangles of the voxels.
+  /// return: The attribute volume.
+  pub fn sample_volume(&self, closest: &ClosestTriangleVolume) -> Result<AttributeVolume> {
+    closest.validate()?;
+    let num_of_voxels = closest.num_of_voxels();
+    let mut volume = AttributeVolume {
+      dimensions: closest.dimensions,
+      center: closest.center,
+      extents: closest.extents,
+      voxel_size: closest.voxel_size,
+      colors: Vec::with_capacity(num_of_voxels),
+      tex_coords: Vec::with_capacity(num_of_voxels),
+      material_indices: Vec::with_capacity(num_of_voxels),
+    };
+
+    for (triangle, weights) in closest.triangles.iter().zip(closest.barycentrics.iter()) {
+      if *triangle == NO_CLOSEST_TRIANGLE {
+        volume.colors.push([0.0; 4]);
+        volume.tex_coords.push([0.0; 2]);
+        volume.material_indices.push(NO_MATERIAL);
+        continue;
+      }
+      let (primitive_index, local_triangle) = closest.locate(*triangle)
+        .ok_or(anyhow::anyhow!("The closest triangle {} is out of the ranges.", triangle))?;
+      let primitive = self.primitives.get(primitive_index as usize)
+        .ok_or(anyhow::anyhow!("The primitive {} has no attributes.", primitive_index))?;
+      let vertices = primitive.indices.get(local_triangle as usize * 3..local_triangle as usize * 3 + 3)
+        .ok_or(anyhow::anyhow!("The triangle {} is out of the primitive {}.", local_triangle, primitive_index))?;
+
+      let tex_coord = interpolate(&primitive.tex_coords, vertices, weights, [0.0; 2])?;
+      let vertex_color = interpolate(&primitive.colors, vertices, weights, [1.0; 4])?;
+      let base_color = match self.materials.get(primitive.material_index as usize) {
+        Some(material) => {
+          let texel = material.base_color_texture
+            .and_then(|index| self.textures.get(index))
+            .map(|texture| texture.sample(tex_coord))
+            .unwrap_or([1.0; 4]);
+          [0, 1, 2, 3].map(|c| material.base_color[c] * texel[c])
+        },
+        None => [1.0; 4],
+      };
+      volume.colors.push([0, 1, 2, 3].map(|c| base_color[c] * vertex_color[c]));
+      volume.tex_coords.push(tex_coord);
+      volume.material_indices.push(primitive.material_index);
+    }
+
+    Ok(volume)
+  }
+
+}
+
+/// The vertex attributes sampled at the closest surface point of each voxel.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AttributeVolume {
+  pub dimensions: [u32; 3],
+  pub center: [f32; 3],
+  pub extents: [f32; 3],
+  pub voxel_size: f32,
+  pub colors: Vec<[f32; 4]>, // The linear RGBA of the voxels with X changing fastest, empty if not loaded.
+  pub tex_coords: Vec<[f32; 2]>, // The TEXCOORD_0 of the voxels, empty if not loaded.
+  pub material_indices: Vec<u32>, // The material index of the voxels, empty if not loaded.
+}
+
+/// The implementation of the attribute volume.
+impl AttributeVolume {
+
+  /// Get the number of voxels.
+  /// return: The number of voxels.
+  pub fn num_of_voxels(&self) -> usize {
+    self.dimensions.iter().map(|d| *d as usize).product()
+  }
+
+  /// Check the loaded channels match the dimensions.
+  /// return: The result.
+  pub fn validate(&self) -> Result<()> {
+    if self.dimensions.contains(&0) {
+      return Err(anyhow::anyhow!("The volume dimensions {:?} contain zero.", self.dimensions));
+    }
+    let num_of_voxels = self.num_of_voxels();
+    for (channel, len) in AttributeChannel::ALL.iter().zip([self.colors.len(), self.tex_coords.len(), self.material_indices.len()]) {
+      if len != 0 && len != num_of_voxels {
+        return Err(anyhow::anyhow!(
+          "The {} channel has {} voxels, but the dimensions {:?} need {}.",
+          channel.name(),
+          len,
+          self.dimensions,
+          num_of_voxels,
+        ));
+      }
+    }
+    Ok(())
+  }
+
+  /// Save the channels as the KTX2 3D textures next to the path, see AttributeChannel::get_path.
+  /// param path: The file path of the attributes.
+  /// return: The result.
+  pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+    self.validate()?;
+    for channel in AttributeChannel::ALL.iter() {
+      let path = channel.get_path(path.as_ref());
+      let file = std::fs::File::create(&path)
+        .with_context(|| format!("Failed to create the attribute file: {:?}", path))?;
+      let mut writer = std::io::BufWriter::new(file);
+      write_attribute_ktx2(&mut writer, self, *channel)
+        .with_context(|| format!("Failed to write the attribute file: {:?}", path))?;
+      writer.flush()
+        .with_context(|| format!("Failed to flush the attribute file: {:?}", path))?;
+    }
+    Ok(())
+  }
+
+  /// Load the channels from the KTX2 3D textures next to the path, see AttributeChannel::get_path.
+  /// param path: The file path of the attributes.
+  /// return: The attribute volume.
+  pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+    let mut volume: Option<AttributeVolume> = None;
+    for channel in AttributeChannel::ALL.iter() {
+      let path = channel.get_path(path.as_ref());
+      let bytes = std::fs::read(&path)
+        .with_context(|| format!("Failed to read the attribute file: {:?}", path))?;
+      let (file_channel, loaded) = read_attribute_ktx2(&bytes)
+        .with_context(|| format!("Failed to parse the attribute file: {:?}", path))?;
+      if file_channel != *channel {
+        return Err(anyhow::anyhow!("The attribute file {:?} has the {} channel.", path, file_channel.name()));
+      }
+      match volume.as_mut() {
+        Some(volume) => {
+          if volume.dimensions != loaded.dimensions {
+            return Err(anyhow::anyhow!(
+              "The dimensions {:?} of the attribute file {:?} do not match {:?}.",
+              loaded.dimensions,
+              path,
+              volume.dimensions,
+            ));
+          }
+          match channel {
+            AttributeChannel::Color => volume.colors = loaded.colors,
+            AttributeChannel::TexCoord => volume.tex_coords = loaded.tex_coords,
+            AttributeChannel::Material => volume.material_indices = loaded.material_indices,
+          }
+        },
+        None => volume = Some(loaded),
+      }
+    }
+    volume.ok_or(anyhow::anyhow!("The attribute volume has no channel."))
+  }
+
+}
+
+/// Write a channel of the attribute volume as a KTX2 3D texture.
+/// The color is R8G8B8A8_SRGB, the texture coordinates are R32G32_SFLOAT and the material index is R32_UINT.
+/// The volume information and the channel name are stored in the key/value data.
+/// param writer: The writer.
+/// param volume: The attribute volume.
+/// param channel: The channel to write.
+/// return: The result.
+pub fn write_attribute_ktx2<W: Write>(writer: &mut W, volume: &AttributeVolume, channel: AttributeChannel) -> Result<()> {
+  volume.validate()?;
+  let (format, type_size, dfd, data) = match channel {
+    AttributeChannel::Color => (
+      VK_FORMAT_R8G8B8A8_SRGB,
+      1,
+      build_channels_dfd(1, 4, KHR_DF_TRANSFER_SRGB, 0, [0, 0xFF]),
+      volume.colors.iter()
+        .flat_map(|c| [linear_to_srgb(c[0]), linear_to_srgb(c[1]), linear_to_srgb(c[2]), c[3]])
+        .map(|v| (v.clamp(0.0, 1.0) * 255.0).round() as u8)
+        .collect::<Vec<_>>(),
+    ),
+    AttributeChannel::TexCoord => (
+      VK_FORMAT_R32G32_SFLOAT,
+      4,
+      build_channels_dfd(4, 2, KHR_DF_TRANSFER_LINEAR, KHR_DF_SAMPLE_DATATYPE_FLOAT | KHR_DF_SAMPLE_DATATYPE_SIGNED, [(-1.0f32).to_bits(), 1.0f32.to_bits()]),
+      volume.tex_coords.iter().flatten().flat_map(|v| v.to_le_bytes()).collect::<Vec<_>>(),
+    ),
+    AttributeChannel::Material => (
+      VK_FORMAT_R32_UINT,
+      4,
+      build_channels_dfd(4, 1, KHR_DF_TRANSFER_LINEAR, 0, [0, u32::MAX]),
+      volume.material_indices.iter().flat_map(|v| v.to_le_bytes()).collect::<Vec<_>>(),
+    ),
+  };
+  if data.is_empty() {
+    return Err(anyhow::anyhow!("The {} channel of the attribute volume is not loaded.", channel.name()));
+  }
+
+  let key_values = vec![
+    (format!("{}.attribute", KTX2_METADATA_PREFIX), channel.name().to_string()),
+    (format!("{}.center", KTX2_METADATA_PREFIX), format!("{} {} {}", volume.center[0], volume.center[1], volume.center[2])),
+    (format!("{}.extents", KTX2_METADATA_PREFIX), format!("{} {} {}", volume.extents[0], volume.extents[1], volume.extents[2])),
+    (format!("{}.voxelSize", KTX2_METADATA_PREFIX), format!("{}", volume.voxel_size)),
+  ];
+  write_ktx2_texture(writer, format, type_size, volume.dimensions, &dfd, key_values, &[data])
+}
+
+/// Read a channel of the attribute volume from the KTX2 3D texture written by write_attribute_ktx2.
+/// param bytes: The file content.
+/// return: The channel and the attribute volume with only that channel loaded.
+pub fn read_attribute_ktx2(bytes: &[u8]) -> Result<(AttributeChannel, AttributeVolume)> {
+  let texture = parse_ktx2_texture(bytes)?;
+  let (channel, texel_size) = match texture.format {
+    VK_FORMAT_R8G8B8A8_SRGB => (AttributeChannel::Color, 4),
+    VK_FORMAT_R32G32_SFLOAT => (AttributeChannel::TexCoord, 8),
+    VK_FORMAT_R32_UINT => (AttributeChannel::Material, 4),
+    format => return Err(anyhow::anyhow!("Unsupported KTX2 attribute format {}.", format)),
+  };
+  texture.check_texel_size(texel_size)?;
+
+  let (info, _) = from_metadata(&texture.key_values, texture.dimensions)?;
+  let mut volume = AttributeVolume {
+    dimensions: info.dimensions,
+    center: info.center,
+    extents: info.extents,
+    voxel_size: info.voxel_size,
+    colors: Vec::new(),
+    tex_coords: Vec::new(),
+    material_indices: Vec::new(),
+  };
+  let data = texture.levels[0];
+  match channel {
+    AttributeChannel::Color => volume.colors = data.chunks_exact(4)
+      .map(|c| {
+        let c = [c[0], c[1], c[2], c[3]].map(|v| v as f32 / 255.0);
+        [srgb_to_linear(c[0]), srgb_to_linear(c[1]), srgb_to_linear(c[2]), c[3]]
+      })
+      .collect(),
+    AttributeChannel::TexCoord => volume.tex_coords = data.chunks_exact(8)
+      .map(|b| [
+        f32::from_le_bytes([b[0], b[1], b[2], b[3]]),
+        f32::from_le_bytes([b[4], b[5], b[6], b[7]]),
+      ])
+      .collect(),
+    AttributeChannel::Material => volume.material_indices = data.chunks_exact(4)
+      .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+      .collect(),
+  }
+  Ok((channel, volume))
+}
+
+/// Decode the sRGB encoded value to linear.
+/// param value: The sRGB value in [0, 1].
+/// return: The linear value.
+pub fn srgb_to_linear(value: f32) -> f32 {
+  if value <= 0.04045 {
+    value / 12.92
+  } else {
+    ((value + 0.055) / 1.055).powf(2.4)
+  }
+}
+
+/// Encode the linear value to sRGB.
+/// param value: The linear value in [0, 1].
+/// return: The sRGB value.
+pub fn linear_to_srgb(value: f32) -> f32 {
+  if value <= 0.0031308 {
+    value * 12.92
+  } else {
+    1.055 * value.powf(1.0 / 2.4) - 0.055
+  }
+}
+
+/// Interpolate the attribute of the triangle vertices by the barycentrics.
+/// param values: The attribute of the vertices, empty if the primitive has none.
+/// param vertices: The vertex indices of the triangle.
+/// param weights: The barycentrics.
+/// param default: The value of the missing attribute.
+/// return: The interpolated value.
+fn interpolate<const N: usize>(values: &[[f32; N]], vertices: &[u32], weights: &[f32; 3], default: [f32; N]) -> Result<[f32; N]> {
+  if values.is_empty() {
+    return Ok(default);
+  }
+  let mut result = [0.0; N];
+  for (vertex, weight) in vertices.iter().zip(weights.iter()) {
+    let value = values.get(*vertex as usize)
+      .ok_or(anyhow::anyhow!("The vertex {} is out of the {} attributes.", vertex, values.len()))?;
+    for (r, v) in result.iter_mut().zip(value.iter()) {
+      *r += v * weight;
+    }
+  }
+  Ok(result)
+}
@@ -0,0 +1,170 @@
+use std::io::{Read, Write};
+
+use anyhow::{Result, Context};
+
+use super::{
+  SDFVolume,
+  ValueEncoding,
+  DistanceUnit,
+  mip_dimensions,
+  num_of_mip_levels,
+};
+
+/// The magic number of the binary format.
+pub const BINARY_MAGIC: [u8; 4] = *b"HSDF";
+
+/// The current version of the binary format.
+pub const BINARY_VERSION: u32 = 3;
+
+/// The flag bit for the signed distance field.
+const FLAG_SDF: u32 = 1;
+
+/// Write the volume in the little-endian binary format.
+/// The header layout is:
+///   magic: [u8; 4], version: u32, flags: u32, encoding: u32,
+///   dimensions: [u32; 3], center: [f32; 3], extents: [f32; 3],
+///   voxel_size: f32, surface_offset: f32, unit: u32 (since version 2), mip_levels: u32 (since version 3),
+/// followed by the voxel values of each mip level from the level 0 in the given encoding.
+/// The version 1 files have no unit and the values are normalized, the version 1 and 2 files have no mips.
+/// Only the f32 and f16 encodings are supported.
+/// param writer: The writer.
+/// param volume: The volume.
+/// param encoding: The value encoding.
+/// return: The result.
+pub fn write_binary<W: Write>(writer: &mut W, volume: &SDFVolume, encoding: ValueEncoding) -> Result<()> {
+  let encoding_id = encoding_to_u32(encoding)?;
+
+  let mut header = Vec::with_capacity(64);
+  header.extend_from_slice(&BINARY_MAGIC);
+  header.extend_from_slice(&BINARY_VERSION.to_le_bytes());
+  header.extend_from_slice(&(if volume.is_sdf { FLAG_SDF } else { 0 }).to_le_bytes());
+  header.extend_from_slice(&encoding_id.to_le_bytes());
+  for d in volume.dimensions.iter() {
+    header.extend_from_slice(&d.to_le_bytes());
+  }
+  for v in volume.center.iter().chain(volume.extents.iter()) {
+    header.extend_from_slice(&v.to_le_bytes());
+  }
+  header.extend_from_slice(&volume.voxel_size.to_le_bytes());
+  header.extend_from_slice(&volume.surface_offset.to_le_bytes());
+  header.extend_from_slice(&volume.unit.id().to_le_bytes());
+  header.extend_from_slice(&volume.num_of_mip_levels().to_le_bytes());
+  writer.write_all(&header)
+    .with_context(|| "Failed to write the binary header.")?;
+
+  for data in std::iter::once(&volume.data).chain(volume.mips.iter()) {
+    writer.write_all(&encoding.encode(data, [0.0, 1.0]))
+      .with_context(|| "Failed to write the binary data.")?;
+  }
+
+  Ok(())
+}
+
+/// Read the volume in the little-endian binary format.
+/// param reader: The reader.
+/// return: The volume.
+pub fn read_binary<R: Read>(reader: &mut R) -> Result<SDFVolume> {
+  let mut magic = [0u8; 4];
+  reader.read_exact(&mut magic)
+    .with_context(|| "Failed to read the binary magic.")?;
+  if magic != BINARY_MAGIC {
+    return Err(anyhow::anyhow!("Invalid binary magic {:?}.", magic));
+  }
+
+  let version = read_u32(reader)?;
+  if version == 0 || version > BINARY_VERSION {
+    return Err(anyhow::anyhow!("Unsupported binary version {}, the max supported version is {}.", version, BINARY_VERSION));
+  }
+  let flags = read_u32(reader)?;
+  let encoding = encoding_from_u32(read_u32(reader)?)?;
+  let dimensions = [read_u32(reader)?, read_u32(reader)?, read_u32(reader)?];
+  let center = [read_f32(reader)?, read_f32(reader)?, read_f32(reader)?];
+  let extents = [read_f32(reader)?, read_f32(reader)?, read_f32(reader)?];
+  let voxel_size = read_f32(reader)?;
+  let surface_offset = read_f32(reader)?;
+  let unit = if version >= 2 { DistanceUnit::from_id(read_u32(reader)?)? } else { DistanceUnit::Normalized };
+  let mip_levels = if version >= 3 { read_u32(reader)? } else { 1 };
+  if mip_levels == 0 || mip_levels > num_of_mip_levels(dimensions) {
+    return Err(anyhow::anyhow!("The {} mip levels are out of range [1, {}].", mip_levels, num_of_mip_levels(dimensions)));
+  }
+
+  let mut levels = Vec::with_capacity(mip_levels as usize);
+  for level in 0..mip_levels {
+    let num_of_voxels = checked_num_of_voxels(mip_dimensions(dimensions, level))?;
+    let bytes = read_values(reader, num_of_voxels, encoding.size())
+      .with_context(|| format!("Failed to read {} voxels of the mip level {} of the binary data.", num_of_voxels, level))?;
+    levels.push(encoding.decode(&bytes, [0.0, 1.0]));
+  }
+  let data = levels.remove(0);
+
+  Ok(SDFVolume {
+    dimensions,
+    center,
+    extents,
+    voxel_size,
+    is_sdf: flags & FLAG_SDF != 0,
+    surface_offset,
+    unit,
+    data,
+    mips: levels,
+    gradients: Vec::new(),
+  })
+}
+
+/// Get the number of voxels of the dimensions read from a file.
+/// param dimensions: The dimensions.
+/// return: The number of voxels, or an error if it overflows.
+pub(super) fn checked_num_of_voxels(dimensions: [u32; 3]) -> Result<usize> {
+  dimensions.iter()
+    .try_fold(1usize, |a, b| a.checked_mul(*b as usize))
+    .ok_or(anyhow::anyhow!("The number of voxels of the dimensions {:?} overflows.", dimensions))
+}
+
+/// Read the bytes of the values whose count comes from a file header.
+/// The bytes are read as they come instead of allocating the full size up front,
+/// so a corrupt count fails at the end of the input rather than by a huge allocation.
+/// param reader: The reader.
+/// param count: The number of values.
+/// param size: The size of a value in bytes.
+/// return: The bytes.
+pub(super) fn read_values<R: Read>(reader: &mut R, count: usize, size: usize) -> Result<Vec<u8>> {
+  let len = count.checked_mul(size)
+    .ok_or(anyhow::anyhow!("The size of {} values of {} bytes overflows.", count, size))?;
+  let mut bytes = Vec::new();
+  reader.by_ref().take(len as u64).read_to_end(&mut bytes)
+    .with_context(|| format!("Failed to read {} bytes.", len))?;
+  if bytes.len() != len {
+    return Err(anyhow::anyhow!("The data ends after {} of {} bytes.", bytes.len(), len));
+  }
+  Ok(bytes)
+}
+
+fn encoding_to_u32(encoding: ValueEncoding) -> Result<u32> {
+  match encoding {
+    ValueEncoding::F32 => Ok(0),
+    ValueEncoding::F16 => Ok(1),
+    _ => Err(anyhow::anyhow!("The binary format does not support the {} encoding.", encoding.name())),
+  }
+}
+
+fn encoding_from_u32(value: u32) -> Result<ValueEncoding> {
+  match value {
+    0 => Ok(ValueEncoding::F32),
+    1 => Ok(ValueEncoding::F16),
+    _ => Err(anyhow::anyhow!("Unknown value encoding {}.", value)),
+  }
+}
+
+fn read_u32<R: Read>(reader: &mut R) -> Result<u32> {
+  let mut bytes = [0u8; 4];
+  reader.read_exact(&mut bytes)
+    .with_context(|| "Failed to read the binary header.")?;
+  Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_f32<R: Read>(reader: &mut R) -> Result<f32> {
+  let mut bytes = [0u8; 4];
+  reader.read_exact(&mut bytes)
+    .with_context(|| "Failed to read the binary header.")?;
+  Ok(f32::from_le_bytes(bytes))
+}
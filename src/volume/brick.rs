@@ -0,0 +1,189 @@
+use anyhow::Result;
+
+/// A brick of the grid baked on its own.
+/// The core voxels are stitched into the volume, the halo around them is only baked
+/// to find the surfaces across the brick borders.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VolumeBrick {
+  pub core_min: [u32; 3],  // The first core voxel in the grid.
+  pub core_size: [u32; 3], // The number of the core voxels.
+  pub min: [u32; 3],       // The first voxel with the halo in the grid, the halo is clamped to the grid.
+  pub size: [u32; 3],      // The number of the voxels with the halo.
+}
+
+/// The implementation of the volume brick.
+impl VolumeBrick {
+
+  /// Get the number of the voxels with the halo.
+  /// return: The number of voxels.
+  pub fn num_of_voxels(&self) -> u64 {
+    self.size.iter().map(|s| *s as u64).product()
+  }
+
+  /// Copy the core voxels of the brick to the grid.
+  /// param brick_data: The voxels of the brick with the halo, X changing fastest.
+  /// param dimensions: The dimensions of the grid.
+  /// param data: The voxels of the grid, X changing fastest.
+  /// return: The result.
+  pub fn copy_core_to(&self, brick_data: &[f32], dimensions: [u32; 3], data: &mut [f32]) -> Result<()> {
+    if (brick_data.len() as u64) < self.num_of_voxels() {
+      return Err(anyhow::anyhow!("The brick has {} voxels, less than {:?}.", brick_data.len(), self.size));
+    }
+    let num_of_voxels = dimensions.iter().map(|d| *d as usize).product::<usize>();
+    if data.len() < num_of_voxels {
+      return Err(anyhow::anyhow!("The grid has {} voxels, less than {:?}.", data.len(), dimensions));
+    }
+    if (0..3).any(|i| self.core_min[i] < self.min[i] || self.core_min[i] + self.core_size[i] > (self.min[i] + self.size[i]).min(dimensions[i])) {
+      return Err(anyhow::anyhow!("The core of the brick {:?} is out of the brick or the grid {:?}.", self, dimensions));
+    }
+
+    let offset = [0, 1, 2].map(|i| (self.core_min[i] - self.min[i]) as usize);
+    let row_length = self.core_size[0] as usize;
+    for z in 0..self.core_size[2] as usize {
+      for y in 0..self.core_size[1] as usize {
+        let src = offset[0] + self.size[0] as usize * (offset[1] + y + self.size[1] as usize * (offset[2] + z));
+        let dst = self.core_min[0] as usize
+          + dimensions[0] as usize * (self.core_min[1] as usize + y + dimensions[1] as usize * (self.core_min[2] as usize + z));
+        data[dst..dst + row_length].copy_from_slice(&brick_data[src..src + row_length]);
+      }
+    }
+
+    Ok(())
+  }
+
+}
+
+/// Split the grid into the bricks of which the voxels with the halo are not more than the limit.
+/// The axis with the most core voxels is split first, so the bricks stay close to cubes.
+/// param dimensions: The dimensions of the grid.
+/// param halo: The halo in voxels around the core of each brick.
+/// param max_voxels: The max number of the voxels of a brick with the halo.
+/// return: The bricks with X changing fastest, only one if the whole grid fits.
+pub fn split_into_bricks(dimensions: [u32; 3], halo: u32, max_voxels: u64) -> Result<Vec<VolumeBrick>> {
+  if dimensions.contains(&0) {
+    return Err(anyhow::anyhow!("The dimensions {:?} are empty.", dimensions));
+  }
+
+  let mut counts = [1u32; 3];
+  let core_sizes = loop {
+    let core_sizes = [0, 1, 2].map(|i| dimensions[i].div_ceil(counts[i]));
+    let max_brick_voxels = (0..3)
+      .map(|i| (core_sizes[i] as u64 + 2 * halo as u64).min(dimensions[i] as u64))
+      .product::<u64>();
+    if max_brick_voxels <= max_voxels {
+      break core_sizes;
+    }
+
+    let axis = (0..3)
+      .filter(|i| core_sizes[*i] > 1)
+      .max_by_key(|i| core_sizes[*i])
+      .ok_or(anyhow::anyhow!(
+        "The halo of {} voxels needs {} voxels in a brick, more than the limit {}.",
+        halo,
+        max_brick_voxels,
+        max_voxels,
+      ))?;
+    counts[axis] += 1;
+  };
+
+  let counts = [0, 1, 2].map(|i| dimensions[i].div_ceil(core_sizes[i]));
+  let mut bricks = Vec::with_capacity(counts.iter().map(|c| *c as usize).product());
+  for k in 0..counts[2] {
+    for j in 0..counts[1] {
+      for i in 0..counts[0] {
+        let core_min = [i * core_sizes[0], j * core_sizes[1], k * core_sizes[2]];
+        let core_size = [0, 1, 2].map(|a| core_sizes[a].min(dimensions[a] - core_min[a]));
+        let min = [0, 1, 2].map(|a| core_min[a].saturating_sub(halo));
+        let max = [0, 1, 2].map(|a| (core_min[a] + core_size[a]).saturating_add(halo).min(dimensions[a]));
+        bricks.push(VolumeBrick {
+          core_min,
+          core_size,
+          min,
+          size: [0, 1, 2].map(|a| max[a] - min[a]),
+        });
+      }
+    }
+  }
+
+  Ok(bricks)
+}
+
+/// Bound the distances of the stitched bricks which may miss the closer surfaces out of their bricks.
+/// A voxel within the halo of the surfaces in its brick has the exact distance, since any closer surface is in the halo too.
+/// The others are at least the halo from any surface, and at least the exact distance at the nearest coarse sample
+/// minus the way to it, so they take the larger of both. This lower bound never overshoots the sphere tracing.
+/// The signs are kept.
+/// param dimensions: The dimensions of the grid.
+/// param data: The stitched distances with the surface offset, X changing fastest. The unknown ones are infinite with the sign.
+/// param is_sdf: Whether the distances are signed.
+/// param surface_offset: The surface offset of the distances.
+/// param halo: The halo in voxels around each brick.
+/// param voxel_size: The size of a voxel in the unit of the distances.
+/// param coarse_step: The voxels between the coarse samples, the bound is looser by up to half the diagonal of the step.
+/// param get_distance: Get the exact unsigned distance without the surface offset at the center of the voxel.
+/// return: The result.
+#[allow(clippy::too_many_arguments)]
+pub fn bound_far_distances<F>(
+  dimensions: [u32; 3],
+  data: &mut [f32],
+  is_sdf: bool,
+  surface_offset: f32,
+  halo: u32,
+  voxel_size: f32,
+  coarse_step: u32,
+  get_distance: F,
+) -> Result<()>
+where
+  F: Fn([u32; 3]) -> f32,
+{
+  if dimensions.contains(&0) {
+    return Err(anyhow::anyhow!("The dimensions {:?} are empty.", dimensions));
+  }
+  let num_of_voxels = dimensions.iter().map(|d| *d as usize).product::<usize>();
+  if data.len() < num_of_voxels {
+    return Err(anyhow::anyhow!("The grid has {} voxels, less than {:?}.", data.len(), dimensions));
+  }
+
+  // The coarse samples are at the centers of the cells of the step, clamped to the grid.
+  let step = coarse_step.max(1);
+  let coarse_dimensions = dimensions.map(|d| d.div_ceil(step));
+  let get_sample = |cell: [u32; 3]| [0, 1, 2].map(|i| (cell[i] * step + step / 2).min(dimensions[i] - 1));
+  let mut coarse_distances = Vec::with_capacity(coarse_dimensions.iter().map(|d| *d as usize).product());
+  for k in 0..coarse_dimensions[2] {
+    for j in 0..coarse_dimensions[1] {
+      for i in 0..coarse_dimensions[0] {
+        coarse_distances.push(get_distance(get_sample([i, j, k])));
+      }
+    }
+  }
+
+  let halo_distance = halo as f32 * voxel_size;
+  for z in 0..dimensions[2] {
+    for y in 0..dimensions[1] {
+      for x in 0..dimensions[0] {
+        let index = (x + dimensions[0] * (y + dimensions[1] * z)) as usize;
+        let (sign, distance) = if is_sdf {
+          let distance = data[index] + surface_offset;
+          (if distance < 0.0 { -1.0 } else { 1.0 }, distance.abs())
+        } else {
+          (1.0, data[index] - surface_offset)
+        };
+        if distance <= halo_distance {
+          continue;
+        }
+
+        let cell = [x / step, y / step, z / step];
+        let sample = get_sample(cell);
+        let way = [x, y, z].iter().zip(sample.iter())
+          .map(|(v, s)| (*v as f32 - *s as f32).powi(2))
+          .sum::<f32>()
+          .sqrt() * voxel_size;
+        let coarse_index = (cell[0] + coarse_dimensions[0] * (cell[1] + coarse_dimensions[1] * cell[2])) as usize;
+        let bound = halo_distance.max(coarse_distances[coarse_index] - way).min(distance);
+        data[index] = if is_sdf { sign * bound - surface_offset } else { bound + surface_offset };
+      }
+    }
+  }
+
+  Ok(())
+}
@@ -0,0 +1,253 @@
+use std::io::{Read, Write};
+
+use anyhow::{Result, Context};
+
+use super::binary::{
+  checked_num_of_voxels,
+  read_values,
+};
+
+/// The magic number of the closest triangles format.
+pub const CLOSEST_MAGIC: [u8; 4] = *b"HSCT";
+
+/// The current version of the closest triangles format.
+pub const CLOSEST_VERSION: u32 = 1;
+
+/// The triangle index of the voxels without the closest triangle.
+pub const NO_CLOSEST_TRIANGLE: u32 = u32::MAX;
+
+/// The triangles of a primitive in the baked geometry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TriangleRange {
+  pub primitive_index: u32, // The primitive index in the scene.
+  pub first_triangle: u32, // The first triangle of the primitive in the baked geometry.
+  pub num_of_triangles: u32,
+}
+
+/// The closest triangle of each voxel and the barycentrics of the closest point on it.
+/// The triangle indices are in the baked geometry, the ranges map them back to the primitives in the scene.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClosestTriangleVolume {
+  pub dimensions: [u32; 3],
+  pub center: [f32; 3],
+  pub extents: [f32; 3],
+  pub voxel_size: f32,
+  pub ranges: Vec<TriangleRange>, // The primitives one after another in the order of the baked geometry.
+  pub triangles: Vec<u32>, // The closest triangle of each voxel with X changing fastest, or NO_CLOSEST_TRIANGLE.
+  pub barycentrics: Vec<[f32; 3]>, // The weights of the vertices a, b and c of the closest triangle.
+}
+
+/// The implementation of the closest triangle volume.
+impl ClosestTriangleVolume {
+
+  /// Get the number of voxels.
+  /// return: The number of voxels.
+  pub fn num_of_voxels(&self) -> usize {
+    self.dimensions.iter().map(|d| *d as usize).product()
+  }
+
+  /// Get the number of triangles in the baked geometry.
+  /// return: The number of triangles.
+  pub fn num_of_triangles(&self) -> u32 {
+    self.ranges.last().map(|r| r.first_triangle + r.num_of_triangles).unwrap_or(0)
+  }
+
+  /// Get the index of the voxel.
+  /// param x: The x coordinate.
+  /// param y: The y coordinate.
+  /// param z: The z coordinate.
+  /// return: The index.
+  pub fn index(&self, x: u32, y: u32, z: u32) -> usize {
+    (x + self.dimensions[0] * (y + self.dimensions[1] * z)) as usize
+  }
+
+  /// Check the voxels match the dimensions and the triangles are in the ranges.
+  /// return: The result.
+  pub fn validate(&self) -> Result<()> {
+    if self.dimensions.contains(&0) {
+      return Err(anyhow::anyhow!("The volume dimensions {:?} contain zero.", self.dimensions));
+    }
+    let num_of_voxels = self.num_of_voxels();
+    if self.triangles.len() != num_of_voxels || self.barycentrics.len() != num_of_voxels {
+      return Err(anyhow::anyhow!(
+        "The closest triangles have {} triangles and {} barycentrics, but the dimensions {:?} need {}.",
+        self.triangles.len(),
+        self.barycentrics.len(),
+        self.dimensions,
+        num_of_voxels,
+      ));
+    }
+    let mut first_triangle = 0u32;
+    for range in self.ranges.iter() {
+      if range.first_triangle != first_triangle {
+        return Err(anyhow::anyhow!(
+          "The triangles of the primitive {} start at {}, but the previous primitives end at {}.",
+          range.primitive_index,
+          range.first_triangle,
+          first_triangle,
+        ));
+      }
+      first_triangle = first_triangle.checked_add(range.num_of_triangles)
+        .ok_or(anyhow::anyhow!("The triangles of the primitive {} are too many.", range.primitive_index))?;
+    }
+    if let Some(triangle) = self.triangles.iter().find(|t| **t != NO_CLOSEST_TRIANGLE && **t >= first_triangle) {
+      return Err(anyhow::anyhow!("The closest triangle {} is out of the {} triangles.", triangle, first_triangle));
+    }
+    Ok(())
+  }
+
+  /// Find the primitive of the triangle in the baked geometry.
+  /// param triangle: The triangle index in the baked geometry.
+  /// return: The primitive index in the scene and the triangle index in the primitive, None if it is out of the ranges.
+  pub fn locate(&self, triangle: u32) -> Option<(u32, u32)> {
+    let index = self.ranges.partition_point(|r| r.first_triangle + r.num_of_triangles <= triangle);
+    self.ranges.get(index)
+      .filter(|r| triangle >= r.first_triangle)
+      .map(|r| (r.primitive_index, triangle - r.first_triangle))
+  }
+
+  /// Save the closest triangles to the file in the closest triangles format.
+  /// param path: The file path.
+  /// return: The result.
+  pub fn save<P: AsRef<std::path::Path>>(&self, path: P) -> Result<()> {
+    let path = path.as_ref();
+    let file = std::fs::File::create(path)
+      .with_context(|| format!("Failed to create the closest triangles file: {:?}", path))?;
+    let mut writer = std::io::BufWriter::new(file);
+    write_closest_triangles(&mut writer, self)
+      .with_context(|| format!("Failed to write the closest triangles file: {:?}", path))?;
+    writer.flush()
+      .with_context(|| format!("Failed to flush the closest triangles file: {:?}", path))?;
+    Ok(())
+  }
+
+  /// Load the closest triangles from the file in the closest triangles format.
+  /// param path: The file path.
+  /// return: The closest triangle volume.
+  pub fn load<P: AsRef<std::path::Path>>(path: P) -> Result<Self> {
+    let path = path.as_ref();
+    let bytes = std::fs::read(path)
+      .with_context(|| format!("Failed to read the closest triangles file: {:?}", path))?;
+    read_closest_triangles(&mut bytes.as_slice())
+      .with_context(|| format!("Failed to parse the closest triangles file: {:?}", path))
+  }
+
+}
+
+/// Write the closest triangles in the little-endian closest triangles format.
+/// The header layout is:
+///   magic: [u8; 4], version: u32,
+///   dimensions: [u32; 3], center: [f32; 3], extents: [f32; 3], voxel_size: f32,
+///   num_of_ranges: u32,
+/// followed by the ranges as [u32; 3] of the primitive index, the first triangle and the number of triangles,
+/// and the voxels as the triangle index u32 and the barycentrics [f32; 3].
+/// param writer: The writer.
+/// param volume: The closest triangle volume.
+/// return: The result.
+pub fn write_closest_triangles<W: Write>(writer: &mut W, volume: &ClosestTriangleVolume) -> Result<()> {
+  volume.validate()?;
+
+  let mut header = Vec::with_capacity(52);
+  header.extend_from_slice(&CLOSEST_MAGIC);
+  header.extend_from_slice(&CLOSEST_VERSION.to_le_bytes());
+  for d in volume.dimensions.iter() {
+    header.extend_from_slice(&d.to_le_bytes());
+  }
+  for v in volume.center.iter().chain(volume.extents.iter()) {
+    header.extend_from_slice(&v.to_le_bytes());
+  }
+  header.extend_from_slice(&volume.voxel_size.to_le_bytes());
+  header.extend_from_slice(&(volume.ranges.len() as u32).to_le_bytes());
+  writer.write_all(&header)
+    .with_context(|| "Failed to write the closest triangles header.")?;
+
+  let mut ranges = Vec::with_capacity(volume.ranges.len() * 12);
+  for range in volume.ranges.iter() {
+    ranges.extend_from_slice(&range.primitive_index.to_le_bytes());
+    ranges.extend_from_slice(&range.first_triangle.to_le_bytes());
+    ranges.extend_from_slice(&range.num_of_triangles.to_le_bytes());
+  }
+  writer.write_all(&ranges)
+    .with_context(|| "Failed to write the closest triangles ranges.")?;
+
+  let mut voxels = Vec::with_capacity(volume.num_of_voxels() * 16);
+  for (triangle, barycentrics) in volume.triangles.iter().zip(volume.barycentrics.iter()) {
+    voxels.extend_from_slice(&triangle.to_le_bytes());
+    for w in barycentrics.iter() {
+      voxels.extend_from_slice(&w.to_le_bytes());
+    }
+  }
+  writer.write_all(&voxels)
+    .with_context(|| "Failed to write the closest triangles voxels.")?;
+
+  Ok(())
+}
+
+/// Read the closest triangles in the little-endian closest triangles format.
+/// param reader: The reader.
+/// return: The closest triangle volume.
+pub fn read_closest_triangles<R: Read>(reader: &mut R) -> Result<ClosestTriangleVolume> {
+  let mut magic = [0u8; 4];
+  reader.read_exact(&mut magic)
+    .with_context(|| "Failed to read the closest triangles magic.")?;
+  if magic != CLOSEST_MAGIC {
+    return Err(anyhow::anyhow!("Invalid closest triangles magic {:?}.", magic));
+  }
+
+  let version = read_u32(reader)?;
+  if version == 0 || version > CLOSEST_VERSION {
+    return Err(anyhow::anyhow!("Unsupported closest triangles version {}, the max supported version is {}.", version, CLOSEST_VERSION));
+  }
+  let dimensions = [read_u32(reader)?, read_u32(reader)?, read_u32(reader)?];
+  let center = [read_f32(reader)?, read_f32(reader)?, read_f32(reader)?];
+  let extents = [read_f32(reader)?, read_f32(reader)?, read_f32(reader)?];
+  let voxel_size = read_f32(reader)?;
+  let num_of_ranges = read_u32(reader)? as usize;
+
+  // The counts come from the header, so the sizes are checked and the bytes are read as they come.
+  let bytes = read_values(reader, num_of_ranges, 12)
+    .with_context(|| format!("Failed to read {} closest triangles ranges.", num_of_ranges))?;
+  let ranges = bytes.chunks_exact(12)
+    .map(|b| TriangleRange {
+      primitive_index: u32::from_le_bytes([b[0], b[1], b[2], b[3]]),
+      first_triangle: u32::from_le_bytes([b[4], b[5], b[6], b[7]]),
+      num_of_triangles: u32::from_le_bytes([b[8], b[9], b[10], b[11]]),
+    })
+    .collect();
+
+  let num_of_voxels = checked_num_of_voxels(dimensions)?;
+  let bytes = read_values(reader, num_of_voxels, 16)
+    .with_context(|| format!("Failed to read {} closest triangles voxels.", num_of_voxels))?;
+  let mut triangles = Vec::with_capacity(num_of_voxels);
+  let mut barycentrics = Vec::with_capacity(num_of_voxels);
+  for b in bytes.chunks_exact(16) {
+    triangles.push(u32::from_le_bytes([b[0], b[1], b[2], b[3]]));
+    barycentrics.push([1, 2, 3].map(|i| f32::from_le_bytes([b[i * 4], b[i * 4 + 1], b[i * 4 + 2], b[i * 4 + 3]])));
+  }
+
+  let volume = ClosestTriangleVolume {
+    dimensions,
+    center,
+    extents,
+    voxel_size,
+    ranges,
+    triangles,
+    barycentrics,
+  };
+  volume.validate()?;
+  Ok(volume)
+}
+
+fn read_u32<R: Read>(reader: &mut R) -> Result<u32> {
+  let mut bytes = [0u8; 4];
+  reader.read_exact(&mut bytes)
+    .with_context(|| "Failed to read the closest triangles header.")?;
+  Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_f32<R: Read>(reader: &mut R) -> Result<f32> {
+  let mut bytes = [0u8; 4];
+  reader.read_exact(&mut bytes)
+    .with_context(|| "Failed to read the closest triangles header.")?;
+  Ok(f32::from_le_bytes(bytes))
+}
@@ -0,0 +1,222 @@
+use std::io::Write;
+
+use anyhow::{Result, Context};
+
+use super::{
+  SDFVolume,
+  ValueEncoding,
+  DistanceUnit,
+  mip_dimensions,
+  num_of_mip_levels,
+};
+
+/// The magic number of DDS.
+pub const DDS_MAGIC: [u8; 4] = *b"DDS ";
+
+/// The tag of the SDF metadata stored in the reserved fields of the DDS header.
+/// The layout of dwReserved1 is:
+///   [0]: "HSDF", [1]: flags (bit 0 is set for SDF, bits 8..16 are the distance unit id), [2]/[3]: remap min/max,
+///   [4..7]: center, [7..10]: extents, [10]: voxel size.
+/// The surface offset is stored in the dwReserved2 field.
+pub const DDS_METADATA_TAG: [u8; 4] = *b"HSDF";
+
+const DDSD_CAPS: u32 = 0x1;
+const DDSD_HEIGHT: u32 = 0x2;
+const DDSD_WIDTH: u32 = 0x4;
+const DDSD_PITCH: u32 = 0x8;
+const DDSD_PIXELFORMAT: u32 = 0x1000;
+const DDSD_MIPMAPCOUNT: u32 = 0x20000;
+const DDSD_DEPTH: u32 = 0x800000;
+const DDPF_FOURCC: u32 = 0x4;
+const DDSCAPS_TEXTURE: u32 = 0x1000;
+const DDSCAPS_COMPLEX: u32 = 0x8;
+const DDSCAPS_MIPMAP: u32 = 0x400000;
+const DDSCAPS2_VOLUME: u32 = 0x200000;
+const D3D10_RESOURCE_DIMENSION_TEXTURE3D: u32 = 4;
+
+const DXGI_FORMAT_R32_FLOAT: u32 = 41;
+const DXGI_FORMAT_R16_FLOAT: u32 = 54;
+const DXGI_FORMAT_R16_UNORM: u32 = 56;
+const DXGI_FORMAT_R8_UNORM: u32 = 61;
+const DXGI_FORMAT_R32G32B32A32_FLOAT: u32 = 2;
+const DXGI_FORMAT_R16G16B16A16_FLOAT: u32 = 10;
+const DXGI_FORMAT_R16G16B16A16_UNORM: u32 = 11;
+const DXGI_FORMAT_R8G8B8A8_UNORM: u32 = 28;
+
+const DDS_HEADER_SIZE: usize = 4 + 124 + 20;
+
+/// Write the volume as a single channel DDS 3D texture with the DX10 header, with the mip levels of the volume.
+/// The volume with the gradients is written as RGBA, the gradient in RGB and the distance in A.
+/// DDS has no key/value data, so the volume information and the remap range are stored in the reserved fields.
+/// param writer: The writer.
+/// param volume: The volume.
+/// param encoding: The value encoding.
+/// param remap_range: The [min, max] range of the normalized encodings.
+/// return: The result.
+pub fn write_dds<W: Write>(writer: &mut W, volume: &SDFVolume, encoding: ValueEncoding, remap_range: [f32; 2]) -> Result<()> {
+  let has_gradients = !volume.gradients.is_empty();
+  let gradient_levels = volume.gradient_levels();
+  let data = std::iter::once(&volume.data).chain(volume.mips.iter())
+    .enumerate()
+    .flat_map(|(level, data)| match gradient_levels.get(level) {
+      Some(gradients) => encoding.encode_rgba(gradients, data, remap_range),
+      None => encoding.encode(data, remap_range),
+    })
+    .collect::<Vec<_>>();
+  let mip_levels = volume.num_of_mip_levels();
+  let pitch = volume.dimensions[0] * (encoding.size() * if has_gradients { 4 } else { 1 }) as u32;
+
+  let mut reserved1 = [0u32; 11];
+  reserved1[0] = u32::from_le_bytes(DDS_METADATA_TAG);
+  reserved1[1] = (if volume.is_sdf { 1 } else { 0 }) | (volume.unit.id() << 8);
+  reserved1[2] = remap_range[0].to_bits();
+  reserved1[3] = remap_range[1].to_bits();
+  for i in 0..3 {
+    reserved1[4 + i] = volume.center[i].to_bits();
+    reserved1[7 + i] = volume.extents[i].to_bits();
+  }
+  reserved1[10] = volume.voxel_size.to_bits();
+
+  let mut words = vec![
+    124, // dwSize.
+    DDSD_CAPS | DDSD_HEIGHT | DDSD_WIDTH | DDSD_PITCH | DDSD_PIXELFORMAT | DDSD_DEPTH | (if mip_levels > 1 { DDSD_MIPMAPCOUNT } else { 0 }),
+    volume.dimensions[1],
+    volume.dimensions[0],
+    pitch,
+    volume.dimensions[2],
+    mip_levels, // dwMipMapCount.
+  ];
+  words.extend_from_slice(&reserved1);
+  words.extend_from_slice(&[
+    32, // ddspf.dwSize.
+    DDPF_FOURCC,
+    u32::from_le_bytes(*b"DX10"),
+    0,
+    0,
+    0,
+    0,
+    0,
+    DDSCAPS_TEXTURE | DDSCAPS_COMPLEX | (if mip_levels > 1 { DDSCAPS_MIPMAP } else { 0 }),
+    DDSCAPS2_VOLUME,
+    0,
+    0,
+    volume.surface_offset.to_bits(), // dwReserved2.
+    // DDS_HEADER_DXT10.
+    dxgi_format(encoding, has_gradients),
+    D3D10_RESOURCE_DIMENSION_TEXTURE3D,
+    0,
+    1,
+    0,
+  ]);
+
+  let mut bytes = Vec::with_capacity(DDS_HEADER_SIZE + data.len());
+  bytes.extend_from_slice(&DDS_MAGIC);
+  for w in words.iter() {
+    bytes.extend_from_slice(&w.to_le_bytes());
+  }
+  bytes.extend_from_slice(&data);
+
+  writer.write_all(&bytes)
+    .with_context(|| "Failed to write the DDS data.")?;
+
+  Ok(())
+}
+
+/// Read the single channel or RGBA DDS 3D texture written by write_dds.
+/// param bytes: The file content.
+/// return: The volume.
+pub fn read_dds(bytes: &[u8]) -> Result<SDFVolume> {
+  if bytes.len() < DDS_HEADER_SIZE || !bytes.starts_with(&DDS_MAGIC) {
+    return Err(anyhow::anyhow!("Invalid DDS header."));
+  }
+  let read_u32 = |offset: usize| u32::from_le_bytes([bytes[offset], bytes[offset + 1], bytes[offset + 2], bytes[offset + 3]]);
+  let read_f32 = |offset: usize| f32::from_bits(read_u32(offset));
+
+  if read_u32(84) != u32::from_le_bytes(*b"DX10") {
+    return Err(anyhow::anyhow!("Only the DDS with the DX10 header is supported."));
+  }
+  let (encoding, has_gradients) = match read_u32(128) {
+    DXGI_FORMAT_R32_FLOAT => (ValueEncoding::F32, false),
+    DXGI_FORMAT_R16_FLOAT => (ValueEncoding::F16, false),
+    DXGI_FORMAT_R16_UNORM => (ValueEncoding::Unorm16, false),
+    DXGI_FORMAT_R8_UNORM => (ValueEncoding::Unorm8, false),
+    DXGI_FORMAT_R32G32B32A32_FLOAT => (ValueEncoding::F32, true),
+    DXGI_FORMAT_R16G16B16A16_FLOAT => (ValueEncoding::F16, true),
+    DXGI_FORMAT_R16G16B16A16_UNORM => (ValueEncoding::Unorm16, true),
+    DXGI_FORMAT_R8G8B8A8_UNORM => (ValueEncoding::Unorm8, true),
+    format => return Err(anyhow::anyhow!("Unsupported DXGI format {}.", format)),
+  };
+  let texel_size = encoding.size() * if has_gradients { 4 } else { 1 };
+  let dimensions = [read_u32(16), read_u32(12), read_u32(24).max(1)];
+  let mip_levels = read_u32(28).max(1);
+  if mip_levels > num_of_mip_levels(dimensions) {
+    return Err(anyhow::anyhow!("The DDS mip map count {} is out of range [1, {}].", mip_levels, num_of_mip_levels(dimensions)));
+  }
+  let mut offset = DDS_HEADER_SIZE;
+  let mut levels = Vec::with_capacity(mip_levels as usize);
+  for level in 0..mip_levels {
+    let num_of_voxels = mip_dimensions(dimensions, level).iter().map(|d| *d as usize).product::<usize>();
+    let data_length = num_of_voxels * texel_size;
+    levels.push(bytes.get(offset..offset + data_length)
+      .ok_or(anyhow::anyhow!("The DDS data is truncated."))?);
+    offset += data_length;
+  }
+
+  // The reserved fields start at offset 32.
+  let has_metadata = read_u32(32) == u32::from_le_bytes(DDS_METADATA_TAG);
+  let remap_range = if has_metadata { [read_f32(40), read_f32(44)] } else { [0.0, 1.0] };
+  // The gradients of the mip levels are derived from their values, only the values are kept.
+  let decode = |level: &[u8]| if has_gradients {
+    encoding.decode_rgba(level, remap_range)
+  } else {
+    (Vec::new(), encoding.decode(level, remap_range))
+  };
+  let (gradients, data) = decode(levels[0]);
+  let mips = levels[1..].iter().map(|level| decode(level).1).collect();
+  let volume = if has_metadata {
+    SDFVolume {
+      dimensions,
+      center: [read_f32(48), read_f32(52), read_f32(56)],
+      extents: [read_f32(60), read_f32(64), read_f32(68)],
+      voxel_size: read_f32(72),
+      is_sdf: read_u32(36) & 1 != 0,
+      surface_offset: read_f32(124),
+      unit: DistanceUnit::from_id((read_u32(36) >> 8) & 0xff)?,
+      data,
+      mips,
+      gradients,
+    }
+  } else {
+    SDFVolume {
+      dimensions,
+      center: [0.0; 3],
+      extents: [0.0; 3],
+      voxel_size: 0.0,
+      is_sdf: true,
+      surface_offset: 0.0,
+      unit: DistanceUnit::Normalized,
+      data,
+      mips,
+      gradients,
+    }
+  };
+
+  Ok(volume)
+}
+
+/// Get the DXGI format of the encoding.
+/// param encoding: The value encoding.
+/// param has_gradients: Whether the texels are RGBA with the gradients.
+/// return: The DXGI format.
+fn dxgi_format(encoding: ValueEncoding, has_gradients: bool) -> u32 {
+  match (encoding, has_gradients) {
+    (ValueEncoding::F32, false) => DXGI_FORMAT_R32_FLOAT,
+    (ValueEncoding::F16, false) => DXGI_FORMAT_R16_FLOAT,
+    (ValueEncoding::Unorm8, false) => DXGI_FORMAT_R8_UNORM,
+    (ValueEncoding::Unorm16, false) => DXGI_FORMAT_R16_UNORM,
+    (ValueEncoding::F32, true) => DXGI_FORMAT_R32G32B32A32_FLOAT,
+    (ValueEncoding::F16, true) => DXGI_FORMAT_R16G16B16A16_FLOAT,
+    (ValueEncoding::Unorm8, true) => DXGI_FORMAT_R8G8B8A8_UNORM,
+    (ValueEncoding::Unorm16, true) => DXGI_FORMAT_R16G16B16A16_UNORM,
+  }
+}
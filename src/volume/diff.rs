@@ -0,0 +1,133 @@
+use anyhow::Result;
+
+use super::SDFVolume;
+
+/// The number of bins in the error histogram of each slice.
+pub const NUM_OF_HISTOGRAM_BINS: usize = 8;
+
+/// The relative tolerance when comparing the bounds of two volumes.
+const BOUNDS_TOLERANCE: f32 = 1e-4;
+
+/// The errors of one Z slice.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SliceDiff {
+  pub rmse: f32,
+  pub max_error: f32,
+  pub num_of_sign_flips: usize,
+  // The number of voxels in each equal width bin of [0, max error of the volume].
+  pub histogram: [usize; NUM_OF_HISTOGRAM_BINS],
+}
+
+/// The difference between two volumes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VolumeDiff {
+  pub rmse: f32,
+  pub max_error: f32,
+  pub max_error_location: [u32; 3],
+  pub sign_flips: Vec<[u32; 3]>, // The voxels of which the sign differs, only for the SDF.
+  pub slices: Vec<SliceDiff>,
+  pub difference: SDFVolume, // The first volume minus the second one.
+}
+
+/// Check whether the two values are equal within the relative tolerance.
+/// param a: The first value.
+/// param b: The second value.
+/// return: True if equal.
+fn is_nearly_equal(a: f32, b: f32) -> bool {
+  (a - b).abs() <= BOUNDS_TOLERANCE * a.abs().max(b.abs()).max(1.0)
+}
+
+/// Compare the two volumes voxel by voxel.
+/// The dimensions must be the same. The bounds are checked if both volumes have them, the text volume has none.
+/// The second volume is converted to the distance unit of the first one.
+/// param a: The first volume.
+/// param b: The second volume.
+/// return: The difference.
+pub fn diff_volumes(a: &SDFVolume, b: &SDFVolume) -> Result<VolumeDiff> {
+  a.validate()?;
+  b.validate()?;
+  if a.dimensions != b.dimensions {
+    return Err(anyhow::anyhow!("The dimensions {:?} and {:?} do not match.", a.dimensions, b.dimensions));
+  }
+  let has_bounds = a.voxel_size > 0.0 && b.voxel_size > 0.0;
+  if has_bounds {
+    let is_same_bounds = a.center.iter().zip(b.center.iter()).all(|(x, y)| is_nearly_equal(*x, *y))
+      && a.extents.iter().zip(b.extents.iter()).all(|(x, y)| is_nearly_equal(*x, *y));
+    if !is_same_bounds {
+      return Err(anyhow::anyhow!(
+        "The bounds (center {:?}, extents {:?}) and (center {:?}, extents {:?}) do not match.",
+        a.center, a.extents, b.center, b.extents,
+      ));
+    }
+  }
+  let mut b = b.clone();
+  if a.unit != b.unit {
+    if !has_bounds {
+      return Err(anyhow::anyhow!(
+        "The {} and {} units can not be compared without the bounds.", a.unit.name(), b.unit.name()
+      ));
+    }
+    b.convert_unit(a.unit)?;
+  }
+
+  let data = a.data.iter().zip(b.data.iter()).map(|(x, y)| x - y).collect::<Vec<_>>();
+  let difference = SDFVolume {
+    is_sdf: true,
+    surface_offset: 0.0,
+    data,
+    mips: Vec::new(),
+    gradients: Vec::new(),
+    ..a.clone()
+  };
+
+  let [dim_x, dim_y, dim_z] = a.dimensions;
+  let slice_size = (dim_x * dim_y) as usize;
+  let max_error = difference.data.iter().fold(0.0f32, |m, v| m.max(v.abs()));
+  let max_error_index = difference.data.iter().position(|v| v.abs() == max_error).unwrap_or(0);
+  let sum_of_squares = difference.data.iter().map(|v| (*v as f64) * (*v as f64)).sum::<f64>();
+
+  let mut sign_flips = Vec::new();
+  let mut slices = Vec::with_capacity(dim_z as usize);
+  for z in 0..dim_z {
+    let mut slice = SliceDiff {
+      rmse: 0.0,
+      max_error: 0.0,
+      num_of_sign_flips: 0,
+      histogram: [0; NUM_OF_HISTOGRAM_BINS],
+    };
+    let mut slice_sum_of_squares = 0.0f64;
+    for y in 0..dim_y {
+      for x in 0..dim_x {
+        let index = difference.index(x, y, z);
+        let error = difference.data[index].abs();
+        slice_sum_of_squares += (error as f64) * (error as f64);
+        slice.max_error = slice.max_error.max(error);
+        let bin = if max_error > 0.0 {
+          ((error / max_error * NUM_OF_HISTOGRAM_BINS as f32) as usize).min(NUM_OF_HISTOGRAM_BINS - 1)
+        } else {
+          0
+        };
+        slice.histogram[bin] += 1;
+        if a.is_sdf && b.is_sdf && (a.data[index] < 0.0) != (b.data[index] < 0.0) {
+          slice.num_of_sign_flips += 1;
+          sign_flips.push([x, y, z]);
+        }
+      }
+    }
+    slice.rmse = (slice_sum_of_squares / slice_size as f64).sqrt() as f32;
+    slices.push(slice);
+  }
+
+  Ok(VolumeDiff {
+    rmse: (sum_of_squares / difference.data.len() as f64).sqrt() as f32,
+    max_error,
+    max_error_location: [
+      (max_error_index % dim_x as usize) as u32,
+      (max_error_index / dim_x as usize % dim_y as usize) as u32,
+      (max_error_index / slice_size) as u32,
+    ],
+    sign_flips,
+    slices,
+    difference,
+  })
+}
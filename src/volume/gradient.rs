@@ -0,0 +1,44 @@
+/// Compute the normalized gradients of the voxels by the central differences.
+/// The voxels on the borders use the one-sided differences, the flat voxels get the zero gradient.
+/// The voxels are cubes, so the gradient directions do not depend on the voxel size.
+/// param dimensions: The dimensions of the voxels.
+/// param data: The voxel values, X changing fastest.
+/// return: The gradients of the voxels.
+pub fn central_difference_gradients(dimensions: [u32; 3], data: &[f32]) -> Vec<[f32; 3]> {
+  let dims = dimensions.map(|d| d as usize);
+  let strides = [1, dims[0], dims[0] * dims[1]];
+
+  let mut gradients = Vec::with_capacity(data.len());
+  for z in 0..dims[2] {
+    for y in 0..dims[1] {
+      for x in 0..dims[0] {
+        let coord = [x, y, z];
+        let index = x + strides[1] * y + strides[2] * z;
+        let mut gradient = [0.0f32; 3];
+        for axis in 0..3 {
+          let prev = coord[axis].saturating_sub(1);
+          let next = (coord[axis] + 1).min(dims[axis] - 1);
+          if next > prev {
+            let a = data[index - (coord[axis] - prev) * strides[axis]];
+            let b = data[index + (next - coord[axis]) * strides[axis]];
+            gradient[axis] = (b - a) / (next - prev) as f32;
+          }
+        }
+        gradients.push(normalize_gradient(gradient));
+      }
+    }
+  }
+  gradients
+}
+
+/// Normalize the gradient, or get zero if it has no direction.
+/// param gradient: The gradient.
+/// return: The unit gradient or zero.
+pub fn normalize_gradient(gradient: [f32; 3]) -> [f32; 3] {
+  let length = (gradient[0] * gradient[0] + gradient[1] * gradient[1] + gradient[2] * gradient[2]).sqrt();
+  if length > f32::EPSILON && length.is_finite() {
+    gradient.map(|g| g / length)
+  } else {
+    [0.0; 3]
+  }
+}
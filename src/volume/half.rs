@@ -0,0 +1,79 @@
+/// Convert a f32 to the bits of a IEEE 754 half float, rounding to nearest even.
+/// param value: The f32 value.
+/// return: The half float bits.
+pub fn f32_to_f16_bits(value: f32) -> u16 {
+  let bits = value.to_bits();
+  let sign = ((bits >> 16) & 0x8000) as u16;
+  let exponent = ((bits >> 23) & 0xFF) as i32;
+  let mantissa = bits & 0x007F_FFFF;
+
+  // NaN and infinity.
+  if exponent == 0xFF {
+    return if mantissa != 0 {
+      sign | 0x7E00
+    } else {
+      sign | 0x7C00
+    };
+  }
+
+  let half_exponent = exponent - 127 + 15;
+  if half_exponent >= 0x1F {
+    // Overflow to infinity.
+    return sign | 0x7C00;
+  }
+
+  if half_exponent <= 0 {
+    // Subnormal or zero.
+    if half_exponent < -10 {
+      return sign;
+    }
+    let mantissa = mantissa | 0x0080_0000;
+    let shift = (14 - half_exponent) as u32;
+    let half_mantissa = mantissa >> shift;
+    let round_bit = 1u32 << (shift - 1);
+    let remainder = mantissa & ((round_bit << 1) - 1);
+    let mut result = half_mantissa;
+    if remainder > round_bit || (remainder == round_bit && (half_mantissa & 1) != 0) {
+      result += 1;
+    }
+    return sign | result as u16;
+  }
+
+  let half_mantissa = mantissa >> 13;
+  let remainder = mantissa & 0x1FFF;
+  let mut result = ((half_exponent as u32) << 10) | half_mantissa;
+  if remainder > 0x1000 || (remainder == 0x1000 && (half_mantissa & 1) != 0) {
+    // The carry may propagate into the exponent, which is still correct.
+    result += 1;
+  }
+  sign | result as u16
+}
+
+/// Convert the bits of a IEEE 754 half float to a f32.
+/// param bits: The half float bits.
+/// return: The f32 value.
+pub fn f16_bits_to_f32(bits: u16) -> f32 {
+  let sign = ((bits & 0x8000) as u32) << 16;
+  let exponent = ((bits >> 10) & 0x1F) as u32;
+  let mantissa = (bits & 0x03FF) as u32;
+
+  let result = if exponent == 0 {
+    if mantissa == 0 {
+      sign
+    } else {
+      // Normalize the subnormal value.
+      let mut exponent = 127 - 15 + 1;
+      let mut mantissa = mantissa;
+      while mantissa & 0x0400 == 0 {
+        mantissa <<= 1;
+        exponent -= 1;
+      }
+      sign | (exponent << 23) | ((mantissa & 0x03FF) << 13)
+    }
+  } else if exponent == 0x1F {
+    sign | 0x7F80_0000 | (mantissa << 13)
+  } else {
+    sign | ((exponent + 127 - 15) << 23) | (mantissa << 13)
+  };
+  f32::from_bits(result)
+}
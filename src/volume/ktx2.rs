@@ -0,0 +1,416 @@
+use std::io::Write;
+
+use anyhow::{Result, Context};
+
+use super::{
+  SDFVolume,
+  ValueEncoding,
+  DistanceUnit,
+  mip_dimensions,
+  num_of_mip_levels,
+};
+
+/// The file identifier of KTX2.
+pub const KTX2_IDENTIFIER: [u8; 12] = [0xAB, 0x4B, 0x54, 0x58, 0x20, 0x32, 0x30, 0xBB, 0x0D, 0x0A, 0x1A, 0x0A];
+
+/// The key prefix of the SDF metadata in the key/value data.
+pub const KTX2_METADATA_PREFIX: &str = "HalaSDF";
+
+const VK_FORMAT_R8_UNORM: u32 = 9;
+const VK_FORMAT_R16_UNORM: u32 = 70;
+const VK_FORMAT_R16_SFLOAT: u32 = 76;
+const VK_FORMAT_R32_SFLOAT: u32 = 100;
+const VK_FORMAT_R8G8B8A8_UNORM: u32 = 37;
+const VK_FORMAT_R16G16B16A16_UNORM: u32 = 91;
+const VK_FORMAT_R16G16B16A16_SFLOAT: u32 = 97;
+const VK_FORMAT_R32G32B32A32_SFLOAT: u32 = 109;
+
+pub(super) const KHR_DF_TRANSFER_LINEAR: u32 = 1;
+pub(super) const KHR_DF_TRANSFER_SRGB: u32 = 2;
+pub(super) const KHR_DF_SAMPLE_DATATYPE_LINEAR: u32 = 0x10;
+pub(super) const KHR_DF_SAMPLE_DATATYPE_SIGNED: u32 = 0x40;
+pub(super) const KHR_DF_SAMPLE_DATATYPE_FLOAT: u32 = 0x80;
+
+const KTX2_HEADER_SIZE: usize = 80;
+const KTX2_LEVEL_INDEX_SIZE: usize = 24;
+
+/// Write the volume as a single channel KTX2 3D texture, with a level for each mip level of the volume.
+/// The volume with the gradients is written as RGBA, the gradient in RGB and the distance in A.
+/// The volume information and the remap range are stored in the key/value data,
+/// a normalized value n is decoded by remap_min + n * (remap_max - remap_min),
+/// and a normalized gradient component n by n * 2 - 1.
+/// param writer: The writer.
+/// param volume: The volume.
+/// param encoding: The value encoding.
+/// param remap_range: The [min, max] range of the normalized encodings.
+/// return: The result.
+pub fn write_ktx2<W: Write>(writer: &mut W, volume: &SDFVolume, encoding: ValueEncoding, remap_range: [f32; 2]) -> Result<()> {
+  let type_size = match encoding {
+    ValueEncoding::Unorm8 => 1u32,
+    ValueEncoding::F16 | ValueEncoding::Unorm16 => 2,
+    ValueEncoding::F32 => 4,
+  };
+  let has_gradients = !volume.gradients.is_empty();
+  let gradient_levels = volume.gradient_levels();
+  let levels = std::iter::once(&volume.data).chain(volume.mips.iter())
+    .enumerate()
+    .map(|(level, data)| match gradient_levels.get(level) {
+      Some(gradients) => encoding.encode_rgba(gradients, data, remap_range),
+      None => encoding.encode(data, remap_range),
+    })
+    .collect::<Vec<_>>();
+
+  // Data format descriptor.
+  let dfd = build_dfd(encoding, type_size, if has_gradients { 4 } else { 1 });
+
+  write_ktx2_texture(
+    writer,
+    vk_format(encoding, has_gradients),
+    type_size,
+    volume.dimensions,
+    &dfd,
+    get_metadata(volume, encoding, remap_range),
+    &levels,
+  )
+}
+
+/// Write the KTX2 3D texture of the levels, the level 0 is the largest one.
+/// param writer: The writer.
+/// param format: The VkFormat.
+/// param type_size: The size of the channel type in bytes.
+/// param dimensions: The dimensions of the level 0.
+/// param dfd: The data format descriptor.
+/// param key_values: The key/value pairs, the writer is added.
+/// param levels: The texels of the levels.
+/// return: The result.
+pub(super) fn write_ktx2_texture<W: Write>(
+  writer: &mut W,
+  format: u32,
+  type_size: u32,
+  dimensions: [u32; 3],
+  dfd: &[u8],
+  mut key_values: Vec<(String, String)>,
+  levels: &[Vec<u8>],
+) -> Result<()> {
+  // Key/value data, the keys must be sorted.
+  key_values.push(("KTXwriter".to_string(), format!("hala-sdf-baker {}", env!("CARGO_PKG_VERSION"))));
+  key_values.sort_by(|a, b| a.0.as_bytes().cmp(b.0.as_bytes()));
+  let mut kvd = Vec::new();
+  for (key, value) in key_values.iter() {
+    let length = key.len() + 1 + value.len() + 1;
+    kvd.extend_from_slice(&(length as u32).to_le_bytes());
+    kvd.extend_from_slice(key.as_bytes());
+    kvd.push(0);
+    kvd.extend_from_slice(value.as_bytes());
+    kvd.push(0);
+    kvd.resize((kvd.len() + 3) & !3, 0);
+  }
+
+  // Layout.
+  let dfd_offset = KTX2_HEADER_SIZE + KTX2_LEVEL_INDEX_SIZE * levels.len();
+  let kvd_offset = dfd_offset + dfd.len();
+  // The levels are stored from the smallest one, each aligned to lcm(type size, 4).
+  let mut level_offsets = vec![0; levels.len()];
+  let mut offset = kvd_offset + kvd.len();
+  for (level, data) in levels.iter().enumerate().rev() {
+    offset = (offset + 3) & !3;
+    level_offsets[level] = offset;
+    offset += data.len();
+  }
+
+  let mut bytes = Vec::with_capacity(offset);
+  bytes.extend_from_slice(&KTX2_IDENTIFIER);
+  for v in [
+    format,
+    type_size,
+    dimensions[0],
+    dimensions[1],
+    dimensions[2],
+    0, // layerCount.
+    1, // faceCount.
+    levels.len() as u32, // levelCount.
+    0, // supercompressionScheme.
+    dfd_offset as u32,
+    dfd.len() as u32,
+    kvd_offset as u32,
+    kvd.len() as u32,
+  ] {
+    bytes.extend_from_slice(&v.to_le_bytes());
+  }
+  bytes.extend_from_slice(&0u64.to_le_bytes()); // sgdByteOffset.
+  bytes.extend_from_slice(&0u64.to_le_bytes()); // sgdByteLength.
+  for (data, level_offset) in levels.iter().zip(level_offsets.iter()) {
+    bytes.extend_from_slice(&(*level_offset as u64).to_le_bytes());
+    bytes.extend_from_slice(&(data.len() as u64).to_le_bytes());
+    bytes.extend_from_slice(&(data.len() as u64).to_le_bytes());
+  }
+  bytes.extend_from_slice(dfd);
+  bytes.extend_from_slice(&kvd);
+  for (data, level_offset) in levels.iter().zip(level_offsets.iter()).rev() {
+    bytes.resize(*level_offset, 0);
+    bytes.extend_from_slice(data);
+  }
+
+  writer.write_all(&bytes)
+    .with_context(|| "Failed to write the KTX2 data.")?;
+
+  Ok(())
+}
+
+/// Read the single channel or RGBA KTX2 3D texture written by write_ktx2.
+/// param bytes: The file content.
+/// return: The volume.
+pub fn read_ktx2(bytes: &[u8]) -> Result<SDFVolume> {
+  let texture = parse_ktx2_texture(bytes)?;
+  let (encoding, has_gradients) = match texture.format {
+    VK_FORMAT_R8_UNORM => (ValueEncoding::Unorm8, false),
+    VK_FORMAT_R16_UNORM => (ValueEncoding::Unorm16, false),
+    VK_FORMAT_R16_SFLOAT => (ValueEncoding::F16, false),
+    VK_FORMAT_R32_SFLOAT => (ValueEncoding::F32, false),
+    VK_FORMAT_R8G8B8A8_UNORM => (ValueEncoding::Unorm8, true),
+    VK_FORMAT_R16G16B16A16_UNORM => (ValueEncoding::Unorm16, true),
+    VK_FORMAT_R16G16B16A16_SFLOAT => (ValueEncoding::F16, true),
+    VK_FORMAT_R32G32B32A32_SFLOAT => (ValueEncoding::F32, true),
+    format => return Err(anyhow::anyhow!("Unsupported KTX2 format {}.", format)),
+  };
+  texture.check_texel_size(encoding.size() * if has_gradients { 4 } else { 1 })?;
+
+  let (mut volume, remap_range) = from_metadata(&texture.key_values, texture.dimensions)?;
+  if has_gradients {
+    (volume.gradients, volume.data) = encoding.decode_rgba(texture.levels[0], remap_range);
+  } else {
+    volume.data = encoding.decode(texture.levels[0], remap_range);
+  }
+  // The gradients of the mip levels are derived from their values, only the values are kept.
+  volume.mips = texture.levels[1..].iter()
+    .map(|level| if has_gradients {
+      encoding.decode_rgba(level, remap_range).1
+    } else {
+      encoding.decode(level, remap_range)
+    })
+    .collect();
+  Ok(volume)
+}
+
+/// The uncompressed KTX2 3D texture parsed from the file content.
+pub(super) struct Ktx2Texture<'a> {
+  pub format: u32, // The VkFormat.
+  pub dimensions: [u32; 3],
+  pub key_values: Vec<(String, String)>,
+  pub levels: Vec<&'a [u8]>, // The texels of the levels, the level 0 is the largest one.
+}
+
+/// The implementation of the KTX2 texture.
+impl Ktx2Texture<'_> {
+
+  /// Check each level has the texels of its dimensions.
+  /// param texel_size: The size of a texel in bytes.
+  /// return: The result.
+  pub fn check_texel_size(&self, texel_size: usize) -> Result<()> {
+    for (level, data) in self.levels.iter().enumerate() {
+      let num_of_voxels = mip_dimensions(self.dimensions, level as u32).iter().map(|d| *d as usize).product::<usize>();
+      if data.len() != num_of_voxels * texel_size {
+        return Err(anyhow::anyhow!("The KTX2 level {} has {} bytes, but {} voxels are expected.", level, data.len(), num_of_voxels));
+      }
+    }
+    Ok(())
+  }
+
+}
+
+/// Parse the header, the key/value data and the levels of the uncompressed KTX2 3D texture.
+/// param bytes: The file content.
+/// return: The texture.
+pub(super) fn parse_ktx2_texture(bytes: &[u8]) -> Result<Ktx2Texture<'_>> {
+  if bytes.len() < KTX2_HEADER_SIZE + KTX2_LEVEL_INDEX_SIZE || !bytes.starts_with(&KTX2_IDENTIFIER) {
+    return Err(anyhow::anyhow!("Invalid KTX2 header."));
+  }
+  let read_u32 = |offset: usize| u32::from_le_bytes([bytes[offset], bytes[offset + 1], bytes[offset + 2], bytes[offset + 3]]);
+  let read_u64 = |offset: usize| read_u32(offset) as u64 | ((read_u32(offset + 4) as u64) << 32);
+
+  let dimensions = [read_u32(20), read_u32(24), read_u32(28).max(1)];
+  if read_u32(44) != 0 {
+    return Err(anyhow::anyhow!("The supercompressed KTX2 is not supported."));
+  }
+  let level_count = read_u32(40).max(1);
+  if level_count > num_of_mip_levels(dimensions) || bytes.len() < KTX2_HEADER_SIZE + KTX2_LEVEL_INDEX_SIZE * level_count as usize {
+    return Err(anyhow::anyhow!("The KTX2 level count {} is out of range [1, {}].", level_count, num_of_mip_levels(dimensions)));
+  }
+  let kvd_offset = read_u32(56) as usize;
+  let kvd_length = read_u32(60) as usize;
+  let level_ranges = (0..level_count as usize)
+    .map(|level| {
+      let level_offset = read_u64(KTX2_HEADER_SIZE + KTX2_LEVEL_INDEX_SIZE * level) as usize;
+      let level_length = read_u64(KTX2_HEADER_SIZE + KTX2_LEVEL_INDEX_SIZE * level + 8) as usize;
+      level_offset..level_offset + level_length
+    })
+    .collect::<Vec<_>>();
+  if kvd_offset + kvd_length > bytes.len() || level_ranges.iter().any(|r| r.end > bytes.len()) {
+    return Err(anyhow::anyhow!("The KTX2 data is truncated."));
+  }
+
+  // Parse the key/value data.
+  let mut key_values = Vec::new();
+  let mut offset = kvd_offset;
+  while offset + 4 <= kvd_offset + kvd_length {
+    let length = read_u32(offset) as usize;
+    let entry = bytes.get(offset + 4..offset + 4 + length)
+      .ok_or(anyhow::anyhow!("The KTX2 key/value data is truncated."))?;
+    let mut parts = entry.splitn(2, |b| *b == 0);
+    let key = String::from_utf8_lossy(parts.next().unwrap_or_default()).to_string();
+    let value = String::from_utf8_lossy(parts.next().unwrap_or_default()).trim_end_matches('\0').to_string();
+    key_values.push((key, value));
+    offset += 4 + length;
+    offset += (4 - offset % 4) % 4;
+  }
+
+  Ok(Ktx2Texture {
+    format: read_u32(12),
+    dimensions,
+    key_values,
+    levels: level_ranges.into_iter().map(|range| &bytes[range]).collect(),
+  })
+}
+
+/// Get the SDF metadata as key/value pairs.
+/// param volume: The volume.
+/// param encoding: The value encoding.
+/// param remap_range: The remap range.
+/// return: The key/value pairs.
+pub(super) fn get_metadata(volume: &SDFVolume, encoding: ValueEncoding, remap_range: [f32; 2]) -> Vec<(String, String)> {
+  let mut key_values = vec![
+    (format!("{}.isSDF", KTX2_METADATA_PREFIX), (if volume.is_sdf { "1" } else { "0" }).to_string()),
+    (format!("{}.center", KTX2_METADATA_PREFIX), format!("{} {} {}", volume.center[0], volume.center[1], volume.center[2])),
+    (format!("{}.extents", KTX2_METADATA_PREFIX), format!("{} {} {}", volume.extents[0], volume.extents[1], volume.extents[2])),
+    (format!("{}.voxelSize", KTX2_METADATA_PREFIX), format!("{}", volume.voxel_size)),
+    (format!("{}.surfaceOffset", KTX2_METADATA_PREFIX), format!("{}", volume.surface_offset)),
+    (format!("{}.unit", KTX2_METADATA_PREFIX), volume.unit.name().to_string()),
+  ];
+  if encoding.is_normalized() {
+    key_values.push((format!("{}.remapMin", KTX2_METADATA_PREFIX), format!("{}", remap_range[0])));
+    key_values.push((format!("{}.remapMax", KTX2_METADATA_PREFIX), format!("{}", remap_range[1])));
+  }
+  key_values
+}
+
+/// Create an empty volume from the SDF metadata.
+/// param key_values: The key/value pairs.
+/// param dimensions: The dimensions.
+/// return: The volume without data and the remap range.
+pub(super) fn from_metadata(key_values: &[(String, String)], dimensions: [u32; 3]) -> Result<(SDFVolume, [f32; 2])> {
+  let get = |name: &str| {
+    let key = format!("{}.{}", KTX2_METADATA_PREFIX, name);
+    key_values.iter().find(|(k, _)| *k == key).map(|(_, v)| v.as_str())
+  };
+  let parse_floats = |name: &str| -> Result<Vec<f32>> {
+    match get(name) {
+      Some(value) => value.split_whitespace()
+        .map(|v| v.parse::<f32>().with_context(|| format!("Invalid metadata {} \"{}\".", name, value)))
+        .collect(),
+      None => Ok(Vec::new()),
+    }
+  };
+  let parse_vec3 = |name: &str| -> Result<[f32; 3]> {
+    let values = parse_floats(name)?;
+    Ok(if values.len() == 3 { [values[0], values[1], values[2]] } else { [0.0; 3] })
+  };
+  let parse_f32 = |name: &str, default: f32| -> Result<f32> {
+    Ok(parse_floats(name)?.first().copied().unwrap_or(default))
+  };
+
+  let volume = SDFVolume {
+    dimensions,
+    center: parse_vec3("center")?,
+    extents: parse_vec3("extents")?,
+    voxel_size: parse_f32("voxelSize", 0.0)?,
+    is_sdf: get("isSDF") != Some("0"),
+    surface_offset: parse_f32("surfaceOffset", 0.0)?,
+    unit: match get("unit") {
+      Some(unit) => unit.parse::<DistanceUnit>()?,
+      None => DistanceUnit::Normalized,
+    },
+    data: Vec::new(),
+    mips: Vec::new(),
+    gradients: Vec::new(),
+  };
+  let remap_range = [parse_f32("remapMin", 0.0)?, parse_f32("remapMax", 1.0)?];
+  Ok((volume, remap_range))
+}
+
+/// Get the Vulkan format of the encoding.
+/// param encoding: The value encoding.
+/// param has_gradients: Whether the texels are RGBA with the gradients.
+/// return: The VkFormat.
+fn vk_format(encoding: ValueEncoding, has_gradients: bool) -> u32 {
+  match (encoding, has_gradients) {
+    (ValueEncoding::F32, false) => VK_FORMAT_R32_SFLOAT,
+    (ValueEncoding::F16, false) => VK_FORMAT_R16_SFLOAT,
+    (ValueEncoding::Unorm8, false) => VK_FORMAT_R8_UNORM,
+    (ValueEncoding::Unorm16, false) => VK_FORMAT_R16_UNORM,
+    (ValueEncoding::F32, true) => VK_FORMAT_R32G32B32A32_SFLOAT,
+    (ValueEncoding::F16, true) => VK_FORMAT_R16G16B16A16_SFLOAT,
+    (ValueEncoding::Unorm8, true) => VK_FORMAT_R8G8B8A8_UNORM,
+    (ValueEncoding::Unorm16, true) => VK_FORMAT_R16G16B16A16_UNORM,
+  }
+}
+
+/// Build the basic data format descriptor of the red channel, or the RGBA channels.
+/// param encoding: The value encoding.
+/// param type_size: The size of the value in bytes.
+/// param num_of_channels: The number of the channels, 1 or 4.
+/// return: The data format descriptor.
+fn build_dfd(encoding: ValueEncoding, type_size: u32, num_of_channels: u32) -> Vec<u8> {
+  match encoding {
+    ValueEncoding::F32 | ValueEncoding::F16 => build_channels_dfd(
+      type_size,
+      num_of_channels,
+      KHR_DF_TRANSFER_LINEAR,
+      KHR_DF_SAMPLE_DATATYPE_FLOAT | KHR_DF_SAMPLE_DATATYPE_SIGNED,
+      [(-1.0f32).to_bits(), 1.0f32.to_bits()],
+    ),
+    ValueEncoding::Unorm8 => build_channels_dfd(type_size, num_of_channels, KHR_DF_TRANSFER_LINEAR, 0, [0, 0xFF]),
+    ValueEncoding::Unorm16 => build_channels_dfd(type_size, num_of_channels, KHR_DF_TRANSFER_LINEAR, 0, [0, 0xFFFF]),
+  }
+}
+
+/// Build the basic data format descriptor of the first channels of RGBA, all of the same type.
+/// The alpha channel of the sRGB transfer is linear.
+/// param type_size: The size of the channel type in bytes.
+/// param num_of_channels: The number of the channels, 1 to 4.
+/// param transfer: The transfer function, KHR_DF_TRANSFER_LINEAR or KHR_DF_TRANSFER_SRGB.
+/// param channel_type: The sample datatype qualifiers of the channels.
+/// param sample_range: The lower and upper values of the samples.
+/// return: The data format descriptor.
+pub(super) fn build_channels_dfd(type_size: u32, num_of_channels: u32, transfer: u32, channel_type: u32, sample_range: [u32; 2]) -> Vec<u8> {
+  const KHR_DF_MODEL_RGBSDA: u32 = 1;
+  const KHR_DF_PRIMARIES_BT709: u32 = 1;
+  const KHR_DF_CHANNEL_IDS: [u32; 4] = [0, 1, 2, 15]; // Red, green, blue and alpha.
+
+  let block_size = 24u32 + 16 * num_of_channels;
+  let bit_length = type_size * 8 - 1;
+
+  let mut words = vec![
+    4 + block_size, // dfdTotalSize.
+    0, // vendorId and descriptorType.
+    2 | (block_size << 16), // versionNumber and descriptorBlockSize.
+    KHR_DF_MODEL_RGBSDA | (KHR_DF_PRIMARIES_BT709 << 8) | (transfer << 16),
+    0, // texelBlockDimension.
+    type_size * num_of_channels, // bytesPlane0.
+    0,
+  ];
+  for (channel, channel_id) in KHR_DF_CHANNEL_IDS.iter().enumerate().take(num_of_channels as usize) {
+    let bit_offset = channel as u32 * type_size * 8;
+    let channel_type = if transfer == KHR_DF_TRANSFER_SRGB && *channel_id == 15 {
+      channel_type | KHR_DF_SAMPLE_DATATYPE_LINEAR
+    } else {
+      channel_type
+    };
+    words.extend_from_slice(&[
+      bit_offset | (bit_length << 16) | ((channel_type | channel_id) << 24), // bitOffset, bitLength, channelType and channel id.
+      0, // samplePosition.
+      sample_range[0],
+      sample_range[1],
+    ]);
+  }
+  words.iter().flat_map(|w| w.to_le_bytes()).collect()
+}
@@ -0,0 +1,77 @@
+use std::path::Path;
+
+use anyhow::{Result, Context};
+use serde::{Serialize, Deserialize};
+
+use super::{
+  OutputFormat,
+  ValueEncoding,
+  DistanceUnit,
+};
+
+/// The baked item in the manifest.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BakeManifestItem {
+  pub name: String,
+  pub file: String,
+  pub mesh_index: usize,
+  pub primitive_index: Option<usize>, // None if the primitives of the mesh or the scene are merged.
+  pub center: [f32; 3],
+  pub extents: [f32; 3],
+  pub dimensions: [u32; 3],
+  pub voxel_size: f32,
+}
+
+/// The manifest of the files baked in one pass.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BakeManifest {
+  pub is_sdf: bool,
+  pub output_format: OutputFormat,
+  pub value_encoding: ValueEncoding,
+  pub distance_unit: DistanceUnit,
+  pub items: Vec<BakeManifestItem>,
+}
+
+/// The implementation of the bake manifest.
+impl BakeManifest {
+
+  /// Save the manifest to the YAML file.
+  /// param path: The file path.
+  /// return: The result.
+  pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+    let path = path.as_ref();
+    let file = std::fs::File::create(path)
+      .with_context(|| format!("Failed to create the manifest file: {:?}", path))?;
+    serde_yaml::to_writer(file, self)
+      .with_context(|| format!("Failed to write the manifest file: {:?}", path))?;
+    Ok(())
+  }
+
+  /// Load the manifest from the YAML file.
+  /// param path: The file path.
+  /// return: The manifest.
+  pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+    let path = path.as_ref();
+    let file = std::fs::File::open(path)
+      .with_context(|| format!("Failed to open the manifest file: {:?}", path))?;
+    let manifest = serde_yaml::from_reader(file)
+      .with_context(|| format!("Failed to parse the manifest file: {:?}", path))?;
+    Ok(manifest)
+  }
+
+}
+
+/// Make the name safe to be used as a file name.
+/// The characters other than ASCII letters, digits, '-' and '_' are replaced by '_'.
+/// param name: The name.
+/// return: The file name.
+pub fn sanitize_file_name(name: &str) -> String {
+  let file_name = name.chars()
+    .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+    .collect::<String>();
+  if file_name.is_empty() {
+    "unnamed".to_string()
+  } else {
+    file_name
+  }
+}
@@ -0,0 +1,94 @@
+use anyhow::Result;
+
+/// Get the dimensions of the mip level, halved and rounded down at each level like the GPU textures.
+/// param dimensions: The dimensions of the level 0.
+/// param level: The mip level.
+/// return: The dimensions of the level.
+pub fn mip_dimensions(dimensions: [u32; 3], level: u32) -> [u32; 3] {
+  dimensions.map(|d| d.checked_shr(level).unwrap_or(0).max(1))
+}
+
+/// Get the number of the levels of the full mip chain down to a single voxel, with the level 0.
+/// param dimensions: The dimensions of the level 0.
+/// return: The number of levels.
+pub fn num_of_mip_levels(dimensions: [u32; 3]) -> u32 {
+  u32::BITS - dimensions.iter().copied().max().unwrap_or(0).leading_zeros()
+}
+
+/// Build the mip chain with the conservative min distance downsampling.
+/// Each voxel of a level takes the min of the voxels of the finer level its cell overlaps,
+/// and the level 1 also subtracts the margin from the voxel centers of the level 0 to their cells.
+/// So every mip voxel is a lower bound of the distance anywhere in its cell, and a sphere tracer
+/// never steps over the surface by the coarse levels.
+/// param dimensions: The dimensions of the level 0.
+/// param data: The voxels of the level 0, X changing fastest.
+/// param margin: The half diagonal of a voxel of the level 0 in the distance unit of the data.
+/// return: The voxels of the levels from 1 down to a single voxel.
+pub fn build_mip_chain(dimensions: [u32; 3], data: &[f32], margin: f32) -> Result<Vec<Vec<f32>>> {
+  if dimensions.contains(&0) {
+    return Err(anyhow::anyhow!("The dimensions {:?} are empty.", dimensions));
+  }
+  if data.len() != dimensions.iter().map(|d| *d as usize).product::<usize>() {
+    return Err(anyhow::anyhow!(
+      "The mip level 0 has {} voxels, but the dimensions {:?} need {}.",
+      data.len(),
+      dimensions,
+      dimensions.iter().map(|d| *d as usize).product::<usize>(),
+    ));
+  }
+
+  let mut mips: Vec<Vec<f32>> = Vec::new();
+  let mut source_dimensions = dimensions;
+  for level in 1..num_of_mip_levels(dimensions) {
+    let target_dimensions = mip_dimensions(dimensions, level);
+    let source = mips.last().map(|m| m.as_slice()).unwrap_or(data);
+    let mut mip = downsample_min(source_dimensions, source, target_dimensions);
+    if level == 1 {
+      for v in mip.iter_mut() {
+        *v -= margin;
+      }
+    }
+    mips.push(mip);
+    source_dimensions = target_dimensions;
+  }
+
+  Ok(mips)
+}
+
+/// Downsample the voxels by the min of the source voxels overlapped by each target voxel.
+/// The min is separable, so the axes are reduced one by one.
+/// param source_dimensions: The dimensions of the source voxels.
+/// param source: The source voxels, X changing fastest.
+/// param target_dimensions: The dimensions of the target voxels.
+/// return: The target voxels, X changing fastest.
+fn downsample_min(source_dimensions: [u32; 3], source: &[f32], target_dimensions: [u32; 3]) -> Vec<f32> {
+  let mut dimensions = source_dimensions.map(|d| d as usize);
+  let mut data = source.to_vec();
+  for axis in 0..3 {
+    let source_size = dimensions[axis];
+    let target_size = target_dimensions[axis] as usize;
+    let stride = dimensions[..axis].iter().product::<usize>();
+    let mut reduced_dimensions = dimensions;
+    reduced_dimensions[axis] = target_size;
+    let outer = reduced_dimensions[axis + 1..].iter().product::<usize>();
+
+    let mut reduced = Vec::with_capacity(reduced_dimensions.iter().product());
+    for o in 0..outer {
+      for t in 0..target_size {
+        // The source voxels whose cells overlap the cell [t, t + 1) / target_size.
+        let begin = t * source_size / target_size;
+        let end = ((t + 1) * source_size).div_ceil(target_size);
+        for i in 0..stride {
+          let value = (begin..end)
+            .map(|s| data[i + stride * (s + source_size * o)])
+            .fold(f32::INFINITY, f32::min);
+          reduced.push(value);
+        }
+      }
+    }
+
+    data = reduced;
+    dimensions = reduced_dimensions;
+  }
+  data
+}
@@ -0,0 +1,548 @@
+use std::path::Path;
+
+use anyhow::{Result, Context};
+use serde::{Serialize, Deserialize};
+
+mod half;
+mod text;
+mod binary;
+mod ktx2;
+mod dds;
+mod nanovdb;
+mod manifest;
+mod diff;
+mod brick;
+mod sparse;
+mod mip;
+mod gradient;
+mod closest;
+mod attribute;
+
+pub use half::*;
+pub use text::*;
+pub use binary::*;
+pub use ktx2::*;
+pub use dds::*;
+pub use nanovdb::*;
+pub use manifest::*;
+pub use diff::*;
+pub use brick::*;
+pub use sparse::*;
+pub use mip::*;
+pub use gradient::*;
+pub use closest::*;
+pub use attribute::*;
+
+/// The output file format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OutputFormat {
+  Text,
+  Binary,
+  Ktx2,
+  Dds,
+  NanoVdb,
+  Sparse,
+}
+
+/// The implementation of the output file format.
+impl OutputFormat {
+  pub const ALL: [OutputFormat; 6] = [
+    OutputFormat::Text,
+    OutputFormat::Binary,
+    OutputFormat::Ktx2,
+    OutputFormat::Dds,
+    OutputFormat::NanoVdb,
+    OutputFormat::Sparse,
+  ];
+
+  /// Get the name of the format.
+  /// return: The name.
+  pub fn name(&self) -> &'static str {
+    match self {
+      OutputFormat::Text => "text",
+      OutputFormat::Binary => "binary",
+      OutputFormat::Ktx2 => "ktx2",
+      OutputFormat::Dds => "dds",
+      OutputFormat::NanoVdb => "nanovdb",
+      OutputFormat::Sparse => "sparse",
+    }
+  }
+
+  /// Get the file extension of the format.
+  /// return: The extension without the dot.
+  pub fn extension(&self) -> &'static str {
+    match self {
+      OutputFormat::Text => "txt",
+      OutputFormat::Binary => "bin",
+      OutputFormat::Ktx2 => "ktx2",
+      OutputFormat::Dds => "dds",
+      OutputFormat::NanoVdb => "nvdb",
+      OutputFormat::Sparse => "hsb",
+    }
+  }
+}
+
+impl std::str::FromStr for OutputFormat {
+  type Err = anyhow::Error;
+
+  fn from_str(s: &str) -> Result<Self> {
+    OutputFormat::ALL.iter()
+      .find(|format| format.name().eq_ignore_ascii_case(s))
+      .copied()
+      .ok_or(anyhow::anyhow!("Unknown output format \"{}\".", s))
+  }
+}
+
+/// The unit of the distance values.
+/// The baker computes the distances normalized by the longest side of the bounds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DistanceUnit {
+  Normalized,
+  Voxel,
+  World,
+}
+
+/// The implementation of the distance unit.
+impl DistanceUnit {
+  pub const ALL: [DistanceUnit; 3] = [
+    DistanceUnit::Normalized,
+    DistanceUnit::Voxel,
+    DistanceUnit::World,
+  ];
+
+  /// Get the name of the unit.
+  /// return: The name.
+  pub fn name(&self) -> &'static str {
+    match self {
+      DistanceUnit::Normalized => "normalized",
+      DistanceUnit::Voxel => "voxel",
+      DistanceUnit::World => "world",
+    }
+  }
+
+  /// Get the id of the unit stored in the binary headers.
+  /// return: The id.
+  pub fn id(&self) -> u32 {
+    match self {
+      DistanceUnit::Normalized => 0,
+      DistanceUnit::Voxel => 1,
+      DistanceUnit::World => 2,
+    }
+  }
+
+  /// Get the unit from the id stored in the binary headers.
+  /// param id: The id.
+  /// return: The unit.
+  pub fn from_id(id: u32) -> Result<Self> {
+    DistanceUnit::ALL.iter()
+      .find(|unit| unit.id() == id)
+      .copied()
+      .ok_or(anyhow::anyhow!("Unknown distance unit {}.", id))
+  }
+}
+
+impl std::str::FromStr for DistanceUnit {
+  type Err = anyhow::Error;
+
+  fn from_str(s: &str) -> Result<Self> {
+    DistanceUnit::ALL.iter()
+      .find(|unit| unit.name().eq_ignore_ascii_case(s))
+      .copied()
+      .ok_or(anyhow::anyhow!("Unknown distance unit \"{}\".", s))
+  }
+}
+
+/// The encoding of the voxel values.
+/// The normalized encodings remap the values from the remap range to [0, 1].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ValueEncoding {
+  F32,
+  F16,
+  Unorm8,
+  Unorm16,
+}
+
+/// The implementation of the value encoding.
+impl ValueEncoding {
+  pub const ALL: [ValueEncoding; 4] = [
+    ValueEncoding::F32,
+    ValueEncoding::F16,
+    ValueEncoding::Unorm8,
+    ValueEncoding::Unorm16,
+  ];
+
+  /// Get the name of the encoding.
+  /// return: The name.
+  pub fn name(&self) -> &'static str {
+    match self {
+      ValueEncoding::F32 => "f32",
+      ValueEncoding::F16 => "f16",
+      ValueEncoding::Unorm8 => "unorm8",
+      ValueEncoding::Unorm16 => "unorm16",
+    }
+  }
+
+  /// Get the size of one value in bytes.
+  /// return: The size.
+  pub fn size(&self) -> usize {
+    match self {
+      ValueEncoding::F32 => 4,
+      ValueEncoding::F16 => 2,
+      ValueEncoding::Unorm8 => 1,
+      ValueEncoding::Unorm16 => 2,
+    }
+  }
+
+  /// Whether the encoding is normalized.
+  /// return: True if the values are remapped to [0, 1].
+  pub fn is_normalized(&self) -> bool {
+    matches!(self, ValueEncoding::Unorm8 | ValueEncoding::Unorm16)
+  }
+
+  /// Encode the values to little-endian bytes.
+  /// param data: The values.
+  /// param remap_range: The [min, max] range mapped to [0, 1] by the normalized encodings.
+  /// return: The bytes.
+  pub fn encode(&self, data: &[f32], remap_range: [f32; 2]) -> Vec<u8> {
+    let scale = if remap_range[1] > remap_range[0] { 1.0 / (remap_range[1] - remap_range[0]) } else { 0.0 };
+    let normalize = |v: f32| ((v - remap_range[0]) * scale).clamp(0.0, 1.0);
+
+    let mut bytes = Vec::with_capacity(data.len() * self.size());
+    match self {
+      ValueEncoding::F32 => {
+        for v in data.iter() {
+          bytes.extend_from_slice(&v.to_le_bytes());
+        }
+      },
+      ValueEncoding::F16 => {
+        for v in data.iter() {
+          bytes.extend_from_slice(&f32_to_f16_bits(*v).to_le_bytes());
+        }
+      },
+      ValueEncoding::Unorm8 => {
+        for v in data.iter() {
+          bytes.push((normalize(*v) * 255.0).round() as u8);
+        }
+      },
+      ValueEncoding::Unorm16 => {
+        for v in data.iter() {
+          bytes.extend_from_slice(&((normalize(*v) * 65535.0).round() as u16).to_le_bytes());
+        }
+      },
+    }
+    bytes
+  }
+
+  /// Decode the values from little-endian bytes.
+  /// param bytes: The bytes.
+  /// param remap_range: The [min, max] range mapped to [0, 1] by the normalized encodings.
+  /// return: The values.
+  pub fn decode(&self, bytes: &[u8], remap_range: [f32; 2]) -> Vec<f32> {
+    let denormalize = |n: f32| remap_range[0] + n * (remap_range[1] - remap_range[0]);
+    match self {
+      ValueEncoding::F32 => bytes.chunks_exact(4)
+        .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .collect(),
+      ValueEncoding::F16 => bytes.chunks_exact(2)
+        .map(|b| f16_bits_to_f32(u16::from_le_bytes([b[0], b[1]])))
+        .collect(),
+      ValueEncoding::Unorm8 => bytes.iter()
+        .map(|b| denormalize(*b as f32 / 255.0))
+        .collect(),
+      ValueEncoding::Unorm16 => bytes.chunks_exact(2)
+        .map(|b| denormalize(u16::from_le_bytes([b[0], b[1]]) as f32 / 65535.0))
+        .collect(),
+    }
+  }
+
+  /// Encode the gradients and the values to the interleaved RGBA texels, the gradient in RGB and the value in A.
+  /// The normalized encodings map the gradient components from [-1, 1] to [0, 1].
+  /// param gradients: The gradients.
+  /// param data: The values.
+  /// param remap_range: The [min, max] range of the values mapped to [0, 1] by the normalized encodings.
+  /// return: The bytes.
+  pub fn encode_rgba(&self, gradients: &[[f32; 3]], data: &[f32], remap_range: [f32; 2]) -> Vec<u8> {
+    let size = self.size();
+    let channels = [
+      self.encode(&gradients.iter().map(|g| g[0]).collect::<Vec<_>>(), [-1.0, 1.0]),
+      self.encode(&gradients.iter().map(|g| g[1]).collect::<Vec<_>>(), [-1.0, 1.0]),
+      self.encode(&gradients.iter().map(|g| g[2]).collect::<Vec<_>>(), [-1.0, 1.0]),
+      self.encode(data, remap_range),
+    ];
+
+    let mut bytes = Vec::with_capacity(data.len() * size * 4);
+    for i in 0..data.len() {
+      for channel in channels.iter() {
+        bytes.extend_from_slice(&channel[i * size..(i + 1) * size]);
+      }
+    }
+    bytes
+  }
+
+  /// Decode the gradients and the values from the interleaved RGBA texels written by encode_rgba.
+  /// param bytes: The bytes.
+  /// param remap_range: The [min, max] range of the values mapped to [0, 1] by the normalized encodings.
+  /// return: The gradients and the values.
+  pub fn decode_rgba(&self, bytes: &[u8], remap_range: [f32; 2]) -> (Vec<[f32; 3]>, Vec<f32>) {
+    let size = self.size();
+    let channel = |c: usize| bytes.chunks_exact(size * 4)
+      .flat_map(|texel| texel[c * size..(c + 1) * size].iter().copied())
+      .collect::<Vec<_>>();
+    let [x, y, z] = [0, 1, 2].map(|c| self.decode(&channel(c), [-1.0, 1.0]));
+    let gradients = x.iter().zip(y.iter()).zip(z.iter())
+      .map(|((x, y), z)| [*x, *y, *z])
+      .collect();
+    (gradients, self.decode(&channel(3), remap_range))
+  }
+}
+
+impl std::str::FromStr for ValueEncoding {
+  type Err = anyhow::Error;
+
+  fn from_str(s: &str) -> Result<Self> {
+    ValueEncoding::ALL.iter()
+      .find(|encoding| encoding.name().eq_ignore_ascii_case(s))
+      .copied()
+      .ok_or(anyhow::anyhow!("Unknown value encoding \"{}\".", s))
+  }
+}
+
+/// The baked distance field volume.
+/// The voxels are stored with X changing fastest, then Y, then Z.
+/// The mip levels have the same bounds and the dimensions of mip_dimensions.
+/// The gradients do not depend on the distance unit.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SDFVolume {
+  pub dimensions: [u32; 3],
+  pub center: [f32; 3],
+  pub extents: [f32; 3],
+  pub voxel_size: f32,
+  pub is_sdf: bool,
+  pub surface_offset: f32,
+  pub unit: DistanceUnit,
+  pub data: Vec<f32>,
+  pub mips: Vec<Vec<f32>>, // The voxels of the mip levels from 1, empty without the mip chain.
+  pub gradients: Vec<[f32; 3]>, // The normalized gradients of the voxels of the level 0, empty without the gradient channel.
+}
+
+/// The implementation of the SDF volume.
+impl SDFVolume {
+
+  /// Get the number of voxels.
+  /// return: The number of voxels.
+  pub fn num_of_voxels(&self) -> usize {
+    self.dimensions[0] as usize * self.dimensions[1] as usize * self.dimensions[2] as usize
+  }
+
+  /// Get the linear index of the voxel.
+  /// param x: The x coordinate.
+  /// param y: The y coordinate.
+  /// param z: The z coordinate.
+  /// return: The index.
+  pub fn index(&self, x: u32, y: u32, z: u32) -> usize {
+    x as usize + self.dimensions[0] as usize * (y as usize + self.dimensions[1] as usize * z as usize)
+  }
+
+  /// Get the value of the voxel.
+  /// param x: The x coordinate.
+  /// param y: The y coordinate.
+  /// param z: The z coordinate.
+  /// return: The value.
+  pub fn get(&self, x: u32, y: u32, z: u32) -> f32 {
+    self.data[self.index(x, y, z)]
+  }
+
+  /// Get the scale from the values to world units.
+  /// return: The scale.
+  pub fn world_scale(&self) -> f32 {
+    self.unit_scale(self.unit)
+  }
+
+  /// Get the size of the unit in world units.
+  /// param unit: The distance unit.
+  /// return: The size.
+  fn unit_scale(&self, unit: DistanceUnit) -> f32 {
+    match unit {
+      DistanceUnit::Normalized => self.voxel_size * self.dimensions.iter().copied().max().unwrap_or(1) as f32,
+      DistanceUnit::Voxel => self.voxel_size,
+      DistanceUnit::World => 1.0,
+    }
+  }
+
+  /// Convert the values and the surface offset to the unit.
+  /// param unit: The distance unit.
+  /// return: The result.
+  pub fn convert_unit(&mut self, unit: DistanceUnit) -> Result<()> {
+    if unit == self.unit {
+      return Ok(());
+    }
+    if !self.voxel_size.is_finite() || self.voxel_size <= 0.0 {
+      return Err(anyhow::anyhow!("The volume without the voxel size can not be converted to the {} unit.", unit.name()));
+    }
+
+    let scale = self.unit_scale(self.unit) / self.unit_scale(unit);
+    for v in self.data.iter_mut().chain(self.mips.iter_mut().flatten()) {
+      *v *= scale;
+    }
+    self.surface_offset *= scale;
+    self.unit = unit;
+    Ok(())
+  }
+
+  /// Get the default remap range of the normalized encodings.
+  /// The SDF range is symmetric around zero so the surface maps to 0.5, the UDF range starts from zero.
+  /// return: The [min, max] range.
+  pub fn remap_range(&self) -> [f32; 2] {
+    let max_abs = self.data.iter().chain(self.mips.iter().flatten()).fold(0.0f32, |a, b| a.max(b.abs()));
+    let max_abs = if max_abs > 0.0 { max_abs } else { 1.0 };
+    if self.is_sdf {
+      [-max_abs, max_abs]
+    } else {
+      [0.0, max_abs]
+    }
+  }
+
+  /// Get the number of the mip levels with the level 0.
+  /// return: The number of levels.
+  pub fn num_of_mip_levels(&self) -> u32 {
+    1 + self.mips.len() as u32
+  }
+
+  /// Build the full mip chain of the volume with the conservative min distance downsampling.
+  /// return: The result.
+  pub fn build_mips(&mut self) -> Result<()> {
+    if !self.voxel_size.is_finite() || self.voxel_size <= 0.0 {
+      return Err(anyhow::anyhow!("The volume without the voxel size can not build the mip chain."));
+    }
+    self.validate()?;
+
+    let margin = 0.5 * 3.0f32.sqrt() * self.voxel_size / self.world_scale();
+    self.mips = build_mip_chain(self.dimensions, &self.data, margin)?;
+    Ok(())
+  }
+
+  /// Compute the gradients of the voxels by the central differences of the values.
+  /// return: The result.
+  pub fn build_gradients(&mut self) -> Result<()> {
+    self.validate()?;
+
+    self.gradients = central_difference_gradients(self.dimensions, &self.data);
+    Ok(())
+  }
+
+  /// Get the gradients of all mip levels, the level 0 has the gradients of the volume,
+  /// and the levels from 1 get the central differences of their values.
+  /// return: The gradients of the levels, empty without the gradient channel.
+  pub fn gradient_levels(&self) -> Vec<Vec<[f32; 3]>> {
+    if self.gradients.is_empty() {
+      return Vec::new();
+    }
+    std::iter::once(self.gradients.clone())
+      .chain(self.mips.iter().enumerate().map(|(index, mip)| {
+        central_difference_gradients(mip_dimensions(self.dimensions, index as u32 + 1), mip)
+      }))
+      .collect()
+  }
+
+  /// Check the data, gradient and mip lengths match the dimensions.
+  /// return: The result.
+  pub fn validate(&self) -> Result<()> {
+    if self.dimensions.contains(&0) {
+      return Err(anyhow::anyhow!("The volume dimensions {:?} contain zero.", self.dimensions));
+    }
+    if self.data.len() != self.num_of_voxels() {
+      return Err(anyhow::anyhow!(
+        "The volume has {} values, but the dimensions {:?} need {}.",
+        self.data.len(),
+        self.dimensions,
+        self.num_of_voxels(),
+      ));
+    }
+    if !self.gradients.is_empty() && self.gradients.len() != self.num_of_voxels() {
+      return Err(anyhow::anyhow!(
+        "The volume has {} gradients, but the dimensions {:?} need {}.",
+        self.gradients.len(),
+        self.dimensions,
+        self.num_of_voxels(),
+      ));
+    }
+    if self.num_of_mip_levels() > num_of_mip_levels(self.dimensions) {
+      return Err(anyhow::anyhow!(
+        "The volume has {} mip levels, more than {} of the dimensions {:?}.",
+        self.num_of_mip_levels(),
+        num_of_mip_levels(self.dimensions),
+        self.dimensions,
+      ));
+    }
+    for (index, mip) in self.mips.iter().enumerate() {
+      let dimensions = mip_dimensions(self.dimensions, index as u32 + 1);
+      if mip.len() != dimensions.iter().map(|d| *d as usize).product::<usize>() {
+        return Err(anyhow::anyhow!(
+          "The mip level {} has {} values, but the dimensions {:?} need {}.",
+          index + 1,
+          mip.len(),
+          dimensions,
+          dimensions.iter().map(|d| *d as usize).product::<usize>(),
+        ));
+      }
+    }
+    Ok(())
+  }
+
+  /// Save the volume to the file.
+  /// param path: The file path.
+  /// param format: The file format.
+  /// The mip chain is only stored by the binary, KTX2 and DDS formats, the gradients only by the KTX2 and DDS formats.
+  /// param encoding: The value encoding, not used by the text and NanoVDB formats.
+  /// param narrow_band: The half width of the narrow band in voxels, only used by the NanoVDB and sparse formats.
+  /// return: The result.
+  pub fn save<P: AsRef<Path>>(&self, path: P, format: OutputFormat, encoding: ValueEncoding, narrow_band: f32) -> Result<()> {
+    let path = path.as_ref();
+    self.validate()?;
+    let file = std::fs::File::create(path)
+      .with_context(|| format!("Failed to create the volume file: {:?}", path))?;
+    let mut writer = std::io::BufWriter::new(file);
+    match format {
+      OutputFormat::Text => write_text(&mut writer, self),
+      OutputFormat::Binary => write_binary(&mut writer, self, encoding),
+      OutputFormat::Ktx2 => write_ktx2(&mut writer, self, encoding, self.remap_range()),
+      OutputFormat::Dds => write_dds(&mut writer, self, encoding, self.remap_range()),
+      OutputFormat::NanoVdb => write_nanovdb(&mut writer, self, if self.is_sdf { "sdf" } else { "udf" }, narrow_band),
+      OutputFormat::Sparse => SparseVolume::from_volume(self, narrow_band, SPARSE_BRICK_SIZE)
+        .and_then(|sparse| write_sparse(&mut writer, &sparse, encoding)),
+    }.with_context(|| format!("Failed to write the volume file: {:?}", path))?;
+    std::io::Write::flush(&mut writer)
+      .with_context(|| format!("Failed to flush the volume file: {:?}", path))?;
+    Ok(())
+  }
+
+  /// Load the volume from the file, the format is detected by the magic number.
+  /// The text, binary, KTX2, DDS and sparse files written by this crate are supported,
+  /// the sparse files are expanded to the dense volume.
+  /// param path: The file path.
+  /// return: The volume.
+  pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+    let path = path.as_ref();
+    let bytes = std::fs::read(path)
+      .with_context(|| format!("Failed to read the volume file: {:?}", path))?;
+    let volume = if bytes.starts_with(&BINARY_MAGIC) {
+      read_binary(&mut bytes.as_slice())
+    } else if bytes.starts_with(&SPARSE_MAGIC) {
+      read_sparse(&mut bytes.as_slice()).map(|sparse| sparse.to_volume())
+    } else if bytes.starts_with(&KTX2_IDENTIFIER) {
+      read_ktx2(&bytes)
+    } else if bytes.starts_with(&DDS_MAGIC) {
+      read_dds(&bytes)
+    } else if bytes.starts_with(&NANOVDB_MAGIC.to_le_bytes()) {
+      Err(anyhow::anyhow!("Loading the NanoVDB file is not supported."))
+    } else {
+      read_text(&mut bytes.as_slice())
+    }.with_context(|| format!("Failed to parse the volume file: {:?}", path))?;
+    volume.validate()?;
+    Ok(volume)
+  }
+
+}
@@ -0,0 +1,461 @@
+use std::io::Write;
+
+use anyhow::{Result, Context};
+
+use super::SDFVolume;
+
+/// The magic number of NanoVDB, "NanoVDB0" in little-endian.
+pub const NANOVDB_MAGIC: u64 = 0x304244566f6e614e;
+
+/// The NanoVDB version written to the file, 32.6.0.
+pub const NANOVDB_VERSION: u32 = (32 << 21) | (6 << 10);
+
+const GRID_DATA_SIZE: usize = 672;
+const TREE_DATA_SIZE: usize = 64;
+const ROOT_DATA_SIZE: usize = 64;
+const ROOT_TILE_SIZE: usize = 32;
+const UPPER_TABLE_OFFSET: usize = 8256;
+const UPPER_NODE_SIZE: usize = UPPER_TABLE_OFFSET + 8 * 32 * 32 * 32;
+const LOWER_TABLE_OFFSET: usize = 1088;
+const LOWER_NODE_SIZE: usize = LOWER_TABLE_OFFSET + 8 * 16 * 16 * 16;
+const LEAF_VALUES_OFFSET: usize = 96;
+const LEAF_NODE_SIZE: usize = LEAF_VALUES_OFFSET + 4 * 8 * 8 * 8;
+
+const GRID_TYPE_FLOAT: u32 = 1;
+const GRID_CLASS_UNKNOWN: u32 = 0;
+const GRID_CLASS_LEVEL_SET: u32 = 1;
+const GRID_FLAG_HAS_BBOX: u32 = 1 << 1;
+const GRID_FLAG_HAS_MIN_MAX: u32 = 1 << 2;
+const GRID_FLAG_IS_BREADTH_FIRST: u32 = 1 << 5;
+const NODE_FLAG_HAS_BBOX: u8 = 1 << 1;
+
+/// The leaf node with 8^3 voxels.
+struct Leaf {
+  origin: [i32; 3],
+  values: Vec<f32>,
+  active: Vec<bool>,
+  bbox: Option<([i32; 3], [i32; 3])>,
+  min_max: [f32; 2],
+  num_of_active: u64,
+}
+
+/// The internal node, the lower one has 16^3 leaves and the upper one has 32^3 lower nodes.
+struct Internal {
+  origin: [i32; 3],
+  // The child index or the tile value of each table entry.
+  table: Vec<Result<usize, f32>>,
+  bbox: Option<([i32; 3], [i32; 3])>,
+  min_max: [f32; 2],
+}
+
+/// Write the volume as a NanoVDB float grid.
+/// The voxel (i, j, k) of the volume is the index coordinate (i, j, k) of the grid,
+/// and the index to world transform places the voxel centers inside the bounds of the volume.
+/// The values are converted to world units and clamped to the narrow band, the voxels outside
+/// the band are inactive and the leaf nodes without active voxels are dropped as tiles.
+/// param writer: The writer.
+/// param volume: The volume.
+/// param name: The grid name.
+/// param narrow_band: The half width of the narrow band in voxels.
+/// return: The result.
+pub fn write_nanovdb<W: Write>(writer: &mut W, volume: &SDFVolume, name: &str, narrow_band: f32) -> Result<()> {
+  if narrow_band <= 0.0 {
+    return Err(anyhow::anyhow!("The narrow band {} is not positive.", narrow_band));
+  }
+  if name.len() >= 256 {
+    return Err(anyhow::anyhow!("The grid name \"{}\" is too long.", name));
+  }
+  if volume.dimensions.iter().any(|d| *d > 4096) {
+    return Err(anyhow::anyhow!("The dimensions {:?} are larger than one upper node.", volume.dimensions));
+  }
+
+  let scale = volume.world_scale();
+  let background = narrow_band * volume.voxel_size;
+  let dims = [volume.dimensions[0] as i32, volume.dimensions[1] as i32, volume.dimensions[2] as i32];
+  let get_value = |x: i32, y: i32, z: i32| -> Option<f32> {
+    if x < dims[0] && y < dims[1] && z < dims[2] {
+      Some(volume.get(x as u32, y as u32, z as u32) * scale)
+    } else {
+      None
+    }
+  };
+  // The sign of the region is the sign of the average value inside the volume.
+  let region_value = |origin: [i32; 3], size: i32| -> f32 {
+    let mut sum = 0.0f64;
+    for z in origin[2]..(origin[2] + size).min(dims[2]) {
+      for y in origin[1]..(origin[1] + size).min(dims[1]) {
+        for x in origin[0]..(origin[0] + size).min(dims[0]) {
+          sum += get_value(x, y, z).unwrap_or(0.0) as f64;
+        }
+      }
+    }
+    if volume.is_sdf && sum < 0.0 { -background } else { background }
+  };
+
+  // Build the leaf nodes.
+  let mut leaves = Vec::new();
+  let mut lowers = Vec::new();
+  for lz in (0..dims[2]).step_by(128) {
+    for ly in (0..dims[1]).step_by(128) {
+      for lx in (0..dims[0]).step_by(128) {
+        let mut lower = Internal {
+          origin: [lx, ly, lz],
+          table: Vec::with_capacity(4096),
+          bbox: None,
+          min_max: [f32::MAX, f32::MIN],
+        };
+        for n in 0..4096i32 {
+          let origin = [lx + ((n >> 8) & 15) * 8, ly + ((n >> 4) & 15) * 8, lz + (n & 15) * 8];
+          if origin[0] >= dims[0] || origin[1] >= dims[1] || origin[2] >= dims[2] {
+            lower.table.push(Err(background));
+            continue;
+          }
+          match build_leaf(origin, background, volume.is_sdf, get_value) {
+            Some(leaf) => {
+              lower.bbox = union_bbox(lower.bbox, leaf.bbox);
+              lower.min_max = [lower.min_max[0].min(leaf.min_max[0]), lower.min_max[1].max(leaf.min_max[1])];
+              lower.table.push(Ok(leaves.len()));
+              leaves.push(leaf);
+            },
+            None => lower.table.push(Err(region_value(origin, 8))),
+          }
+        }
+        if lower.table.iter().any(|t| t.is_ok()) {
+          lowers.push(lower);
+        }
+      }
+    }
+  }
+
+  // Build the only upper node.
+  let mut upper = Internal {
+    origin: [0, 0, 0],
+    table: Vec::with_capacity(32768),
+    bbox: None,
+    min_max: [f32::MAX, f32::MIN],
+  };
+  let mut lower_index = 0;
+  for n in 0..32768i32 {
+    let origin = [((n >> 10) & 31) * 128, ((n >> 5) & 31) * 128, (n & 31) * 128];
+    if lower_index < lowers.len() && lowers[lower_index].origin == origin {
+      let lower = &lowers[lower_index];
+      upper.bbox = union_bbox(upper.bbox, lower.bbox);
+      upper.min_max = [upper.min_max[0].min(lower.min_max[0]), upper.min_max[1].max(lower.min_max[1])];
+      upper.table.push(Ok(lower_index));
+      lower_index += 1;
+    } else if origin[0] >= dims[0] || origin[1] >= dims[1] || origin[2] >= dims[2] {
+      upper.table.push(Err(background));
+    } else {
+      upper.table.push(Err(region_value(origin, 128)));
+    }
+  }
+  // The lower nodes must be stored in the order of the upper table.
+  let lower_order = upper.table.iter().filter_map(|t| t.as_ref().ok().copied()).collect::<Vec<_>>();
+  let lowers = reorder(lowers, &lower_order);
+  for (i, t) in upper.table.iter_mut().filter(|t| t.is_ok()).enumerate() {
+    *t = Ok(i);
+  }
+  // The leaf nodes must be stored in the order of the lower nodes.
+  let leaf_order = lowers.iter()
+    .flat_map(|lower| lower.table.iter().filter_map(|t| t.as_ref().ok().copied()))
+    .collect::<Vec<_>>();
+  let leaves = reorder(leaves, &leaf_order);
+  let mut lowers = lowers;
+  let mut leaf_index = 0;
+  for lower in lowers.iter_mut() {
+    for t in lower.table.iter_mut().filter(|t| t.is_ok()) {
+      *t = Ok(leaf_index);
+      leaf_index += 1;
+    }
+  }
+
+  let has_upper = !lowers.is_empty();
+  let num_of_voxels = leaves.iter().map(|leaf| leaf.num_of_active).sum::<u64>();
+  let index_bbox = upper.bbox.unwrap_or(([0; 3], [0; 3]));
+  let min_max = if has_upper { upper.min_max } else { [0.0, 0.0] };
+
+  // Layout.
+  let num_of_root_tiles = if has_upper { 1 } else { 0 };
+  let tree_offset = GRID_DATA_SIZE;
+  let root_offset = tree_offset + TREE_DATA_SIZE;
+  let upper_offset = root_offset + ROOT_DATA_SIZE + ROOT_TILE_SIZE * num_of_root_tiles;
+  let lower_offset = upper_offset + if has_upper { UPPER_NODE_SIZE } else { 0 };
+  let leaf_offset = lower_offset + LOWER_NODE_SIZE * lowers.len();
+  let grid_size = leaf_offset + LEAF_NODE_SIZE * leaves.len();
+
+  let mut buffer = vec![0u8; grid_size];
+
+  // Grid data.
+  let voxel_size = volume.voxel_size as f64;
+  let translation = [
+    (volume.center[0] - volume.extents[0]) as f64 + 0.5 * voxel_size,
+    (volume.center[1] - volume.extents[1]) as f64 + 0.5 * voxel_size,
+    (volume.center[2] - volume.extents[2]) as f64 + 0.5 * voxel_size,
+  ];
+  let index_to_world = |ijk: [i32; 3], offset: f64| -> [f64; 3] {
+    [
+      (ijk[0] as f64 + offset) * voxel_size + translation[0],
+      (ijk[1] as f64 + offset) * voxel_size + translation[1],
+      (ijk[2] as f64 + offset) * voxel_size + translation[2],
+    ]
+  };
+  let world_bbox = [index_to_world(index_bbox.0, 0.0), index_to_world(index_bbox.1, 1.0)];
+  let grid_class = if volume.is_sdf { GRID_CLASS_LEVEL_SET } else { GRID_CLASS_UNKNOWN };
+  {
+    let grid = &mut buffer[..GRID_DATA_SIZE];
+    put_u64(grid, 0, NANOVDB_MAGIC);
+    put_u64(grid, 8, u64::MAX); // No checksum.
+    put_u32(grid, 16, NANOVDB_VERSION);
+    put_u32(grid, 20, GRID_FLAG_HAS_BBOX | GRID_FLAG_HAS_MIN_MAX | GRID_FLAG_IS_BREADTH_FIRST);
+    put_u32(grid, 24, 0); // Grid index.
+    put_u32(grid, 28, 1); // Grid count.
+    put_u64(grid, 32, grid_size as u64);
+    grid[40..40 + name.len()].copy_from_slice(name.as_bytes());
+    // The map.
+    let inv_voxel_size = 1.0 / voxel_size;
+    for (i, t) in translation.iter().enumerate() {
+      put_f32(grid, 296 + (i * 4) * 4, voxel_size as f32);
+      put_f32(grid, 332 + (i * 4) * 4, inv_voxel_size as f32);
+      put_f32(grid, 368 + i * 4, *t as f32);
+      put_f64(grid, 384 + (i * 4) * 8, voxel_size);
+      put_f64(grid, 456 + (i * 4) * 8, inv_voxel_size);
+      put_f64(grid, 528 + i * 8, *t);
+    }
+    put_f32(grid, 380, 1.0); // Taper.
+    put_f64(grid, 552, 1.0); // Taper.
+    for (i, v) in world_bbox[0].iter().chain(world_bbox[1].iter()).enumerate() {
+      put_f64(grid, 560 + i * 8, *v);
+    }
+    for i in 0..3 {
+      put_f64(grid, 608 + i * 8, voxel_size);
+    }
+    put_u32(grid, 632, grid_class);
+    put_u32(grid, 636, GRID_TYPE_FLOAT);
+  }
+
+  // Tree data, the node offsets are relative to the tree.
+  {
+    let tree = &mut buffer[tree_offset..tree_offset + TREE_DATA_SIZE];
+    put_u64(tree, 0, if leaves.is_empty() { 0 } else { (leaf_offset - tree_offset) as u64 });
+    put_u64(tree, 8, if lowers.is_empty() { 0 } else { (lower_offset - tree_offset) as u64 });
+    put_u64(tree, 16, if has_upper { (upper_offset - tree_offset) as u64 } else { 0 });
+    put_u64(tree, 24, (root_offset - tree_offset) as u64);
+    put_u32(tree, 32, leaves.len() as u32);
+    put_u32(tree, 36, lowers.len() as u32);
+    put_u32(tree, 40, if has_upper { 1 } else { 0 });
+    put_u64(tree, 56, num_of_voxels);
+  }
+
+  // Root data.
+  {
+    let root = &mut buffer[root_offset..upper_offset];
+    put_bbox(root, 0, index_bbox);
+    put_u32(root, 24, num_of_root_tiles as u32);
+    put_f32(root, 28, background);
+    put_f32(root, 32, min_max[0]);
+    put_f32(root, 36, min_max[1]);
+    if has_upper {
+      // The upper node at the origin has the key 0.
+      put_u64(root, ROOT_DATA_SIZE, 0);
+      put_u64(root, ROOT_DATA_SIZE + 8, (upper_offset - root_offset) as u64);
+    }
+  }
+
+  // Internal nodes, the child offsets are relative to the node.
+  if has_upper {
+    let node = &mut buffer[upper_offset..lower_offset];
+    write_internal(node, &upper, 5, UPPER_TABLE_OFFSET, |i| (lower_offset + i * LOWER_NODE_SIZE - upper_offset) as u64);
+  }
+  for (i, lower) in lowers.iter().enumerate() {
+    let node_offset = lower_offset + i * LOWER_NODE_SIZE;
+    let node = &mut buffer[node_offset..node_offset + LOWER_NODE_SIZE];
+    write_internal(node, lower, 4, LOWER_TABLE_OFFSET, |j| (leaf_offset + j * LEAF_NODE_SIZE - node_offset) as u64);
+  }
+
+  // Leaf nodes.
+  for (i, leaf) in leaves.iter().enumerate() {
+    let node_offset = leaf_offset + i * LEAF_NODE_SIZE;
+    let node = &mut buffer[node_offset..node_offset + LEAF_NODE_SIZE];
+    let (bbox_min, bbox_max) = leaf.bbox.unwrap_or((leaf.origin, leaf.origin));
+    for a in 0..3 {
+      put_u32(node, a * 4, bbox_min[a] as u32);
+      node[12 + a] = (bbox_max[a] - bbox_min[a]) as u8;
+    }
+    node[15] = NODE_FLAG_HAS_BBOX;
+    put_mask(node, 16, &leaf.active);
+    put_f32(node, 80, leaf.min_max[0]);
+    put_f32(node, 84, leaf.min_max[1]);
+    for (j, v) in leaf.values.iter().enumerate() {
+      put_f32(node, LEAF_VALUES_OFFSET + j * 4, *v);
+    }
+  }
+
+  // File header and the grid meta data.
+  let mut header = Vec::with_capacity(16 + 176 + name.len() + 1);
+  header.extend_from_slice(&NANOVDB_MAGIC.to_le_bytes());
+  header.extend_from_slice(&NANOVDB_VERSION.to_le_bytes());
+  header.extend_from_slice(&1u16.to_le_bytes()); // Grid count.
+  header.extend_from_slice(&0u16.to_le_bytes()); // No codec.
+  header.extend_from_slice(&(grid_size as u64).to_le_bytes());
+  header.extend_from_slice(&(grid_size as u64).to_le_bytes());
+  header.extend_from_slice(&string_hash(name).to_le_bytes());
+  header.extend_from_slice(&num_of_voxels.to_le_bytes());
+  header.extend_from_slice(&GRID_TYPE_FLOAT.to_le_bytes());
+  header.extend_from_slice(&grid_class.to_le_bytes());
+  for v in world_bbox[0].iter().chain(world_bbox[1].iter()) {
+    header.extend_from_slice(&v.to_le_bytes());
+  }
+  for v in index_bbox.0.iter().chain(index_bbox.1.iter()) {
+    header.extend_from_slice(&v.to_le_bytes());
+  }
+  for _ in 0..3 {
+    header.extend_from_slice(&voxel_size.to_le_bytes());
+  }
+  header.extend_from_slice(&((name.len() + 1) as u32).to_le_bytes());
+  for v in [leaves.len() as u32, lowers.len() as u32, if has_upper { 1 } else { 0 }, 1] {
+    header.extend_from_slice(&v.to_le_bytes());
+  }
+  header.extend_from_slice(&[0u8; 12]); // No active tiles.
+  header.extend_from_slice(&0u16.to_le_bytes()); // No codec.
+  header.extend_from_slice(&0u16.to_le_bytes()); // Padding.
+  header.extend_from_slice(&NANOVDB_VERSION.to_le_bytes());
+  header.extend_from_slice(name.as_bytes());
+  header.push(0);
+
+  writer.write_all(&header)
+    .with_context(|| "Failed to write the NanoVDB header.")?;
+  writer.write_all(&buffer)
+    .with_context(|| "Failed to write the NanoVDB grid.")?;
+
+  Ok(())
+}
+
+/// Build the leaf node, return None if there is no active voxel in the narrow band.
+/// param origin: The origin of the leaf node.
+/// param background: The background value, which is also the half width of the narrow band.
+/// param is_sdf: Whether the values are signed.
+/// param get_value: Get the value in world units, None if outside the volume.
+/// return: The leaf node.
+fn build_leaf<F>(origin: [i32; 3], background: f32, is_sdf: bool, get_value: F) -> Option<Leaf>
+  where F: Fn(i32, i32, i32) -> Option<f32>
+{
+  let mut leaf = Leaf {
+    origin,
+    values: vec![background; 512],
+    active: vec![false; 512],
+    bbox: None,
+    min_max: [f32::MAX, f32::MIN],
+    num_of_active: 0,
+  };
+  for n in 0..512i32 {
+    let ijk = [origin[0] + (n >> 6), origin[1] + ((n >> 3) & 7), origin[2] + (n & 7)];
+    let Some(value) = get_value(ijk[0], ijk[1], ijk[2]) else {
+      continue;
+    };
+    let value = if is_sdf { value } else { value.abs() };
+    leaf.values[n as usize] = value.clamp(-background, background);
+    if value.abs() < background {
+      leaf.active[n as usize] = true;
+      leaf.num_of_active += 1;
+      leaf.bbox = union_bbox(leaf.bbox, Some((ijk, ijk)));
+      leaf.min_max = [leaf.min_max[0].min(value), leaf.min_max[1].max(value)];
+    }
+  }
+
+  if leaf.num_of_active > 0 {
+    Some(leaf)
+  } else {
+    None
+  }
+}
+
+/// Write the internal node.
+/// param node: The node buffer.
+/// param internal: The internal node.
+/// param log2_dim: The log2 of the table dimension.
+/// param table_offset: The offset of the table in the node.
+/// param child_offset: Get the offset of the child relative to the node.
+fn write_internal<F>(node: &mut [u8], internal: &Internal, log2_dim: usize, table_offset: usize, child_offset: F)
+  where F: Fn(usize) -> u64
+{
+  let mask_size = (1 << (3 * log2_dim)) / 8;
+  put_bbox(node, 0, internal.bbox.unwrap_or((internal.origin, internal.origin)));
+  put_u64(node, 24, NODE_FLAG_HAS_BBOX as u64);
+  // The value mask is empty since there is no active tile.
+  let child_mask = internal.table.iter().map(|t| t.is_ok()).collect::<Vec<_>>();
+  put_mask(node, 32 + mask_size, &child_mask);
+  put_f32(node, 32 + mask_size * 2, internal.min_max[0]);
+  put_f32(node, 32 + mask_size * 2 + 4, internal.min_max[1]);
+  for (n, t) in internal.table.iter().enumerate() {
+    match t {
+      Ok(child) => put_u64(node, table_offset + n * 8, child_offset(*child)),
+      Err(value) => put_f32(node, table_offset + n * 8, *value),
+    }
+  }
+}
+
+/// Reorder the nodes.
+/// param nodes: The nodes.
+/// param order: The new order of the old indices.
+/// return: The reordered nodes.
+fn reorder<T>(nodes: Vec<T>, order: &[usize]) -> Vec<T> {
+  let mut nodes = nodes.into_iter().map(Some).collect::<Vec<_>>();
+  order.iter().filter_map(|i| nodes[*i].take()).collect()
+}
+
+/// Get the union of two bounding boxes.
+/// param a: The first bounding box.
+/// param b: The second bounding box.
+/// return: The union.
+fn union_bbox(a: Option<([i32; 3], [i32; 3])>, b: Option<([i32; 3], [i32; 3])>) -> Option<([i32; 3], [i32; 3])> {
+  match (a, b) {
+    (Some(a), Some(b)) => Some((
+      [a.0[0].min(b.0[0]), a.0[1].min(b.0[1]), a.0[2].min(b.0[2])],
+      [a.1[0].max(b.1[0]), a.1[1].max(b.1[1]), a.1[2].max(b.1[2])],
+    )),
+    (Some(a), None) => Some(a),
+    (None, b) => b,
+  }
+}
+
+/// The string hash of NanoVDB, used as the name key.
+/// param name: The name.
+/// return: The hash.
+fn string_hash(name: &str) -> u64 {
+  let mut hash = 0u64;
+  for c in name.bytes() {
+    let overflow = hash >> (64 - 8);
+    hash = hash.wrapping_mul(67);
+    hash = hash.wrapping_add(c as u64 + overflow);
+  }
+  hash
+}
+
+fn put_u32(buffer: &mut [u8], offset: usize, value: u32) {
+  buffer[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+}
+
+fn put_u64(buffer: &mut [u8], offset: usize, value: u64) {
+  buffer[offset..offset + 8].copy_from_slice(&value.to_le_bytes());
+}
+
+fn put_f32(buffer: &mut [u8], offset: usize, value: f32) {
+  buffer[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+}
+
+fn put_f64(buffer: &mut [u8], offset: usize, value: f64) {
+  buffer[offset..offset + 8].copy_from_slice(&value.to_le_bytes());
+}
+
+fn put_bbox(buffer: &mut [u8], offset: usize, bbox: ([i32; 3], [i32; 3])) {
+  for (i, v) in bbox.0.iter().chain(bbox.1.iter()).enumerate() {
+    buffer[offset + i * 4..offset + i * 4 + 4].copy_from_slice(&v.to_le_bytes());
+  }
+}
+
+fn put_mask(buffer: &mut [u8], offset: usize, bits: &[bool]) {
+  for (word_index, word_bits) in bits.chunks(64).enumerate() {
+    let word = word_bits.iter().enumerate().fold(0u64, |w, (i, b)| if *b { w | (1u64 << i) } else { w });
+    put_u64(buffer, offset + word_index * 8, word);
+  }
+}
@@ -0,0 +1,350 @@
+use std::io::{Read, Write};
+
+use anyhow::{Result, Context};
+
+use super::{
+  SDFVolume,
+  ValueEncoding,
+  DistanceUnit,
+};
+use super::binary::{
+  checked_num_of_voxels,
+  read_values,
+};
+
+/// The magic number of the sparse brick format.
+pub const SPARSE_MAGIC: [u8; 4] = *b"HSBK";
+
+/// The current version of the sparse brick format.
+pub const SPARSE_VERSION: u32 = 1;
+
+/// The default number of voxels along each side of a brick.
+pub const SPARSE_BRICK_SIZE: u32 = 8;
+
+/// The indirection entry of an empty brick outside the surface, or of any empty UDF brick.
+pub const SPARSE_EMPTY_OUTSIDE: u32 = u32::MAX;
+
+/// The indirection entry of an empty brick inside the surface.
+pub const SPARSE_EMPTY_INSIDE: u32 = u32::MAX - 1;
+
+/// The max number of voxels along each side of a brick.
+const MAX_BRICK_SIZE: u32 = 256;
+
+/// The flag bit for the signed distance field.
+const FLAG_SDF: u32 = 1;
+
+/// The narrow band volume stored in bricks.
+/// Only the bricks with voxels inside the band are kept in the atlas, the other bricks
+/// are marked in the indirection grid and read as the band with the sign of their region.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SparseVolume {
+  pub dimensions: [u32; 3],
+  pub center: [f32; 3],
+  pub extents: [f32; 3],
+  pub voxel_size: f32,
+  pub is_sdf: bool,
+  pub surface_offset: f32,
+  pub unit: DistanceUnit,
+  pub band: f32, // The half width of the narrow band in the distance unit, the values are clamped to it.
+  pub brick_size: u32,
+  pub indirection_dimensions: [u32; 3],
+  pub indirection: Vec<u32>, // The atlas slot of each brick with X changing fastest, or the empty markers.
+  pub atlas: Vec<f32>, // The voxels of the occupied bricks one after another, X changing fastest in each brick.
+}
+
+/// The implementation of the sparse volume.
+impl SparseVolume {
+
+  /// Split the volume into bricks and keep the bricks within the narrow band.
+  /// The bricks on the far sides are padded by repeating the last voxels of the volume.
+  /// param volume: The volume.
+  /// param narrow_band: The half width of the narrow band in voxels.
+  /// param brick_size: The number of voxels along each side of a brick.
+  /// return: The sparse volume.
+  pub fn from_volume(volume: &SDFVolume, narrow_band: f32, brick_size: u32) -> Result<Self> {
+    volume.validate()?;
+    if !narrow_band.is_finite() || narrow_band <= 0.0 {
+      return Err(anyhow::anyhow!("The narrow band {} is not positive.", narrow_band));
+    }
+    if !(1..=MAX_BRICK_SIZE).contains(&brick_size) {
+      return Err(anyhow::anyhow!("The brick size {} is out of range [1, {}].", brick_size, MAX_BRICK_SIZE));
+    }
+    if !volume.voxel_size.is_finite() || volume.voxel_size <= 0.0 {
+      return Err(anyhow::anyhow!("The volume without the voxel size can not be split into the narrow band bricks."));
+    }
+
+    let band = narrow_band * volume.voxel_size / volume.world_scale();
+    let indirection_dimensions = [0, 1, 2].map(|i| volume.dimensions[i].div_ceil(brick_size));
+    let num_of_bricks = indirection_dimensions.iter().map(|d| *d as usize).product::<usize>();
+    let mut indirection = Vec::with_capacity(num_of_bricks);
+    let mut atlas = Vec::new();
+    let mut brick = Vec::with_capacity(brick_size.pow(3) as usize);
+    for bz in 0..indirection_dimensions[2] {
+      for by in 0..indirection_dimensions[1] {
+        for bx in 0..indirection_dimensions[0] {
+          brick.clear();
+          let mut is_occupied = false;
+          let mut sum = 0.0f64;
+          for z in bz * brick_size..(bz + 1) * brick_size {
+            for y in by * brick_size..(by + 1) * brick_size {
+              for x in bx * brick_size..(bx + 1) * brick_size {
+                let value = volume.get(
+                  x.min(volume.dimensions[0] - 1),
+                  y.min(volume.dimensions[1] - 1),
+                  z.min(volume.dimensions[2] - 1),
+                );
+                is_occupied |= value.abs() < band;
+                sum += value as f64;
+                brick.push(value.clamp(-band, band));
+              }
+            }
+          }
+
+          if is_occupied {
+            indirection.push((atlas.len() / brick.len()) as u32);
+            atlas.extend_from_slice(&brick);
+          } else if volume.is_sdf && sum < 0.0 {
+            indirection.push(SPARSE_EMPTY_INSIDE);
+          } else {
+            indirection.push(SPARSE_EMPTY_OUTSIDE);
+          }
+        }
+      }
+    }
+
+    Ok(Self {
+      dimensions: volume.dimensions,
+      center: volume.center,
+      extents: volume.extents,
+      voxel_size: volume.voxel_size,
+      is_sdf: volume.is_sdf,
+      surface_offset: volume.surface_offset,
+      unit: volume.unit,
+      band,
+      brick_size,
+      indirection_dimensions,
+      indirection,
+      atlas,
+    })
+  }
+
+  /// Get the number of the bricks in the atlas.
+  /// return: The number of bricks.
+  pub fn num_of_bricks(&self) -> usize {
+    self.atlas.len().checked_div(self.brick_size.pow(3) as usize).unwrap_or(0)
+  }
+
+  /// Check the indirection grid and the atlas match the dimensions.
+  /// return: The result.
+  pub fn validate(&self) -> Result<()> {
+    if self.dimensions.contains(&0) {
+      return Err(anyhow::anyhow!("The volume dimensions {:?} contain zero.", self.dimensions));
+    }
+    if !(1..=MAX_BRICK_SIZE).contains(&self.brick_size) {
+      return Err(anyhow::anyhow!("The brick size {} is out of range [1, {}].", self.brick_size, MAX_BRICK_SIZE));
+    }
+    let indirection_dimensions = [0, 1, 2].map(|i| self.dimensions[i].div_ceil(self.brick_size));
+    if indirection_dimensions != self.indirection_dimensions {
+      return Err(anyhow::anyhow!(
+        "The indirection dimensions {:?} do not match the bricks {:?} of the volume.",
+        self.indirection_dimensions,
+        indirection_dimensions,
+      ));
+    }
+    if self.indirection.len() != indirection_dimensions.iter().map(|d| *d as usize).product::<usize>() {
+      return Err(anyhow::anyhow!(
+        "The indirection grid has {} entries, but the dimensions {:?} need {}.",
+        self.indirection.len(),
+        indirection_dimensions,
+        indirection_dimensions.iter().map(|d| *d as usize).product::<usize>(),
+      ));
+    }
+    if !self.atlas.len().is_multiple_of(self.brick_size.pow(3) as usize) {
+      return Err(anyhow::anyhow!("The atlas has {} values, which are not whole bricks.", self.atlas.len()));
+    }
+    let num_of_bricks = self.num_of_bricks();
+    if let Some(slot) = self.indirection.iter().find(|s| **s < SPARSE_EMPTY_INSIDE && **s as usize >= num_of_bricks) {
+      return Err(anyhow::anyhow!("The brick slot {} is out of the atlas of {} bricks.", slot, num_of_bricks));
+    }
+    Ok(())
+  }
+
+  /// Expand the bricks to the dense volume, the voxels of the empty bricks are set to the band.
+  /// return: The volume.
+  pub fn to_volume(&self) -> SDFVolume {
+    let brick_size = self.brick_size as usize;
+    let mut volume = SDFVolume {
+      dimensions: self.dimensions,
+      center: self.center,
+      extents: self.extents,
+      voxel_size: self.voxel_size,
+      is_sdf: self.is_sdf,
+      surface_offset: self.surface_offset,
+      unit: self.unit,
+      data: Vec::new(),
+      mips: Vec::new(),
+      gradients: Vec::new(),
+    };
+    volume.data.reserve(volume.num_of_voxels());
+    for z in 0..self.dimensions[2] as usize {
+      for y in 0..self.dimensions[1] as usize {
+        for x in 0..self.dimensions[0] as usize {
+          let brick_index = x / brick_size
+            + self.indirection_dimensions[0] as usize * (y / brick_size + self.indirection_dimensions[1] as usize * (z / brick_size));
+          let value = match self.indirection[brick_index] {
+            SPARSE_EMPTY_INSIDE => -self.band,
+            SPARSE_EMPTY_OUTSIDE => self.band,
+            slot => self.atlas[slot as usize * brick_size.pow(3)
+              + x % brick_size + brick_size * (y % brick_size + brick_size * (z % brick_size))],
+          };
+          volume.data.push(value);
+        }
+      }
+    }
+    volume
+  }
+
+}
+
+/// Write the sparse volume in the little-endian sparse brick format.
+/// The header layout is:
+///   magic: [u8; 4], version: u32, flags: u32, encoding: u32,
+///   dimensions: [u32; 3], center: [f32; 3], extents: [f32; 3],
+///   voxel_size: f32, surface_offset: f32, unit: u32, band: f32,
+///   brick_size: u32, indirection_dimensions: [u32; 3], num_of_bricks: u32,
+/// followed by the indirection entries as u32 and the atlas values in the given encoding.
+/// Only the f32 and f16 encodings are supported.
+/// param writer: The writer.
+/// param sparse: The sparse volume.
+/// param encoding: The value encoding.
+/// return: The result.
+pub fn write_sparse<W: Write>(writer: &mut W, sparse: &SparseVolume, encoding: ValueEncoding) -> Result<()> {
+  sparse.validate()?;
+  let encoding_id = match encoding {
+    ValueEncoding::F32 => 0u32,
+    ValueEncoding::F16 => 1u32,
+    _ => return Err(anyhow::anyhow!("The sparse format does not support the {} encoding.", encoding.name())),
+  };
+
+  let mut header = Vec::with_capacity(96);
+  header.extend_from_slice(&SPARSE_MAGIC);
+  header.extend_from_slice(&SPARSE_VERSION.to_le_bytes());
+  header.extend_from_slice(&(if sparse.is_sdf { FLAG_SDF } else { 0 }).to_le_bytes());
+  header.extend_from_slice(&encoding_id.to_le_bytes());
+  for d in sparse.dimensions.iter() {
+    header.extend_from_slice(&d.to_le_bytes());
+  }
+  for v in sparse.center.iter().chain(sparse.extents.iter()) {
+    header.extend_from_slice(&v.to_le_bytes());
+  }
+  header.extend_from_slice(&sparse.voxel_size.to_le_bytes());
+  header.extend_from_slice(&sparse.surface_offset.to_le_bytes());
+  header.extend_from_slice(&sparse.unit.id().to_le_bytes());
+  header.extend_from_slice(&sparse.band.to_le_bytes());
+  header.extend_from_slice(&sparse.brick_size.to_le_bytes());
+  for d in sparse.indirection_dimensions.iter() {
+    header.extend_from_slice(&d.to_le_bytes());
+  }
+  header.extend_from_slice(&(sparse.num_of_bricks() as u32).to_le_bytes());
+  writer.write_all(&header)
+    .with_context(|| "Failed to write the sparse header.")?;
+
+  let mut indirection = Vec::with_capacity(sparse.indirection.len() * 4);
+  for entry in sparse.indirection.iter() {
+    indirection.extend_from_slice(&entry.to_le_bytes());
+  }
+  writer.write_all(&indirection)
+    .with_context(|| "Failed to write the sparse indirection grid.")?;
+
+  writer.write_all(&encoding.encode(&sparse.atlas, [0.0, 1.0]))
+    .with_context(|| "Failed to write the sparse brick atlas.")?;
+
+  Ok(())
+}
+
+/// Read the sparse volume in the little-endian sparse brick format.
+/// param reader: The reader.
+/// return: The sparse volume.
+pub fn read_sparse<R: Read>(reader: &mut R) -> Result<SparseVolume> {
+  let mut magic = [0u8; 4];
+  reader.read_exact(&mut magic)
+    .with_context(|| "Failed to read the sparse magic.")?;
+  if magic != SPARSE_MAGIC {
+    return Err(anyhow::anyhow!("Invalid sparse magic {:?}.", magic));
+  }
+
+  let version = read_u32(reader)?;
+  if version == 0 || version > SPARSE_VERSION {
+    return Err(anyhow::anyhow!("Unsupported sparse version {}, the max supported version is {}.", version, SPARSE_VERSION));
+  }
+  let flags = read_u32(reader)?;
+  let encoding = match read_u32(reader)? {
+    0 => ValueEncoding::F32,
+    1 => ValueEncoding::F16,
+    value => return Err(anyhow::anyhow!("Unknown value encoding {}.", value)),
+  };
+  let dimensions = [read_u32(reader)?, read_u32(reader)?, read_u32(reader)?];
+  let center = [read_f32(reader)?, read_f32(reader)?, read_f32(reader)?];
+  let extents = [read_f32(reader)?, read_f32(reader)?, read_f32(reader)?];
+  let voxel_size = read_f32(reader)?;
+  let surface_offset = read_f32(reader)?;
+  let unit = DistanceUnit::from_id(read_u32(reader)?)?;
+  let band = read_f32(reader)?;
+  let brick_size = read_u32(reader)?;
+  let indirection_dimensions = [read_u32(reader)?, read_u32(reader)?, read_u32(reader)?];
+  let num_of_bricks = read_u32(reader)? as usize;
+  if !(1..=MAX_BRICK_SIZE).contains(&brick_size) || (0..3).any(|i| indirection_dimensions[i] != dimensions[i].div_ceil(brick_size)) {
+    return Err(anyhow::anyhow!(
+      "The indirection dimensions {:?} do not match the dimensions {:?} and the brick size {}.",
+      indirection_dimensions,
+      dimensions,
+      brick_size,
+    ));
+  }
+
+  // The counts come from the header, so the sizes are checked and the bytes are read as they come.
+  let num_of_entries = checked_num_of_voxels(indirection_dimensions)?;
+  let bytes = read_values(reader, num_of_entries, 4)
+    .with_context(|| format!("Failed to read {} entries of the sparse indirection grid.", num_of_entries))?;
+  let indirection = bytes.chunks_exact(4)
+    .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    .collect();
+
+  let num_of_values = checked_num_of_voxels([brick_size; 3])?
+    .checked_mul(num_of_bricks)
+    .ok_or(anyhow::anyhow!("The number of values of {} bricks of {} voxels overflows.", num_of_bricks, brick_size))?;
+  let bytes = read_values(reader, num_of_values, encoding.size())
+    .with_context(|| format!("Failed to read {} bricks of the sparse brick atlas.", num_of_bricks))?;
+  let atlas = encoding.decode(&bytes, [0.0, 1.0]);
+
+  let sparse = SparseVolume {
+    dimensions,
+    center,
+    extents,
+    voxel_size,
+    is_sdf: flags & FLAG_SDF != 0,
+    surface_offset,
+    unit,
+    band,
+    brick_size,
+    indirection_dimensions,
+    indirection,
+    atlas,
+  };
+  sparse.validate()?;
+  Ok(sparse)
+}
+
+fn read_u32<R: Read>(reader: &mut R) -> Result<u32> {
+  let mut bytes = [0u8; 4];
+  reader.read_exact(&mut bytes)
+    .with_context(|| "Failed to read the sparse header.")?;
+  Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_f32<R: Read>(reader: &mut R) -> Result<f32> {
+  let mut bytes = [0u8; 4];
+  reader.read_exact(&mut bytes)
+    .with_context(|| "Failed to read the sparse header.")?;
+  Ok(f32::from_le_bytes(bytes))
+}
@@ -0,0 +1,73 @@
+use std::io::{Read, Write};
+
+use anyhow::{Result, Context};
+
+use super::{
+  SDFVolume,
+  DistanceUnit,
+};
+
+/// Write the volume in the text format.
+/// The first line is the dimensions "X Y Z", followed by one value per line.
+/// The distance unit is appended to the first line as "X Y Z unit" if the values are not normalized.
+/// param writer: The writer.
+/// param volume: The volume.
+/// return: The result.
+pub fn write_text<W: Write>(writer: &mut W, volume: &SDFVolume) -> Result<()> {
+  // Write dimensions.
+  let header = if volume.unit == DistanceUnit::Normalized {
+    format!("{} {} {}\n", volume.dimensions[0], volume.dimensions[1], volume.dimensions[2])
+  } else {
+    format!("{} {} {} {}\n", volume.dimensions[0], volume.dimensions[1], volume.dimensions[2], volume.unit.name())
+  };
+  writer.write_all(header.as_bytes())
+    .with_context(|| "Failed to write the dimensions.")?;
+  // Write distance data.
+  for value in volume.data.iter() {
+    writer.write_all(format!("{}\n", value).as_bytes())
+      .with_context(|| "Failed to write the distance data.")?;
+  }
+  Ok(())
+}
+
+/// Read the volume in the text format.
+/// The text format only has the dimensions and the unit, so the other fields are left as default.
+/// param reader: The reader.
+/// return: The volume.
+pub fn read_text<R: Read>(reader: &mut R) -> Result<SDFVolume> {
+  let mut content = String::new();
+  reader.read_to_string(&mut content)
+    .with_context(|| "Failed to read the text volume.")?;
+
+  let mut lines = content.lines();
+  let header = lines.next().ok_or(anyhow::anyhow!("The text volume is empty."))?;
+  let fields = header.split_whitespace().collect::<Vec<_>>();
+  if fields.len() != 3 && fields.len() != 4 {
+    return Err(anyhow::anyhow!("Invalid dimensions \"{}\".", header));
+  }
+  let dimensions = fields[..3].iter()
+    .map(|v| v.parse::<u32>().with_context(|| format!("Invalid dimensions \"{}\".", header)))
+    .collect::<Result<Vec<_>>>()?;
+  let unit = match fields.get(3) {
+    Some(unit) => unit.parse::<DistanceUnit>()?,
+    None => DistanceUnit::Normalized,
+  };
+
+  let data = lines
+    .filter(|line| !line.trim().is_empty())
+    .map(|line| line.trim().parse::<f32>().with_context(|| format!("Invalid value \"{}\".", line)))
+    .collect::<Result<Vec<_>>>()?;
+
+  Ok(SDFVolume {
+    dimensions: [dimensions[0], dimensions[1], dimensions[2]],
+    center: [0.0; 3],
+    extents: [0.0; 3],
+    voxel_size: 0.0,
+    is_sdf: data.iter().any(|v| *v < 0.0),
+    surface_offset: 0.0,
+    unit,
+    data,
+    mips: Vec::new(),
+    gradients: Vec::new(),
+  })
+}
@@ -0,0 +1,305 @@
+//! Bake the procedural shapes with the CPU backend and compare them to the closed-form SDFs.
+//! The errors are measured in voxels, so the thresholds are comparable between the resolutions.
+
+use std::f32::consts::PI;
+
+use glam::{Vec2, Vec3};
+
+use hala_renderer::scene::HalaBounds;
+
+use hala_sdf_baker::baker::cpu::SDFCPUBaker;
+use hala_sdf_baker::baker::settings::SDFBakerSettings;
+use hala_sdf_baker::mesh::TriangleMesh;
+use hala_sdf_baker::volume::{
+  SDFVolume,
+  DistanceUnit,
+};
+
+/// The number of segments around the axis of the revolved shapes.
+const SEGMENTS: u32 = 128;
+
+/// The error thresholds of a shape at a resolution.
+struct Thresholds {
+  rmse: f32,
+  max_error: f32,
+  sign_mismatch_rate: f32,
+}
+
+/// The error statistics of a baked field.
+#[derive(Debug)]
+struct ErrorStats {
+  rmse: f32,
+  max_error: f32,
+  sign_mismatch_rate: f32,
+}
+
+/// Revolve the profile in the XY plane with X >= 0 around the Y axis.
+/// The normals point outside when the profile goes from the top to the bottom.
+/// param profile: The (radius, height) points.
+/// param is_closed: Whether the last point connects to the first one.
+/// return: The triangle mesh.
+fn revolve(profile: &[Vec2], is_closed: bool) -> TriangleMesh {
+  let mut positions = Vec::new();
+  for point in profile.iter() {
+    for j in 0..SEGMENTS {
+      let phi = 2.0 * PI * j as f32 / SEGMENTS as f32;
+      positions.push([point.x * phi.cos(), point.y, point.x * phi.sin()]);
+    }
+  }
+
+  let num_of_rings = profile.len() as u32;
+  let num_of_bands = if is_closed { num_of_rings } else { num_of_rings - 1 };
+  let mut indices = Vec::new();
+  for i in 0..num_of_bands {
+    let ring = i * SEGMENTS;
+    let next_ring = (i + 1) % num_of_rings * SEGMENTS;
+    for j in 0..SEGMENTS {
+      let next_j = (j + 1) % SEGMENTS;
+      indices.extend_from_slice(&[ring + j, ring + next_j, next_ring + j]);
+      indices.extend_from_slice(&[ring + next_j, next_ring + next_j, next_ring + j]);
+    }
+  }
+
+  TriangleMesh::new(positions, indices)
+}
+
+/// Create the UV sphere.
+/// param radius: The radius.
+/// return: The triangle mesh.
+fn uv_sphere(radius: f32) -> TriangleMesh {
+  let profile = (0..=SEGMENTS / 2)
+    .map(|i| {
+      let theta = PI * i as f32 / (SEGMENTS / 2) as f32;
+      Vec2::new(radius * theta.sin(), radius * theta.cos())
+    })
+    .collect::<Vec<_>>();
+  revolve(&profile, false)
+}
+
+/// Create the capsule along the Y axis.
+/// param half_height: The half length of the segment.
+/// param radius: The radius.
+/// return: The triangle mesh.
+fn capsule(half_height: f32, radius: f32) -> TriangleMesh {
+  let quarter = SEGMENTS / 4;
+  let top = (0..=quarter).map(|i| {
+    let theta = 0.5 * PI * i as f32 / quarter as f32;
+    Vec2::new(radius * theta.sin(), radius * theta.cos() + half_height)
+  });
+  let bottom = (0..=quarter).map(|i| {
+    let theta = 0.5 * PI + 0.5 * PI * i as f32 / quarter as f32;
+    Vec2::new(radius * theta.sin(), radius * theta.cos() - half_height)
+  });
+  revolve(&top.chain(bottom).collect::<Vec<_>>(), false)
+}
+
+/// Create the torus around the Y axis.
+/// param major_radius: The radius of the ring.
+/// param minor_radius: The radius of the tube.
+/// return: The triangle mesh.
+fn torus(major_radius: f32, minor_radius: f32) -> TriangleMesh {
+  let profile = (0..SEGMENTS / 2)
+    .map(|i| {
+      let theta = 2.0 * PI * i as f32 / (SEGMENTS / 2) as f32;
+      Vec2::new(major_radius + minor_radius * theta.sin(), minor_radius * theta.cos())
+    })
+    .collect::<Vec<_>>();
+  revolve(&profile, true)
+}
+
+/// Create the axis aligned box.
+/// param half_size: The half size.
+/// return: The triangle mesh.
+fn cuboid(half_size: Vec3) -> TriangleMesh {
+  let positions = (0..8)
+    .map(|i| [
+      if i & 1 == 0 { -half_size.x } else { half_size.x },
+      if i & 2 == 0 { -half_size.y } else { half_size.y },
+      if i & 4 == 0 { -half_size.z } else { half_size.z },
+    ])
+    .collect();
+  let indices = vec![
+    0, 2, 1, 1, 2, 3, // -Z
+    4, 5, 6, 5, 7, 6, // +Z
+    0, 1, 4, 1, 5, 4, // -Y
+    2, 6, 3, 3, 6, 7, // +Y
+    0, 4, 2, 2, 4, 6, // -X
+    1, 3, 5, 3, 7, 5, // +X
+  ];
+  TriangleMesh::new(positions, indices)
+}
+
+/// The closed-form SDF of the box.
+fn box_sdf(p: Vec3, half_size: Vec3) -> f32 {
+  let q = p.abs() - half_size;
+  q.max(Vec3::ZERO).length() + q.max_element().min(0.0)
+}
+
+/// Get the settings with the box fitted to the mesh.
+/// param mesh: The triangle mesh.
+/// param resolution: The max resolution.
+/// return: The settings in world units.
+fn fitted_settings(mesh: &TriangleMesh, resolution: i32) -> SDFBakerSettings {
+  let (min, max) = mesh.bounds().unwrap();
+  let min = Vec3::from_array(min);
+  let max = Vec3::from_array(max);
+  let mut settings = SDFBakerSettings {
+    max_resolution: resolution,
+    distance_unit: DistanceUnit::World,
+    ..Default::default()
+  };
+  settings.fit_box_to_bounds(&HalaBounds {
+    center: ((min + max) * 0.5).to_array(),
+    extents: ((max - min) * 0.5).to_array(),
+  });
+  settings.snap_box_to_bounds();
+  settings
+}
+
+/// Bake the mesh on the CPU with the box fitted to the mesh.
+/// param mesh: The triangle mesh.
+/// param resolution: The max resolution.
+/// return: The SDF volume in world units.
+fn bake(mesh: &TriangleMesh, resolution: i32) -> SDFVolume {
+  SDFCPUBaker::new(mesh).bake(&fitted_settings(mesh, resolution)).unwrap()
+}
+
+/// Compare the volume to the exact SDF at every voxel center.
+/// The voxels between the tessellated and the exact surface may have different signs.
+/// param volume: The SDF volume in world units.
+/// param exact: The closed-form SDF.
+/// return: The errors in voxels.
+fn measure<F: Fn(Vec3) -> f32>(volume: &SDFVolume, exact: F) -> ErrorStats {
+  let [dim_x, dim_y, dim_z] = volume.dimensions;
+  let min_corner = Vec3::from_array(volume.center) - Vec3::from_array(volume.extents);
+
+  let mut sum_of_squares = 0.0f64;
+  let mut max_error = 0.0f32;
+  let mut num_of_sign_mismatches = 0;
+  for z in 0..dim_z {
+    for y in 0..dim_y {
+      for x in 0..dim_x {
+        let pt = min_corner + (Vec3::new(x as f32, y as f32, z as f32) + Vec3::splat(0.5)) * volume.voxel_size;
+        let expected = exact(pt);
+        let actual = volume.data[(x + dim_x * (y + dim_y * z)) as usize];
+        let error = (actual - expected).abs() / volume.voxel_size;
+        sum_of_squares += (error * error) as f64;
+        max_error = max_error.max(error);
+        if (actual < 0.0) != (expected < 0.0) {
+          num_of_sign_mismatches += 1;
+        }
+      }
+    }
+  }
+
+  let num_of_voxels = volume.data.len();
+  ErrorStats {
+    rmse: (sum_of_squares / num_of_voxels as f64).sqrt() as f32,
+    max_error,
+    sign_mismatch_rate: num_of_sign_mismatches as f32 / num_of_voxels as f32,
+  }
+}
+
+/// Bake the mesh at the resolutions and check the errors.
+/// param name: The shape name.
+/// param mesh: The triangle mesh.
+/// param exact: The closed-form SDF.
+/// param cases: The resolutions and their thresholds.
+fn check_shape<F: Fn(Vec3) -> f32>(name: &str, mesh: &TriangleMesh, exact: F, cases: &[(i32, Thresholds)]) {
+  for (resolution, thresholds) in cases.iter() {
+    let volume = bake(mesh, *resolution);
+    let stats = measure(&volume, &exact);
+    assert!(stats.rmse <= thresholds.rmse, "The RMSE of {} at {} is too large, {:?}.", name, resolution, stats);
+    assert!(stats.max_error <= thresholds.max_error, "The max error of {} at {} is too large, {:?}.", name, resolution, stats);
+    assert!(
+      stats.sign_mismatch_rate <= thresholds.sign_mismatch_rate,
+      "The sign mismatch rate of {} at {} is too large, {:?}.", name, resolution, stats,
+    );
+  }
+}
+
+#[test]
+fn test_uv_sphere() {
+  check_shape(
+    "sphere",
+    &uv_sphere(1.0),
+    |p| p.length() - 1.0,
+    &[
+      (32, Thresholds { rmse: 0.02, max_error: 0.05, sign_mismatch_rate: 0.005 }),
+      (64, Thresholds { rmse: 0.02, max_error: 0.05, sign_mismatch_rate: 0.005 }),
+    ],
+  );
+}
+
+#[test]
+fn test_box() {
+  let half_size = Vec3::new(1.0, 0.5, 0.75);
+  check_shape(
+    "box",
+    &cuboid(half_size),
+    |p| box_sdf(p, half_size),
+    &[
+      (32, Thresholds { rmse: 1e-3, max_error: 1e-2, sign_mismatch_rate: 0.0 }),
+      (64, Thresholds { rmse: 1e-3, max_error: 1e-2, sign_mismatch_rate: 0.0 }),
+    ],
+  );
+}
+
+#[test]
+fn test_torus() {
+  check_shape(
+    "torus",
+    &torus(1.0, 0.25),
+    |p| Vec2::new(Vec2::new(p.x, p.z).length() - 1.0, p.y).length() - 0.25,
+    &[
+      (32, Thresholds { rmse: 0.02, max_error: 0.05, sign_mismatch_rate: 0.005 }),
+      (64, Thresholds { rmse: 0.02, max_error: 0.05, sign_mismatch_rate: 0.005 }),
+    ],
+  );
+}
+
+#[test]
+fn test_capsule() {
+  check_shape(
+    "capsule",
+    &capsule(0.5, 0.4),
+    |p| (p - Vec3::new(0.0, p.y.clamp(-0.5, 0.5), 0.0)).length() - 0.4,
+    &[
+      (32, Thresholds { rmse: 0.02, max_error: 0.05, sign_mismatch_rate: 0.005 }),
+      (64, Thresholds { rmse: 0.02, max_error: 0.05, sign_mismatch_rate: 0.005 }),
+    ],
+  );
+}
+
+#[test]
+fn test_thin_plate() {
+  // Thinner than a voxel at both resolutions.
+  let half_size = Vec3::new(1.0, 0.01, 1.0);
+  check_shape(
+    "thin plate",
+    &cuboid(half_size),
+    |p| box_sdf(p, half_size),
+    &[
+      (32, Thresholds { rmse: 1e-3, max_error: 1e-2, sign_mismatch_rate: 0.0 }),
+      (64, Thresholds { rmse: 1e-3, max_error: 1e-2, sign_mismatch_rate: 0.0 }),
+    ],
+  );
+}
+
+#[test]
+fn test_normalized_data() {
+  // The CPU backend of the baker keeps the data normalized by the longest side of the box, as the GPU reads back.
+  let mesh = capsule(0.5, 0.4);
+  let settings = fitted_settings(&mesh, 32);
+  let baker = SDFCPUBaker::new(&mesh);
+  let volume = baker.bake(&settings).unwrap();
+  let max_size = settings.actual_size.iter().fold(0.0, |a: f32, b| a.max(*b));
+  for is_sdf in [true, false] {
+    let data = baker.bake_data(&settings, is_sdf).unwrap();
+    assert_eq!(data.len(), volume.data.len());
+    for (normalized, world) in data.iter().zip(volume.data.iter()) {
+      let expected = if is_sdf { *world } else { world.abs() };
+      assert!((normalized * max_size - expected).abs() < 1e-5, "{} * {} is not {}.", normalized, max_size, expected);
+    }
+  }
+}
@@ -0,0 +1,125 @@
+//! Sample the vertex attributes at the closest points of the voxels and save them as volume textures.
+
+use hala_sdf_baker::volume::*;
+
+/// Create the attributes of a textured primitive with the vertex colors and an untextured one.
+/// return: The scene attributes.
+fn two_primitive_scene() -> SceneAttributes {
+  SceneAttributes {
+    primitives: vec![
+      SurfaceAttributes {
+        indices: vec![0, 1, 2, 2, 1, 3],
+        tex_coords: vec![[0.0, 0.0], [1.0, 0.0], [0.0, 1.0], [1.0, 1.0]],
+        colors: vec![[1.0, 0.5, 0.5, 1.0]; 4],
+        material_index: 1,
+      },
+      SurfaceAttributes {
+        indices: vec![0, 1, 2],
+        tex_coords: Vec::new(),
+        colors: Vec::new(),
+        material_index: 0,
+      },
+    ],
+    materials: vec![
+      SurfaceMaterial { base_color: [0.2, 0.4, 0.6, 1.0], base_color_texture: None },
+      SurfaceMaterial { base_color: [1.0, 1.0, 0.5, 1.0], base_color_texture: Some(0) },
+    ],
+    // The left texels are red and the right ones are green.
+    textures: vec![
+      SurfaceTexture::from_bytes(2, 2, &[255, 0, 0, 255, 0, 255, 0, 255, 255, 0, 0, 255, 0, 255, 0, 255], true).unwrap(),
+    ],
+  }
+}
+
+/// Create the closest triangles of the voxels on the primitives of the scene.
+/// return: The closest triangle volume.
+fn closest_triangles() -> ClosestTriangleVolume {
+  ClosestTriangleVolume {
+    dimensions: [2, 2, 1],
+    center: [0.0; 3],
+    extents: [1.0, 1.0, 0.5],
+    voxel_size: 1.0,
+    ranges: vec![
+      TriangleRange { primitive_index: 0, first_triangle: 0, num_of_triangles: 2 },
+      TriangleRange { primitive_index: 1, first_triangle: 2, num_of_triangles: 1 },
+    ],
+    triangles: vec![0, 1, 2, NO_CLOSEST_TRIANGLE],
+    barycentrics: vec![
+      [1.0, 0.0, 0.0],
+      [0.0, 1.0, 0.0],
+      [0.2, 0.3, 0.5],
+      [0.0; 3],
+    ],
+  }
+}
+
+/// Whether the values are close.
+/// param a: The values.
+/// param b: The expected values.
+/// param eps: The tolerance.
+/// return: True if close.
+fn is_close(a: &[f32], b: &[f32], eps: f32) -> bool {
+  a.len() == b.len() && a.iter().zip(b.iter()).all(|(a, b)| (a - b).abs() <= eps)
+}
+
+#[test]
+fn textures_sample_bilinearly_with_repeat() {
+  let texture = SurfaceTexture::from_values(2, 1, &[0.0, 1.0], false).unwrap();
+  assert_eq!(texture.sample([0.25, 0.5]), [0.0, 0.0, 0.0, 1.0]);
+  assert_eq!(texture.sample([0.75, 0.5]), [1.0, 1.0, 1.0, 1.0]);
+  assert!(is_close(&texture.sample([0.5, 0.5]), &[0.5, 0.5, 0.5, 1.0], 1e-6));
+  // The left border blends with the right texel.
+  assert!(is_close(&texture.sample([0.0, 0.5]), &[0.5, 0.5, 0.5, 1.0], 1e-6));
+  assert_eq!(texture.sample([1.25, -2.5]), texture.sample([0.25, 0.5]));
+
+  assert!(SurfaceTexture::from_values(2, 2, &[0.0; 5], false).is_err());
+  assert!((srgb_to_linear(linear_to_srgb(0.3)) - 0.3).abs() < 1e-6);
+}
+
+#[test]
+fn attributes_are_sampled_at_the_closest_points() {
+  let scene = two_primitive_scene();
+  let volume = scene.sample_volume(&closest_triangles()).unwrap();
+  volume.validate().unwrap();
+
+  assert_eq!(volume.material_indices, vec![1, 1, 0, NO_MATERIAL]);
+  assert_eq!(volume.tex_coords[0], [0.0, 0.0]);
+  assert_eq!(volume.tex_coords[1], [1.0, 0.0]);
+  // The UV (0, 0) is between the four texels, half red and half green.
+  assert!(is_close(&volume.colors[0], &[0.5, 0.25, 0.0, 1.0], 1e-6));
+  // The untextured primitive without the vertex colors takes the base color factor.
+  assert_eq!(volume.tex_coords[2], [0.0, 0.0]);
+  assert!(is_close(&volume.colors[2], &[0.2, 0.4, 0.6, 1.0], 1e-6));
+  assert_eq!(volume.colors[3], [0.0; 4]);
+
+  // The triangles out of the primitive are rejected.
+  let mut closest = closest_triangles();
+  closest.ranges[1].num_of_triangles = 2;
+  closest.triangles[2] = 3;
+  assert!(scene.sample_volume(&closest).is_err());
+}
+
+#[test]
+fn attribute_channels_round_trip_as_ktx2() {
+  let volume = two_primitive_scene().sample_volume(&closest_triangles()).unwrap();
+
+  for channel in AttributeChannel::ALL {
+    let mut bytes = Vec::new();
+    write_attribute_ktx2(&mut bytes, &volume, channel).unwrap();
+    let (read_channel, read) = read_attribute_ktx2(&bytes).unwrap();
+    assert_eq!(read_channel, channel);
+    assert_eq!(read.dimensions, volume.dimensions);
+    assert_eq!(read.extents, volume.extents);
+    match channel {
+      AttributeChannel::Color => {
+        // The colors are quantized to 8 bits in sRGB.
+        for (a, b) in read.colors.iter().zip(volume.colors.iter()) {
+          assert!(is_close(a, b, 2.0 / 255.0), "{:?} != {:?}", a, b);
+        }
+      },
+      AttributeChannel::TexCoord => assert_eq!(read.tex_coords, volume.tex_coords),
+      AttributeChannel::Material => assert_eq!(read.material_indices, volume.material_indices),
+    }
+  }
+  assert_eq!(AttributeChannel::Color.get_path("./out/output.ktx2"), std::path::Path::new("./out/output_color.ktx2"));
+}
@@ -0,0 +1,83 @@
+//! Read the versioned binary volumes, from the files of the older versions and the corrupt headers.
+
+mod common;
+
+use hala_sdf_baker::volume::*;
+
+use common::*;
+
+/// Write the binary header of a version by hand.
+/// param version: The version.
+/// param encoding: The encoding id.
+/// param dimensions: The dimensions.
+/// return: The header bytes.
+fn header(version: u32, encoding: u32, dimensions: [u32; 3]) -> Vec<u8> {
+  let mut bytes = BINARY_MAGIC.to_vec();
+  for v in [version, 1, encoding].iter().chain(dimensions.iter()) {
+    bytes.extend_from_slice(&v.to_le_bytes());
+  }
+  for v in [0.0f32, 0.0, 0.0, 1.0, 1.5, 2.0, 0.5, 0.25] {
+    bytes.extend_from_slice(&v.to_le_bytes());
+  }
+  if version >= 2 {
+    bytes.extend_from_slice(&DistanceUnit::Voxel.id().to_le_bytes());
+  }
+  if version >= 3 {
+    bytes.extend_from_slice(&1u32.to_le_bytes());
+  }
+  bytes
+}
+
+#[test]
+fn older_binary_versions_are_read() {
+  let values = (0..24).map(|i| i as f32 * 0.125 - 1.0).collect::<Vec<_>>();
+  for version in 1..=BINARY_VERSION {
+    let mut bytes = header(version, 0, [4, 3, 2]);
+    bytes.extend(values.iter().flat_map(|v| v.to_le_bytes()));
+    let volume = read_binary(&mut bytes.as_slice()).unwrap();
+    volume.validate().unwrap();
+    assert_eq!(volume.dimensions, [4, 3, 2]);
+    assert_eq!(volume.extents, [1.0, 1.5, 2.0]);
+    assert_eq!(volume.voxel_size, 0.5);
+    assert_eq!(volume.surface_offset, 0.25);
+    assert!(volume.is_sdf);
+    assert!(volume.mips.is_empty());
+    // The version 1 files have no unit and the values are normalized.
+    assert_eq!(volume.unit, if version == 1 { DistanceUnit::Normalized } else { DistanceUnit::Voxel });
+    assert_eq!(volume.data, values);
+  }
+}
+
+#[test]
+fn binary_volume_round_trips() {
+  let volume = sphere_volume([9, 7, 5], 2.5);
+  for encoding in [ValueEncoding::F32, ValueEncoding::F16] {
+    let mut bytes = Vec::new();
+    write_binary(&mut bytes, &volume, encoding).unwrap();
+    let read = read_binary(&mut bytes.as_slice()).unwrap();
+    assert_eq!(read.dimensions, volume.dimensions);
+    for (a, b) in read.data.iter().zip(volume.data.iter()) {
+      assert!((a - b).abs() <= 4e-3, "{} != {}", a, b);
+    }
+  }
+  assert!(write_binary(&mut Vec::new(), &volume, ValueEncoding::Unorm8).is_err());
+}
+
+#[test]
+fn corrupt_binary_headers_are_rejected() {
+  // The voxels of the header do not fit in the input.
+  let mut bytes = header(BINARY_VERSION, 0, [4, 3, 2]);
+  bytes.extend_from_slice(&[0u8; 23 * 4]);
+  assert!(read_binary(&mut bytes.as_slice()).is_err());
+
+  // The huge dimensions neither overflow nor allocate the voxels.
+  let bytes = header(BINARY_VERSION, 1, [u32::MAX, u32::MAX, u32::MAX]);
+  assert!(read_binary(&mut bytes.as_slice()).is_err());
+  let bytes = header(BINARY_VERSION, 0, [1 << 20, 1 << 20, 1 << 10]);
+  assert!(read_binary(&mut bytes.as_slice()).is_err());
+
+  let bytes = header(BINARY_VERSION + 1, 0, [1, 1, 1]);
+  assert!(read_binary(&mut bytes.as_slice()).is_err());
+  let bytes = header(BINARY_VERSION, 7, [1, 1, 1]);
+  assert!(read_binary(&mut bytes.as_slice()).is_err());
+}
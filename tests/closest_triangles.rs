@@ -0,0 +1,64 @@
+//! Store the closest triangles of the voxels and map them back to the primitives.
+
+use hala_sdf_baker::volume::*;
+
+/// Create the closest triangles of two primitives in a small grid.
+/// return: The closest triangle volume.
+fn two_primitive_volume() -> ClosestTriangleVolume {
+  let dimensions = [4, 3, 2];
+  let num_of_voxels = 24;
+  ClosestTriangleVolume {
+    dimensions,
+    center: [0.5, -1.0, 2.0],
+    extents: [2.0, 1.5, 1.0],
+    voxel_size: 1.0,
+    ranges: vec![
+      TriangleRange { primitive_index: 3, first_triangle: 0, num_of_triangles: 5 },
+      TriangleRange { primitive_index: 4, first_triangle: 5, num_of_triangles: 2 },
+    ],
+    triangles: (0..num_of_voxels).map(|i| if i % 5 == 4 { NO_CLOSEST_TRIANGLE } else { i % 7 }).collect(),
+    barycentrics: (0..num_of_voxels).map(|i| {
+      let t = i as f32 / num_of_voxels as f32;
+      [1.0 - t, t * 0.25, t * 0.75]
+    }).collect(),
+  }
+}
+
+#[test]
+fn closest_triangles_round_trip() {
+  let volume = two_primitive_volume();
+  volume.validate().unwrap();
+
+  let mut bytes = Vec::new();
+  write_closest_triangles(&mut bytes, &volume).unwrap();
+  assert_eq!(bytes.len(), 52 + 2 * 12 + 24 * 16);
+  assert_eq!(read_closest_triangles(&mut bytes.as_slice()).unwrap(), volume);
+
+  // The truncated voxels are rejected.
+  bytes.truncate(bytes.len() - 4);
+  assert!(read_closest_triangles(&mut bytes.as_slice()).is_err());
+
+  // The corrupt count of the ranges fails at the end of the data instead of allocating it up front.
+  assert_eq!(u32::from_le_bytes(bytes[48..52].try_into().unwrap()), 2);
+  bytes[48..52].copy_from_slice(&u32::MAX.to_le_bytes());
+  assert!(read_closest_triangles(&mut bytes.as_slice()).is_err());
+}
+
+#[test]
+fn closest_triangles_map_to_the_primitives() {
+  let mut volume = two_primitive_volume();
+  assert_eq!(volume.num_of_triangles(), 7);
+  assert_eq!(volume.locate(0), Some((3, 0)));
+  assert_eq!(volume.locate(4), Some((3, 4)));
+  assert_eq!(volume.locate(5), Some((4, 0)));
+  assert_eq!(volume.locate(6), Some((4, 1)));
+  assert_eq!(volume.locate(7), None);
+  assert_eq!(volume.locate(NO_CLOSEST_TRIANGLE), None);
+
+  // The triangles out of the ranges and the gaps between the ranges are invalid.
+  volume.triangles[0] = 7;
+  assert!(volume.validate().is_err());
+  volume.triangles[0] = 0;
+  volume.ranges[1].first_triangle = 6;
+  assert!(volume.validate().is_err());
+}
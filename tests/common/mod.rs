@@ -0,0 +1,80 @@
+//! The volume and mesh fixtures shared by the tests.
+
+#![allow(dead_code)]
+
+use std::f32::consts::PI;
+
+use hala_sdf_baker::mesh::TriangleMesh;
+use hala_sdf_baker::volume::*;
+
+/// The distance to a sphere in the center of the grid, in voxels.
+/// param dimensions: The dimensions of the grid.
+/// param radius: The radius of the sphere in voxels.
+/// param p: The position in voxels from the min corner of the grid.
+/// return: The signed distance.
+pub fn sphere_distance(dimensions: [u32; 3], radius: f32, p: [f32; 3]) -> f32 {
+  let c = dimensions.map(|d| d as f32 * 0.5);
+  ((p[0] - c[0]).powi(2) + (p[1] - c[1]).powi(2) + (p[2] - c[2]).powi(2)).sqrt() - radius
+}
+
+/// Create the volume in voxels centered at the origin, with the values of the voxel centers.
+/// param dimensions: The dimensions of the grid.
+/// param is_sdf: Whether the values are signed.
+/// param distance: The distance at the position in voxels from the min corner of the grid.
+/// return: The volume.
+pub fn voxel_volume<F: Fn([f32; 3]) -> f32>(dimensions: [u32; 3], is_sdf: bool, distance: F) -> SDFVolume {
+  let mut data = Vec::with_capacity(dimensions.iter().map(|d| *d as usize).product());
+  for z in 0..dimensions[2] {
+    for y in 0..dimensions[1] {
+      for x in 0..dimensions[0] {
+        data.push(distance([x, y, z].map(|v| v as f32 + 0.5)));
+      }
+    }
+  }
+  SDFVolume {
+    dimensions,
+    center: [0.0, 0.0, 0.0],
+    extents: dimensions.map(|d| d as f32 * 0.5),
+    voxel_size: 1.0,
+    is_sdf,
+    surface_offset: 0.0,
+    unit: DistanceUnit::Voxel,
+    data,
+    mips: Vec::new(),
+    gradients: Vec::new(),
+  }
+}
+
+/// Create the SDF of a sphere in voxels.
+/// param dimensions: The dimensions of the grid.
+/// param radius: The radius of the sphere in voxels.
+/// return: The volume.
+pub fn sphere_volume(dimensions: [u32; 3], radius: f32) -> SDFVolume {
+  voxel_volume(dimensions, true, |p| sphere_distance(dimensions, radius, p))
+}
+
+/// Create the UV sphere at the origin.
+/// The first ring of the triangles is around the +Y pole.
+/// param radius: The radius.
+/// param segments: The number of segments around the Y axis, the half of them from the pole to the pole.
+/// return: The triangle mesh.
+pub fn uv_sphere(radius: f32, segments: u32) -> TriangleMesh {
+  let rings = segments / 2;
+  let mut positions = Vec::new();
+  for i in 0..=rings {
+    let theta = PI * i as f32 / rings as f32;
+    for j in 0..segments {
+      let phi = 2.0 * PI * j as f32 / segments as f32;
+      positions.push([radius * theta.sin() * phi.cos(), radius * theta.cos(), -radius * theta.sin() * phi.sin()]);
+    }
+  }
+  let mut indices = Vec::new();
+  for i in 0..rings {
+    for j in 0..segments {
+      let a = i * segments + j;
+      let b = i * segments + (j + 1) % segments;
+      indices.extend_from_slice(&[a, a + segments, b, b, a + segments, b + segments]);
+    }
+  }
+  TriangleMesh::new(positions, indices)
+}
@@ -0,0 +1,241 @@
+//! Fill the exterior of the open shells and the spiral corridors, and compare the signs with the exact ones and the GPU.
+
+mod common;
+
+use std::collections::VecDeque;
+
+use glam::Vec3;
+
+use hala_renderer::renderer::HalaRendererTrait;
+use hala_renderer::scene::HalaBounds;
+
+use hala_sdf_baker::baker::SDFBaker;
+use hala_sdf_baker::baker::cpu::SDFCPUBaker;
+use hala_sdf_baker::baker::cpu::flood_fill::*;
+use hala_sdf_baker::baker::settings::{
+  SDFBakerSettings,
+  SignMode,
+  BakeBackend,
+};
+use hala_sdf_baker::mesh::TriangleMesh;
+
+use common::*;
+
+/// Create the unit sphere with the hole of the first ring of the triangles around the +Y pole.
+/// The hole is about 0.2 in radius.
+/// return: The triangle mesh.
+fn open_sphere() -> TriangleMesh {
+  let mut mesh = uv_sphere(1.0, 32);
+  mesh.indices.drain(..32 * 6);
+  mesh
+}
+
+/// Get the flood fill settings with the box fitted to the mesh and a margin, so the dilated walls stay off the border.
+/// param mesh: The triangle mesh.
+/// param dilation: The fill dilation.
+/// return: The settings.
+fn fill_settings(mesh: &TriangleMesh, dilation: i32) -> SDFBakerSettings {
+  let (min, max) = mesh.bounds().unwrap();
+  let min = Vec3::from_array(min);
+  let max = Vec3::from_array(max);
+  let mut settings = SDFBakerSettings {
+    max_resolution: 32,
+    sign_mode: SignMode::FloodFill,
+    fill_dilation: dilation,
+    ..Default::default()
+  };
+  settings.fit_box_to_bounds(&HalaBounds {
+    center: ((min + max) * 0.5).to_array(),
+    extents: ((max - min) * 0.625).to_array(),
+  });
+  settings.snap_box_to_bounds();
+  settings
+}
+
+/// Get the exact distance of the unit sphere at each voxel center, in voxels.
+/// param settings: The settings.
+/// return: The distances in X, Y, Z order.
+fn unit_sphere_distances(settings: &SDFBakerSettings) -> Vec<f32> {
+  let dimensions = settings.estimate_grid_size();
+  let bounds = settings.get_bounds();
+  let max_size = settings.actual_size.iter().fold(0.0, |a: f32, b| a.max(*b));
+  let voxel_size = max_size / *dimensions.iter().max().unwrap() as f32;
+  let min_corner = Vec3::from_array(bounds.center) - Vec3::from_array(bounds.extents);
+  let mut distances = Vec::new();
+  for z in 0..dimensions[2] {
+    for y in 0..dimensions[1] {
+      for x in 0..dimensions[0] {
+        let p = min_corner + (Vec3::new(x as f32, y as f32, z as f32) + Vec3::splat(0.5)) * voxel_size;
+        distances.push((p.length() - 1.0) / voxel_size);
+      }
+    }
+  }
+  distances
+}
+
+/// Get the inside of each voxel of the flood fill.
+/// param fill: The flood fill.
+/// return: The inside in X, Y, Z order.
+fn inside_voxels(fill: &FloodFill) -> Vec<bool> {
+  let [dim_x, dim_y, dim_z] = fill.dimensions.map(|d| d as i32);
+  (0..dim_z)
+    .flat_map(|z| (0..dim_y).flat_map(move |y| (0..dim_x).map(move |x| [x, y, z])))
+    .map(|coord| fill.is_inside(coord))
+    .collect()
+}
+
+/// Fill the exterior from the border by the breadth first search of the 6 neighbors.
+/// param dimensions: The dimensions of the voxels.
+/// param walls: The walls in X, Y, Z order.
+/// return: Whether each voxel is reached.
+fn breadth_first_fill(dimensions: [u32; 3], walls: &[bool]) -> Vec<bool> {
+  let [dim_x, dim_y, dim_z] = dimensions.map(|d| d as i32);
+  let index = |x: i32, y: i32, z: i32| ((z * dim_y + y) * dim_x + x) as usize;
+  let mut is_reached = vec![false; walls.len()];
+  let mut queue = VecDeque::new();
+  for z in 0..dim_z {
+    for y in 0..dim_y {
+      for x in 0..dim_x {
+        let is_border = x == 0 || y == 0 || z == 0 || x == dim_x - 1 || y == dim_y - 1 || z == dim_z - 1;
+        if is_border && !walls[index(x, y, z)] {
+          is_reached[index(x, y, z)] = true;
+          queue.push_back([x, y, z]);
+        }
+      }
+    }
+  }
+  while let Some([x, y, z]) = queue.pop_front() {
+    for [dx, dy, dz] in [[-1, 0, 0], [1, 0, 0], [0, -1, 0], [0, 1, 0], [0, 0, -1], [0, 0, 1]] {
+      let (nx, ny, nz) = (x + dx, y + dy, z + dz);
+      if nx < 0 || ny < 0 || nz < 0 || nx >= dim_x || ny >= dim_y || nz >= dim_z {
+        continue;
+      }
+      let i = index(nx, ny, nz);
+      if !walls[i] && !is_reached[i] {
+        is_reached[i] = true;
+        queue.push_back([nx, ny, nz]);
+      }
+    }
+  }
+  is_reached
+}
+
+#[test]
+fn test_open_shell() {
+  let mesh = open_sphere();
+  let baker = SDFCPUBaker::new(&mesh);
+
+  // The exterior leaks in through the hole, so nothing is inside.
+  let settings = fill_settings(&mesh, 0);
+  let fill = baker.flood_fill(&settings);
+  assert!(fill.states.iter().all(|s| *s != FillState::Open));
+  assert!(inside_voxels(&fill).iter().all(|is_inside| !is_inside));
+
+  // The dilation closes the hole. The thick walls are judged along the axes,
+  // so only the voxels within 1.5 voxels of the surface may differ from the exact sphere.
+  let settings = fill_settings(&mesh, 3);
+  let fill = baker.flood_fill(&settings);
+  let inside = inside_voxels(&fill);
+  for (i, distance) in unit_sphere_distances(&settings).iter().enumerate() {
+    if distance.abs() > 1.5 {
+      assert_eq!(inside[i], *distance < 0.0, "The voxel {} at {} voxels from the sphere.", i, distance);
+    }
+  }
+
+  // The CPU bake takes the sign from the flood fill.
+  let data = baker.bake_data(&settings, true).unwrap();
+  assert!(data.iter().zip(inside.iter()).all(|(d, is_inside)| (*d < 0.0) == *is_inside));
+}
+
+#[test]
+fn test_walls() {
+  // The walls of the closed sphere are on both sides, not all inside.
+  let mesh = uv_sphere(1.0, 32);
+  let settings = fill_settings(&mesh, 0);
+  let fill = SDFCPUBaker::new(&mesh).flood_fill(&settings);
+  let inside = inside_voxels(&fill);
+  let distances = unit_sphere_distances(&settings);
+
+  let mut num_of_inside_walls = 0;
+  let mut num_of_outside_walls = 0;
+  for (i, state) in fill.states.iter().enumerate() {
+    if *state == FillState::Wall {
+      if inside[i] {
+        num_of_inside_walls += 1;
+      } else {
+        num_of_outside_walls += 1;
+      }
+      assert!(distances[i].abs() < 1.0, "The wall voxel {} is {} voxels from the sphere.", i, distances[i]);
+    } else {
+      assert_eq!(inside[i], distances[i] < 0.0, "The voxel {} at {} voxels from the sphere.", i, distances[i]);
+    }
+  }
+  assert!(num_of_inside_walls > 0 && num_of_outside_walls > 0, "{} walls inside and {} outside.", num_of_inside_walls, num_of_outside_walls);
+}
+
+#[test]
+fn test_spiral() {
+  // The square spiral of the walls with the 1 voxel corridor between the lids, open at the outer end.
+  let dimensions = [48, 48, 5];
+  let index = |x: i32, y: i32, z: i32| ((z * 48 + y) * 48 + x) as usize;
+  let mut walls = vec![false; 48 * 48 * 5];
+  let (mut x, mut y) = (24, 24);
+  let directions = [(1, 0), (0, 1), (-1, 0), (0, -1)];
+  for segment in 0..40 {
+    let (dx, dy) = directions[segment % 4];
+    for _ in 0..2 * (segment / 2 + 1) {
+      walls[index(x, y, 2)] = true;
+      x += dx;
+      y += dy;
+    }
+  }
+  for y in 2..46 {
+    for x in 2..46 {
+      walls[index(x, y, 1)] = true;
+      walls[index(x, y, 3)] = true;
+    }
+  }
+
+  // The fill goes on until the whole corridor is reached, far more than the 8 rounds of a batch.
+  for connectivity in [6, 26] {
+    let fill = FloodFill::new(dimensions, &walls, connectivity, 0);
+    let expected = breadth_first_fill(dimensions, &walls);
+    for (i, state) in fill.states.iter().enumerate() {
+      assert_eq!(*state == FillState::Exterior, expected[i], "The voxel {} with the {} neighbors.", i, connectivity);
+    }
+    assert!(fill.num_of_rounds > FLOOD_FILL_SWEEP_ROUNDS, "The fill converges in {} rounds.", fill.num_of_rounds);
+    assert!(fill.num_of_rounds < FLOOD_FILL_MAX_SWEEP_ROUNDS);
+  }
+}
+
+#[test]
+#[ignore = "It needs a Vulkan device and the compiled shaders."]
+fn test_gpu_matches_cpu() {
+  let gpu_req = hala_gfx::HalaGPURequirements {
+    width: 1024,
+    height: 768,
+    version: (1, 3, 0),
+    require_depth: true,
+    ..Default::default()
+  };
+  let mut baker = SDFBaker::new("SDF Baker Test", &gpu_req, None).unwrap();
+  baker.settings.backend = BakeBackend::Gpu;
+  baker.settings.sign_mode = SignMode::FloodFill;
+  baker.settings.fill_dilation = 3;
+  baker.settings.max_resolution = 32;
+  baker.set_triangle_mesh("open_sphere", open_sphere());
+
+  baker.bake_sdf().unwrap();
+  let diff = baker.compare_with_cpu().unwrap();
+  baker.wait_idle().unwrap();
+
+  // The conservative rasterization may mark a few more walls than the CPU overlap test.
+  let num_of_voxels = diff.difference.data.len();
+  assert!(
+    diff.sign_flips.len() * 100 <= num_of_voxels,
+    "{} of {} voxels flip the sign, the first at {:?}.",
+    diff.sign_flips.len(),
+    num_of_voxels,
+    diff.sign_flips.first(),
+  );
+}
@@ -0,0 +1,61 @@
+//! Compute the gradients of the volumes and pack them with the distances as RGBA textures.
+
+mod common;
+
+use hala_sdf_baker::volume::*;
+
+use common::*;
+
+#[test]
+fn gradients_point_away_from_the_sphere() {
+  let dimensions = [24, 20, 16];
+  let mut volume = sphere_volume(dimensions, 5.0);
+  volume.build_gradients().unwrap();
+  volume.validate().unwrap();
+
+  let c = dimensions.map(|d| d as f32 * 0.5);
+  for z in 1..dimensions[2] - 1 {
+    for y in 1..dimensions[1] - 1 {
+      for x in 1..dimensions[0] - 1 {
+        let g = volume.gradients[volume.index(x, y, z)];
+        let p = [x, y, z].map(|v| v as f32 + 0.5);
+        let r = normalize_gradient([p[0] - c[0], p[1] - c[1], p[2] - c[2]]);
+        let cos = g[0] * r[0] + g[1] * r[1] + g[2] * r[2];
+        assert!(cos > 0.95, "The gradient {:?} of the voxel {:?} is off the radial direction {:?}.", g, [x, y, z], r);
+      }
+    }
+  }
+}
+
+#[test]
+fn flat_voxels_have_no_gradient() {
+  let gradients = central_difference_gradients([3, 1, 2], &[1.0; 6]);
+  assert!(gradients.iter().all(|g| *g == [0.0; 3]));
+  // A single voxel along an axis has no difference on it.
+  let gradients = central_difference_gradients([2, 1, 1], &[0.0, 2.0]);
+  assert_eq!(gradients, vec![[1.0, 0.0, 0.0]; 2]);
+}
+
+#[test]
+fn gradients_round_trip_as_rgba() {
+  let mut volume = sphere_volume([20, 17, 9], 5.0);
+  volume.build_gradients().unwrap();
+  volume.build_mips().unwrap();
+
+  let mut bytes = Vec::new();
+  write_ktx2(&mut bytes, &volume, ValueEncoding::F32, volume.remap_range()).unwrap();
+  assert_eq!(read_ktx2(&bytes).unwrap(), volume);
+
+  let mut bytes = Vec::new();
+  write_dds(&mut bytes, &volume, ValueEncoding::F32, volume.remap_range()).unwrap();
+  assert_eq!(read_dds(&bytes).unwrap(), volume);
+
+  // The normalized encodings map the gradients from [-1, 1].
+  let mut bytes = Vec::new();
+  write_ktx2(&mut bytes, &volume, ValueEncoding::Unorm8, volume.remap_range()).unwrap();
+  let unorm = read_ktx2(&bytes).unwrap();
+  for (a, b) in unorm.gradients.iter().flatten().zip(volume.gradients.iter().flatten()) {
+    assert!((a - b).abs() <= 1.0 / 255.0 + 1e-6);
+  }
+  assert_eq!(unorm.mips.len(), volume.mips.len());
+}
@@ -0,0 +1,165 @@
+//! Extract the isosurfaces of the analytic volumes and write them in the mesh formats read back by the readers.
+
+mod common;
+
+use std::collections::HashMap;
+use std::f32::consts::PI;
+
+use glam::Vec3;
+
+use hala_sdf_baker::mesh::*;
+
+use common::*;
+
+/// Check that each directed edge is used once and its opposite once,
+/// so the mesh is closed and all triangles have the same orientation.
+/// param mesh: The triangle mesh.
+fn assert_closed(mesh: &TriangleMesh) {
+  let mut edges = HashMap::new();
+  for triangle in mesh.indices.chunks_exact(3) {
+    for k in 0..3 {
+      *edges.entry((triangle[k], triangle[(k + 1) % 3])).or_insert(0) += 1;
+    }
+  }
+  for (&(a, b), &count) in edges.iter() {
+    assert_eq!(count, 1, "The edge ({}, {}) is used {} times in the same direction.", a, b, count);
+    assert_eq!(edges.get(&(b, a)), Some(&1), "The edge ({}, {}) has no opposite, the mesh is open.", a, b);
+  }
+}
+
+/// Get the volume enclosed by the mesh, positive if the triangles face outside.
+/// param mesh: The triangle mesh.
+/// return: The signed volume.
+fn signed_volume(mesh: &TriangleMesh) -> f32 {
+  (0..mesh.num_of_triangles())
+    .map(|i| {
+      let [a, b, c] = mesh.triangle(i).map(Vec3::from_array);
+      a.dot(b.cross(c)) / 6.0
+    })
+    .sum()
+}
+
+/// Get the volume of the ball.
+/// param radius: The radius.
+/// return: The volume.
+fn ball_volume(radius: f32) -> f32 {
+  4.0 / 3.0 * PI * radius.powi(3)
+}
+
+/// Get the positions of all triangles, which do not depend on the vertex sharing.
+/// param mesh: The triangle mesh.
+/// return: The triangles.
+fn triangles(mesh: &TriangleMesh) -> Vec<[[f32; 3]; 3]> {
+  (0..mesh.num_of_triangles()).map(|i| mesh.triangle(i)).collect()
+}
+
+#[test]
+fn test_sdf_sphere() {
+  // The volume is in voxels with the sphere at the origin.
+  let volume = sphere_volume([24, 24, 24], 8.0);
+  let mesh = extract_isosurface(&volume, 0.0).unwrap();
+
+  assert!(mesh.num_of_triangles() > 0);
+  assert_closed(&mesh);
+  let enclosed = signed_volume(&mesh);
+  assert!((enclosed / ball_volume(8.0) - 1.0).abs() < 0.03, "The enclosed volume is {}.", enclosed);
+  for p in mesh.positions.iter() {
+    let radius = Vec3::from_array(*p).length();
+    assert!((radius - 8.0).abs() < 0.5, "The vertex {:?} is {} from the center.", p, radius);
+  }
+  for i in 0..mesh.num_of_triangles() {
+    let [a, b, c] = mesh.triangle(i).map(Vec3::from_array);
+    assert!((b - a).cross(c - a).dot(a + b + c) > 0.0, "The triangle {} faces inside.", i);
+  }
+
+  // The iso value moves the surface.
+  let enclosed = signed_volume(&extract_isosurface(&volume, 2.0).unwrap());
+  assert!((enclosed / ball_volume(10.0) - 1.0).abs() < 0.03, "The enclosed volume at the iso value 2 is {}.", enclosed);
+}
+
+#[test]
+fn test_udf_shell() {
+  let dimensions = [24, 24, 24];
+  let volume = voxel_volume(dimensions, false, |p| sphere_distance(dimensions, 8.0, p).abs());
+
+  // The shell has an outer sphere facing outside and an inner sphere facing the center.
+  let mesh = extract_isosurface(&volume, 1.5).unwrap();
+  assert_closed(&mesh);
+  let enclosed = signed_volume(&mesh);
+  let expected = ball_volume(9.5) - ball_volume(6.5);
+  assert!((enclosed / expected - 1.0).abs() < 0.03, "The enclosed volume is {}, not {}.", enclosed, expected);
+  for i in 0..mesh.num_of_triangles() {
+    let [a, b, c] = mesh.triangle(i).map(Vec3::from_array);
+    let centroid = (a + b + c) / 3.0;
+    let is_outer = centroid.length() > 8.0;
+    assert_eq!((b - a).cross(c - a).dot(centroid) > 0.0, is_outer, "The triangle {} faces the wrong side.", i);
+  }
+
+  // The UDF never goes below 0.
+  for iso_value in [0.0, -1.0] {
+    let error = extract_isosurface(&volume, iso_value).unwrap_err();
+    assert!(error.to_string().contains("positive iso value"), "{}", error);
+  }
+}
+
+#[test]
+fn test_round_trips() {
+  let mesh = extract_isosurface(&sphere_volume([16, 12, 10], 4.0), 0.0).unwrap();
+  assert!(mesh.num_of_triangles() > 0);
+
+  let mut bytes = Vec::new();
+  write_obj(&mut bytes, &mesh).unwrap();
+  assert_eq!(read_obj(&mut bytes.as_slice()).unwrap(), mesh);
+
+  let mut bytes = Vec::new();
+  write_ply(&mut bytes, &mesh).unwrap();
+  assert_eq!(read_ply(&mut bytes.as_slice()).unwrap(), mesh);
+
+  // The STL does not share the vertices.
+  let mut bytes = Vec::new();
+  write_stl(&mut bytes, &mesh).unwrap();
+  assert_eq!(triangles(&read_stl(&mut bytes.as_slice()).unwrap()), triangles(&mesh));
+
+  // The GLB is read by the glTF crate.
+  let mut bytes = Vec::new();
+  write_glb(&mut bytes, &mesh).unwrap();
+  let gltf = gltf::Gltf::from_slice(&bytes).unwrap();
+  let blob = gltf.blob.as_deref().unwrap();
+  let primitive = gltf.meshes().next().unwrap().primitives().next().unwrap();
+  let reader = primitive.reader(|_| Some(blob));
+  assert_eq!(reader.read_positions().unwrap().collect::<Vec<_>>(), mesh.positions);
+  assert_eq!(reader.read_indices().unwrap().into_u32().collect::<Vec<_>>(), mesh.indices);
+  let (min, max) = mesh.bounds().unwrap();
+  let bounds = primitive.bounding_box();
+  assert_eq!((bounds.min, bounds.max), (min, max));
+}
+
+#[test]
+fn test_scene_glb() {
+  let mesh = extract_isosurface(&sphere_volume([16, 12, 10], 4.0), 0.0).unwrap();
+  let mut bytes = Vec::new();
+  write_scene_glb(&mut bytes, &mesh, "sphere").unwrap();
+  let gltf = gltf::Gltf::from_slice(&bytes).unwrap();
+  let blob = gltf.blob.as_deref().unwrap();
+
+  // The mesh has the smooth unit normals facing outside and the material.
+  let node = gltf.nodes().find(|n| n.mesh().is_some()).unwrap();
+  assert_eq!(node.name(), Some("sphere"));
+  let primitive = node.mesh().unwrap().primitives().next().unwrap();
+  assert!(primitive.material().index().is_some());
+  let reader = primitive.reader(|_| Some(blob));
+  assert_eq!(reader.read_positions().unwrap().collect::<Vec<_>>(), mesh.positions);
+  assert_eq!(reader.read_indices().unwrap().into_u32().collect::<Vec<_>>(), mesh.indices);
+  for (p, n) in reader.read_positions().unwrap().zip(reader.read_normals().unwrap()) {
+    let (p, n) = (Vec3::from_array(p), Vec3::from_array(n));
+    assert!((n.length() - 1.0).abs() < 1e-5);
+    assert!(n.dot(p.normalize()) > 0.9, "The normal {} at {} does not face outside.", n, p);
+  }
+
+  // The camera looks at the center of the mesh.
+  let node = gltf.nodes().find(|n| n.camera().is_some()).unwrap();
+  let (translation, rotation, _) = node.transform().decomposed();
+  let forward = glam::Quat::from_array(rotation) * Vec3::NEG_Z;
+  let to_center = -Vec3::from_array(translation).normalize();
+  assert!(forward.dot(to_center) > 0.999, "The camera looks at {}, not {}.", forward, to_center);
+}
@@ -0,0 +1,189 @@
+//! Read the OBJ, STL and PLY meshes, the XYZ point clouds and the glTF vertex colors without the GPU, written by the exporters or by hand.
+
+use hala_sdf_baker::mesh::*;
+
+/// Create the axis aligned box with the unequal sides.
+/// return: The triangle mesh.
+fn cuboid() -> TriangleMesh {
+  let positions = (0..8)
+    .map(|i| [
+      if i & 1 == 0 { -1.0 } else { 1.0 },
+      if i & 2 == 0 { -0.5 } else { 0.5 },
+      if i & 4 == 0 { -0.75 } else { 0.75 },
+    ])
+    .collect();
+  let indices = vec![
+    0, 2, 1, 1, 2, 3, // -Z
+    4, 5, 6, 5, 7, 6, // +Z
+    0, 1, 4, 1, 5, 4, // -Y
+    2, 6, 3, 3, 6, 7, // +Y
+    0, 4, 2, 2, 4, 6, // -X
+    1, 3, 5, 3, 7, 5, // +X
+  ];
+  TriangleMesh::new(positions, indices)
+}
+
+/// Get the positions of all triangles, which do not depend on the vertex sharing.
+/// param mesh: The triangle mesh.
+/// return: The triangles.
+fn triangles(mesh: &TriangleMesh) -> Vec<[[f32; 3]; 3]> {
+  (0..mesh.num_of_triangles()).map(|i| mesh.triangle(i)).collect()
+}
+
+#[test]
+fn test_obj() {
+  let mesh = cuboid();
+  let mut bytes = Vec::new();
+  write_obj(&mut bytes, &mesh).unwrap();
+  assert_eq!(triangles(&read_obj(&mut bytes.as_slice()).unwrap()), triangles(&mesh));
+
+  // The quad with the texture coordinate and normal indices, and a negative index.
+  let text = "v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nvt 0 0\nvn 0 0 1\nf 1/1/1 2/1 3//1 -1\n";
+  let quad = read_obj(&mut text.as_bytes()).unwrap();
+  assert_eq!(quad.indices, vec![0, 1, 2, 0, 2, 3]);
+
+  assert!(read_obj(&mut "v 0 0 0\nf 1 2 3\n".as_bytes()).is_err());
+}
+
+#[test]
+fn test_stl() {
+  let mesh = cuboid();
+  let mut bytes = Vec::new();
+  write_stl(&mut bytes, &mesh).unwrap();
+  assert_eq!(triangles(&read_stl(&mut bytes.as_slice()).unwrap()), triangles(&mesh));
+
+  // The binary file may also begin with "solid".
+  bytes[..5].copy_from_slice(b"solid");
+  assert_eq!(triangles(&read_stl(&mut bytes.as_slice()).unwrap()), triangles(&mesh));
+
+  let text = "solid t\n facet normal 0 0 1\n  outer loop\n   vertex 0 0 0\n   vertex 1 0 0\n   vertex 0 1e0 0\n  endloop\n endfacet\nendsolid t\n";
+  let triangle = read_stl(&mut text.as_bytes()).unwrap();
+  assert_eq!(triangles(&triangle), vec![[[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]]]);
+
+  assert!(read_stl(&mut "not a mesh".as_bytes()).is_err());
+}
+
+#[test]
+fn test_ply() {
+  let mesh = cuboid();
+  let mut bytes = Vec::new();
+  write_ply(&mut bytes, &mesh).unwrap();
+  assert_eq!(read_ply(&mut bytes.as_slice()).unwrap(), mesh);
+
+  // The ASCII quad with the extra properties and elements.
+  let text = concat!(
+    "ply\nformat ascii 1.0\ncomment hand written\n",
+    "element vertex 4\nproperty double x\nproperty float y\nproperty float z\nproperty uchar red\n",
+    "element face 1\nproperty list uchar int vertex_index\nproperty float quality\n",
+    "element edge 1\nproperty int vertex1\nproperty int vertex2\n",
+    "end_header\n",
+    "0 0 0 255\n1 0 0 255\n1 1 0 255\n0 1 0 255\n4 0 1 2 3 0.5\n0 1\n",
+  );
+  let quad = read_ply(&mut text.as_bytes()).unwrap();
+  assert_eq!(quad.indices, vec![0, 1, 2, 0, 2, 3]);
+  assert_eq!(quad.positions[2], [1.0, 1.0, 0.0]);
+
+  // The big-endian triangle with a short property.
+  let mut bytes = concat!(
+    "ply\nformat binary_big_endian 1.0\n",
+    "element vertex 3\nproperty float x\nproperty float y\nproperty float z\nproperty short s\n",
+    "element face 1\nproperty list uchar ushort vertex_indices\n",
+    "end_header\n",
+  ).as_bytes().to_vec();
+  for position in [[0.0f32, 0.0, 0.0], [2.0, 0.0, 0.0], [0.0, 3.0, 0.0]] {
+    for value in position {
+      bytes.extend_from_slice(&value.to_be_bytes());
+    }
+    bytes.extend_from_slice(&7i16.to_be_bytes());
+  }
+  bytes.push(3);
+  for index in [0u16, 1, 2] {
+    bytes.extend_from_slice(&index.to_be_bytes());
+  }
+  let triangle = read_ply(&mut bytes.as_slice()).unwrap();
+  assert_eq!(triangles(&triangle), vec![[[0.0, 0.0, 0.0], [2.0, 0.0, 0.0], [0.0, 3.0, 0.0]]]);
+
+  bytes.pop();
+  assert!(read_ply(&mut bytes.as_slice()).is_err());
+
+  // The negative, float and out of range indices are not truncated to the valid ones.
+  let header = concat!(
+    "ply\nformat ascii 1.0\n",
+    "element vertex 3\nproperty float x\nproperty float y\nproperty float z\n",
+    "element face 1\nproperty list uchar double vertex_indices\n",
+    "end_header\n",
+    "0 0 0\n1 0 0\n0 1 0\n",
+  );
+  for face in ["3 0 1 -1", "3 0 1.5 2", "3 0 1 4294967296", "-3 0 1 2"] {
+    let text = format!("{}{}\n", header, face);
+    let error = read_ply(&mut text.as_bytes()).unwrap_err();
+    assert!(error.to_string().contains("PLY"), "{}: {}", face, error);
+  }
+  assert_eq!(read_ply(&mut format!("{}3 0 1 2\n", header).as_bytes()).unwrap().indices, vec![0, 1, 2]);
+}
+
+#[test]
+fn test_load_by_extension() {
+  let mesh = cuboid();
+  let dir = std::env::temp_dir();
+  for format in [MeshFormat::Obj, MeshFormat::Ply, MeshFormat::Stl] {
+    let path = dir.join(format!("hala_sdf_baker_loader.{}", format.name()));
+    mesh.save(&path, format).unwrap();
+    assert_eq!(triangles(&TriangleMesh::load(&path).unwrap()), triangles(&mesh));
+    std::fs::remove_file(&path).unwrap();
+  }
+}
+
+#[test]
+fn test_xyz() {
+  let text = "# scanned\n0 0 0 0 0 1\n\n1.5,2,-3\n// comment\n4 5 6 255 0 0\n";
+  let points = read_xyz(&mut text.as_bytes()).unwrap();
+  assert_eq!(points.positions, vec![[0.0, 0.0, 0.0], [1.5, 2.0, -3.0], [4.0, 5.0, 6.0]]);
+  assert_eq!(points.bounds(), Some(([0.0, 0.0, -3.0], [4.0, 5.0, 6.0])));
+  assert_eq!(points.get_position_bytes().len(), 36);
+
+  assert!(read_xyz(&mut "1 2\n".as_bytes()).is_err());
+}
+
+#[test]
+fn test_gltf_vertex_colors() {
+  // One triangle with the normalized u16 RGBA colors, then the same triangle without COLOR_0.
+  let mut bin = Vec::new();
+  for v in [0.0f32, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0] {
+    bin.extend_from_slice(&v.to_le_bytes());
+  }
+  for v in [65535u16, 0, 0, 65535, 0, 65535, 0, 32768, 0, 0, 65535, 0] {
+    bin.extend_from_slice(&v.to_le_bytes());
+  }
+  let json = format!(
+    concat!(
+      "{{\"asset\":{{\"version\":\"2.0\"}},",
+      "\"meshes\":[{{\"primitives\":[",
+      "{{\"attributes\":{{\"POSITION\":0,\"COLOR_0\":1}}}},",
+      "{{\"attributes\":{{\"POSITION\":0}}}}]}}],",
+      "\"accessors\":[",
+      "{{\"bufferView\":0,\"componentType\":5126,\"count\":3,\"type\":\"VEC3\",\"min\":[0,0,0],\"max\":[1,1,0]}},",
+      "{{\"bufferView\":1,\"componentType\":5123,\"normalized\":true,\"count\":3,\"type\":\"VEC4\"}}],",
+      "\"bufferViews\":[",
+      "{{\"buffer\":0,\"byteOffset\":0,\"byteLength\":36}},",
+      "{{\"buffer\":0,\"byteOffset\":36,\"byteLength\":24}}],",
+      "\"buffers\":[{{\"uri\":\"hala_sdf_baker_colors.bin\",\"byteLength\":{}}}]}}",
+    ),
+    bin.len(),
+  );
+  let dir = std::env::temp_dir();
+  let bin_path = dir.join("hala_sdf_baker_colors.bin");
+  let path = dir.join("hala_sdf_baker_colors.gltf");
+  std::fs::write(&bin_path, &bin).unwrap();
+  std::fs::write(&path, json).unwrap();
+
+  let colors = read_gltf_vertex_colors(&path).unwrap();
+  assert_eq!(colors.len(), 2);
+  assert_eq!(colors[0].len(), 3);
+  assert_eq!(colors[0][0], [1.0, 0.0, 0.0, 1.0]);
+  assert!((colors[0][1][3] - 0.5).abs() < 1e-4);
+  assert_eq!(colors[0][2], [0.0, 0.0, 1.0, 0.0]);
+  assert!(colors[1].is_empty());
+  std::fs::remove_file(&bin_path).unwrap();
+  std::fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,66 @@
+//! Build the conservative mip chains of the volumes and store them in the file formats.
+
+mod common;
+
+use hala_sdf_baker::volume::*;
+
+use common::*;
+
+#[test]
+fn mip_chain_goes_down_to_one_voxel() {
+  assert_eq!(num_of_mip_levels([64, 32, 48]), 7);
+  assert_eq!(num_of_mip_levels([1, 1, 1]), 1);
+  assert_eq!(mip_dimensions([64, 32, 48], 3), [8, 4, 6]);
+  assert_eq!(mip_dimensions([64, 32, 48], 6), [1, 1, 1]);
+
+  let mut volume = sphere_volume([37, 20, 9], 6.0);
+  volume.build_mips().unwrap();
+  assert_eq!(volume.num_of_mip_levels(), num_of_mip_levels([37, 20, 9]));
+  assert_eq!(volume.mips.last().unwrap().len(), 1);
+  volume.validate().unwrap();
+}
+
+#[test]
+fn mip_voxels_are_lower_bounds_in_their_cells() {
+  let dimensions = [45, 32, 27];
+  let radius = 9.0;
+  let mut volume = sphere_volume(dimensions, radius);
+  volume.build_mips().unwrap();
+
+  for (index, mip) in volume.mips.iter().enumerate() {
+    let mip_dims = mip_dimensions(dimensions, index as u32 + 1);
+    let cell = [0, 1, 2].map(|i| dimensions[i] as f32 / mip_dims[i] as f32);
+    for z in 0..mip_dims[2] {
+      for y in 0..mip_dims[1] {
+        for x in 0..mip_dims[0] {
+          let value = mip[(x + mip_dims[0] * (y + mip_dims[1] * z)) as usize];
+          // Sample the corners, the center and the points between them in the cell.
+          for s in 0..27 {
+            let t = [s % 3, s / 3 % 3, s / 9].map(|v| v as f32 * 0.5);
+            let p = [0, 1, 2].map(|i| ([x, y, z][i] as f32 + t[i]) * cell[i]);
+            let distance = sphere_distance(dimensions, radius, p);
+            assert!(value <= distance + 1e-4, "The level {} voxel {:?} is {} over the distance {}.", index + 1, [x, y, z], value, distance);
+          }
+        }
+      }
+    }
+  }
+}
+
+#[test]
+fn mips_round_trip_in_the_formats() {
+  let mut volume = sphere_volume([20, 17, 9], 5.0);
+  volume.build_mips().unwrap();
+
+  let mut bytes = Vec::new();
+  write_binary(&mut bytes, &volume, ValueEncoding::F32).unwrap();
+  assert_eq!(read_binary(&mut bytes.as_slice()).unwrap(), volume);
+
+  let mut bytes = Vec::new();
+  write_ktx2(&mut bytes, &volume, ValueEncoding::F32, volume.remap_range()).unwrap();
+  assert_eq!(read_ktx2(&bytes).unwrap(), volume);
+
+  let mut bytes = Vec::new();
+  write_dds(&mut bytes, &volume, ValueEncoding::F32, volume.remap_range()).unwrap();
+  assert_eq!(read_dds(&bytes).unwrap(), volume);
+}
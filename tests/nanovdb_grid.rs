@@ -0,0 +1,153 @@
+//! Write the NanoVDB float grids and look the voxels up through the written tree.
+
+mod common;
+
+use hala_sdf_baker::volume::*;
+
+use common::*;
+
+/// The size of the file header and the grid meta data before the grid name.
+const FILE_HEADER_SIZE: usize = 16 + 176;
+
+/// The offset of the tree from the grid.
+const TREE_OFFSET: usize = 672;
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+  u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+}
+
+fn read_u64(bytes: &[u8], offset: usize) -> u64 {
+  u64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap())
+}
+
+fn read_f32(bytes: &[u8], offset: usize) -> f32 {
+  f32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+}
+
+fn read_f64(bytes: &[u8], offset: usize) -> f64 {
+  f64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap())
+}
+
+fn read_bit(bytes: &[u8], offset: usize, n: usize) -> bool {
+  (read_u64(bytes, offset + (n >> 6) * 8) >> (n & 63)) & 1 == 1
+}
+
+/// Get the grid of the NanoVDB file.
+/// param bytes: The file content.
+/// return: The grid.
+fn grid(bytes: &[u8]) -> &[u8] {
+  assert_eq!(read_u64(bytes, 0), NANOVDB_MAGIC);
+  assert_eq!(read_u32(bytes, 8), NANOVDB_VERSION);
+  let grid_size = read_u64(bytes, 16) as usize;
+  let name_size = read_u32(bytes, 16 + 136) as usize;
+  &bytes[FILE_HEADER_SIZE + name_size..FILE_HEADER_SIZE + name_size + grid_size]
+}
+
+/// Look up the voxel through the root, the upper, the lower and the leaf nodes.
+/// param grid: The grid.
+/// param ijk: The index coordinate.
+/// return: The value and whether it is active.
+fn lookup(grid: &[u8], ijk: [i32; 3]) -> (f32, bool) {
+  let root = TREE_OFFSET + read_u64(grid, TREE_OFFSET + 24) as usize;
+  let num_of_tiles = read_u32(grid, root + 24) as usize;
+  let background = read_f32(grid, root + 28);
+  let key = ((ijk[2] as u32 >> 12) as u64) | (((ijk[1] as u32 >> 12) as u64) << 21) | (((ijk[0] as u32 >> 12) as u64) << 42);
+  for tile in 0..num_of_tiles {
+    let tile_offset = root + 64 + tile * 32;
+    if read_u64(grid, tile_offset) != key {
+      continue;
+    }
+    let upper = root + read_u64(grid, tile_offset + 8) as usize;
+    let n = ((((ijk[0] & 4095) >> 7) << 10) | (((ijk[1] & 4095) >> 7) << 5) | ((ijk[2] & 4095) >> 7)) as usize;
+    if !read_bit(grid, upper + 32 + 4096, n) {
+      return (read_f32(grid, upper + 8256 + n * 8), read_bit(grid, upper + 32, n));
+    }
+    let lower = upper + read_u64(grid, upper + 8256 + n * 8) as usize;
+    let n = ((((ijk[0] & 127) >> 3) << 8) | (((ijk[1] & 127) >> 3) << 4) | ((ijk[2] & 127) >> 3)) as usize;
+    if !read_bit(grid, lower + 32 + 512, n) {
+      return (read_f32(grid, lower + 1088 + n * 8), read_bit(grid, lower + 32, n));
+    }
+    let leaf = lower + read_u64(grid, lower + 1088 + n * 8) as usize;
+    let n = (((ijk[0] & 7) << 6) | ((ijk[1] & 7) << 3) | (ijk[2] & 7)) as usize;
+    return (read_f32(grid, leaf + 96 + n * 4), read_bit(grid, leaf + 16, n));
+  }
+  (background, false)
+}
+
+/// Create the SDF of a sphere crossing the lower nodes, with the world voxel size and center.
+/// return: The volume.
+fn world_sphere_volume() -> SDFVolume {
+  // The grid spans two lower nodes in Z.
+  let mut volume = voxel_volume([40, 24, 136], true, |p| sphere_distance([40, 24, 256], 11.0, p));
+  volume.center = [1.0, 2.0, 3.0];
+  volume.voxel_size = 0.05;
+  volume.extents = volume.dimensions.map(|d| d as f32 * 0.05 * 0.5);
+  volume
+}
+
+#[test]
+fn nanovdb_grid_has_the_header_and_the_transform() {
+  let volume = world_sphere_volume();
+  let mut bytes = Vec::new();
+  write_nanovdb(&mut bytes, &volume, "sdf", 3.0).unwrap();
+  let grid = grid(&bytes);
+
+  assert_eq!(read_u64(grid, 0), NANOVDB_MAGIC);
+  assert_eq!(read_u64(grid, 32) as usize, grid.len());
+  assert_eq!(&grid[40..44], b"sdf\0");
+  // The float level set.
+  assert_eq!(read_u32(grid, 632), 1);
+  assert_eq!(read_u32(grid, 636), 1);
+
+  // The index (0, 0, 0) is the center of the min voxel.
+  assert!((read_f64(grid, 384) - 0.05).abs() < 1e-7);
+  let min_corner = [0, 1, 2].map(|i| (volume.center[i] - volume.extents[i]) as f64 + 0.025);
+  for (i, min) in min_corner.iter().enumerate() {
+    assert!((read_f64(grid, 528 + i * 8) - min).abs() < 1e-6);
+  }
+
+  // The node counts of the tree match the grid size.
+  let num_of_leaves = read_u32(grid, TREE_OFFSET + 32) as usize;
+  let num_of_lowers = read_u32(grid, TREE_OFFSET + 36) as usize;
+  assert_eq!(num_of_lowers, 2);
+  let leaf_offset = read_u64(grid, TREE_OFFSET) as usize;
+  assert_eq!((TREE_OFFSET + leaf_offset) % 32, 0);
+  assert_eq!(grid.len(), TREE_OFFSET + leaf_offset + num_of_leaves * (96 + 4 * 512));
+
+  assert!(write_nanovdb(&mut Vec::new(), &volume, "sdf", 0.0).is_err());
+}
+
+#[test]
+fn nanovdb_narrow_band_keeps_the_surface_voxels() {
+  let volume = world_sphere_volume();
+  let narrow_band = 3.0;
+  let mut bytes = Vec::new();
+  write_nanovdb(&mut bytes, &volume, "sdf", narrow_band).unwrap();
+  let grid = grid(&bytes);
+
+  let background = narrow_band * volume.voxel_size;
+  let root = TREE_OFFSET + read_u64(grid, TREE_OFFSET + 24) as usize;
+  assert!((read_f32(grid, root + 28) - background).abs() < 1e-6);
+
+  let mut num_of_active = 0u64;
+  for z in 0..volume.dimensions[2] {
+    for y in 0..volume.dimensions[1] {
+      for x in 0..volume.dimensions[0] {
+        let world = volume.get(x, y, z) * volume.world_scale();
+        let (value, is_active) = lookup(grid, [x as i32, y as i32, z as i32]);
+        if world.abs() < background {
+          assert!(is_active, "The voxel {:?} in the band is inactive.", [x, y, z]);
+          assert!((value - world).abs() < 1e-5);
+          num_of_active += 1;
+        } else {
+          assert!(!is_active, "The voxel {:?} out of the band is active.", [x, y, z]);
+          assert_eq!(value, background.copysign(world), "The voxel {:?} has the wrong background sign.", [x, y, z]);
+        }
+      }
+    }
+  }
+  assert!(num_of_active > 0);
+  assert_eq!(read_u64(grid, TREE_OFFSET + 56), num_of_active);
+  // The file header also has the active voxel count.
+  assert_eq!(read_u64(&bytes, 16 + 24), num_of_active);
+}
@@ -0,0 +1,73 @@
+//! Split the volumes into the narrow band bricks and write them in the sparse format.
+
+mod common;
+
+use hala_sdf_baker::volume::*;
+
+use common::*;
+
+#[test]
+fn bricks_keep_the_narrow_band() {
+  let volume = sphere_volume([37, 40, 29], 13.0);
+  let sparse = SparseVolume::from_volume(&volume, 3.0, SPARSE_BRICK_SIZE).unwrap();
+  assert_eq!(sparse.indirection_dimensions, [5, 5, 4]);
+  assert!(sparse.num_of_bricks() > 0);
+  assert!(sparse.num_of_bricks() < sparse.indirection.len());
+  // The bricks in the center of the sphere are inside.
+  assert!(sparse.indirection.contains(&SPARSE_EMPTY_INSIDE));
+  assert!(sparse.indirection.contains(&SPARSE_EMPTY_OUTSIDE));
+
+  let dense = sparse.to_volume();
+  for (a, b) in dense.data.iter().zip(volume.data.iter()) {
+    assert_eq!(*a, b.clamp(-3.0, 3.0));
+  }
+}
+
+#[test]
+fn band_is_in_the_distance_unit() {
+  let mut volume = sphere_volume([32, 32, 32], 8.0);
+  volume.voxel_size = 0.5;
+  volume.convert_unit(DistanceUnit::Normalized).unwrap();
+  let sparse = SparseVolume::from_volume(&volume, 2.0, 4).unwrap();
+  assert!((sparse.band - 2.0 / 32.0).abs() < 1e-6);
+  assert!(sparse.atlas.iter().all(|v| v.abs() <= sparse.band));
+}
+
+#[test]
+fn sparse_round_trip() {
+  let volume = sphere_volume([20, 17, 9], 6.0);
+  let sparse = SparseVolume::from_volume(&volume, 2.5, SPARSE_BRICK_SIZE).unwrap();
+
+  let mut bytes = Vec::new();
+  write_sparse(&mut bytes, &sparse, ValueEncoding::F32).unwrap();
+  assert_eq!(read_sparse(&mut bytes.as_slice()).unwrap(), sparse);
+
+  let mut bytes = Vec::new();
+  write_sparse(&mut bytes, &sparse, ValueEncoding::F16).unwrap();
+  let half = read_sparse(&mut bytes.as_slice()).unwrap();
+  assert_eq!(half.indirection, sparse.indirection);
+  for (a, b) in half.atlas.iter().zip(sparse.atlas.iter()) {
+    assert!((a - b).abs() < 1e-2);
+  }
+
+  assert!(write_sparse(&mut Vec::new(), &sparse, ValueEncoding::Unorm8).is_err());
+
+  // The corrupt count of the bricks fails at the end of the data instead of allocating the atlas up front.
+  let mut bytes = Vec::new();
+  write_sparse(&mut bytes, &sparse, ValueEncoding::F32).unwrap();
+  assert_eq!(u32::from_le_bytes(bytes[84..88].try_into().unwrap()) as usize, sparse.num_of_bricks());
+  bytes[84..88].copy_from_slice(&u32::MAX.to_le_bytes());
+  assert!(read_sparse(&mut bytes.as_slice()).is_err());
+}
+
+#[test]
+fn udf_bricks_are_outside() {
+  let mut volume = sphere_volume([16, 16, 16], 4.0);
+  volume.is_sdf = false;
+  for v in volume.data.iter_mut() {
+    *v = v.abs();
+  }
+  let sparse = SparseVolume::from_volume(&volume, 1.0, 4).unwrap();
+  assert!(!sparse.indirection.contains(&SPARSE_EMPTY_INSIDE));
+  assert!(sparse.indirection.contains(&SPARSE_EMPTY_OUTSIDE));
+}
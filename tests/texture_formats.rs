@@ -0,0 +1,135 @@
+//! Check the KTX2 and DDS 3D textures against the independent parsers of the formats.
+
+mod common;
+
+use hala_sdf_baker::volume::*;
+
+use common::*;
+
+/// Create the volumes of the texture layouts, single channel or RGBA, with or without the mip chain.
+/// return: The volumes.
+fn texture_volumes() -> Vec<SDFVolume> {
+  let volume = sphere_volume([12, 10, 6], 3.5);
+  let mut with_mips = volume.clone();
+  with_mips.build_mips().unwrap();
+  let mut with_gradients = with_mips.clone();
+  with_gradients.build_gradients().unwrap();
+  vec![volume, with_mips, with_gradients]
+}
+
+/// Get the bytes of the texels of all levels of the volume.
+/// param volume: The volume.
+/// param encoding: The value encoding.
+/// return: The bytes of each level.
+fn level_bytes(volume: &SDFVolume, encoding: ValueEncoding) -> Vec<Vec<u8>> {
+  let gradient_levels = volume.gradient_levels();
+  std::iter::once(&volume.data).chain(volume.mips.iter())
+    .enumerate()
+    .map(|(level, data)| match gradient_levels.get(level) {
+      Some(gradients) => encoding.encode_rgba(gradients, data, volume.remap_range()),
+      None => encoding.encode(data, volume.remap_range()),
+    })
+    .collect()
+}
+
+#[test]
+fn ktx2_textures_conform_to_the_specification() {
+  for volume in texture_volumes() {
+    let has_gradients = !volume.gradients.is_empty();
+    for encoding in ValueEncoding::ALL {
+      let mut bytes = Vec::new();
+      write_ktx2(&mut bytes, &volume, encoding, volume.remap_range()).unwrap();
+      let reader = ktx2::Reader::new(&bytes[..]).unwrap();
+
+      let header = reader.header();
+      let format = match (encoding, has_gradients) {
+        (ValueEncoding::F32, false) => ktx2::Format::R32_SFLOAT,
+        (ValueEncoding::F16, false) => ktx2::Format::R16_SFLOAT,
+        (ValueEncoding::Unorm16, false) => ktx2::Format::R16_UNORM,
+        (ValueEncoding::Unorm8, false) => ktx2::Format::R8_UNORM,
+        (ValueEncoding::F32, true) => ktx2::Format::R32G32B32A32_SFLOAT,
+        (ValueEncoding::F16, true) => ktx2::Format::R16G16B16A16_SFLOAT,
+        (ValueEncoding::Unorm16, true) => ktx2::Format::R16G16B16A16_UNORM,
+        (ValueEncoding::Unorm8, true) => ktx2::Format::R8G8B8A8_UNORM,
+      };
+      assert_eq!(header.format, Some(format));
+      assert_eq!(header.type_size as usize, encoding.size());
+      assert_eq!([header.pixel_width, header.pixel_height, header.pixel_depth], volume.dimensions);
+      assert_eq!((header.layer_count, header.face_count), (0, 1));
+      assert_eq!(header.level_count, volume.num_of_mip_levels());
+      assert!(header.supercompression_scheme.is_none());
+
+      // The levels are the encoded values, the level 0 is the largest one.
+      let levels = reader.levels().map(|level| level.data.to_vec()).collect::<Vec<_>>();
+      assert_eq!(levels, level_bytes(&volume, encoding));
+
+      // The basic data format descriptor describes a sample for each channel.
+      let dfd = reader.dfd_blocks().next().unwrap();
+      assert_eq!((dfd.header.vendor_id, dfd.header.descriptor_type), (0, 0));
+      let basic = ktx2::DfdBlockBasic::parse(dfd.data).unwrap();
+      assert_eq!(basic.header.color_model, Some(ktx2::ColorModel::RGBSDA));
+      assert_eq!(basic.header.transfer_function, Some(ktx2::TransferFunction::Linear));
+      assert_eq!(basic.header.bytes_planes[0] as usize, encoding.size() * if has_gradients { 4 } else { 1 });
+      let samples = basic.sample_information().collect::<Vec<_>>();
+      assert_eq!(samples.len(), if has_gradients { 4 } else { 1 });
+      for (channel, sample) in samples.iter().enumerate() {
+        assert_eq!(sample.bit_offset as usize, channel * encoding.size() * 8);
+        assert_eq!(sample.bit_length.get() as usize, encoding.size() * 8);
+        let is_float = matches!(encoding, ValueEncoding::F32 | ValueEncoding::F16);
+        assert_eq!(sample.channel_type_qualifiers.contains(ktx2::ChannelTypeQualifiers::FLOAT), is_float);
+      }
+      // The alpha channel has the distance.
+      if has_gradients {
+        assert_eq!(samples[3].channel_type, 15);
+      }
+
+      // The key/value data keeps the volume information.
+      let key_values = reader.key_value_data()
+        .map(|(key, value)| (key.to_string(), String::from_utf8_lossy(value).trim_end_matches('\0').to_string()))
+        .collect::<Vec<_>>();
+      let keys = key_values.iter().map(|(key, _)| key.as_str()).collect::<Vec<_>>();
+      assert!(keys.windows(2).all(|w| w[0] < w[1]), "The keys {:?} are not sorted.", keys);
+      assert!(keys.contains(&"KTXwriter"));
+      let value = |name: &str| key_values.iter()
+        .find(|(key, _)| *key == format!("{}.{}", KTX2_METADATA_PREFIX, name))
+        .map(|(_, value)| value.clone());
+      assert_eq!(value("voxelSize"), Some("1".to_string()));
+      assert_eq!(value("unit"), Some("voxel".to_string()));
+      assert_eq!(value("remapMin").is_some(), encoding.is_normalized());
+    }
+  }
+}
+
+#[test]
+fn dds_textures_conform_to_the_specification() {
+  for volume in texture_volumes() {
+    let has_gradients = !volume.gradients.is_empty();
+    for encoding in ValueEncoding::ALL {
+      let mut bytes = Vec::new();
+      write_dds(&mut bytes, &volume, encoding, volume.remap_range()).unwrap();
+      let dds = ddsfile::Dds::read(&mut bytes.as_slice()).unwrap();
+
+      let format = match (encoding, has_gradients) {
+        (ValueEncoding::F32, false) => ddsfile::DxgiFormat::R32_Float,
+        (ValueEncoding::F16, false) => ddsfile::DxgiFormat::R16_Float,
+        (ValueEncoding::Unorm16, false) => ddsfile::DxgiFormat::R16_UNorm,
+        (ValueEncoding::Unorm8, false) => ddsfile::DxgiFormat::R8_UNorm,
+        (ValueEncoding::F32, true) => ddsfile::DxgiFormat::R32G32B32A32_Float,
+        (ValueEncoding::F16, true) => ddsfile::DxgiFormat::R16G16B16A16_Float,
+        (ValueEncoding::Unorm16, true) => ddsfile::DxgiFormat::R16G16B16A16_UNorm,
+        (ValueEncoding::Unorm8, true) => ddsfile::DxgiFormat::R8G8B8A8_UNorm,
+      };
+      assert_eq!(dds.get_dxgi_format(), Some(format));
+      let header10 = dds.header10.as_ref().unwrap();
+      assert_eq!(header10.resource_dimension, ddsfile::D3D10ResourceDimension::Texture3D);
+      assert_eq!(header10.array_size, 1);
+      assert_eq!([dds.get_width(), dds.get_height(), dds.get_depth()], volume.dimensions);
+      assert!(dds.header.caps2.contains(ddsfile::Caps2::VOLUME));
+      assert_eq!(dds.get_num_mipmap_levels(), volume.num_of_mip_levels());
+      assert_eq!(dds.get_pitch(), Some(volume.dimensions[0] * (encoding.size() * if has_gradients { 4 } else { 1 }) as u32));
+
+      // The levels follow each other from the level 0.
+      assert_eq!(dds.data, level_bytes(&volume, encoding).concat());
+    }
+  }
+}
@@ -0,0 +1,149 @@
+//! Split the grids into the bricks of the tiled bake and stitch them back without the GPU.
+
+use hala_sdf_baker::volume::*;
+
+/// Fill the grid with the linear index of each voxel.
+/// param dimensions: The dimensions of the grid.
+/// return: The voxel data.
+fn indexed_grid(dimensions: [u32; 3]) -> Vec<f32> {
+  (0..dimensions.iter().product::<u32>()).map(|i| i as f32).collect()
+}
+
+/// Cut the voxels of the brick with the halo out of the grid, as a bake of the brick would return.
+/// param brick: The brick.
+/// param dimensions: The dimensions of the grid.
+/// param data: The voxel data of the grid.
+/// return: The voxel data of the brick.
+fn cut_brick(brick: &VolumeBrick, dimensions: [u32; 3], data: &[f32]) -> Vec<f32> {
+  let mut brick_data = Vec::with_capacity(brick.num_of_voxels() as usize);
+  for z in brick.min[2]..brick.min[2] + brick.size[2] {
+    for y in brick.min[1]..brick.min[1] + brick.size[1] {
+      for x in brick.min[0]..brick.min[0] + brick.size[0] {
+        brick_data.push(data[(x + dimensions[0] * (y + dimensions[1] * z)) as usize]);
+      }
+    }
+  }
+  brick_data
+}
+
+#[test]
+fn whole_grid_is_one_brick() {
+  let dimensions = [64, 32, 48];
+  let bricks = split_into_bricks(dimensions, 8, u64::MAX).unwrap();
+  assert_eq!(bricks, vec![VolumeBrick {
+    core_min: [0, 0, 0],
+    core_size: dimensions,
+    min: [0, 0, 0],
+    size: dimensions,
+  }]);
+}
+
+#[test]
+fn bricks_cover_the_grid_within_the_limit() {
+  let dimensions = [97, 64, 33];
+  let halo = 4;
+  let max_voxels = 40 * 40 * 40;
+  let bricks = split_into_bricks(dimensions, halo, max_voxels).unwrap();
+  assert!(bricks.len() > 1);
+
+  let mut coverage = vec![0u32; dimensions.iter().product::<u32>() as usize];
+  for brick in bricks.iter() {
+    assert!(brick.num_of_voxels() <= max_voxels, "The brick {:?} is over the limit.", brick);
+    for (i, dimension) in dimensions.iter().enumerate() {
+      // The halo is kept unless it is clamped by the grid.
+      assert_eq!(brick.min[i], brick.core_min[i].saturating_sub(halo));
+      assert_eq!(brick.min[i] + brick.size[i], (brick.core_min[i] + brick.core_size[i] + halo).min(*dimension));
+    }
+    for z in brick.core_min[2]..brick.core_min[2] + brick.core_size[2] {
+      for y in brick.core_min[1]..brick.core_min[1] + brick.core_size[1] {
+        for x in brick.core_min[0]..brick.core_min[0] + brick.core_size[0] {
+          coverage[(x + dimensions[0] * (y + dimensions[1] * z)) as usize] += 1;
+        }
+      }
+    }
+  }
+  assert!(coverage.iter().all(|c| *c == 1), "Some voxels are not in exactly one core.");
+}
+
+#[test]
+fn stitched_bricks_match_the_grid() {
+  let dimensions = [50, 37, 21];
+  let data = indexed_grid(dimensions);
+  let bricks = split_into_bricks(dimensions, 3, 16 * 16 * 16).unwrap();
+  assert!(bricks.len() > 1);
+
+  let mut stitched = vec![-1.0; data.len()];
+  for brick in bricks.iter() {
+    brick.copy_core_to(&cut_brick(brick, dimensions, &data), dimensions, &mut stitched).unwrap();
+  }
+  assert_eq!(stitched, data);
+}
+
+#[test]
+fn halo_over_the_limit_is_rejected() {
+  assert!(split_into_bricks([64, 64, 64], 8, 16 * 16 * 16).is_err());
+  assert!(split_into_bricks([0, 64, 64], 0, u64::MAX).is_err());
+}
+
+#[test]
+fn far_distances_are_bounded_from_below() {
+  // The points on the sphere in the corner of the grid, most bricks have none of them.
+  let dimensions = [32, 32, 32];
+  let center = [8.0, 8.0, 8.0];
+  let radius = 5.0;
+  let points = (0..300)
+    .map(|i| {
+      let y = 1.0 - (i as f32 + 0.5) / 150.0;
+      let r = (1.0 - y * y).sqrt();
+      let phi = i as f32 * 2.399_963;
+      [center[0] + radius * r * phi.cos(), center[1] + radius * y, center[2] + radius * r * phi.sin()]
+    })
+    .collect::<Vec<_>>();
+  let voxel_center = |v: [u32; 3]| v.map(|c| c as f32 + 0.5);
+  let point_distance = |p: [f32; 3], q: &[f32; 3]| (0..3).map(|i| (p[i] - q[i]).powi(2)).sum::<f32>().sqrt();
+  let get_distance = |v: [u32; 3]| points.iter().map(|q| point_distance(voxel_center(v), q)).fold(f32::MAX, f32::min);
+  let get_sign = |v: [u32; 3]| if point_distance(voxel_center(v), &center) < radius { -1.0 } else { 1.0 };
+
+  // Each brick only sees the points in it, and the brick without any point knows only the signs.
+  let halo = 3;
+  let surface_offset = 0.25;
+  let bricks = split_into_bricks(dimensions, halo, 16 * 16 * 16).unwrap();
+  assert!(bricks.len() > 8);
+  let mut data = vec![0.0; dimensions.iter().product::<u32>() as usize];
+  for brick in bricks.iter() {
+    let is_in_brick = |q: &&[f32; 3]| (0..3).all(|i| q[i] >= brick.min[i] as f32 && q[i] < (brick.min[i] + brick.size[i]) as f32);
+    let mut brick_data = Vec::with_capacity(brick.num_of_voxels() as usize);
+    for z in brick.min[2]..brick.min[2] + brick.size[2] {
+      for y in brick.min[1]..brick.min[1] + brick.size[1] {
+        for x in brick.min[0]..brick.min[0] + brick.size[0] {
+          let distance = points.iter().filter(is_in_brick).map(|q| point_distance(voxel_center([x, y, z]), q)).fold(f32::INFINITY, f32::min);
+          brick_data.push(get_sign([x, y, z]) * distance - surface_offset);
+        }
+      }
+    }
+    brick.copy_core_to(&brick_data, dimensions, &mut data).unwrap();
+  }
+  let stitched = data.clone();
+
+  let coarse_step = 5;
+  bound_far_distances(dimensions, &mut data, true, surface_offset, halo, 1.0, coarse_step, get_distance).unwrap();
+  for z in 0..dimensions[2] {
+    for y in 0..dimensions[1] {
+      for x in 0..dimensions[0] {
+        let index = (x + dimensions[0] * (y + dimensions[1] * z)) as usize;
+        let exact = get_distance([x, y, z]);
+        let distance = data[index] + surface_offset;
+        assert_eq!(distance < 0.0, get_sign([x, y, z]) < 0.0, "The voxel {:?} flips the sign.", [x, y, z]);
+        if exact <= halo as f32 {
+          // The closest point is in the halo, so the brick has it.
+          assert_eq!(data[index], stitched[index], "The exact voxel {:?} is changed.", [x, y, z]);
+        } else {
+          // Never over the exact distance, and looser by at most the diagonal of the coarse step.
+          let distance = distance.abs();
+          assert!(distance >= halo as f32 && distance <= exact + 1e-4, "The voxel {:?} is bounded to {}, the exact is {}.", [x, y, z], distance, exact);
+          assert!(distance >= exact - 3.0f32.sqrt() * coarse_step as f32, "The voxel {:?} is bounded to {}, the exact is {}.", [x, y, z], distance, exact);
+        }
+      }
+    }
+  }
+}
@@ -0,0 +1,108 @@
+//! Compare the volumes voxel by voxel, the max and RMS errors, the sign flips and the slice histograms.
+
+mod common;
+
+use hala_sdf_baker::volume::*;
+
+use common::*;
+
+#[test]
+fn test_same_volumes() {
+  let volume = sphere_volume([12, 10, 8], 3.0);
+  let diff = diff_volumes(&volume, &volume).unwrap();
+
+  assert_eq!(diff.rmse, 0.0);
+  assert_eq!(diff.max_error, 0.0);
+  assert!(diff.sign_flips.is_empty());
+  assert_eq!(diff.slices.len(), 8);
+  for slice in diff.slices.iter() {
+    assert_eq!(slice.histogram[0], 12 * 10);
+    assert_eq!(slice.num_of_sign_flips, 0);
+  }
+  assert!(diff.difference.data.iter().all(|v| *v == 0.0));
+}
+
+#[test]
+fn test_max_and_rms_errors() {
+  let a = sphere_volume([12, 10, 8], 3.0);
+  let mut b = a.clone();
+  let index = b.index(3, 4, 5);
+  b.data[index] -= 2.0;
+  let other = b.index(7, 2, 1);
+  b.data[other] += 1.0;
+
+  let diff = diff_volumes(&a, &b).unwrap();
+  let num_of_voxels = (12 * 10 * 8) as f32;
+  assert_eq!(diff.max_error, 2.0);
+  assert_eq!(diff.max_error_location, [3, 4, 5]);
+  assert!((diff.rmse - (5.0 / num_of_voxels).sqrt()).abs() < 1e-6, "The RMSE is {}.", diff.rmse);
+  assert_eq!(diff.difference.data[index], 2.0);
+  assert_eq!(diff.difference.data[other], -1.0);
+
+  // Only the slices 1 and 5 have errors, in the top and the middle bins of the histogram.
+  for (z, slice) in diff.slices.iter().enumerate() {
+    let expected_max_error = match z {
+      5 => 2.0,
+      1 => 1.0,
+      _ => 0.0,
+    };
+    assert_eq!(slice.max_error, expected_max_error, "The max error of the slice {}.", z);
+    assert!((slice.rmse - expected_max_error / (12.0 * 10.0f32).sqrt()).abs() < 1e-6, "The RMSE of the slice {}.", z);
+    assert_eq!(slice.histogram.iter().sum::<usize>(), 12 * 10);
+  }
+  assert_eq!(diff.slices[5].histogram[NUM_OF_HISTOGRAM_BINS - 1], 1);
+  assert_eq!(diff.slices[1].histogram[NUM_OF_HISTOGRAM_BINS / 2], 1);
+}
+
+#[test]
+fn test_sign_flips() {
+  // The voxels between the two spheres flip the sign.
+  let dimensions = [16, 12, 10];
+  let a = sphere_volume(dimensions, 5.0);
+  let b = sphere_volume(dimensions, 4.0);
+  let diff = diff_volumes(&a, &b).unwrap();
+
+  let mut expected = Vec::new();
+  for z in 0..dimensions[2] {
+    for y in 0..dimensions[1] {
+      for x in 0..dimensions[0] {
+        let index = a.index(x, y, z);
+        if (a.data[index] < 0.0) != (b.data[index] < 0.0) {
+          expected.push([x, y, z]);
+        }
+      }
+    }
+  }
+  assert!(!expected.is_empty());
+  assert_eq!(diff.sign_flips, expected);
+  for (z, slice) in diff.slices.iter().enumerate() {
+    assert_eq!(slice.num_of_sign_flips, expected.iter().filter(|p| p[2] == z as u32).count());
+  }
+  assert!((diff.max_error - 1.0).abs() < 1e-5, "The max error is {}.", diff.max_error);
+  assert!((diff.rmse - 1.0).abs() < 1e-5, "The RMSE is {}.", diff.rmse);
+
+  // The UDF has no sign to flip.
+  let a = voxel_volume(dimensions, false, |p| sphere_distance(dimensions, 5.0, p).abs());
+  let b = voxel_volume(dimensions, false, |p| -sphere_distance(dimensions, 4.0, p));
+  assert!(diff_volumes(&a, &b).unwrap().sign_flips.is_empty());
+}
+
+#[test]
+fn test_mismatched_volumes() {
+  let a = sphere_volume([12, 10, 8], 3.0);
+  assert!(diff_volumes(&a, &sphere_volume([12, 10, 9], 3.0)).is_err());
+
+  let mut b = a.clone();
+  b.center = [1.0, 0.0, 0.0];
+  assert!(diff_volumes(&a, &b).is_err());
+
+  // The second volume is converted to the unit of the first one.
+  let mut b = a.clone();
+  b.voxel_size = 0.5;
+  b.extents = b.extents.map(|e| e * 0.5);
+  let mut a = b.clone();
+  a.convert_unit(DistanceUnit::World).unwrap();
+  let diff = diff_volumes(&a, &b).unwrap();
+  assert!(diff.max_error < 1e-6, "The max error is {}.", diff.max_error);
+  assert_eq!(diff.difference.unit, DistanceUnit::World);
+}
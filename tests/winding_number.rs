@@ -0,0 +1,68 @@
+//! Check the BVH of the winding number fits the stack of the shader and the GPU signs match the CPU ones.
+
+mod common;
+
+use glam::Vec3;
+
+use hala_renderer::renderer::HalaRendererTrait;
+
+use hala_sdf_baker::baker::SDFBaker;
+use hala_sdf_baker::baker::cpu::bvh::{
+  BVH,
+  WINDING_NUMBER_STACK_SIZE,
+};
+use hala_sdf_baker::baker::settings::{
+  SignMode,
+  BakeBackend,
+};
+
+use common::*;
+
+#[test]
+fn test_stack_size() {
+  let mesh = uv_sphere(1.0, 512);
+  let triangles = (0..mesh.num_of_triangles())
+    .map(|i| mesh.triangle(i).map(Vec3::from_array))
+    .collect::<Vec<_>>();
+  let num_of_triangles = triangles.len();
+  let bvh = BVH::new(triangles);
+
+  // The median split keeps the BVH balanced, so the stack grows with the log of the triangles.
+  let stack_size = bvh.get_winding_stack_size();
+  let depth = (num_of_triangles as f32).log2().ceil() as usize;
+  assert!(stack_size >= 2 && stack_size <= depth + 1, "The stack size is {} for {} triangles.", stack_size, num_of_triangles);
+  assert!(stack_size <= WINDING_NUMBER_STACK_SIZE);
+
+  assert_eq!(BVH::new(Vec::new()).get_winding_stack_size(), 0);
+}
+
+#[test]
+#[ignore = "It needs a Vulkan device and the compiled shaders."]
+fn test_gpu_matches_cpu() {
+  let gpu_req = hala_gfx::HalaGPURequirements {
+    width: 1024,
+    height: 768,
+    version: (1, 3, 0),
+    require_depth: true,
+    ..Default::default()
+  };
+  let mut baker = SDFBaker::new("SDF Baker Test", &gpu_req, None).unwrap();
+  baker.settings.backend = BakeBackend::Gpu;
+  baker.settings.sign_mode = SignMode::WindingNumber;
+  baker.settings.max_resolution = 64;
+  baker.set_triangle_mesh("sphere", uv_sphere(1.0, 64));
+
+  baker.bake_sdf().unwrap();
+  let diff = baker.compare_with_cpu().unwrap();
+  baker.wait_idle().unwrap();
+
+  // Both walk the same BVH, so only the voxels on the surface may flip.
+  let num_of_voxels = diff.difference.data.len();
+  assert!(
+    diff.sign_flips.len() * 1000 <= num_of_voxels,
+    "{} of {} voxels flip the sign, the first at {:?}.",
+    diff.sign_flips.len(),
+    num_of_voxels,
+    diff.sign_flips.first(),
+  );
+}